@@ -0,0 +1,113 @@
+//! Event bus benchmarks: emit throughput, subscription matching and history
+//! upkeep. Run with `cargo bench --bench event_bus`.
+//!
+//! The app is a binary crate, so the bus sources are compiled in directly.
+
+#![allow(dead_code, unused_imports)]
+// Lints in the included sources are reported by the main crate.
+#![allow(clippy::derivable_impls, clippy::type_complexity, clippy::unwrap_or_default)]
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod core {
+    pub fn run_id() -> &'static str {
+        "bench"
+    }
+}
+
+#[path = "../src/event_bus/types.rs"]
+mod types;
+#[path = "../src/event_bus/bus.rs"]
+mod bus;
+
+use bus::{EventBus, EventHandler, EventListener};
+use types::{Event, EventType};
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread().enable_all().build().unwrap()
+}
+
+fn custom_event(name: &str) -> Event {
+    Event::new(
+        EventType::Custom { name: name.to_string(), payload: serde_json::json!({ "value": 1 }) },
+        "bench",
+    )
+}
+
+fn noop_listener() -> Arc<dyn EventListener> {
+    Arc::new(EventHandler::new(|_event| Box::pin(async { Ok(()) })))
+}
+
+/// Emits with 0, 1 and 10 listeners on the emitted name.
+fn emit_throughput(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("emit");
+    group.throughput(Throughput::Elements(1));
+    for listeners in [0, 1, 10] {
+        let bus = EventBus::new();
+        for _ in 0..listeners {
+            bus.subscribe("bench.event", noop_listener());
+        }
+        let event = custom_event("bench.event");
+        group.bench_with_input(BenchmarkId::from_parameter(listeners), &listeners, |b, _| {
+            b.to_async(&rt).iter(|| async { bus.emit(black_box(event.clone())).await.unwrap() });
+        });
+        rt.block_on(bus.drain(Duration::from_secs(5)));
+    }
+    group.finish();
+}
+
+/// Emits an event none of N subscriptions match, mixing exact names and
+/// wildcard patterns, to isolate the cost of matching.
+fn pattern_matching(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("match_subscriptions");
+    for subscriptions in [10, 100, 1000] {
+        let bus = EventBus::new();
+        for i in 0..subscriptions {
+            let pattern = match i % 3 {
+                0 => format!("plugin{}.event", i),
+                1 => format!("plugin{}.*", i),
+                _ => format!("plugin{}.**", i),
+            };
+            bus.subscribe(&pattern, noop_listener());
+        }
+        let event = custom_event("unmatched.event");
+        group.bench_with_input(BenchmarkId::from_parameter(subscriptions), &subscriptions, |b, _| {
+            b.to_async(&rt).iter(|| async { bus.emit(black_box(event.clone())).await.unwrap() });
+        });
+    }
+    group.finish();
+}
+
+/// Emits into an empty and a full history, then reads the latest entries.
+fn history(c: &mut Criterion) {
+    let rt = runtime();
+    let mut group = c.benchmark_group("history");
+    let event = custom_event("bench.event");
+
+    let empty = EventBus::new();
+    group.bench_function("emit_empty_history", |b| {
+        b.to_async(&rt).iter(|| async { empty.emit(black_box(event.clone())).await.unwrap() });
+    });
+
+    let full = EventBus::new();
+    rt.block_on(async {
+        for _ in 0..1000 {
+            full.emit(event.clone()).await.unwrap();
+        }
+    });
+    group.bench_function("emit_full_history", |b| {
+        b.to_async(&rt).iter(|| async { full.emit(black_box(event.clone())).await.unwrap() });
+    });
+    group.bench_function("read_last_100", |b| {
+        b.iter(|| black_box(full.get_event_history(Some(100))));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, emit_throughput, pattern_matching, history);
+criterion_main!(benches);
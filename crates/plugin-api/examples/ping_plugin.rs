@@ -0,0 +1,39 @@
+//! Answers every `client.ping` event with a `client.pong` carrying the same
+//! payload. WebSocket clients can emit `client.ping` to try it.
+
+use rustwebui_plugin_api::{declare_plugin, Host, LogLevel, PluginDeclaration, ABI_VERSION};
+use std::ffi::{c_char, CStr};
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+static HOST: AtomicPtr<Host> = AtomicPtr::new(std::ptr::null_mut());
+
+unsafe extern "C" fn on_start(host: *const Host) -> i32 {
+    HOST.store(host.cast_mut(), Ordering::Release);
+    if let Some(host) = unsafe { host.as_ref() } {
+        host.log_message(LogLevel::Info, "ping plugin started");
+    }
+    0
+}
+
+unsafe extern "C" fn on_event(_name: *const c_char, payload: *const c_char) {
+    let Some(host) = (unsafe { HOST.load(Ordering::Acquire).as_ref() }) else { return };
+    let payload = unsafe { CStr::from_ptr(payload) }.to_string_lossy();
+    if !host.emit_event("client.pong", &payload) {
+        host.log_message(LogLevel::Warn, "could not emit client.pong");
+    }
+}
+
+unsafe extern "C" fn on_shutdown() {
+    HOST.store(std::ptr::null_mut(), Ordering::Release);
+}
+
+declare_plugin!(PluginDeclaration {
+    abi_version: ABI_VERSION,
+    name: c"ping".as_ptr(),
+    version: c"1.0.0".as_ptr(),
+    description: c"Answers client.ping with client.pong".as_ptr(),
+    subscriptions: c"client.ping".as_ptr(),
+    on_start: Some(on_start),
+    on_event: Some(on_event),
+    on_shutdown: Some(on_shutdown),
+});
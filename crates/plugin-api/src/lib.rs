@@ -0,0 +1,121 @@
+//! The interface between rustwebui-app and the dynamic plugins it loads from
+//! its plugin directory. Plugins are `cdylib`s and only `#[repr(C)]` types
+//! and `extern "C"` functions cross the boundary, so a plugin doesn't have to
+//! be built with the app's compiler or against its crates: it has to agree
+//! on [`ABI_VERSION`].
+//!
+//! A plugin exports its [`PluginDeclaration`] with [`declare_plugin!`]:
+//!
+//! ```ignore
+//! use rustwebui_plugin_api::{declare_plugin, Host, PluginDeclaration, ABI_VERSION};
+//!
+//! unsafe extern "C" fn on_start(_host: *const Host) -> i32 {
+//!     0
+//! }
+//!
+//! declare_plugin!(PluginDeclaration {
+//!     abi_version: ABI_VERSION,
+//!     name: c"hello".as_ptr(),
+//!     version: c"0.1.0".as_ptr(),
+//!     description: c"Says hello".as_ptr(),
+//!     subscriptions: c"app.*".as_ptr(),
+//!     on_start: Some(on_start),
+//!     on_event: None,
+//!     on_shutdown: None,
+//! });
+//! ```
+
+use std::ffi::{c_char, c_void, CString};
+
+/// Changes whenever a type in this crate changes shape; the app refuses
+/// plugins declaring another version.
+pub const ABI_VERSION: u32 = 1;
+
+/// Name of the function [`declare_plugin!`] exports, NUL-terminated for
+/// `dlsym`.
+pub const DECLARATION_SYMBOL: &[u8] = b"rustwebui_plugin_declaration\0";
+
+/// Type of the function exported as [`DECLARATION_SYMBOL`].
+pub type DeclarationFn = unsafe extern "C" fn() -> *const PluginDeclaration;
+
+/// What a plugin library declares about itself. Strings are NUL-terminated
+/// UTF-8 that stay valid while the library is loaded, e.g. `c"..."` literals.
+#[repr(C)]
+pub struct PluginDeclaration {
+    /// [`ABI_VERSION`] of the crate the plugin was built against.
+    pub abi_version: u32,
+    pub name: *const c_char,
+    pub version: *const c_char,
+    /// May be null.
+    pub description: *const c_char,
+    /// Comma-separated event name patterns passed to `on_event`, e.g.
+    /// `user.*,app.shutdown`. May be null.
+    pub subscriptions: *const c_char,
+    /// Called once the library is loaded. `host` stays valid until
+    /// `on_shutdown` returns. A non-zero result aborts the load.
+    pub on_start: Option<unsafe extern "C" fn(host: *const Host) -> i32>,
+    /// Called with the name and the JSON payload of each subscribed event, on
+    /// one of the app's threads: a custom event's payload, or the fields of a
+    /// built-in one. The strings are only valid during the call.
+    pub on_event: Option<unsafe extern "C" fn(name: *const c_char, payload: *const c_char)>,
+    /// Called before the library is unloaded, or when the app exits.
+    pub on_shutdown: Option<unsafe extern "C" fn()>,
+}
+
+// The pointers only ever point at immutable, 'static data in the library.
+unsafe impl Sync for PluginDeclaration {}
+
+/// Levels for [`Host::log`].
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+/// Callbacks into the app. They may be called from any thread.
+#[repr(C)]
+pub struct Host {
+    /// Passed back as the first argument of every callback.
+    pub context: *mut c_void,
+    /// Emits a custom event named `name` with the JSON `payload` on the
+    /// app's event bus. Returns 0 on success, non-zero when a string is not
+    /// UTF-8 or the payload is not JSON.
+    pub emit: unsafe extern "C" fn(context: *mut c_void, name: *const c_char, payload: *const c_char) -> i32,
+    /// Logs `message` under the plugin's name.
+    pub log: unsafe extern "C" fn(context: *mut c_void, level: LogLevel, message: *const c_char),
+}
+
+unsafe impl Send for Host {}
+unsafe impl Sync for Host {}
+
+impl Host {
+    /// [`Host::emit`] for Rust strings. `false` when either contains a NUL
+    /// byte or the app refused the event.
+    pub fn emit_event(&self, name: &str, payload_json: &str) -> bool {
+        let (Ok(name), Ok(payload)) = (CString::new(name), CString::new(payload_json)) else { return false };
+        unsafe { (self.emit)(self.context, name.as_ptr(), payload.as_ptr()) == 0 }
+    }
+
+    /// [`Host::log`] for Rust strings. Messages with NUL bytes are dropped.
+    pub fn log_message(&self, level: LogLevel, message: &str) {
+        if let Ok(message) = CString::new(message) {
+            unsafe { (self.log)(self.context, level, message.as_ptr()) }
+        }
+    }
+}
+
+/// Exports `$declaration`, a constant [`PluginDeclaration`], as
+/// [`DECLARATION_SYMBOL`].
+#[macro_export]
+macro_rules! declare_plugin {
+    ($declaration:expr) => {
+        #[unsafe(no_mangle)]
+        pub extern "C" fn rustwebui_plugin_declaration() -> *const $crate::PluginDeclaration {
+            static DECLARATION: $crate::PluginDeclaration = $declaration;
+            &DECLARATION
+        }
+    };
+}
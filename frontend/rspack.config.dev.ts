@@ -55,11 +55,15 @@ export default defineConfig({
     cssFilename: 'static/css/[name].[contenthash:8].css',
     clean: false, // Don't clean for faster incremental builds
   },
-  server: {
+  devServer: {
     port: 3000,
-    strictPort: true,
     open: true,
     hot: true, // Enable hot module replacement
+    // Pages may be served through the app's dev proxy (server.dev_proxy_url),
+    // which can't carry the hot reload socket, so connect to this server directly.
+    client: {
+      webSocketURL: 'ws://localhost:3000/ws',
+    },
   },
   optimization: {
     splitChunks: false, // Disable for faster incremental builds
@@ -8,6 +8,32 @@ export const appConfig: AppConfig = {
   version: '1.0.0',
 };
 
+/** Startup values served by the backend at `/runtime-config.json`. */
+export interface RuntimeConfig {
+  port: number;
+  run_id: string;
+  version: string;
+  dev_mode: boolean;
+  /**
+   * Random for each run of the backend. Send it as `X-Session-Token`, or as
+   * `?token=` for `EventSource` and `WebSocket`, to reach `/api/*` and the
+   * event streams.
+   */
+  session_token: string;
+  /** Event stream, e.g. `ws://localhost:8080/ws/events`. */
+  ws_url: string | null;
+  features: Record<string, boolean>;
+}
+
+export async function loadRuntimeConfig(): Promise<RuntimeConfig | null> {
+  try {
+    const response = await fetch('/runtime-config.json', { cache: 'no-store' });
+    return response.ok ? ((await response.json()) as RuntimeConfig) : null;
+  } catch {
+    return null;
+  }
+}
+
 export const SIDEBAR_WIDTH = 200;
 export const STATUS_BAR_HEIGHT = 28;
 export const HEADER_HEIGHT = 50;
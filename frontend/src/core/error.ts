@@ -76,3 +76,10 @@ export const ErrorCodes = {
   NOT_FOUND: 'NOT_FOUND' as ErrorCode,
   UNKNOWN: 'UNKNOWN' as ErrorCode,
 };
+
+/** Envelope of every payload sent by the backend; `error.code` is the Rust `ErrorCode` in snake_case. */
+export interface RpcResponse<T = unknown> {
+  ok: boolean;
+  data: T | null;
+  error: { code: string; message: string; details: unknown } | null;
+}
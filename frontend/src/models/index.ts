@@ -6,10 +6,59 @@ export interface User {
   status: string;
 }
 
+/** A `search_users` result; `highlights` holds `[start, end)` ranges of the matched text per field. */
+export interface UserMatch extends User {
+  rank: number;
+  highlights: Partial<Record<'name' | 'email' | 'role', [number, number][]>>;
+}
+
+export interface PoolStats {
+  max_size: number;
+  connections: number;
+  idle_connections: number;
+}
+
+/** Data of the response to `add_user` and the bindings that change settings or the counter. */
+export interface MutationResult {
+  /** ID of the inserted row; null when several rows changed. */
+  id: number | null;
+  affected_rows: number;
+  /** The new state, for settings and the counter. */
+  value?: unknown;
+}
+
+/** Data of the response to `import_users`, `backup_database` and `restore_database`. */
+export interface JobStarted {
+  /** Also in the payload of the event that reports the outcome. */
+  job_id: string;
+}
+
+export interface StatementCacheStats {
+  capacity: number;
+  hits: number;
+  misses: number;
+}
+
+export interface IndexStats {
+  name: string;
+  table: string;
+  unique: boolean;
+  columns: string[];
+}
+
 export interface DbStats {
   users: number;
   tables: string[];
-  size: string;
+  size: number;
+  page_count: number;
+  page_size: number;
+  freelist_count: number;
+  file_size: number | null;
+  wal_size: number | null;
+  row_counts: Record<string, number>;
+  indexes: IndexStats[];
+  pool: PoolStats;
+  statement_cache: StatementCacheStats;
 }
 
 export interface SystemInfo {
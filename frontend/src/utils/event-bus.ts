@@ -360,7 +360,7 @@ class EnhancedEventBus {
     if (typeof window !== 'undefined') {
       window.handleBackendEvent = (eventJson) => {
         try {
-          const event = JSON.parse(eventJson);
+          const event = typeof eventJson === 'string' ? JSON.parse(eventJson) : eventJson;
           this.emit(event.event || 'backend.event', event.data, {
             source: 'backend',
             correlationId: event.correlationId,
@@ -525,6 +525,17 @@ if (process.env.NODE_ENV !== 'production') {
 // Initialize WebUI handlers
 if (typeof window !== 'undefined') {
   globalEventBus.initWebUI();
+
+  // Plugins granted the clipboard capability write through the page, which
+  // owns the browser clipboard.
+  globalEventBus.subscribe('clipboard.write', (event) => {
+    const text = event.data?.data?.text;
+    if (typeof text === 'string' && navigator.clipboard) {
+      navigator.clipboard.writeText(text).catch((error) => {
+        console.error('[EventBus] Clipboard write failed:', error);
+      });
+    }
+  });
 }
 
 // Default export
@@ -0,0 +1,153 @@
+//! Services shared by viewmodels, plugins and background tasks. One
+//! [`AppState`] is built at startup and handed down, so a test can build its
+//! own with a private event bus and in-memory database.
+//!
+//! WebUI handlers are plain `fn`s and cannot capture it; they look up the
+//! state attached to the window that raised the event with [`AppState::of`].
+
+use crate::bridge::BindSurface;
+use crate::core::cache::ResponseCache;
+use crate::core::{AppConfig, Database};
+use crate::event_bus::EventBus;
+use crate::services::CounterService;
+use crate::viewmodels::window::WindowRegistry;
+use crate::websocket_manager::{WebSocketHandle, WebSocketManager};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::warn;
+use webui_rs::webui;
+
+/// States by the ID of the window they are attached to. Handlers only get
+/// the window ID, so this is how they find the instance owning the window;
+/// each entry lives as long as its [`Attachment`].
+static WINDOW_STATES: Lazy<RwLock<HashMap<usize, AppState>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+#[derive(Clone)]
+pub struct AppState {
+    db: Arc<Database>,
+    config: Arc<AppConfig>,
+    bus: EventBus,
+    counter: Arc<CounterService>,
+    windows: WindowRegistry,
+    cache: ResponseCache,
+    websocket: Arc<Mutex<Option<WebSocketHandle>>>,
+}
+
+impl AppState {
+    /// Emits on `bus` and persists the counter to `db`.
+    pub fn new(db: Arc<Database>, config: AppConfig, bus: EventBus) -> Self {
+        let counter = CounterService::with_database(Arc::clone(&db)).unwrap_or_else(|e| {
+            warn!("Counter will not be persisted: {}", e);
+            CounterService::in_memory()
+        });
+        Self {
+            db,
+            config: Arc::new(config),
+            bus,
+            counter: Arc::new(counter),
+            windows: WindowRegistry::default(),
+            cache: ResponseCache::new(),
+            websocket: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Makes the state available to handlers bound on `window` until the
+    /// returned [`Attachment`] is dropped, and sends frontend events to it
+    /// unless another window was attached first.
+    pub fn attach(&self, window: &impl BindSurface) -> Attachment {
+        if self.windows.main().is_none() {
+            self.windows.set_main(window.id());
+        }
+        WINDOW_STATES.write().unwrap().insert(window.id(), self.clone());
+        Attachment { window: window.id() }
+    }
+
+    /// The state attached to the window that raised `event`.
+    pub fn of(event: &webui::Event) -> Option<AppState> {
+        let state = WINDOW_STATES.read().unwrap().get(&event.window).cloned();
+        if state.is_none() {
+            warn!("No app state attached to window {}", event.window);
+        }
+        state
+    }
+
+    pub fn db(&self) -> &Arc<Database> {
+        &self.db
+    }
+
+    /// The configuration the app started with; live reloads are not applied here.
+    pub fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
+    pub fn counter(&self) -> &Arc<CounterService> {
+        &self.counter
+    }
+
+    pub fn windows(&self) -> &WindowRegistry {
+        &self.windows
+    }
+
+    /// Cached handler responses; see [`crate::viewmodels::invalidate_cached_responses`].
+    pub fn cache(&self) -> &ResponseCache {
+        &self.cache
+    }
+
+    pub fn set_websocket(&self, websocket: WebSocketHandle) {
+        *self.websocket.lock().unwrap() = Some(websocket);
+    }
+
+    /// The WebSocket manager, once the window is up.
+    pub fn websocket_manager(&self) -> Option<WebSocketManager> {
+        self.websocket.lock().unwrap().as_ref().map(|websocket| websocket.manager().clone())
+    }
+
+    /// Removes the WebSocket handle so shutdown can stop it.
+    pub fn take_websocket(&self) -> Option<WebSocketHandle> {
+        self.websocket.lock().unwrap().take()
+    }
+}
+
+/// Detaches a state from its window when dropped.
+#[must_use = "the state is detached from the window as soon as this is dropped"]
+pub struct Attachment {
+    window: usize,
+}
+
+impl Drop for Attachment {
+    fn drop(&mut self) {
+        WINDOW_STATES.write().unwrap().remove(&self.window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::config::ConfigFormat;
+    use crate::testing::memory_db;
+
+    fn test_state() -> AppState {
+        let db = Arc::new(memory_db());
+        let config = AppConfig::parse("", ConfigFormat::Toml).unwrap();
+        AppState::new(db, config, EventBus::new())
+    }
+
+    #[test]
+    fn states_do_not_share_services() {
+        let first = test_state();
+        let second = test_state();
+
+        first.counter().increment();
+        first.windows().set_main(7);
+
+        assert_eq!(first.counter().get(), 1);
+        assert_eq!(second.counter().get(), 0);
+        assert_eq!(second.windows().main(), None);
+        assert_eq!(first.clone().windows().main(), Some(7));
+    }
+}
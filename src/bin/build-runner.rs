@@ -0,0 +1,109 @@
+//! Runs the frontend and cargo builds as tracked steps, printing per-step
+//! timings and writing a `BuildSummary` JSON artifact.
+//!
+//! ```sh
+//! cargo run --bin build-runner -- --release --summary target/build-summary.json
+//! ```
+
+// Only the standalone parts of the build logger; the tracker needs the event bus.
+#[path = "../build_logger"]
+#[allow(dead_code)]
+mod build_logger {
+    pub mod display;
+    pub mod progress;
+}
+
+use build_logger::display::{print_progress_bar, print_step_completed, print_step_failed};
+use build_logger::progress::BuildProgress;
+use clap::Parser;
+use std::path::PathBuf;
+use std::process::{Command, ExitCode};
+
+#[derive(Debug, Parser)]
+#[command(about = "Build the frontend and the Rust application with step timings")]
+struct Args {
+    /// Build the Rust application in release mode
+    #[arg(long)]
+    release: bool,
+
+    /// Reuse the existing frontend/dist instead of rebuilding it
+    #[arg(long)]
+    skip_frontend: bool,
+
+    /// Where to write the JSON build summary
+    #[arg(long, default_value = "target/build-summary.json")]
+    summary: PathBuf,
+}
+
+struct Step {
+    name: &'static str,
+    program: &'static str,
+    args: Vec<&'static str>,
+    dir: &'static str,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let mut cargo_args = vec!["build", "--bin", env!("CARGO_PKG_NAME")];
+    if args.release {
+        cargo_args.push("--release");
+    }
+    let steps = [
+        Step { name: "frontend-deps", program: "bun", args: vec!["install"], dir: "frontend" },
+        Step { name: "frontend", program: "bun", args: vec!["build-frontend.js"], dir: "." },
+        Step { name: "cargo", program: "cargo", args: cargo_args, dir: "." },
+    ];
+
+    let mut progress = BuildProgress::new();
+    progress.init_steps(steps.iter().map(|s| s.name).collect());
+    let mut failed = false;
+
+    for step in &steps {
+        if args.skip_frontend && step.name.starts_with("frontend") {
+            progress.skip_step(step.name, "--skip-frontend");
+            continue;
+        }
+        if failed {
+            progress.skip_step(step.name, "earlier step failed");
+            continue;
+        }
+
+        progress.start_step(step.name);
+        let status = Command::new(step.program).args(&step.args).current_dir(step.dir).status();
+        let command = format!("{} {}", step.program, step.args.join(" "));
+        match status {
+            Ok(status) if status.success() => {
+                progress.complete_step(step.name, &command);
+                print_step_completed(step.name, progress.step_duration_ms(step.name).unwrap_or(0), &command);
+            }
+            Ok(status) => {
+                let message = format!("exit {}: {}", status.code().unwrap_or(-1), command);
+                progress.fail_step(step.name, &message);
+                print_step_failed(step.name, &message);
+                failed = true;
+            }
+            Err(e) => {
+                let message = format!("failed to run {}: {}", step.program, e);
+                progress.fail_step(step.name, &message);
+                print_step_failed(step.name, &message);
+                failed = true;
+            }
+        }
+    }
+
+    print_progress_bar(&progress);
+    let summary = progress.summary();
+    println!("{} in {}ms", summary.summary, summary.elapsed_ms);
+
+    if let Some(parent) = args.summary.parent().filter(|p| !p.as_os_str().is_empty()) {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match serde_json::to_string_pretty(&summary).map(|json| std::fs::write(&args.summary, json)) {
+        Ok(Ok(())) => println!("Build summary written to {}", args.summary.display()),
+        Ok(Err(e)) => eprintln!("Failed to write {}: {}", args.summary.display(), e),
+        Err(e) => eprintln!("Failed to serialize build summary: {}", e),
+    }
+
+    if summary.success { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
@@ -0,0 +1,99 @@
+//! Helpers for reading arguments passed from `webui.call(name, ...args)` and
+//! answering the call, plus window functions webui-rs doesn't wrap.
+//!
+//! The webui-rs `Event` only carries the element name, so arguments are read
+//! through the C library's interface functions using the event number.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use webui_rs::webui;
+
+unsafe extern "C" {
+    fn webui_interface_get_string_at(window: usize, event_number: usize, index: usize) -> *const c_char;
+    fn webui_interface_set_response(window: usize, event_number: usize, response: *const c_char);
+    fn webui_set_size(window: usize, width: u32, height: u32);
+    fn webui_set_minimum_size(window: usize, width: u32, height: u32);
+    fn webui_set_resizable(window: usize, status: bool);
+    fn webui_set_frameless(window: usize, status: bool);
+    fn webui_maximize(window: usize);
+    fn webui_exit();
+}
+
+/// What viewmodels need from a window: an ID to attach state to and a way
+/// to bind handlers. Implemented by `webui::Window`, and by
+/// `testing::MockWindow` so handlers can be called without a browser.
+pub trait BindSurface {
+    fn id(&self) -> usize;
+    fn bind(&mut self, name: &str, handler: fn(webui::Event));
+}
+
+impl BindSurface for webui::Window {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn bind(&mut self, name: &str, handler: fn(webui::Event)) {
+        webui::Window::bind(self, name, handler);
+    }
+}
+
+/// Returns the argument at `index` as a string, if one was passed.
+pub fn event_arg(event: &webui::Event, index: usize) -> Option<String> {
+    #[cfg(test)]
+    if let Some(arg) = crate::testing::mock_arg(event, index) {
+        return arg;
+    }
+    let ptr = unsafe { webui_interface_get_string_at(event.window, event.event_number, index) };
+    if ptr.is_null() {
+        return None;
+    }
+    let value = unsafe { CStr::from_ptr(ptr) }.to_string_lossy().to_string();
+    if value.is_empty() { None } else { Some(value) }
+}
+
+/// Parses the argument at `index` as JSON, returning `Value::Null` when absent
+/// or malformed.
+pub fn event_json(event: &webui::Event, index: usize) -> serde_json::Value {
+    event_arg(event, index)
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or(serde_json::Value::Null)
+}
+
+/// Resolves the page's `webui.call` promise with `response` as JSON text.
+/// Only takes effect before the handler returns.
+pub fn event_respond(event: &webui::Event, response: &serde_json::Value) {
+    let text = response.to_string();
+    #[cfg(test)]
+    if crate::testing::mock_respond(event, &text) {
+        return;
+    }
+    // JSON text never contains a NUL byte; `to_string` escapes them.
+    let Ok(text) = CString::new(text) else { return };
+    unsafe { webui_interface_set_response(event.window, event.event_number, text.as_ptr()) }
+}
+
+pub fn set_window_size(window: &webui::Window, width: u32, height: u32) {
+    unsafe { webui_set_size(window.id, width, height) }
+}
+
+pub fn set_window_minimum_size(window: &webui::Window, width: u32, height: u32) {
+    unsafe { webui_set_minimum_size(window.id, width, height) }
+}
+
+pub fn set_window_resizable(window: &webui::Window, resizable: bool) {
+    unsafe { webui_set_resizable(window.id, resizable) }
+}
+
+/// Must be called before the window is shown.
+pub fn set_window_frameless(window: &webui::Window, frameless: bool) {
+    unsafe { webui_set_frameless(window.id, frameless) }
+}
+
+pub fn maximize_window(window: &webui::Window) {
+    unsafe { webui_maximize(window.id) }
+}
+
+/// Closes every window, making `webui::wait()` return.
+pub fn exit_webui() {
+    unsafe { webui_exit() }
+}
@@ -1,4 +1,5 @@
 use crate::build_logger::progress::{BuildProgress, StepStatus};
+use std::io::{self, Write};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Instant;
 
@@ -69,14 +70,7 @@ pub fn print_progress_bar(progress: &BuildProgress) {
 
     println!("\n=== Build Progress ===");
     for (i, step) in progress.steps.iter().enumerate() {
-        let status_char = match step.status {
-            StepStatus::Completed => "✓",
-            StepStatus::Failed => "✗",
-            StepStatus::InProgress => "▶",
-            StepStatus::Pending => "○",
-            StepStatus::Skipped => "⊘",
-        };
-        println!("  {} {}", status_char, step.name);
+        println!("  {} {}", status_char(&step.status), step.name);
         if let Some(ref msg) = in_progress {
             if msg.name == step.name && !step.message.is_empty() {
                 println!("    └─ {}", step.message);
@@ -91,6 +85,83 @@ pub fn print_progress_bar(progress: &BuildProgress) {
     );
 }
 
+fn status_char(status: &StepStatus) -> &'static str {
+    match status {
+        StepStatus::Completed => "✓",
+        StepStatus::Failed => "✗",
+        StepStatus::InProgress => "▶",
+        StepStatus::Pending => "○",
+        StepStatus::Skipped => "⊘",
+    }
+}
+
+/// One line per step plus an overall bar, as drawn by [`LiveDisplay`].
+pub fn progress_lines(progress: &BuildProgress) -> Vec<String> {
+    let width = progress.steps.iter().map(|s| s.name.len()).max().unwrap_or(0);
+    let mut lines: Vec<String> = progress
+        .steps
+        .iter()
+        .map(|step| {
+            let mut line = format!("  {} {:<width$}", status_char(&step.status), step.name, width = width);
+            if !step.message.is_empty() {
+                line.push_str("  ");
+                line.push_str(&step.message);
+            }
+            if let (Some(start), Some(end)) = (step.start_time, step.end_time) {
+                line.push_str(&format!(" ({}ms)", end.duration_since(start).as_millis()));
+            }
+            line.trim_end().to_string()
+        })
+        .collect();
+
+    let done = progress
+        .steps
+        .iter()
+        .filter(|s| !matches!(s.status, StepStatus::Pending | StepStatus::InProgress))
+        .count();
+    let mut bar = ProgressBar::new(progress.steps.len());
+    bar.set(done);
+    let current = progress.get_current_step_name().unwrap_or_default();
+    lines.push(bar.render(&current));
+    lines
+}
+
+/// Redraws a block of lines in place on a terminal. Output written through
+/// [`LiveDisplay::print_above`] scrolls up above the block instead of
+/// tearing it.
+#[derive(Debug, Default)]
+pub struct LiveDisplay {
+    lines: Vec<String>,
+}
+
+impl LiveDisplay {
+    fn clear(&self, out: &mut impl Write) -> io::Result<()> {
+        if !self.lines.is_empty() {
+            write!(out, "\x1b[{}A\x1b[J", self.lines.len())?;
+        }
+        Ok(())
+    }
+
+    fn draw(&self, out: &mut impl Write) -> io::Result<()> {
+        for line in &self.lines {
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()
+    }
+
+    pub fn update(&mut self, out: &mut impl Write, progress: &BuildProgress) -> io::Result<()> {
+        self.clear(out)?;
+        self.lines = progress_lines(progress);
+        self.draw(out)
+    }
+
+    pub fn print_above(&mut self, out: &mut impl Write, text: &[u8]) -> io::Result<()> {
+        self.clear(out)?;
+        out.write_all(text)?;
+        self.draw(out)
+    }
+}
+
 pub fn print_step_completed(step_name: &str, duration_ms: u64, message: &str) {
     println!(
         "[✓] {} completed in {}ms - {}",
@@ -1,5 +1,16 @@
+//! Step progress for the startup sequence and the `build-runner` binary.
+//! `progress` and `display` are standalone; `tracker` drives the global
+//! startup run and reports it on the event bus; `stats` counts log records.
+
 pub mod display;
 pub mod progress;
+pub mod stats;
+pub mod tracker;
 
-pub use display::{print_progress_bar, print_step_completed, print_step_failed, ProgressBar};
-pub use progress::{BuildProgress, BuildStep, StepStatus};
+pub use display::{print_progress_bar, print_step_completed, print_step_failed, progress_lines, LiveDisplay, ProgressBar};
+pub use progress::{BuildProgress, BuildStep, BuildSummary, StepStatus, StepSummary};
+pub use stats::{log_summary, BuildStats, ErrorSample, LogStatsLayer, LogSummary};
+pub use tracker::{
+    build_progress_snapshot, build_summary, complete_step, enable_live_progress, fail_step, finish_build_progress,
+    init_build_progress, live_progress_active, print_above_progress, record_step, start_step,
+};
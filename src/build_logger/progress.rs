@@ -1,3 +1,4 @@
+use serde::Serialize;
 use std::time::Instant;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -19,6 +20,34 @@ pub struct BuildStep {
     pub progress_percent: f32,
 }
 
+impl BuildStep {
+    fn duration_ms(&self) -> Option<u64> {
+        match (self.start_time, self.end_time) {
+            (Some(start), Some(end)) => Some(end.duration_since(start).as_millis() as u64),
+            (Some(start), None) => Some(start.elapsed().as_millis() as u64),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StepSummary {
+    pub name: String,
+    pub status: String,
+    pub message: String,
+    pub duration_ms: Option<u64>,
+}
+
+/// Serializable view of a [`BuildProgress`], for events and build artifacts.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildSummary {
+    pub success: bool,
+    pub progress: f32,
+    pub summary: String,
+    pub elapsed_ms: u64,
+    pub steps: Vec<StepSummary>,
+}
+
 #[derive(Debug)]
 pub struct BuildProgress {
     pub steps: Vec<BuildStep>,
@@ -70,6 +99,20 @@ impl BuildProgress {
         }
     }
 
+    /// Completes a step that ran between `start` and `end`, for work done
+    /// before the run was tracked. The run is treated as starting no later
+    /// than `start`.
+    pub fn record_step(&mut self, name: &str, start: Instant, end: Instant, message: &str) {
+        if let Some(step) = self.steps.iter_mut().find(|s| s.name == name) {
+            step.start_time = Some(start);
+            self.start_time = self.start_time.min(start);
+        }
+        self.complete_step(name, message);
+        if let Some(step) = self.steps.iter_mut().find(|s| s.name == name) {
+            step.end_time = Some(end);
+        }
+    }
+
     pub fn fail_step(&mut self, name: &str, message: &str) {
         if let Some(step) = self.steps.iter_mut().find(|s| s.name == name) {
             step.status = StepStatus::Failed;
@@ -79,12 +122,22 @@ impl BuildProgress {
         }
     }
 
+    pub fn skip_step(&mut self, name: &str, message: &str) {
+        if let Some(step) = self.steps.iter_mut().find(|s| s.name == name) {
+            step.status = StepStatus::Skipped;
+            step.message = message.to_string();
+            self.current_step += 1;
+        }
+    }
+
     pub fn update_progress(&mut self, name: &str, percent: f32) {
         if let Some(step) = self.steps.iter_mut().find(|s| s.name == name) {
             step.progress_percent = percent;
         }
     }
 
+    /// Percentage of the run that is done. Skipped steps count as done, and
+    /// in-progress steps contribute their own percentage.
     pub fn get_overall_progress(&self) -> f32 {
         if self.total_steps == 0 {
             return 0.0;
@@ -92,7 +145,7 @@ impl BuildProgress {
         let completed = self
             .steps
             .iter()
-            .filter(|s| s.status == StepStatus::Completed)
+            .filter(|s| matches!(s.status, StepStatus::Completed | StepStatus::Skipped))
             .count() as f32;
         let in_progress = self
             .steps
@@ -145,6 +198,31 @@ impl BuildProgress {
             .map(|s| s.name.clone())
     }
 
+    /// Time spent in a step so far, or `None` if it never started.
+    pub fn step_duration_ms(&self, name: &str) -> Option<u64> {
+        self.get_step(name).and_then(BuildStep::duration_ms)
+    }
+
+    pub fn summary(&self) -> BuildSummary {
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| StepSummary {
+                name: step.name.clone(),
+                status: format!("{:?}", step.status).to_lowercase(),
+                message: step.message.clone(),
+                duration_ms: step.duration_ms(),
+            })
+            .collect();
+        BuildSummary {
+            success: !self.steps.iter().any(|s| s.status == StepStatus::Failed),
+            progress: self.get_overall_progress(),
+            summary: self.get_status_summary(),
+            elapsed_ms: self.start_time.elapsed().as_millis() as u64,
+            steps,
+        }
+    }
+
     pub fn reset(&mut self) {
         *self = Self::new();
     }
@@ -155,3 +233,64 @@ impl Default for BuildProgress {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(steps: &[&str]) -> BuildProgress {
+        let mut progress = BuildProgress::new();
+        progress.init_steps(steps.to_vec());
+        progress
+    }
+
+    #[test]
+    fn overall_progress_counts_finished_and_partial_steps() {
+        let mut progress = progress(&["a", "b", "c", "d"]);
+        assert_eq!(progress.get_overall_progress(), 0.0);
+
+        progress.start_step("a");
+        progress.complete_step("a", "done");
+        progress.skip_step("b", "not needed");
+        progress.start_step("c");
+        progress.update_progress("c", 50.0);
+        assert_eq!(progress.get_overall_progress(), 62.5);
+        assert_eq!(progress.get_current_step_name().as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn unknown_steps_are_ignored() {
+        let mut progress = progress(&["a"]);
+        progress.complete_step("missing", "done");
+        assert_eq!(progress.current_step, 0);
+        assert_eq!(progress.get_step("a").map(|s| &s.status), Some(&StepStatus::Pending));
+    }
+
+    #[test]
+    fn summary_reports_failure_and_durations() {
+        let mut progress = progress(&["a", "b", "c"]);
+        progress.start_step("a");
+        progress.complete_step("a", "ok");
+        progress.start_step("b");
+        progress.fail_step("b", "exit 1");
+
+        let summary = progress.summary();
+        assert!(!summary.success);
+        assert_eq!(summary.summary, "1/3 completed, 1 failed, 1 pending, 0 in progress");
+        let statuses: Vec<&str> = summary.steps.iter().map(|s| s.status.as_str()).collect();
+        assert_eq!(statuses, ["completed", "failed", "pending"]);
+        assert!(summary.steps[0].duration_ms.is_some());
+        assert_eq!(summary.steps[2].duration_ms, None);
+        assert_eq!(progress.step_duration_ms("b"), summary.steps[1].duration_ms);
+    }
+
+    #[test]
+    fn summary_succeeds_without_failed_steps() {
+        let mut progress = progress(&["a", "b"]);
+        progress.complete_step("a", "ok");
+        progress.skip_step("b", "skipped");
+        let summary = progress.summary();
+        assert!(summary.success);
+        assert_eq!(summary.progress, 100.0);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::core::log_stream::MessageVisitor;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+const MAX_SAMPLES: usize = 20;
+
+/// Record counts per level since startup.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuildStats {
+    pub errors: u64,
+    pub warnings: u64,
+    pub info: u64,
+    pub debug: u64,
+    pub trace: u64,
+}
+
+impl BuildStats {
+    pub fn increment(&mut self, level: &Level) {
+        let count = match *level {
+            Level::ERROR => &mut self.errors,
+            Level::WARN => &mut self.warnings,
+            Level::INFO => &mut self.info,
+            Level::DEBUG => &mut self.debug,
+            Level::TRACE => &mut self.trace,
+        };
+        *count += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.errors + self.warnings + self.info + self.debug + self.trace
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorSample {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Counts plus the most recent warnings and errors, newest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct LogSummary {
+    pub since: String,
+    pub total: u64,
+    pub counts: BuildStats,
+    pub recent_errors: Vec<ErrorSample>,
+}
+
+struct StatsState {
+    since: String,
+    stats: BuildStats,
+    samples: VecDeque<ErrorSample>,
+}
+
+lazy_static! {
+    static ref STATE: Mutex<StatsState> = Mutex::new(StatsState {
+        since: chrono::Utc::now().to_rfc3339(),
+        stats: BuildStats::default(),
+        samples: VecDeque::with_capacity(MAX_SAMPLES),
+    });
+}
+
+pub fn log_summary() -> LogSummary {
+    let state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    LogSummary {
+        since: state.since.clone(),
+        total: state.stats.total(),
+        counts: state.stats.clone(),
+        recent_errors: state.samples.iter().rev().cloned().collect(),
+    }
+}
+
+/// Tracing layer feeding [`log_summary`]. Only sees records that pass the
+/// active log filter.
+pub struct LogStatsLayer;
+
+impl<S: Subscriber> Layer<S> for LogStatsLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let sample = (*metadata.level() <= Level::WARN).then(|| ErrorSample {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: MessageVisitor::format(event),
+        });
+
+        let Ok(mut state) = STATE.lock() else {
+            return;
+        };
+        state.stats.increment(metadata.level());
+        if let Some(sample) = sample {
+            if state.samples.len() == MAX_SAMPLES {
+                state.samples.pop_front();
+            }
+            state.samples.push_back(sample);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::build_logger::display::LiveDisplay;
+use crate::build_logger::progress::{BuildProgress, BuildSummary, StepStatus};
+use crate::event_bus::EventBus;
+use lazy_static::lazy_static;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::error;
+
+lazy_static! {
+    static ref TRACKED: Mutex<Option<(String, BuildProgress, EventBus)>> = Mutex::new(None);
+    /// Set while the run is drawn live on the terminal. Always locked after
+    /// `TRACKED`, never before it.
+    static ref LIVE: Mutex<Option<LiveDisplay>> = Mutex::new(None);
+}
+
+const SOURCE: &str = "build_logger";
+
+/// Starts tracking a run of named steps and emits `build.started` on `bus`.
+/// Step changes then emit `build.progress` with the overall percentage. Must
+/// be called inside the runtime.
+pub fn init_build_progress(build_id: &str, steps: &[&str], bus: &EventBus) {
+    let mut progress = BuildProgress::new();
+    progress.init_steps(steps.to_vec());
+    redraw(&progress);
+    if let Ok(mut tracked) = TRACKED.lock() {
+        *tracked = Some((build_id.to_string(), progress, bus.clone()));
+    }
+    let build_id = build_id.to_string();
+    let bus = bus.clone();
+    tokio::spawn(async move {
+        if let Err(e) = bus.emit_build_started(&build_id, SOURCE).await {
+            error!("Failed to emit build started event: {}", e);
+        }
+    });
+}
+
+fn update(step: &str, apply: impl FnOnce(&mut BuildProgress)) {
+    let Ok(mut tracked) = TRACKED.lock() else {
+        return;
+    };
+    let Some((build_id, progress, bus)) = tracked.as_mut() else {
+        return;
+    };
+    apply(progress);
+    redraw(progress);
+    let build_id = build_id.clone();
+    let bus = bus.clone();
+    let step = step.to_string();
+    let percent = progress.get_overall_progress();
+    tokio::spawn(async move {
+        if let Err(e) = bus.emit_build_progress(&build_id, &step, percent, SOURCE).await {
+            error!("Failed to emit build progress event: {}", e);
+        }
+    });
+}
+
+pub fn start_step(name: &str) {
+    update(name, |progress| progress.start_step(name));
+}
+
+pub fn complete_step(name: &str, message: &str) {
+    update(name, |progress| progress.complete_step(name, message));
+}
+
+/// Completes a step that finished before [`init_build_progress`] was called.
+pub fn record_step(name: &str, start: Instant, end: Instant, message: &str) {
+    update(name, |progress| progress.record_step(name, start, end, message));
+}
+
+pub fn fail_step(name: &str, message: &str) {
+    update(name, |progress| progress.fail_step(name, message));
+}
+
+/// Draws the tracked run in place on stdout, starting with the next
+/// [`init_build_progress`]. Console logging below WARN is held back while the
+/// display is live; the display ends once every step is done or one fails.
+pub fn enable_live_progress() {
+    if let Ok(mut live) = LIVE.lock() {
+        *live = Some(LiveDisplay::default());
+    }
+}
+
+pub fn live_progress_active() -> bool {
+    LIVE.lock().map(|live| live.is_some()).unwrap_or(false)
+}
+
+/// Writes `text` above the live display. Returns false, writing nothing,
+/// when no display is live.
+pub fn print_above_progress(text: &[u8]) -> bool {
+    let Ok(mut live) = LIVE.lock() else {
+        return false;
+    };
+    let Some(display) = live.as_mut() else {
+        return false;
+    };
+    let _ = display.print_above(&mut io::stdout().lock(), text);
+    true
+}
+
+fn redraw(progress: &BuildProgress) {
+    let Ok(mut live) = LIVE.lock() else {
+        return;
+    };
+    let Some(display) = live.as_mut() else {
+        return;
+    };
+    let mut stdout = io::stdout().lock();
+    let _ = display.update(&mut stdout, progress);
+    let failed = progress.steps.iter().any(|s| s.status == StepStatus::Failed);
+    let finished = progress
+        .steps
+        .iter()
+        .all(|s| !matches!(s.status, StepStatus::Pending | StepStatus::InProgress));
+    if failed || finished {
+        let _ = stdout.flush();
+        *live = None;
+    }
+}
+
+/// Emits `build.completed`; the run succeeded if no step failed. The
+/// snapshot stays available afterwards.
+pub fn finish_build_progress() {
+    if let Ok(mut live) = LIVE.lock() {
+        *live = None;
+    }
+    let Ok(tracked) = TRACKED.lock() else {
+        return;
+    };
+    let Some((build_id, progress, bus)) = tracked.as_ref() else {
+        return;
+    };
+    let build_id = build_id.clone();
+    let summary = progress.summary();
+    let bus = bus.clone();
+    tokio::spawn(async move {
+        if let Err(e) = bus.emit_build_completed(&build_id, summary.success, summary.elapsed_ms, SOURCE).await {
+            error!("Failed to emit build completed event: {}", e);
+        }
+    });
+}
+
+pub fn build_summary() -> Option<BuildSummary> {
+    let tracked = TRACKED.lock().ok()?;
+    tracked.as_ref().map(|(_, progress, _)| progress.summary())
+}
+
+/// Current state of the tracked run, for clients that connect mid-way.
+pub fn build_progress_snapshot() -> Value {
+    let Ok(tracked) = TRACKED.lock() else {
+        return Value::Null;
+    };
+    let Some((build_id, progress, _)) = tracked.as_ref() else {
+        return Value::Null;
+    };
+    let mut snapshot = serde_json::to_value(progress.summary()).unwrap_or_default();
+    if let Value::Object(fields) = &mut snapshot {
+        fields.insert("build_id".to_string(), json!(build_id));
+    }
+    snapshot
+}
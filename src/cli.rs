@@ -0,0 +1,83 @@
+use crate::core::{AppConfig, AppResult};
+use clap::Parser;
+use std::path::PathBuf;
+
+/// Command-line options; anything given here overrides `app.config.toml`.
+#[derive(Debug, Clone, Parser)]
+#[command(name = env!("CARGO_PKG_NAME"), version, about = "Rust WebUI application")]
+pub struct Cli {
+    /// Path to a .toml, .json or .yaml config file (defaults to app.config.toml or $APP_CONFIG)
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Port for the internal HTTP server (overrides `server.port`; defaults to the last used port, or a free port in `server.port_range`)
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub port: Option<u16>,
+
+    /// Path to the SQLite database file
+    #[arg(long, value_name = "FILE")]
+    pub db: Option<String>,
+
+    /// Run the backend without opening a window
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Log level or filter directive, e.g. `debug` or `rustwebui_app=trace`
+    #[arg(long, value_name = "LEVEL")]
+    pub log_level: Option<String>,
+
+    /// Hide the startup progress display and only log warnings and errors to the console
+    #[arg(long, short)]
+    pub quiet: bool,
+
+    /// Delete the database and plugin data before starting
+    #[arg(long)]
+    pub reset_data: bool,
+
+    /// Store a secret in the OS keyring (value read from stdin) and exit;
+    /// reference it from config as `keyring:<NAME>`
+    #[arg(long, value_name = "NAME")]
+    pub set_secret: Option<String>,
+
+    /// Remove a secret from the OS keyring and exit
+    #[arg(long, value_name = "NAME")]
+    pub delete_secret: Option<String>,
+
+    /// Generate a new plugin skeleton and exit
+    #[arg(long, value_name = "NAME")]
+    pub new_plugin: Option<String>,
+
+    /// Project directory `--new-plugin` writes into (defaults to the working directory)
+    #[arg(long, value_name = "DIR", requires = "new_plugin")]
+    pub project_dir: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn load_config(&self) -> AppResult<AppConfig> {
+        let mut config = match &self.config {
+            Some(path) => AppConfig::load_from(path)?,
+            None => AppConfig::load()?,
+        };
+        self.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Finishes a freshly loaded config: resolves data paths, then applies
+    /// the command-line overrides (whose paths stay relative to the working directory).
+    pub fn apply(&self, config: &mut AppConfig) {
+        config.resolve_data_paths();
+        if let Some(db) = &self.db {
+            config.database.path = db.clone();
+            config.origins.insert("database.path".to_string(), "cli:--db".to_string());
+        }
+        if let Some(level) = &self.log_level {
+            config.logging.level = level.clone();
+            config.origins.insert("logging.level".to_string(), "cli:--log-level".to_string());
+        }
+        if self.headless {
+            config.app.headless = Some(true);
+            config.origins.insert("app.headless".to_string(), "cli:--headless".to_string());
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Watches the loaded config file and applies safe-to-change settings at
+//! runtime. Changes that need a restart are reported but not applied.
+
+use crate::core::supervisor::Task;
+use crate::core::{log_stream, perf, resources, set_log_level, AppConfig};
+use crate::plugins::PluginRegistry;
+use crate::viewmodels::window::WindowRegistry;
+use crate::viewmodels::set_effective_config;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+
+/// Keys that can change without a restart; entries ending in `.` match a whole section.
+const RELOADABLE_KEYS: &[&str] = &[
+    "logging.level",
+    "logging.stream_level",
+    "logging.stream_sample_every",
+    "window.title",
+    "monitoring.",
+];
+
+type Overrides = Box<dyn Fn(&mut AppConfig) + Send + Sync>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigChange {
+    pub key: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+pub fn is_reloadable(key: &str) -> bool {
+    RELOADABLE_KEYS.iter().any(|allowed| match allowed.strip_suffix('.') {
+        Some(section) => key.strip_prefix(section).is_some_and(|rest| rest.starts_with('.')),
+        None => key == *allowed,
+    })
+}
+
+/// Lists every dotted key whose value differs between two configs.
+pub fn diff(old: &AppConfig, new: &AppConfig) -> Vec<ConfigChange> {
+    let mut old_keys = BTreeMap::new();
+    let mut new_keys = BTreeMap::new();
+    flatten("", &old.redacted(), &mut old_keys);
+    flatten("", &new.redacted(), &mut new_keys);
+
+    let mut keys: Vec<&String> = old_keys.keys().chain(new_keys.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter(|key| old_keys.get(*key) != new_keys.get(*key))
+        .map(|key| ConfigChange {
+            key: key.clone(),
+            old: old_keys.get(key).cloned(),
+            new: new_keys.get(key).cloned(),
+        })
+        .collect()
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                flatten(&path, value, out);
+            }
+        }
+        Value::Null => {}
+        other => {
+            out.insert(prefix.to_string(), other.clone());
+        }
+    }
+}
+
+pub struct ConfigWatcher {
+    path: PathBuf,
+    current: AppConfig,
+    registry: Arc<PluginRegistry>,
+    windows: WindowRegistry,
+    overrides: Overrides,
+}
+
+impl ConfigWatcher {
+    /// `overrides` re-applies command-line options on every reload.
+    pub fn new(
+        path: PathBuf,
+        current: AppConfig,
+        registry: Arc<PluginRegistry>,
+        overrides: impl Fn(&mut AppConfig) + Send + Sync + 'static,
+    ) -> Self {
+        Self { path, current, registry, windows: WindowRegistry::default(), overrides: Box::new(overrides) }
+    }
+
+    /// Window that gets `window.title` changes.
+    pub fn with_windows(mut self, windows: WindowRegistry) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    /// Polls the file as the supervised `config_watcher` task.
+    pub fn watch(self, poll_interval: Duration) {
+        info!("Watching {:?} for configuration changes", self.path);
+        let task = Task::new("config_watcher").with_events(self.registry.bus());
+        let watcher = Arc::new(tokio::sync::Mutex::new(self));
+        task.spawn(move |_| {
+            let watcher = Arc::clone(&watcher);
+            async move {
+                let mut watcher = watcher.lock().await;
+                let mut last_modified = modified_time(&watcher.path);
+                let mut interval = tokio::time::interval(poll_interval);
+                loop {
+                    interval.tick().await;
+                    let modified = modified_time(&watcher.path);
+                    if modified.is_none() || modified == last_modified {
+                        continue;
+                    }
+                    last_modified = modified;
+                    watcher.reload().await;
+                }
+            }
+        });
+    }
+
+    async fn reload(&mut self) {
+        let new = match AppConfig::load_from(&self.path).and_then(|mut new| {
+            (self.overrides)(&mut new);
+            new.validate().map(|()| new)
+        }) {
+            Ok(new) => new,
+            Err(e) => {
+                error!("Failed to reload configuration: {}", e);
+                self.emit("config.reload_failed", e.to_payload()).await;
+                return;
+            }
+        };
+
+        let (applied, rejected): (Vec<_>, Vec<_>) =
+            diff(&self.current, &new).into_iter().partition(|change| is_reloadable(&change.key));
+        self.apply(new);
+
+        if !applied.is_empty() {
+            info!("Applied configuration changes: {:?}", applied.iter().map(|c| &c.key).collect::<Vec<_>>());
+            self.emit("config.changed", json!({ "changes": applied })).await;
+        }
+        if !rejected.is_empty() {
+            let keys: Vec<&str> = rejected.iter().map(|c| c.key.as_str()).collect();
+            warn!("Configuration changes require a restart and were not applied: {:?}", keys);
+            self.emit(
+                "config.restart_required",
+                json!({
+                    "changes": rejected,
+                    "message": format!("Restart the application to apply: {}", keys.join(", ")),
+                }),
+            )
+            .await;
+        }
+    }
+
+    fn apply(&mut self, new: AppConfig) {
+        if new.logging.stream_level != self.current.logging.stream_level
+            || new.logging.stream_sample_every != self.current.logging.stream_sample_every
+        {
+            match log_stream::set_stream_filter(new.get_log_stream_level(), new.get_log_stream_sample_every()) {
+                Ok(()) => {
+                    self.current.logging.stream_level = new.logging.stream_level;
+                    self.current.logging.stream_sample_every = new.logging.stream_sample_every;
+                }
+                Err(e) => error!("Invalid log stream settings: {}", e),
+            }
+        }
+        if new.logging.level != self.current.logging.level {
+            match set_log_level(&new.logging.level) {
+                Ok(()) => self.current.logging.level = new.logging.level,
+                Err(e) => error!("Invalid log level '{}': {}", new.logging.level, e),
+            }
+        }
+        if new.window.title != self.current.window.title {
+            self.windows.set_title(&new.window.title);
+            self.current.window.title = new.window.title;
+        }
+        self.current.monitoring = new.monitoring;
+        perf::set_thresholds(self.current.get_slow_handler_threshold(), self.current.get_slow_query_threshold());
+        resources::configure(&self.current);
+        self.registry.notify_config_changed(&self.current);
+        set_effective_config(&self.current);
+    }
+
+    /// Emits on the plugins' bus, which is the app's.
+    async fn emit(&self, name: &str, payload: Value) {
+        if let Err(e) = self.registry.bus().emit_custom(name, payload, "config_watcher").await {
+            error!("Failed to emit {} event: {}", name, e);
+        }
+    }
+}
+
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_listed_keys_and_sections_are_reloadable() {
+        for key in [
+            "logging.level",
+            "logging.stream_level",
+            "logging.stream_sample_every",
+            "window.title",
+            "monitoring.power_poll_interval_secs",
+        ] {
+            assert!(is_reloadable(key), "{}", key);
+        }
+        for key in ["logging.file", "logging.store_level", "window.width", "monitoring", "monitoringx.a", "database.path"] {
+            assert!(!is_reloadable(key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn diff_lists_changed_keys_in_order() {
+        let old = AppConfig::default();
+        let mut new = old.clone();
+        new.window.title = "Renamed".to_string();
+        new.database.path = "other.db".to_string();
+        new.monitoring.resource_poll_interval_secs = Some(30);
+
+        let changes = diff(&old, &new);
+        let keys: Vec<&str> = changes.iter().map(|change| change.key.as_str()).collect();
+        assert_eq!(keys, ["database.path", "monitoring.resource_poll_interval_secs", "window.title"]);
+        assert_eq!(changes[2].old, Some(Value::from("Rust WebUI Application")));
+        assert_eq!(changes[2].new, Some(Value::from("Renamed")));
+        assert!(diff(&old, &old.clone()).is_empty());
+    }
+}
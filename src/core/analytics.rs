@@ -0,0 +1,418 @@
+//! Opt-in usage analytics: how often each WebUI handler is called, which
+//! features the page reports using, and how many panics occurred. Nothing is
+//! recorded until the user consents. Counts carry no arguments or user data;
+//! they are queued in `analytics_queue` and sent with a random install ID.
+
+use super::config::AnalyticsSettings;
+use super::database::prepare_cached;
+use super::supervisor::Task;
+use super::{AppError, AppResult, Database};
+use crate::event_bus::EventBus;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+/// `app_settings` key holding `granted` or `denied`.
+pub const CONSENT_SETTING: &str = "analytics.consent";
+
+/// `app_settings` key of the random ID reports are sent under. Removed when
+/// consent is withdrawn, so a later opt-in starts a new identity.
+const INSTALL_ID_SETTING: &str = "analytics.install_id";
+
+/// Supervised task that queues and uploads counts.
+pub const ANALYTICS_TASK: &str = "analytics";
+
+/// How often in-memory counts are moved into the queue.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Queued rows sent per report.
+const MAX_REPORT_ROWS: i64 = 1000;
+
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static CRASHES: AtomicU64 = AtomicU64::new(0);
+static COUNTS: Lazy<Mutex<HashMap<(UsageKind, String), u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageKind {
+    Command,
+    Feature,
+    Crash,
+}
+
+impl UsageKind {
+    fn label(self) -> &'static str {
+        match self {
+            UsageKind::Command => "command",
+            UsageKind::Feature => "feature",
+            UsageKind::Crash => "crash",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "command" => Some(UsageKind::Command),
+            "feature" => Some(UsageKind::Feature),
+            "crash" => Some(UsageKind::Crash),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Consent {
+    /// The user has not been asked yet; treated as denied.
+    Unknown,
+    Granted,
+    Denied,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageCount {
+    pub kind: UsageKind,
+    pub name: String,
+    pub count: u64,
+}
+
+/// What an uploader receives: queued counts summed per name.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageReport {
+    pub install_id: String,
+    pub app_version: String,
+    pub os: &'static str,
+    /// Unix millis of the oldest and newest queued count in the report.
+    pub from: i64,
+    pub to: i64,
+    pub usage: Vec<UsageCount>,
+}
+
+/// Where usage reports go. A failed upload leaves the counts queued for the
+/// next attempt.
+pub trait UsageUploader: Send + Sync {
+    fn upload(&self, report: &UsageReport) -> io::Result<()>;
+}
+
+/// POSTs reports as JSON to a plain `http://` endpoint and expects a 2xx
+/// answer.
+pub struct HttpUploader {
+    host: String,
+    address: String,
+    path: String,
+}
+
+impl HttpUploader {
+    pub fn new(endpoint: &str) -> AppResult<Self> {
+        let rest = endpoint
+            .strip_prefix("http://")
+            .ok_or_else(|| AppError::Config(format!("analytics.endpoint: expected an http:// URL, got \"{}\"", endpoint)))?;
+        let (host, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let address = if host.rsplit(']').next().is_some_and(|port| port.contains(':')) {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+        Ok(Self { host: host.to_string(), address, path: path.to_string() })
+    }
+}
+
+impl UsageUploader for HttpUploader {
+    fn upload(&self, report: &UsageReport) -> io::Result<()> {
+        let body = serde_json::to_vec(report)?;
+        let mut stream = TcpStream::connect(&self.address)?;
+        stream.set_read_timeout(Some(UPLOAD_TIMEOUT))?;
+        stream.set_write_timeout(Some(UPLOAD_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.host,
+            body.len()
+        )?;
+        stream.write_all(&body)?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line)?;
+        let status = status_line.split_whitespace().nth(1).and_then(|s| s.parse::<u16>().ok());
+        match status {
+            Some(200..=299) => Ok(()),
+            _ => Err(io::Error::other(format!("endpoint answered \"{}\"", status_line.trim()))),
+        }
+    }
+}
+
+/// Counts one call of a WebUI handler. Called by [`super::perf::handler`].
+pub fn record_command(name: &str) {
+    record(UsageKind::Command, name);
+}
+
+/// Counts one use of a feature reported by the page, e.g. `log_search`.
+pub fn record_feature(name: &str) {
+    record(UsageKind::Feature, name);
+}
+
+/// Counts a panic. Lock-free, since it runs inside the panic hook.
+pub fn record_crash() {
+    if ENABLED.load(Ordering::Relaxed) {
+        CRASHES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn record(kind: UsageKind, name: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let Ok(mut counts) = COUNTS.lock() else {
+        return;
+    };
+    *counts.entry((kind, name.to_string())).or_default() += 1;
+}
+
+pub fn consent(db: &Database) -> AppResult<Consent> {
+    Ok(match db.get_setting(CONSENT_SETTING)?.as_deref() {
+        Some("granted") => Consent::Granted,
+        Some("denied") => Consent::Denied,
+        _ => Consent::Unknown,
+    })
+}
+
+/// Stores the user's answer and starts or stops recording. Withdrawing
+/// consent drops everything recorded so far and the install ID.
+pub fn set_consent(db: &Database, granted: bool) -> AppResult<()> {
+    db.set_setting(CONSENT_SETTING, if granted { "granted" } else { "denied" })?;
+    ENABLED.store(granted, Ordering::Relaxed);
+    if granted {
+        if db.get_setting(INSTALL_ID_SETTING)?.is_none() {
+            db.set_setting(INSTALL_ID_SETTING, &uuid::Uuid::new_v4().to_string())?;
+        }
+    } else {
+        if let Ok(mut counts) = COUNTS.lock() {
+            counts.clear();
+        }
+        CRASHES.store(0, Ordering::Relaxed);
+        db.delete_setting(INSTALL_ID_SETTING)?;
+        let conn = db.get_connection()?;
+        conn.execute("DELETE FROM analytics_queue", [])?;
+    }
+    info!("Usage analytics {}", if granted { "enabled" } else { "disabled" });
+    Ok(())
+}
+
+/// Moves the counts recorded since the last call into `analytics_queue` and
+/// returns how many rows were added.
+pub fn flush(db: &Database) -> AppResult<usize> {
+    let mut counts: Vec<((UsageKind, String), u64)> = match COUNTS.lock() {
+        Ok(mut counts) => counts.drain().collect(),
+        Err(_) => Vec::new(),
+    };
+    let crashes = CRASHES.swap(0, Ordering::Relaxed);
+    if crashes > 0 {
+        counts.push(((UsageKind::Crash, "panic".to_string()), crashes));
+    }
+    if counts.is_empty() {
+        return Ok(0);
+    }
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut conn = db.get_connection()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = prepare_cached(
+            &tx,
+            "INSERT INTO analytics_queue (recorded_at, kind, name, count) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for ((kind, name), count) in &counts {
+            stmt.execute(rusqlite::params![now, kind.label(), name, *count as i64])?;
+        }
+    }
+    tx.commit()?;
+    Ok(counts.len())
+}
+
+/// Number of rows waiting to be uploaded.
+pub fn queued(db: &Database) -> AppResult<i64> {
+    let conn = db.get_connection()?;
+    Ok(conn.query_row("SELECT COUNT(*) FROM analytics_queue", [], |row| row.get(0))?)
+}
+
+struct QueuedRow {
+    id: i64,
+    recorded_at: i64,
+    kind: String,
+    name: String,
+    count: i64,
+}
+
+fn oldest_queued(db: &Database) -> AppResult<Vec<QueuedRow>> {
+    let conn = db.get_connection()?;
+    let mut stmt =
+        prepare_cached(&conn, "SELECT id, recorded_at, kind, name, count FROM analytics_queue ORDER BY id LIMIT ?1")?;
+    let rows = stmt
+        .query_map([MAX_REPORT_ROWS], |row| {
+            Ok(QueuedRow {
+                id: row.get(0)?,
+                recorded_at: row.get(1)?,
+                kind: row.get(2)?,
+                name: row.get(3)?,
+                count: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Records nothing until consent is granted; then queues counts every minute
+/// and sends them to the uploader, if any, every upload interval.
+pub struct Analytics {
+    db: Arc<Database>,
+    uploader: Option<Arc<dyn UsageUploader>>,
+    upload_interval: Duration,
+}
+
+impl Analytics {
+    /// Uses an [`HttpUploader`] when `analytics.endpoint` is set.
+    pub fn from_config(db: Arc<Database>, settings: &AnalyticsSettings) -> Self {
+        let analytics = Self { db, uploader: None, upload_interval: settings.upload_interval() };
+        match settings.endpoint.as_deref().map(HttpUploader::new) {
+            Some(Ok(uploader)) => analytics.with_uploader(uploader),
+            Some(Err(e)) => {
+                warn!("Usage reports will stay queued: {}", e);
+                analytics
+            }
+            None => analytics,
+        }
+    }
+
+    pub fn with_uploader(mut self, uploader: impl UsageUploader + 'static) -> Self {
+        self.uploader = Some(Arc::new(uploader));
+        self
+    }
+
+    /// Restores the stored consent and runs the supervised `analytics` task,
+    /// reporting its failures on `bus`.
+    pub fn start(self, bus: &EventBus) {
+        let granted = matches!(consent(&self.db), Ok(Consent::Granted));
+        ENABLED.store(granted, Ordering::Relaxed);
+        debug!("Usage analytics {}", if granted { "enabled" } else { "off until consent is given" });
+
+        let analytics = Arc::new(self);
+        Task::new(ANALYTICS_TASK).with_events(bus).spawn(move |ctx| {
+            let analytics = Arc::clone(&analytics);
+            async move {
+                let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+                let mut last_upload = Instant::now();
+                loop {
+                    interval.tick().await;
+                    ctx.heartbeat();
+                    if !ENABLED.load(Ordering::Relaxed) {
+                        continue;
+                    }
+                    let upload = analytics.uploader.is_some() && last_upload.elapsed() >= analytics.upload_interval;
+                    if upload {
+                        last_upload = Instant::now();
+                    }
+                    let tick = Arc::clone(&analytics);
+                    match tokio::task::spawn_blocking(move || tick.tick(upload)).await {
+                        Ok(Ok(0)) => {}
+                        Ok(Ok(sent)) => debug!("Uploaded {} usage count(s)", sent),
+                        Ok(Err(e)) => warn!("Usage counts not queued or uploaded: {}", e),
+                        Err(e) => warn!("Usage analytics tick failed: {}", e),
+                    }
+                }
+            }
+        });
+    }
+
+    /// Queues the latest counts and, when `upload` is set, sends the queue.
+    /// Returns how many rows were sent.
+    fn tick(&self, upload: bool) -> AppResult<usize> {
+        flush(&self.db)?;
+        if upload { self.upload_pending() } else { Ok(0) }
+    }
+
+    /// Sends up to 1000 queued rows as one report and deletes them once the
+    /// uploader accepts it. Returns how many rows were sent.
+    fn upload_pending(&self) -> AppResult<usize> {
+        let Some(uploader) = &self.uploader else {
+            return Ok(0);
+        };
+        let Some(install_id) = self.db.get_setting(INSTALL_ID_SETTING)? else {
+            return Ok(0);
+        };
+
+        let rows = oldest_queued(&self.db)?;
+        let Some(last_id) = rows.last().map(|row| row.id) else {
+            return Ok(0);
+        };
+
+        let mut summed: BTreeMap<(&str, &str), u64> = BTreeMap::new();
+        for row in &rows {
+            *summed.entry((row.kind.as_str(), row.name.as_str())).or_default() += row.count as u64;
+        }
+        let report = UsageReport {
+            install_id,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS,
+            from: rows.iter().map(|row| row.recorded_at).min().unwrap_or_default(),
+            to: rows.iter().map(|row| row.recorded_at).max().unwrap_or_default(),
+            usage: summed
+                .into_iter()
+                .filter_map(|((kind, name), count)| {
+                    Some(UsageCount { kind: UsageKind::from_label(kind)?, name: name.to_string(), count })
+                })
+                .collect(),
+        };
+        uploader.upload(&report)?;
+
+        let conn = self.db.get_connection()?;
+        conn.execute("DELETE FROM analytics_queue WHERE id <= ?1", [last_id])?;
+        Ok(rows.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_uploader_posts_report_as_json() {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let port = server.server_addr().to_ip().unwrap().port();
+        let received = std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let path = request.url().to_string();
+            request.respond(tiny_http::Response::empty(204)).unwrap();
+            (path, body)
+        });
+
+        let report = UsageReport {
+            install_id: "install".to_string(),
+            app_version: "1.0.0".to_string(),
+            os: "linux",
+            from: 1,
+            to: 2,
+            usage: vec![UsageCount { kind: UsageKind::Command, name: "get_users".to_string(), count: 3 }],
+        };
+        let uploader = HttpUploader::new(&format!("http://127.0.0.1:{}/usage", port)).unwrap();
+        uploader.upload(&report).unwrap();
+
+        let (path, body) = received.join().unwrap();
+        assert_eq!(path, "/usage");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["usage"][0]["kind"], "command");
+        assert_eq!(body["usage"][0]["count"], 3);
+    }
+}
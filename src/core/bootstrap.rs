@@ -0,0 +1,74 @@
+//! First-launch detection and bootstrap: writes a default config file when
+//! none exists and records the result for the onboarding UI.
+
+use crate::core::{paths, AppConfig, AppResult};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+static STATE: OnceCell<BootstrapState> = OnceCell::new();
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BootstrapState {
+    pub first_run: bool,
+    pub config_path: Option<PathBuf>,
+    pub created_config: bool,
+    pub data_dir: PathBuf,
+}
+
+/// A launch is the first one when there is no database yet.
+pub fn is_first_run(config: &AppConfig) -> bool {
+    !Path::new(config.get_db_path()).exists()
+}
+
+/// Runs first-launch setup. Call after data paths are prepared and before
+/// the database is opened.
+pub fn bootstrap(config: &mut AppConfig) -> BootstrapState {
+    let first_run = is_first_run(config);
+    let mut state = BootstrapState {
+        first_run,
+        config_path: config.source.clone(),
+        created_config: false,
+        data_dir: PathBuf::from(config.get_plugin_data_dir()),
+    };
+    if !first_run {
+        return state;
+    }
+
+    info!("First run detected; bootstrapping");
+    if config.source.is_none() {
+        match write_default_config() {
+            Ok(path) => {
+                info!("Default configuration written to {:?}", path);
+                config.source = Some(path.clone());
+                state.config_path = Some(path);
+                state.created_config = true;
+            }
+            Err(e) => warn!("Failed to write default configuration: {}", e),
+        }
+    }
+    if let Err(e) = fs::create_dir_all(&state.data_dir) {
+        warn!("Failed to create {:?}: {}", state.data_dir, e);
+    }
+    state
+}
+
+fn write_default_config() -> AppResult<PathBuf> {
+    let dir = paths::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("app.config.toml");
+    let body = toml::to_string_pretty(&AppConfig::default())
+        .map_err(|e| crate::core::AppError::Config(e.to_string()))?;
+    fs::write(&path, format!("# Generated on first run; edit to customize.\n\n{}", body))?;
+    Ok(path)
+}
+
+pub fn install_state(state: BootstrapState) {
+    let _ = STATE.set(state);
+}
+
+pub fn state() -> BootstrapState {
+    STATE.get().cloned().unwrap_or_default()
+}
@@ -0,0 +1,87 @@
+//! Short-lived cache for handler responses that the frontend polls, such as
+//! database stats. Entries expire after their TTL or when an event that
+//! changes the underlying data is emitted.
+
+use crate::event_bus::{EventBus, EventHandler};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+struct Cached {
+    value: Value,
+    expires_at: Instant,
+}
+
+#[derive(Clone, Default)]
+pub struct ResponseCache {
+    entries: Arc<Mutex<HashMap<String, Cached>>>,
+}
+
+impl ResponseCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(cached) if cached.expires_at > Instant::now() => Some(cached.value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: &str, value: Value, ttl: Duration) {
+        let cached = Cached { value, expires_at: Instant::now() + ttl };
+        self.entries.lock().unwrap().insert(key.to_string(), cached);
+    }
+
+    /// The cached value for `key`, or the result of `compute`, cached for `ttl`.
+    pub fn get_or_insert_with(&self, key: &str, ttl: Duration, compute: impl FnOnce() -> Value) -> Value {
+        if let Some(value) = self.get(key) {
+            debug!("Cache hit: {}", key);
+            return value;
+        }
+        let value = compute();
+        self.insert(key, value.clone(), ttl);
+        value
+    }
+
+    /// The cached value for `key`, or the result of `fetch`, which is cached
+    /// for `ttl` when it succeeds.
+    pub fn get_or_try_insert_with<E>(
+        &self,
+        key: &str,
+        ttl: Duration,
+        fetch: impl FnOnce() -> Result<Value, E>,
+    ) -> Result<Value, E> {
+        if let Some(value) = self.get(key) {
+            debug!("Cache hit: {}", key);
+            return Ok(value);
+        }
+        let value = fetch()?;
+        self.insert(key, value.clone(), ttl);
+        Ok(value)
+    }
+
+    pub fn invalidate(&self, key: &str) -> bool {
+        self.entries.lock().unwrap().remove(key).is_some()
+    }
+
+    /// Drops `key` whenever an event matching `pattern` is emitted on `bus`.
+    pub fn invalidate_on(&self, bus: &EventBus, pattern: &str, key: &'static str) {
+        let cache = self.clone();
+        let listener = EventHandler::new(move |event| {
+            if cache.invalidate(key) {
+                debug!("Cache entry {} invalidated by {}", key, event.name);
+            }
+            Box::pin(async { Ok(()) })
+        });
+        bus.subscribe(pattern, Arc::new(listener));
+    }
+}
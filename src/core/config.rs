@@ -1,112 +1,987 @@
-use serde::Deserialize;
+use crate::core::config_migration::{self, CURRENT_CONFIG_VERSION};
+use crate::core::database::{Pragmas, PRAGMA_KEYS, SYNCHRONOUS_MODES};
+use crate::core::{paths, secrets, AppError, AppResult};
+use crate::scheduler::CatchUp;
+use crate::server::port::PORT_RANGE;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::net::{IpAddr, Ipv4Addr};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-#[derive(Debug, Deserialize)]
+/// Prefix for environment overrides: `APP__DATABASE__PATH=/data/app.db`
+/// sets `database.path`. Sections and keys are separated by `__`.
+const ENV_PREFIX: &str = "APP__";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
+    /// Schema version of the file; older files are migrated on load.
+    #[serde(default = "default_config_version")]
+    pub config_version: u32,
     pub app: AppSettings,
     pub database: DatabaseSettings,
     pub window: WindowSettings,
     pub logging: LoggingSettings,
+    #[serde(default)]
+    pub scheduler: SchedulerSettings,
+    #[serde(default)]
+    pub monitoring: MonitoringSettings,
+    #[serde(default)]
+    pub plugins: PluginSettings,
+    #[serde(default)]
+    pub paths: PathSettings,
+    #[serde(default)]
+    pub telemetry: TelemetrySettings,
+    #[serde(default)]
+    pub analytics: AnalyticsSettings,
+    #[serde(default)]
+    pub server: ServerSettings,
+    /// File the configuration was loaded from, if any.
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    /// Keys whose values were resolved from the keyring, mapped to their
+    /// `keyring:` reference.
+    #[serde(skip)]
+    pub secret_refs: BTreeMap<String, String>,
+    /// Data paths moved out of the working directory by `resolve_data_paths`,
+    /// as (old location, new location).
+    #[serde(skip)]
+    pub relocated_paths: Vec<(PathBuf, PathBuf)>,
+    /// Which layer supplied each dotted key (`default`, `system:<path>`,
+    /// `user:<path>`, `file:<path>`, `env:<var>` or `cli:<flag>`).
+    #[serde(skip)]
+    pub origins: BTreeMap<String, String>,
+    /// Config files merged into this config, lowest priority first.
+    #[serde(skip)]
+    pub layers: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     pub name: String,
     pub version: String,
+    pub dev_mode: Option<bool>,
+    pub headless: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseSettings {
     pub path: String,
     pub create_sample_data: Option<bool>,
+    /// TOML or JSON fixture loaded on first run instead of the built-in
+    /// sample users; relative to the working directory.
+    pub seed_file: Option<String>,
+    /// Connections kept open for concurrent queries.
+    pub pool_size: Option<u32>,
+    /// Encrypts the database file with SQLCipher; needs a build with the
+    /// `sqlcipher` feature. Best given as a `keyring:` reference.
+    pub encryption_key: Option<String>,
+    #[serde(default)]
+    pub backup: DatabaseBackupSettings,
+    #[serde(default)]
+    pub pragmas: DatabasePragmaSettings,
+    #[serde(default)]
+    pub maintenance: DatabaseMaintenanceSettings,
+}
+
+/// `[database.maintenance]`: periodic optimize, checkpoint and VACUUM.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabaseMaintenanceSettings {
+    /// On by default.
+    pub enabled: Option<bool>,
+    /// Cron expression; defaults to every six hours.
+    pub schedule: Option<String>,
+    /// Also rebuild the database with VACUUM when it is idle; off by default.
+    pub vacuum: Option<bool>,
+}
+
+/// `[database.pragmas]`: SQLite settings for every connection. Unset keys
+/// keep the defaults of [`Pragmas`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabasePragmaSettings {
+    /// `off`, `normal`, `full` or `extra`.
+    pub synchronous: Option<String>,
+    /// Pages when positive, KiB when negative.
+    pub cache_size: Option<i64>,
+    /// Milliseconds.
+    pub busy_timeout: Option<u64>,
+    pub foreign_keys: Option<bool>,
+    /// Bytes; 0 turns memory-mapping off.
+    pub mmap_size: Option<u64>,
+    /// Keys that aren't pragmas this app sets, reported by validation.
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, toml::Value>,
 }
 
-#[derive(Debug, Deserialize)]
+/// `[database.backup]`: scheduled copies of the database.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabaseBackupSettings {
+    pub enabled: Option<bool>,
+    /// Cron expression; defaults to daily at 02:00.
+    pub schedule: Option<String>,
+    /// Where backups are written; relative paths are under the data directory.
+    pub dir: Option<String>,
+    /// Newest backups kept; older ones are deleted after each scheduled run.
+    pub keep: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindowSettings {
     pub title: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub min_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub resizable: Option<bool>,
+    pub decorations: Option<bool>,
+    pub start_maximized: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingSettings {
     pub level: String,
     pub file: String,
     pub append: Option<bool>,
     pub format: Option<String>,
+    /// Overrides `format` for stdout: `text`, `pretty` or `json`.
+    pub console_format: Option<String>,
+    /// Overrides `format` for the log file: `text` or `json`.
+    pub file_format: Option<String>,
+    pub max_file_size: Option<u64>,
+    pub max_files: Option<usize>,
+    /// Gzip rotated log files to `<file>.N.gz`.
+    pub compress_rotated: Option<bool>,
+    /// Cap in bytes on all rotated files together; the oldest are deleted first.
+    pub max_total_size: Option<u64>,
+    pub stream_level: Option<String>,
+    pub stream_sample_every: Option<u64>,
+    pub store_level: Option<String>,
+    pub store_max_records: Option<u64>,
+    pub store_retention_days: Option<u64>,
+    /// Extra destinations, keyed by name: `[logging.sinks.<name>]`.
+    #[serde(default)]
+    pub sinks: BTreeMap<String, LogSinkSettings>,
+    #[serde(default)]
+    pub retention: LogRetentionSettings,
+}
+
+/// `[logging.retention]`: the scheduled cleanup of rotated log files, crash
+/// reports and stored log rows.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogRetentionSettings {
+    pub enabled: Option<bool>,
+    /// Cron expression; defaults to daily at 03:00.
+    pub schedule: Option<String>,
+    /// Age after which rotated log files are deleted. Active files are kept.
+    pub log_file_days: Option<u64>,
+    pub crash_report_days: Option<u64>,
+    /// Newest crash reports kept regardless of age.
+    pub max_crash_reports: Option<usize>,
+    /// Defaults to `logging.store_retention_days`.
+    pub log_row_days: Option<u64>,
+}
+
+/// One `[logging.sinks.<name>]` entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogSinkSettings {
+    /// `file`, `event_bus` or `udp`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub enabled: Option<bool>,
+    pub level: Option<String>,
+    /// `text` or `json`; `udp` sinks send syslog lines unless `json`.
+    pub format: Option<String>,
+    /// `file` sinks; relative paths are resolved against the log directory.
+    pub path: Option<String>,
     pub max_file_size: Option<u64>,
     pub max_files: Option<usize>,
+    /// `udp` sinks, e.g. `127.0.0.1:514`.
+    pub address: Option<String>,
+    /// `event_bus` sinks; defaults to `log.batch`.
+    pub event: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulerSettings {
+    pub enabled: Option<bool>,
+    /// Default for jobs without their own: "skip", "once" or "all".
+    pub catch_up: Option<String>,
+    #[serde(default)]
+    pub jobs: Vec<ScheduledJobSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJobSettings {
+    pub name: String,
+    pub schedule: String,
+    pub event: Option<String>,
+    pub enabled: Option<bool>,
+    pub catch_up: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MonitoringSettings {
+    pub power_poll_interval_secs: Option<u64>,
+    pub plugin_health_interval_secs: Option<u64>,
+    /// WebUI handlers running longer than this are logged as slow.
+    pub slow_handler_ms: Option<u64>,
+    /// Database calls running longer than this are logged as slow.
+    pub slow_query_ms: Option<u64>,
+    pub resource_poll_interval_secs: Option<u64>,
+    /// Limits that emit `resources.limit_exceeded` when crossed; unset
+    /// limits are not checked.
+    pub max_rss_mb: Option<u64>,
+    pub max_open_files: Option<u64>,
+    pub max_tasks: Option<u64>,
+    pub max_queued_events: Option<u64>,
+}
+
+/// Overrides for the platform data and log directories. Relative paths
+/// elsewhere in the config are resolved against these.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathSettings {
+    pub data_dir: Option<String>,
+    pub log_dir: Option<String>,
+}
+
+/// Opt-in usage analytics. Nothing is recorded until the user consents
+/// through `set_analytics_consent`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalyticsSettings {
+    /// Plain `http://` URL usage reports are POSTed to. Without one, counts
+    /// stay queued in the database.
+    pub endpoint: Option<String>,
+    pub upload_interval_secs: Option<u64>,
+}
+
+impl AnalyticsSettings {
+    pub fn upload_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.upload_interval_secs.unwrap_or(3600).max(60))
+    }
+}
+
+/// Headers sent with every frontend file unless overridden in
+/// `[server.headers]`.
+pub const DEFAULT_SECURITY_HEADERS: [(&str, &str); 4] = [
+    ("X-Content-Type-Options", "nosniff"),
+    ("X-Frame-Options", "DENY"),
+    ("Referrer-Policy", "strict-origin-when-cross-origin"),
+    (
+        "Content-Security-Policy",
+        "default-src 'self'; script-src 'self' 'unsafe-inline' 'unsafe-eval'; style-src 'self' 'unsafe-inline'; \
+         img-src 'self' data: blob:; connect-src 'self' ws: wss: http: https:; font-src 'self' data:;",
+    ),
+];
+
+/// Settings of the HTTP server that serves the frontend.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerSettings {
+    /// Set to false to send no security headers at all.
+    pub security_headers: Option<bool>,
+    /// Log method, path, status, size and latency of every HTTP request.
+    pub access_log: Option<bool>,
+    /// Address the HTTP server listens on, e.g. `127.0.0.1` (the default),
+    /// `0.0.0.0` or `::`. Missing or invalid addresses fall back to
+    /// `127.0.0.1`.
+    pub bind_address: Option<String>,
+    /// Port tried first; `--port` overrides it.
+    pub port: Option<u16>,
+    /// First and last port tried after `port` and the previous run's port,
+    /// lowest first. Defaults to [`PORT_RANGE`].
+    pub port_range: Option<[u16; 2]>,
+    /// Accept `POST /api/upload`. Off by default.
+    pub uploads_enabled: Option<bool>,
+    /// Where `POST /api/upload` saves files. Relative paths are under the
+    /// data directory.
+    pub upload_dir: Option<String>,
+    /// Largest upload body accepted, in megabytes.
+    pub max_upload_mb: Option<u64>,
+    /// Largest body accepted for other requests, in bytes.
+    pub max_body_size: Option<u64>,
+    /// Seconds a client may take to send a request body.
+    pub request_timeout: Option<u64>,
+    /// Threads answering requests, so a large file or slow disk doesn't hold
+    /// up every other request.
+    pub workers: Option<usize>,
+    /// A dev server such as `http://localhost:3000` to fetch the frontend
+    /// from instead of `frontend/dist`, for hot module reload.
+    pub dev_proxy_url: Option<String>,
+    /// Serve on this Unix domain socket instead of a TCP port. The window
+    /// then loads through the relay on 127.0.0.1 at the selected port, which any
+    /// local user can connect to. Relative paths are under the data directory.
+    pub unix_socket: Option<String>,
+    /// Header name to value, starting from [`DEFAULT_SECURITY_HEADERS`]. An
+    /// empty value removes a header.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub cors: CorsSettings,
+    #[serde(default)]
+    pub files: FilesSettings,
+    /// File extension (e.g. `wasm.map`) to the content type it is served
+    /// with, for files `mime_guess` gets wrong.
+    #[serde(default)]
+    pub mime_overrides: BTreeMap<String, String>,
+}
+
+/// A folder served read-only under a URL path, e.g. `/files` for exports.
+/// Off by default, since everything in it becomes readable over HTTP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FilesSettings {
+    pub enabled: bool,
+    /// URL path the folder is served under.
+    pub mount: String,
+    /// Relative paths are under the data directory.
+    pub dir: String,
+    /// Answer folder URLs with a listing of their contents, as HTML or, for
+    /// `Accept: application/json` or `?format=json`, as JSON.
+    pub listing: bool,
+}
+
+impl Default for FilesSettings {
+    fn default() -> Self {
+        Self { enabled: false, mount: "/files".to_string(), dir: "exports".to_string(), listing: true }
+    }
+}
+
+/// Cross-origin access to the HTTP server, e.g. from a dev frontend on
+/// another port. Off while `allowed_origins` is empty.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorsSettings {
+    /// Exact origins such as `http://localhost:5173`, or `*` for any.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    /// Request headers the page may send, e.g. `Content-Type`.
+    pub allowed_headers: Vec<String>,
+    /// Send cookies and auth headers cross-origin. Not allowed with `*`.
+    pub allow_credentials: bool,
+    /// How long browsers may cache a preflight answer.
+    pub max_age_secs: u64,
+}
+
+impl Default for CorsSettings {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allowed_methods: ["GET", "POST", "PUT", "DELETE"].map(String::from).to_vec(),
+            allowed_headers: vec!["Content-Type".to_string(), "X-Session-Token".to_string()],
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            security_headers: Some(true),
+            access_log: Some(true),
+            bind_address: Some("127.0.0.1".to_string()),
+            port: None,
+            port_range: None,
+            uploads_enabled: Some(false),
+            upload_dir: Some("uploads".to_string()),
+            max_upload_mb: Some(100),
+            max_body_size: Some(10 * 1024 * 1024),
+            request_timeout: Some(30),
+            workers: Some(4),
+            dev_proxy_url: None,
+            unix_socket: None,
+            cors: CorsSettings::default(),
+            files: FilesSettings::default(),
+            mime_overrides: BTreeMap::new(),
+            headers: DEFAULT_SECURITY_HEADERS
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl ServerSettings {
+    pub fn bind_address(&self) -> IpAddr {
+        self.bind_address.as_deref().and_then(|address| address.parse().ok()).unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+    }
+
+    pub fn port_range(&self) -> RangeInclusive<u16> {
+        match self.port_range {
+            Some([first, last]) => first..=last,
+            None => PORT_RANGE,
+        }
+    }
+
+    pub fn max_body_size(&self) -> u64 {
+        self.max_body_size.unwrap_or(10 * 1024 * 1024)
+    }
+
+    pub fn request_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.request_timeout.unwrap_or(30))
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.unwrap_or(4).max(1)
+    }
+
+    pub fn max_upload_bytes(&self) -> u64 {
+        self.max_upload_mb.unwrap_or(100) * 1024 * 1024
+    }
+
+    /// The headers to send, as (name, value).
+    pub fn security_headers(&self) -> Vec<(&str, &str)> {
+        if !self.security_headers.unwrap_or(true) {
+            return Vec::new();
+        }
+        self.headers
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
+/// OTLP export of traces and logs. Requires a build with `--features telemetry`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelemetrySettings {
+    pub enabled: Option<bool>,
+    pub endpoint: Option<String>,
+    /// `grpc` (port 4317) or `http` (port 4318).
+    pub protocol: Option<String>,
+    pub service_name: Option<String>,
+    pub traces: Option<bool>,
+    pub logs: Option<bool>,
+}
+
+impl TelemetrySettings {
+    pub fn protocol(&self) -> &str {
+        self.protocol.as_deref().unwrap_or("grpc")
+    }
+    pub fn endpoint(&self) -> &str {
+        match (&self.endpoint, self.protocol()) {
+            (Some(endpoint), _) => endpoint,
+            (None, "http") => "http://localhost:4318",
+            (None, _) => "http://localhost:4317",
+        }
+    }
+    pub fn service_name(&self) -> &str {
+        self.service_name.as_deref().unwrap_or(env!("CARGO_PKG_NAME"))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginSettings {
+    pub enabled: Option<Vec<String>>,
+    #[serde(default)]
+    pub disabled: Vec<String>,
+    /// Capabilities granted to each plugin; plugins not listed get none.
+    #[serde(default = "default_plugin_grants")]
+    pub grants: std::collections::HashMap<String, Vec<String>>,
+    pub data_dir: Option<String>,
+    pub directory: Option<String>,
+    #[serde(default)]
+    pub settings: std::collections::HashMap<String, toml::Table>,
+}
+
+impl Default for PluginSettings {
+    fn default() -> Self {
+        Self {
+            enabled: None,
+            disabled: Vec::new(),
+            grants: default_plugin_grants(),
+            data_dir: None,
+            directory: None,
+            settings: std::collections::HashMap::new(),
+        }
+    }
+}
+
+/// What the built-in plugins need.
+fn default_plugin_grants() -> std::collections::HashMap<String, Vec<String>> {
+    std::collections::HashMap::from([
+        ("user".to_string(), vec!["database".to_string()]),
+        ("system".to_string(), vec!["filesystem".to_string(), "clipboard".to_string()]),
+    ])
+}
+
+fn default_config_version() -> u32 {
+    CURRENT_CONFIG_VERSION
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
+            config_version: CURRENT_CONFIG_VERSION,
             app: AppSettings {
                 name: String::from("Rust WebUI Application"),
                 version: String::from("1.0.0"),
+                dev_mode: Some(false),
+                headless: Some(false),
             },
             database: DatabaseSettings {
                 path: String::from("app.db"),
                 create_sample_data: Some(true),
+                seed_file: None,
+                pool_size: Some(4),
+                encryption_key: None,
+                backup: DatabaseBackupSettings::default(),
+                pragmas: DatabasePragmaSettings::default(),
+                maintenance: DatabaseMaintenanceSettings::default(),
             },
             window: WindowSettings {
                 title: String::from("Rust WebUI Application"),
+                width: None,
+                height: None,
+                min_width: None,
+                min_height: None,
+                resizable: Some(true),
+                decorations: Some(true),
+                start_maximized: Some(false),
             },
             logging: LoggingSettings {
                 level: String::from("info"),
                 file: String::from("application.log"),
                 append: Some(true),
                 format: Some(String::from("text")),
+                console_format: None,
+                file_format: Some(String::from("json")),
                 max_file_size: Some(10 * 1024 * 1024),
                 max_files: Some(5),
+                compress_rotated: Some(true),
+                max_total_size: None,
+                stream_level: Some(String::from("info")),
+                stream_sample_every: Some(1),
+                store_level: Some(String::from("info")),
+                store_max_records: Some(50_000),
+                store_retention_days: Some(7),
+                sinks: BTreeMap::new(),
+                retention: LogRetentionSettings::default(),
             },
+            scheduler: SchedulerSettings::default(),
+            monitoring: MonitoringSettings::default(),
+            plugins: PluginSettings::default(),
+            paths: PathSettings::default(),
+            telemetry: TelemetrySettings::default(),
+            analytics: AnalyticsSettings::default(),
+            server: ServerSettings::default(),
+            source: None,
+            secret_refs: Default::default(),
+            relocated_paths: Vec::new(),
+            origins: BTreeMap::new(),
+            layers: Vec::new(),
         }
     }
 }
 
 impl AppConfig {
-    pub fn load() -> Result<Self, Box<dyn std::error::Error>> {
-        let config_paths = [
-            "app.config.toml",
-            "config/app.config.toml",
-            "./app.config.toml",
-            "./config/app.config.toml",
-        ];
+    /// Loads the layered configuration: defaults, the machine-wide file, the
+    /// per-user file, then the first config file found in the working
+    /// directory (or `$APP_CONFIG`). Later layers win.
+    pub fn load() -> AppResult<Self> {
+        let primary = find_config_file(Path::new("."))
+            .or_else(|| find_config_file(Path::new("config")))
+            .or_else(|| env::var("APP_CONFIG").ok().map(PathBuf::from).filter(|p| p.exists()));
+        Self::load_layered(primary.as_deref())
+    }
 
-        let mut config_content = None;
-        let mut config_path = String::new();
+    /// Loads the layered configuration with `path` as the top file layer.
+    /// Read and parse failures are errors rather than a silent fallback to
+    /// defaults.
+    pub fn load_from(path: &Path) -> AppResult<Self> {
+        Self::load_layered(Some(path))
+    }
 
-        for path in &config_paths {
-            if Path::new(path).exists() {
-                config_content = Some(fs::read_to_string(path)?);
-                config_path = path.to_string();
-                break;
-            }
+    fn load_layered(primary: Option<&Path>) -> AppResult<Self> {
+        let mut layers: Vec<(&str, PathBuf)> = Vec::new();
+        if let Some(path) = paths::system_config_dir().and_then(|dir| find_config_file(&dir)) {
+            layers.push(("system", path));
+        }
+        if let Some(path) = paths::config_dir().and_then(|dir| find_config_file(&dir)) {
+            layers.push(("user", path));
+        }
+        if let Some(path) = primary {
+            let same_file = |p: &PathBuf| fs::canonicalize(p).ok() == fs::canonicalize(path).ok();
+            layers.retain(|(_, p)| !same_file(p));
+            layers.push(("file", path.to_path_buf()));
+        }
+
+        let mut table = Self::default_table();
+        let mut origins = BTreeMap::new();
+        record_origins("", &table, "default", &mut origins);
+
+        let source = layers.last().map(|(_, path)| path.clone());
+        let mut labels = Vec::new();
+        for (kind, path) in &layers {
+            let layer = read_layer(path, Some(path) == source.as_ref())?;
+            let label = format!("{}:{}", kind, path.display());
+            record_origins("", &layer, &label, &mut origins);
+            merge_tables(&mut table, layer);
+            println!("Loaded configuration from: {}", path.display());
+            labels.push(label);
         }
 
-        if config_content.is_none() {
-            if let Ok(env_path) = env::var("APP_CONFIG") {
-                if Path::new(&env_path).exists() {
-                    config_content = Some(fs::read_to_string(&env_path)?);
-                    config_path = env_path;
+        let mut config = Self::from_table(table, origins).map_err(|e| match &source {
+            Some(path) => AppError::Config(format!("Failed to parse {}: {}", path.display(), e)),
+            None => AppError::Config(format!("Invalid environment override: {}", e)),
+        })?;
+        config.source = source;
+        config.layers = labels;
+        Ok(config)
+    }
+
+    /// Parses a single config file's contents over the defaults, applying
+    /// environment overrides.
+    pub fn parse(content: &str, format: ConfigFormat) -> Result<Self, String> {
+        let mut table = Self::default_table();
+        merge_tables(&mut table, format.parse(content)?);
+        Self::from_table(table, BTreeMap::new())
+    }
+
+    /// Checks values serde can't, collecting every problem into one error.
+    pub fn validate(&self) -> AppResult<()> {
+        let mut problems = Vec::new();
+
+        if self.app.name.trim().is_empty() {
+            problems.push("app.name: must not be empty".to_string());
+        }
+        if let Err(e) = validate_log_level(&self.logging.level) {
+            problems.push(format!("logging.level: {}", e));
+        }
+        for (key, format, allowed) in [
+            ("logging.format", &self.logging.format, &["text", "json"][..]),
+            ("logging.console_format", &self.logging.console_format, &["text", "pretty", "json"][..]),
+            ("logging.file_format", &self.logging.file_format, &["text", "json"][..]),
+        ] {
+            if let Some(format) = format && !allowed.contains(&format.as_str()) {
+                problems.push(format!("{}: expected one of {}, got \"{}\"", key, allowed.join(", "), format));
+            }
+        }
+        if self.logging.max_file_size == Some(0) {
+            problems.push("logging.max_file_size: must be greater than 0".to_string());
+        }
+        if self.logging.max_files == Some(0) {
+            problems.push("logging.max_files: must be greater than 0".to_string());
+        }
+        if self.logging.max_total_size == Some(0) {
+            problems.push("logging.max_total_size: must be greater than 0".to_string());
+        }
+        for (key, level) in [
+            ("logging.stream_level", &self.logging.stream_level),
+            ("logging.store_level", &self.logging.store_level),
+        ] {
+            if let Some(level) = level && !LOG_LEVELS.contains(&level.to_lowercase().as_str()) {
+                problems.push(format!(
+                    "{}: unknown level \"{}\" (expected one of {})",
+                    key,
+                    level,
+                    LOG_LEVELS.join(", ")
+                ));
+            }
+        }
+        if self.logging.store_max_records == Some(0) {
+            problems.push("logging.store_max_records: must be greater than 0".to_string());
+        }
+        if self.logging.store_retention_days == Some(0) {
+            problems.push("logging.store_retention_days: must be greater than 0".to_string());
+        }
+        if !matches!(self.telemetry.protocol(), "grpc" | "http") {
+            problems.push(format!(
+                "telemetry.protocol: expected \"grpc\" or \"http\", got \"{}\"",
+                self.telemetry.protocol()
+            ));
+        }
+        if let Some(endpoint) = self.analytics.endpoint.as_deref().filter(|e| !e.starts_with("http://")) {
+            problems.push(format!("analytics.endpoint: expected an http:// URL, got \"{}\"", endpoint));
+        }
+        for (name, sink) in &self.logging.sinks {
+            let key = format!("logging.sinks.{}", name);
+            let formats: &[&str] = match sink.kind.as_str() {
+                "file" | "udp" => &["text", "json"],
+                "event_bus" => &[],
+                other => {
+                    problems.push(format!("{}.type: expected file, event_bus or udp, got \"{}\"", key, other));
+                    continue;
                 }
+            };
+            if let Some(format) = &sink.format && !formats.contains(&format.as_str()) {
+                problems.push(format!("{}.format: not supported by {} sinks", key, sink.kind));
+            }
+            if let Some(level) = &sink.level && !LOG_LEVELS.contains(&level.to_lowercase().as_str()) {
+                problems.push(format!("{}.level: unknown level \"{}\"", key, level));
+            }
+            if sink.kind == "file" && sink.path.as_deref().is_none_or(|p| p.trim().is_empty()) {
+                problems.push(format!("{}.path: required for file sinks", key));
+            }
+            if sink.kind == "udp" && sink.address.as_deref().is_none_or(|a| a.trim().is_empty()) {
+                problems.push(format!("{}.address: required for udp sinks", key));
+            }
+        }
+        let retention = &self.logging.retention;
+        for (key, value) in [
+            ("logging.retention.log_file_days", retention.log_file_days),
+            ("logging.retention.crash_report_days", retention.crash_report_days),
+            ("logging.retention.log_row_days", retention.log_row_days),
+        ] {
+            if value == Some(0) {
+                problems.push(format!("{}: must be greater than 0", key));
+            }
+        }
+        if let Some(Err(e)) = retention.schedule.as_deref().map(crate::scheduler::parse_schedule) {
+            problems.push(format!("logging.retention.schedule: {}", e));
+        }
+        if self.logging.stream_sample_every == Some(0) {
+            problems.push("logging.stream_sample_every: must be greater than 0".to_string());
+        }
+        for (key, value) in [
+            ("window.width", self.window.width),
+            ("window.height", self.window.height),
+            ("window.min_width", self.window.min_width),
+            ("window.min_height", self.window.min_height),
+        ] {
+            if value == Some(0) {
+                problems.push(format!("{}: must be greater than 0", key));
+            }
+        }
+        if let (Some(min), Some(width)) = (self.window.min_width, self.window.width) && min > width {
+            problems.push(format!("window.min_width: {} is larger than window.width {}", min, width));
+        }
+        if let (Some(min), Some(height)) = (self.window.min_height, self.window.height) && min > height {
+            problems.push(format!("window.min_height: {} is larger than window.height {}", min, height));
+        }
+        if self.monitoring.power_poll_interval_secs == Some(0) {
+            problems.push("monitoring.power_poll_interval_secs: must be greater than 0".to_string());
+        }
+        if self.monitoring.plugin_health_interval_secs == Some(0) {
+            problems.push("monitoring.plugin_health_interval_secs: must be greater than 0".to_string());
+        }
+        if self.monitoring.resource_poll_interval_secs == Some(0) {
+            problems.push("monitoring.resource_poll_interval_secs: must be greater than 0".to_string());
+        }
+        if let Some(synchronous) = &self.database.pragmas.synchronous
+            && !SYNCHRONOUS_MODES.contains(&synchronous.to_ascii_lowercase().as_str())
+        {
+            problems.push(format!(
+                "database.pragmas.synchronous: unknown mode \"{}\" (expected one of {})",
+                synchronous,
+                SYNCHRONOUS_MODES.join(", ")
+            ));
+        }
+        for key in self.database.pragmas.unknown.keys() {
+            problems.push(format!("database.pragmas.{}: unknown pragma (expected one of {})", key, PRAGMA_KEYS.join(", ")));
+        }
+        if let Some(seed_file) = self.get_db_seed_file().filter(|path| !Path::new(path).is_file()) {
+            problems.push(format!("database.seed_file: {} does not exist", seed_file));
+        }
+        for (key, path) in [
+            ("database.path", self.get_db_path()),
+            ("logging.file", self.get_log_file()),
+            ("plugins.data_dir", self.get_plugin_data_dir()),
+            ("server.upload_dir", self.get_upload_dir()),
+            ("database.backup.dir", self.get_backup_dir()),
+        ] {
+            if path.trim().is_empty() {
+                continue;
+            }
+            if let Err(e) = check_writable(Path::new(path)) {
+                problems.push(format!("{}: {}", key, e));
+            }
+        }
+        if self.server.max_upload_mb == Some(0) {
+            problems.push("server.max_upload_mb: must be greater than 0".to_string());
+        }
+        if self.server.max_body_size == Some(0) {
+            problems.push("server.max_body_size: must be greater than 0".to_string());
+        }
+        if self.server.request_timeout == Some(0) {
+            problems.push("server.request_timeout: must be greater than 0".to_string());
+        }
+        if self.database.pool_size.is_some_and(|size| size == 0 || size > 64) {
+            problems.push("database.pool_size: must be between 1 and 64".to_string());
+        }
+        if let Some(key) = &self.database.encryption_key {
+            if key.is_empty() {
+                problems.push("database.encryption_key: must not be empty".to_string());
+            } else if !cfg!(feature = "sqlcipher") {
+                problems.push("database.encryption_key: this build has no SQLCipher support; rebuild with `--features sqlcipher`".to_string());
+            }
+        }
+        if self.database.backup.keep == Some(0) {
+            problems.push("database.backup.keep: must be greater than 0".to_string());
+        }
+        if let Some(Err(e)) = self.database.backup.schedule.as_deref().map(crate::scheduler::parse_schedule) {
+            problems.push(format!("database.backup.schedule: {}", e));
+        }
+        if let Some(Err(e)) = self.database.maintenance.schedule.as_deref().map(crate::scheduler::parse_schedule) {
+            problems.push(format!("database.maintenance.schedule: {}", e));
+        }
+        if self.server.workers.is_some_and(|workers| workers == 0 || workers > 64) {
+            problems.push("server.workers: must be between 1 and 64".to_string());
+        }
+        if let Some(url) = &self.server.dev_proxy_url {
+            let authority = url.strip_prefix("http://").map(|rest| rest.trim_end_matches('/'));
+            if authority.is_none_or(|authority| authority.is_empty() || authority.contains('/')) {
+                problems.push("server.dev_proxy_url: must be an http://host[:port] URL".to_string());
+            }
+        }
+        if let Some(socket) = &self.server.unix_socket {
+            if !cfg!(unix) {
+                problems.push("server.unix_socket: only supported on Unix".to_string());
+            } else if socket.trim().is_empty() || socket.len() >= 104 {
+                // sun_path holds 104 bytes on macOS and 108 on Linux.
+                problems.push("server.unix_socket: must be a path shorter than 104 bytes".to_string());
+            }
+        }
+        if self.server.bind_address.as_deref().is_some_and(|address| address.parse::<IpAddr>().is_err()) {
+            problems.push("server.bind_address: must be an IP address such as 0.0.0.0, :: or 127.0.0.1".to_string());
+        }
+        for (extension, content_type) in &self.server.mime_overrides {
+            let extension = extension.trim_start_matches('.');
+            if extension.is_empty() || extension.contains(['/', '\\']) {
+                problems.push(format!("server.mime_overrides.{}: must be a file extension such as wasm.map", extension));
+            }
+            if !content_type.contains('/') || content_type.chars().any(|c| c.is_control()) {
+                problems.push(format!("server.mime_overrides.{}: \"{}\" is not a content type", extension, content_type));
+            }
+        }
+        let mount = &self.server.files.mount;
+        if !mount.starts_with('/') || mount.len() < 2 || mount.ends_with('/') || mount.contains("..") {
+            problems.push("server.files.mount: must be a path such as /files".to_string());
+        }
+        if self.server.port == Some(0) {
+            problems.push("server.port: must be between 1 and 65535".to_string());
+        }
+        if self.server.port_range.is_some_and(|[first, last]| first == 0 || first > last) {
+            problems.push("server.port_range: must be [first, last] with 1 <= first <= last".to_string());
+        }
+        let cors = &self.server.cors;
+        for origin in &cors.allowed_origins {
+            if origin != "*" && !(origin.starts_with("http://") || origin.starts_with("https://")) {
+                problems.push(format!("server.cors.allowed_origins: \"{}\" must be * or start with http:// or https://", origin));
+            }
+        }
+        if cors.allow_credentials && cors.allowed_origins.iter().any(|origin| origin == "*") {
+            problems.push("server.cors.allow_credentials: cannot be used with the * origin".to_string());
+        }
+        for method in &cors.allowed_methods {
+            if method.is_empty() || !method.bytes().all(|b| b.is_ascii_uppercase()) {
+                problems.push(format!("server.cors.allowed_methods: \"{}\" must be an uppercase method such as GET", method));
+            }
+        }
+        let mut header_names: Vec<String> = Vec::new();
+        for (name, value) in &self.server.headers {
+            if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)) {
+                problems.push(format!("server.headers: \"{}\" is not a valid header name", name));
+            }
+            if value.chars().any(char::is_control) {
+                problems.push(format!("server.headers.{}: must not contain control characters", name));
+            }
+            let lower = name.to_ascii_lowercase();
+            if header_names.contains(&lower) {
+                problems.push(format!("server.headers.{}: set more than once with different capitalization", name));
+            }
+            header_names.push(lower);
+        }
+        if let Some(Err(e)) = self.scheduler.catch_up.as_deref().map(CatchUp::from_str) {
+            problems.push(format!("scheduler.catch_up: {}", e));
+        }
+        for (index, job) in self.scheduler.jobs.iter().enumerate() {
+            if job.name.trim().is_empty() {
+                problems.push(format!("scheduler.jobs[{}].name: must not be empty", index));
+            }
+            if let Some(Err(e)) = job.catch_up.as_deref().map(CatchUp::from_str) {
+                problems.push(format!("scheduler.jobs[{}].catch_up: {}", index, e));
             }
         }
 
-        if let Some(content) = config_content {
-            match toml::from_str(&content) {
-                Ok(config) => {
-                    println!("Loaded configuration from: {}", config_path);
-                    return Ok(config);
-                }
-                Err(e) => {
-                    eprintln!("Warning: Failed to parse config file: {}", e);
-                    eprintln!("Using default configuration");
-                }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Config(format!(
+                "{} problem(s) found:\n  - {}",
+                problems.len(),
+                problems.join("\n  - ")
+            )))
+        }
+    }
+
+    fn default_table() -> toml::Table {
+        match toml::Value::try_from(AppConfig::default()) {
+            Ok(toml::Value::Table(table)) => table,
+            _ => toml::Table::new(),
+        }
+    }
+
+    /// Applies environment overrides and keyring references on top of the
+    /// file contents and deserializes.
+    fn from_table(mut table: toml::Table, mut origins: BTreeMap<String, String>) -> Result<Self, String> {
+        for (name, key) in apply_env_overrides(&mut table, env::vars()) {
+            println!("Applied environment override: {}", name);
+            origins.insert(key, format!("env:{}", name));
+        }
+        let secret_refs = secrets::resolve_references(&mut table).map_err(|e| e.to_string())?;
+        let mut config: Self = table.try_into().map_err(|e: toml::de::Error| e.to_string())?;
+        config.secret_refs = secret_refs;
+        config.origins = origins;
+        Ok(config)
+    }
+
+    /// Resolves relative database, log, plugin data and upload paths against the
+    /// platform directories (or `[paths]` overrides).
+    pub fn resolve_data_paths(&mut self) {
+        let data_dir = paths::data_dir(self.paths.data_dir.as_deref());
+        let log_dir = paths::log_dir(self.paths.log_dir.as_deref());
+        let plugin_data = self.get_plugin_data_dir().to_string();
+        let upload_dir = self.get_upload_dir().to_string();
+
+        let mut relocated = Vec::new();
+        let mut relocate = |value: &str, base: &Path| {
+            let resolved = paths::resolve(base, value);
+            if resolved != Path::new(value) {
+                relocated.push((PathBuf::from(value), resolved.clone()));
+            }
+            resolved.to_string_lossy().to_string()
+        };
+        self.database.path = relocate(&self.database.path, &data_dir);
+        // An empty log file disables file logging, so leave it empty.
+        if !self.logging.file.trim().is_empty() {
+            self.logging.file = relocate(&self.logging.file, &log_dir);
+        }
+        self.plugins.data_dir = Some(relocate(&plugin_data, &data_dir));
+        self.server.upload_dir = Some(relocate(&upload_dir, &data_dir));
+        self.database.backup.dir = Some(paths::resolve(&data_dir, self.get_backup_dir()).to_string_lossy().to_string());
+        if !self.server.files.dir.trim().is_empty() {
+            self.server.files.dir = paths::resolve(&data_dir, &self.server.files.dir).to_string_lossy().to_string();
+        }
+        if let Some(socket) = self.server.unix_socket.as_mut().filter(|p| !p.trim().is_empty()) {
+            *socket = paths::resolve(&data_dir, socket).to_string_lossy().to_string();
+        }
+        self.relocated_paths = relocated;
+        for sink in self.logging.sinks.values_mut() {
+            if let Some(path) = sink.path.as_mut().filter(|p| !p.trim().is_empty()) {
+                *path = paths::resolve(&log_dir, path).to_string_lossy().to_string();
             }
         }
+    }
 
-        Ok(AppConfig::default())
+    /// The config as JSON with keyring-resolved values replaced by their
+    /// references and an inline encryption key hidden, safe to send to the
+    /// frontend or log.
+    pub fn redacted(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        for (key, reference) in &self.secret_refs {
+            if let Some(slot) = lookup_mut(&mut value, key) {
+                *slot = serde_json::Value::String(reference.clone());
+            }
+        }
+        let plaintext_key = lookup_mut(&mut value, "database.encryption_key")
+            .filter(|slot| slot.is_string() && !self.secret_refs.contains_key("database.encryption_key"));
+        if let Some(slot) = plaintext_key {
+            *slot = serde_json::Value::String("<redacted>".to_string());
+        }
+        value
     }
 
     pub fn get_app_name(&self) -> &str {
@@ -121,6 +996,32 @@ impl AppConfig {
     pub fn should_create_sample_data(&self) -> bool {
         self.database.create_sample_data.unwrap_or(true)
     }
+    pub fn get_db_seed_file(&self) -> Option<&str> {
+        self.database.seed_file.as_deref().filter(|path| !path.trim().is_empty())
+    }
+    pub fn get_db_pool_size(&self) -> u32 {
+        self.database.pool_size.unwrap_or(4).max(1)
+    }
+    pub fn get_db_pragmas(&self) -> Pragmas {
+        let settings = &self.database.pragmas;
+        let defaults = Pragmas::default();
+        Pragmas {
+            synchronous: settings.synchronous.clone().unwrap_or(defaults.synchronous),
+            cache_size: settings.cache_size.unwrap_or(defaults.cache_size),
+            busy_timeout: settings.busy_timeout.unwrap_or(defaults.busy_timeout),
+            foreign_keys: settings.foreign_keys.unwrap_or(defaults.foreign_keys),
+            mmap_size: settings.mmap_size.unwrap_or(defaults.mmap_size),
+        }
+    }
+    pub fn get_db_encryption_key(&self) -> Option<&str> {
+        self.database.encryption_key.as_deref()
+    }
+    pub fn get_backup_dir(&self) -> &str {
+        self.database.backup.dir.as_deref().unwrap_or("backups")
+    }
+    pub fn get_backups_kept(&self) -> usize {
+        self.database.backup.keep.unwrap_or(7).max(1)
+    }
     pub fn get_window_title(&self) -> &str {
         &self.window.title
     }
@@ -133,4 +1034,308 @@ impl AppConfig {
     pub fn is_append_log(&self) -> bool {
         self.logging.append.unwrap_or(true)
     }
+    pub fn get_log_stream_level(&self) -> &str {
+        self.logging.stream_level.as_deref().unwrap_or("info")
+    }
+    pub fn get_log_stream_sample_every(&self) -> u64 {
+        self.logging.stream_sample_every.unwrap_or(1)
+    }
+    pub fn is_scheduler_enabled(&self) -> bool {
+        self.scheduler.enabled.unwrap_or(true)
+    }
+    pub fn is_plugin_enabled(&self, name: &str) -> bool {
+        let listed = match self.plugins.enabled {
+            Some(ref enabled) => enabled.iter().any(|p| p == name),
+            None => true,
+        };
+        listed && !self.plugins.disabled.iter().any(|p| p == name)
+    }
+    pub fn is_headless(&self) -> bool {
+        self.app.headless.unwrap_or(false)
+    }
+    pub fn is_dev_mode(&self) -> bool {
+        self.app.dev_mode.unwrap_or(false)
+    }
+    pub fn get_plugin_directory(&self) -> &str {
+        self.plugins.directory.as_deref().unwrap_or("plugins")
+    }
+    pub fn get_plugin_data_dir(&self) -> &str {
+        self.plugins.data_dir.as_deref().unwrap_or("plugin-data")
+    }
+    pub fn get_upload_dir(&self) -> &str {
+        self.server.upload_dir.as_deref().unwrap_or("uploads")
+    }
+    pub fn get_power_poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.monitoring.power_poll_interval_secs.unwrap_or(30).max(1))
+    }
+    pub fn get_plugin_health_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.monitoring.plugin_health_interval_secs.unwrap_or(60).max(1))
+    }
+    pub fn get_resource_poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.monitoring.resource_poll_interval_secs.unwrap_or(30).max(1))
+    }
+    pub fn get_slow_handler_threshold(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.monitoring.slow_handler_ms.unwrap_or(100))
+    }
+    pub fn get_slow_query_threshold(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.monitoring.slow_query_ms.unwrap_or(50))
+    }
+}
+
+const CONFIG_FILE_NAMES: [&str; 4] = ["app.config.toml", "app.config.json", "app.config.yaml", "app.config.yml"];
+
+/// First config file in `dir`, preferring TOML when several formats exist.
+fn find_config_file(dir: &Path) -> Option<PathBuf> {
+    CONFIG_FILE_NAMES.iter().map(|name| dir.join(name)).find(|p| p.exists())
+}
+
+/// Reads one layer into a table. Only the top file layer is migrated, since
+/// lower layers are usually partial overrides.
+fn read_layer(path: &Path, migrate: bool) -> AppResult<toml::Table> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| AppError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+    let format = ConfigFormat::from_path(path);
+    let content = if migrate {
+        match config_migration::upgrade(path, &content, format) {
+            Ok(upgraded) => upgraded.unwrap_or(content),
+            Err(e) => return Err(AppError::Config(format!("Failed to migrate {}: {}", path.display(), e))),
+        }
+    } else {
+        content
+    };
+    format
+        .parse(&content)
+        .map_err(|e| AppError::Config(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
+/// Deep-merges `overlay` into `base`: tables merge key by key, anything
+/// else (including arrays) is replaced.
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match value {
+            toml::Value::Table(overlay_table) if base.get(&key).is_some_and(|v| v.is_table()) => {
+                if let Some(toml::Value::Table(base_table)) = base.get_mut(&key) {
+                    merge_tables(base_table, overlay_table);
+                }
+            }
+            value => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn record_origins(prefix: &str, table: &toml::Table, label: &str, origins: &mut BTreeMap<String, String>) {
+    for (key, value) in table {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::Table(inner) => record_origins(&path, inner, label, origins),
+            _ => {
+                origins.insert(path, label.to_string());
+            }
+        }
+    }
+}
+
+/// Merges `APP__SECTION__KEY` variables into `table`, returning the names of
+/// the variables that were applied with the dotted keys they set. Values are parsed as TOML (numbers,
+/// booleans, arrays) unless the key already holds a string.
+fn apply_env_overrides(table: &mut toml::Table, vars: impl Iterator<Item = (String, String)>) -> Vec<(String, String)> {
+    let mut applied = Vec::new();
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else { continue };
+        let segments: Vec<String> = path.split("__").map(|s| s.to_lowercase()).collect();
+        if segments.iter().any(|s| s.is_empty()) {
+            continue;
+        }
+
+        let (key, sections) = segments.split_last().expect("split yields at least one segment");
+        let mut current = &mut *table;
+        for section in sections {
+            let entry = current
+                .entry(section.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if !entry.is_table() {
+                *entry = toml::Value::Table(toml::Table::new());
+            }
+            current = entry.as_table_mut().expect("entry was just made a table");
+        }
+
+        let value = match current.get(key) {
+            Some(toml::Value::String(_)) => toml::Value::String(raw),
+            _ => parse_env_value(&raw),
+        };
+        current.insert(key.clone(), value);
+        applied.push((name, segments.join(".")));
+    }
+    applied
+}
+
+fn parse_env_value(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// Follows a dotted key (with optional `[index]` suffixes) into a JSON value.
+fn lookup_mut<'a>(value: &'a mut serde_json::Value, key: &str) -> Option<&'a mut serde_json::Value> {
+    let mut current = value;
+    for segment in key.split('.') {
+        let (name, index) = match segment.split_once('[') {
+            Some((name, rest)) => (name, Some(rest.trim_end_matches(']').parse::<usize>().ok()?)),
+            None => (segment, None),
+        };
+        current = current.get_mut(name)?;
+        if let Some(index) = index {
+            current = current.get_mut(index)?;
+        }
+    }
+    Some(current)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Unknown extensions are treated as TOML.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+            Some("json") => ConfigFormat::Json,
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Toml,
+        }
+    }
+
+    /// Parses into a TOML table so every format shares the override and
+    /// deserialization path.
+    pub fn parse(self, content: &str) -> Result<toml::Table, String> {
+        match self {
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+const LOG_LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error", "off"];
+
+/// Accepts a level name or `target=level` directives separated by commas.
+fn validate_log_level(level: &str) -> Result<(), String> {
+    for directive in level.split(',').map(str::trim) {
+        let name = directive.rsplit_once('=').map_or(directive, |(_, level)| level);
+        if !LOG_LEVELS.contains(&name.to_lowercase().as_str()) {
+            return Err(format!(
+                "unknown level \"{}\" (expected one of {})",
+                name,
+                LOG_LEVELS.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// A path is writable if it exists and isn't read-only, or if its nearest
+/// existing ancestor directory isn't read-only.
+fn check_writable(path: &Path) -> Result<(), String> {
+    let mut candidate = Some(path);
+    while let Some(current) = candidate {
+        let current = if current.as_os_str().is_empty() { Path::new(".") } else { current };
+        if let Ok(metadata) = fs::metadata(current) {
+            return if metadata.permissions().readonly() {
+                Err(format!("{} is not writable", current.display()))
+            } else {
+                Ok(())
+            };
+        }
+        candidate = current.parent();
+    }
+    Err(format!("no existing parent directory for {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_reports_every_problem_at_once() {
+        assert!(AppConfig::default().validate().is_ok());
+
+        let mut config = AppConfig::default();
+        config.app.name = " ".to_string();
+        config.logging.level = "loud".to_string();
+        config.server.port = Some(0);
+        config.window.width = Some(400);
+        config.window.min_width = Some(800);
+
+        let Err(AppError::Config(message)) = config.validate() else {
+            panic!("expected a config error");
+        };
+        assert!(message.starts_with("4 problem(s) found:"), "{}", message);
+        for key in ["app.name", "logging.level", "server.port", "window.min_width"] {
+            assert!(message.contains(&format!("\n  - {}:", key)), "{} missing from {}", key, message);
+        }
+    }
+
+    #[test]
+    fn format_is_detected_from_the_extension() {
+        for (path, format) in [
+            ("app.config.toml", ConfigFormat::Toml),
+            ("app.config.json", ConfigFormat::Json),
+            ("config/app.config.YAML", ConfigFormat::Yaml),
+            ("app.config.yml", ConfigFormat::Yaml),
+            ("app.config", ConfigFormat::Toml),
+            ("app.config.ini", ConfigFormat::Toml),
+        ] {
+            assert_eq!(ConfigFormat::from_path(Path::new(path)), format, "{}", path);
+        }
+    }
+
+    #[test]
+    fn every_format_parses_to_the_same_config() {
+        let sources = [
+            (ConfigFormat::Toml, "[window]\ntitle = \"Demo\"\n\n[database]\npool_size = 2\n"),
+            (ConfigFormat::Json, r#"{ "window": { "title": "Demo" }, "database": { "pool_size": 2 } }"#),
+            (ConfigFormat::Yaml, "window:\n  title: Demo\ndatabase:\n  pool_size: 2\n"),
+        ];
+        for (format, content) in sources {
+            let config = AppConfig::parse(content, format).unwrap();
+            assert_eq!(config.window.title, "Demo", "{:?}", format);
+            assert_eq!(config.database.pool_size, Some(2), "{:?}", format);
+            assert_eq!(config.database.path, "app.db", "{:?}", format);
+        }
+        assert!(AppConfig::parse("{ not json", ConfigFormat::Json).is_err());
+    }
+
+    #[test]
+    fn later_layers_win_key_by_key_and_record_their_origin() {
+        let mut table = AppConfig::default_table();
+        let mut origins = BTreeMap::new();
+        record_origins("", &table, "default", &mut origins);
+
+        let user = ConfigFormat::Toml
+            .parse("[window]\ntitle = \"User\"\nwidth = 800\n\n[server.cors]\nallowed_origins = [\"http://a\"]\n")
+            .unwrap();
+        record_origins("", &user, "user:u.toml", &mut origins);
+        merge_tables(&mut table, user);
+        let file = ConfigFormat::Toml
+            .parse("[window]\ntitle = \"File\"\n\n[server.cors]\nallowed_origins = [\"http://b\"]\n")
+            .unwrap();
+        record_origins("", &file, "file:app.config.toml", &mut origins);
+        merge_tables(&mut table, file);
+
+        let config = AppConfig::from_table(table, origins).unwrap();
+        assert_eq!(config.window.title, "File");
+        assert_eq!(config.window.width, Some(800));
+        assert_eq!(config.server.cors.allowed_origins, ["http://b"]);
+        assert_eq!(config.database.path, "app.db");
+        assert_eq!(config.origins["window.title"], "file:app.config.toml");
+        assert_eq!(config.origins["window.width"], "user:u.toml");
+        assert_eq!(config.origins["server.cors.allowed_origins"], "file:app.config.toml");
+        assert_eq!(config.origins["database.path"], "default");
+    }
 }
@@ -0,0 +1,130 @@
+//! Upgrades config files written for older schema versions. Files without a
+//! `config_version` are version 1. Each step returns the dotted keys to set,
+//! which are written back (keeping TOML comments) after backing up the
+//! original file.
+
+use crate::core::config::{AppConfig, ConfigFormat};
+use crate::core::config_writer::apply_changes;
+use crate::core::{AppError, AppResult};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const CURRENT_CONFIG_VERSION: u32 = 2;
+
+/// Keys without serde defaults; a file missing any of them would not parse.
+const REQUIRED_KEYS: &[&str] = &[
+    "app.name",
+    "app.version",
+    "database.path",
+    "window.title",
+    "logging.level",
+    "logging.file",
+];
+
+/// Version 2 introduced `config_version` and fills required keys that
+/// older, hand-trimmed files may lack.
+fn migrate_v1_to_v2(table: &toml::Table, changes: &mut Map<String, Value>) {
+    let defaults = serde_json::to_value(AppConfig::default()).unwrap_or_default();
+    for key in REQUIRED_KEYS {
+        let (section, field) = key.split_once('.').expect("required keys are section.field");
+        let present = table
+            .get(section)
+            .and_then(|s| s.as_table())
+            .is_some_and(|s| s.contains_key(field));
+        if !present {
+            changes.insert(key.to_string(), defaults[section][field].clone());
+        }
+    }
+}
+
+fn file_version(table: &toml::Table) -> AppResult<u32> {
+    match table.get("config_version") {
+        None => Ok(1),
+        Some(value) => value
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| AppError::Config(format!("config_version must be a positive integer, got {}", value))),
+    }
+}
+
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".v{}.bak", version));
+    PathBuf::from(name)
+}
+
+/// Migrates `content` (read from `path`) to the current version if needed,
+/// writing the upgraded file and a backup of the original. Returns the
+/// upgraded contents, or `None` when the file is already current.
+pub fn upgrade(path: &Path, content: &str, format: ConfigFormat) -> AppResult<Option<String>> {
+    let table = format.parse(content).map_err(AppError::Config)?;
+    let version = file_version(&table)?;
+    if version == CURRENT_CONFIG_VERSION {
+        return Ok(None);
+    }
+    if version > CURRENT_CONFIG_VERSION {
+        return Err(AppError::Config(format!(
+            "{} has config_version {}, but this build only supports up to {}",
+            path.display(),
+            version,
+            CURRENT_CONFIG_VERSION
+        )));
+    }
+
+    let mut changes = Map::new();
+    if version < 2 {
+        migrate_v1_to_v2(&table, &mut changes);
+    }
+    changes.insert("config_version".to_string(), Value::from(CURRENT_CONFIG_VERSION));
+
+    let upgraded = apply_changes(content, format, &changes)?;
+    let backup = backup_path(path, version);
+    fs::copy(path, &backup)?;
+    fs::write(path, &upgraded)?;
+    println!(
+        "Migrated {} from config version {} to {} (backup: {})",
+        path.display(),
+        version,
+        CURRENT_CONFIG_VERSION,
+        backup.display()
+    );
+    Ok(Some(upgraded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_one_files_are_upgraded_and_backed_up() {
+        let dir = std::env::temp_dir().join(format!("rustwebui-config-migration-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.config.toml");
+        let original = "# trimmed by hand\n[app]\nname = \"Demo\"\nversion = \"1.0.0\"\n";
+        fs::write(&path, original).unwrap();
+
+        let upgraded = upgrade(&path, original, ConfigFormat::Toml).unwrap().expect("file is migrated");
+        assert!(upgraded.contains("# trimmed by hand\n[app]"), "{}", upgraded);
+        assert_eq!(fs::read_to_string(&path).unwrap(), upgraded);
+        assert_eq!(fs::read_to_string(backup_path(&path, 1)).unwrap(), original);
+
+        let table = ConfigFormat::Toml.parse(&upgraded).unwrap();
+        assert_eq!(file_version(&table).unwrap(), CURRENT_CONFIG_VERSION);
+        assert_eq!(table["app"]["name"].as_str(), Some("Demo"));
+        assert_eq!(table["database"]["path"].as_str(), Some("app.db"));
+        assert_eq!(table["logging"]["level"].as_str(), Some("info"));
+
+        assert!(upgrade(&path, &upgraded, ConfigFormat::Toml).unwrap().is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn newer_and_malformed_versions_are_refused() {
+        let path = Path::new("app.config.toml");
+        let newer = format!("config_version = {}\n", CURRENT_CONFIG_VERSION + 1);
+        assert!(upgrade(path, &newer, ConfigFormat::Toml).is_err());
+        assert!(upgrade(path, "config_version = \"two\"\n", ConfigFormat::Toml).is_err());
+        assert!(upgrade(path, "config_version = -1\n", ConfigFormat::Toml).is_err());
+    }
+}
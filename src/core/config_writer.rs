@@ -0,0 +1,146 @@
+//! Writes configuration changes back to the file they were loaded from.
+//! TOML files are edited in place so comments and layout survive; JSON and
+//! YAML files are re-serialized.
+
+use crate::core::config::{AppConfig, ConfigFormat};
+use crate::core::{AppError, AppResult};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Applies `changes` (dotted keys such as `window.title`) to the config file.
+/// The result is parsed and validated before anything is written.
+pub fn update_config_file(path: &Path, changes: &Map<String, Value>) -> AppResult<AppConfig> {
+    if changes.is_empty() {
+        return Err(AppError::Config("No changes given".to_string()));
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| AppError::Config(format!("Failed to read {}: {}", path.display(), e)))?;
+    let format = ConfigFormat::from_path(path);
+    let updated = apply_changes(&content, format, changes)?;
+
+    let config = AppConfig::parse(&updated, format).map_err(AppError::Config)?;
+    config.validate()?;
+
+    // Write to a sibling file first so a failed write can't truncate the config.
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, updated)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(config)
+}
+
+/// Applies dotted-key changes to config file contents without writing them.
+pub fn apply_changes(content: &str, format: ConfigFormat, changes: &Map<String, Value>) -> AppResult<String> {
+    match format {
+        ConfigFormat::Toml => update_toml(content, changes),
+        ConfigFormat::Json => update_json(content, changes),
+        ConfigFormat::Yaml => update_yaml(content, changes),
+    }
+}
+
+fn split_key(key: &str) -> AppResult<Vec<&str>> {
+    let segments: Vec<&str> = key.split('.').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        return Err(AppError::Config(format!("Invalid config key: {}", key)));
+    }
+    Ok(segments)
+}
+
+fn update_toml(content: &str, changes: &Map<String, Value>) -> AppResult<String> {
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e| AppError::Config(format!("Failed to parse config: {}", e)))?;
+
+    for (key, value) in changes {
+        let segments = split_key(key)?;
+        let (field, sections) = segments.split_last().expect("split yields at least one segment");
+        let mut table = doc.as_table_mut();
+        for section in sections {
+            let entry = table.entry(section).or_insert_with(toml_edit::table);
+            table = entry
+                .as_table_mut()
+                .ok_or_else(|| AppError::Config(format!("{} is not a table", section)))?;
+        }
+        let new_value = to_toml_value(value).ok_or_else(|| {
+            AppError::Config(format!("Unsupported value for {}: {}", key, value))
+        })?;
+
+        // Keep any trailing comment attached to the old value.
+        match table.get_mut(field).and_then(|item| item.as_value_mut()) {
+            Some(existing) => {
+                let decor = existing.decor().clone();
+                *existing = new_value;
+                *existing.decor_mut() = decor;
+            }
+            None => {
+                table.insert(field, toml_edit::Item::Value(new_value));
+            }
+        }
+    }
+    Ok(doc.to_string())
+}
+
+fn to_toml_value(value: &Value) -> Option<toml_edit::Value> {
+    Some(match value {
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64()?.into(),
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Array(items) => {
+            let mut array = toml_edit::Array::new();
+            for item in items {
+                array.push(to_toml_value(item)?);
+            }
+            array.into()
+        }
+        Value::Null | Value::Object(_) => return None,
+    })
+}
+
+fn update_json(content: &str, changes: &Map<String, Value>) -> AppResult<String> {
+    let mut root: Value = serde_json::from_str(content)?;
+    for (key, value) in changes {
+        let segments = split_key(key)?;
+        let (field, sections) = segments.split_last().expect("split yields at least one segment");
+        let mut current = &mut root;
+        for section in sections {
+            current = current
+                .as_object_mut()
+                .ok_or_else(|| AppError::Config(format!("{} is not an object", section)))?
+                .entry(section.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+        }
+        current
+            .as_object_mut()
+            .ok_or_else(|| AppError::Config(format!("Cannot set {}", key)))?
+            .insert(field.to_string(), value.clone());
+    }
+    Ok(serde_json::to_string_pretty(&root)? + "\n")
+}
+
+fn update_yaml(content: &str, changes: &Map<String, Value>) -> AppResult<String> {
+    let yaml_error = |e: serde_yaml::Error| AppError::Config(format!("YAML error: {}", e));
+    let mut root: serde_yaml::Value = serde_yaml::from_str(content).map_err(yaml_error)?;
+    for (key, value) in changes {
+        let segments = split_key(key)?;
+        let (field, sections) = segments.split_last().expect("split yields at least one segment");
+        let mut current = &mut root;
+        for section in sections {
+            let mapping = current
+                .as_mapping_mut()
+                .ok_or_else(|| AppError::Config(format!("{} is not a mapping", section)))?;
+            current = mapping
+                .entry(serde_yaml::Value::from(*section))
+                .or_insert_with(|| serde_yaml::Value::Mapping(serde_yaml::Mapping::new()));
+        }
+        let value = serde_yaml::to_value(value).map_err(yaml_error)?;
+        current
+            .as_mapping_mut()
+            .ok_or_else(|| AppError::Config(format!("Cannot set {}", key)))?
+            .insert(serde_yaml::Value::from(*field), value);
+    }
+    serde_yaml::to_string(&root).map_err(yaml_error)
+}
@@ -0,0 +1,89 @@
+use super::run_id;
+use crate::event_bus::EventBus;
+use once_cell::sync::OnceCell;
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+
+/// Number of recent events written into a crash log.
+const RECENT_EVENTS: usize = 25;
+
+/// Bus whose history goes into crash logs; panics before it is set have none.
+static EVENTS: OnceCell<EventBus> = OnceCell::new();
+
+/// Includes `bus`'s recent events in crash logs from now on. The hook is
+/// installed before the app state exists, so the bus is handed over later.
+pub fn record_events_from(bus: EventBus) {
+    let _ = EVENTS.set(bus);
+}
+
+/// Writes a `crash-<timestamp>.log` into `crash_dir` for every panic and
+/// mirrors it to tracing, then runs the previous hook. Panics in spawned
+/// tasks are still recovered by tokio; a panic on the main thread still exits.
+pub fn install_panic_hook(crash_dir: PathBuf) {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        super::analytics::record_crash();
+        let report = crash_report(info);
+        match write_crash_log(&crash_dir, &report) {
+            Ok(path) => tracing::error!(target: "panic", "{}\nCrash log written to {}", report.summary, path.display()),
+            Err(e) => tracing::error!(target: "panic", "{}\nFailed to write crash log: {}", report.summary, e),
+        }
+        previous(info);
+    }));
+}
+
+struct CrashReport {
+    summary: String,
+    body: String,
+}
+
+fn crash_report(info: &PanicHookInfo<'_>) -> CrashReport {
+    let thread = std::thread::current();
+    let thread_name = thread.name().unwrap_or("<unnamed>");
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "<non-string panic payload>".to_string());
+    let location = info
+        .location()
+        .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()))
+        .unwrap_or_else(|| "<unknown>".to_string());
+    let summary = format!("Thread '{}' panicked at {}: {}", thread_name, location, message);
+
+    let mut body = String::new();
+    let _ = writeln!(body, "{} v{} crashed at {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"), chrono::Local::now().to_rfc3339());
+    let _ = writeln!(body, "Run ID: {}", run_id());
+    let _ = writeln!(body, "{}\n", summary);
+    let _ = writeln!(body, "Backtrace:\n{}\n", Backtrace::force_capture());
+
+    let _ = writeln!(body, "Recent events (newest first):");
+    match EVENTS.get().map(EventBus::try_get_event_history) {
+        Some(Some(history)) => {
+            // Streamed log records would crowd out everything else.
+            for event in history.iter().rev().filter(|e| !e.name.starts_with("log.")).take(RECENT_EVENTS) {
+                let payload = serde_json::to_string(&event.event_type).unwrap_or_default();
+                let _ = writeln!(body, "  [{}] {} from {}: {}", event.timestamp, event.name, event.source, payload);
+            }
+        }
+        Some(None) => {
+            let _ = writeln!(body, "  <event history was locked>");
+        }
+        None => {
+            let _ = writeln!(body, "  <no event bus yet>");
+        }
+    }
+
+    CrashReport { summary, body }
+}
+
+fn write_crash_log(crash_dir: &Path, report: &CrashReport) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(crash_dir)?;
+    let path = crash_dir.join(format!("crash-{}.log", chrono::Local::now().format("%Y%m%d-%H%M%S%.3f")));
+    fs::write(&path, &report.body)?;
+    Ok(path)
+}
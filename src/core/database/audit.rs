@@ -0,0 +1,108 @@
+//! The `audit_log` table: who inserted, updated or deleted which row of the
+//! app's tables, and what it held before and after.
+
+use super::prepare_cached;
+use crate::core::AppResult;
+use rusqlite::{Connection, Row};
+use serde::Serialize;
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Insert,
+    Update,
+    Delete,
+}
+
+impl Operation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Operation::Insert => "insert",
+            Operation::Update => "update",
+            Operation::Delete => "delete",
+        }
+    }
+}
+
+/// A row of `audit_log`, for `get_audit_log`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Unix time in milliseconds.
+    pub time: i64,
+    pub actor: String,
+    pub operation: String,
+    /// Table of the changed row.
+    pub entity: String,
+    pub entity_id: String,
+    /// The row before the change; `None` for inserts.
+    pub before: Option<Value>,
+    /// The row after the change; `None` for deletes.
+    pub after: Option<Value>,
+}
+
+/// Payload of `database.audit_log_received`, newest entries first.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditPage {
+    pub entries: Vec<AuditEntry>,
+    /// Entries in the whole log.
+    pub total: i64,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+/// Adds an entry for a change to the `entity` row `entity_id`. Call it on the
+/// connection that made the change, so both are kept or rolled back together.
+pub fn record(
+    conn: &Connection,
+    actor: &str,
+    operation: Operation,
+    entity: &str,
+    entity_id: &str,
+    before: Option<Value>,
+    after: Option<Value>,
+) -> AppResult<()> {
+    prepare_cached(
+        conn,
+        "INSERT INTO audit_log (time, actor, operation, entity, entity_id, before, after)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?
+    .execute(rusqlite::params![
+        chrono::Utc::now().timestamp_millis(),
+        actor,
+        operation.as_str(),
+        entity,
+        entity_id,
+        before.map(|value| value.to_string()),
+        after.map(|value| value.to_string()),
+    ])?;
+    Ok(())
+}
+
+/// Up to `limit` entries, newest first, skipping the `offset` newest.
+pub fn page(conn: &Connection, offset: u64, limit: u64) -> AppResult<AuditPage> {
+    let total = conn.query_row("SELECT COUNT(*) FROM audit_log", [], |row| row.get(0))?;
+    let mut stmt = prepare_cached(
+        conn,
+        "SELECT id, time, actor, operation, entity, entity_id, before, after FROM audit_log
+         ORDER BY id DESC LIMIT ?1 OFFSET ?2",
+    )?;
+    let entries = stmt.query_map([limit as i64, offset as i64], entry_from_row)?.collect::<Result<_, _>>()?;
+    Ok(AuditPage { entries, total, offset, limit })
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<AuditEntry> {
+    let json = |at: usize| -> rusqlite::Result<Option<Value>> {
+        Ok(row.get::<_, Option<String>>(at)?.and_then(|text| serde_json::from_str(&text).ok()))
+    };
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        time: row.get(1)?,
+        actor: row.get(2)?,
+        operation: row.get(3)?,
+        entity: row.get(4)?,
+        entity_id: row.get(5)?,
+        before: json(6)?,
+        after: json(7)?,
+    })
+}
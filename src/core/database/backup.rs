@@ -0,0 +1,211 @@
+//! Copies of the database made with SQLite's online backup API, so the app
+//! keeps running while they are taken: on demand through `backup_database`
+//! and on `[database.backup]`'s schedule.
+
+use super::{encryption, migrations, AppliedMigration, Database};
+use crate::core::config::AppConfig;
+use crate::core::{AppError, AppResult};
+use crate::event_bus::EventBus;
+use crate::scheduler::Scheduler;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Scheduler job name of the automatic backup.
+pub const BACKUP_JOB: &str = "database_backup";
+
+/// Payload of `database.backup_completed`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub size: u64,
+    pub duration_ms: u64,
+    /// `manual`, `scheduled` or `pre_restore`.
+    pub trigger: &'static str,
+}
+
+/// A file in the backup folder, for `list_backups`.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupFile {
+    pub name: String,
+    pub size: u64,
+    /// Unix time in milliseconds.
+    pub modified: Option<i64>,
+}
+
+impl Database {
+    /// Copies the database to `path`. The copy is written next to it first
+    /// and renamed into place, so `path` is never a partial backup.
+    pub fn backup(&self, path: &Path) -> AppResult<BackupInfo> {
+        let started = Instant::now();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension("partial");
+        let source = self.get_connection()?;
+        let copied = self.open_file(&partial).and_then(|mut target| copy(&source, &mut target));
+        drop(source);
+        if let Err(e) = copied {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        fs::rename(&partial, path)?;
+        let info = BackupInfo {
+            path: path.to_string_lossy().to_string(),
+            size: fs::metadata(path)?.len(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            trigger: "manual",
+        };
+        info!("Backed up the database to {} ({} bytes)", info.path, info.size);
+        Ok(info)
+    }
+
+    /// Replaces the database's contents with the backup at `path`, then
+    /// applies any migrations the backup predates. The file is checked first;
+    /// a damaged backup, one from a newer build or one encrypted with another
+    /// key is refused.
+    pub fn restore(&self, path: &Path) -> AppResult<Vec<AppliedMigration>> {
+        check_backup(path, self.key.as_deref())?;
+        copy(&self.open_file(path)?, &mut *self.get_connection()?)?;
+        info!("Restored the database from {}", path.display());
+        self.init()
+    }
+}
+
+/// Copies the whole of `source` into `target` with the online backup API,
+/// which needs both to use the same key, if any.
+fn copy(source: &Connection, target: &mut Connection) -> AppResult<()> {
+    Backup::new(source, target)?.run_to_completion(100, Duration::from_millis(100), None)?;
+    Ok(())
+}
+
+fn check_backup(path: &Path, key: Option<&str>) -> AppResult<()> {
+    let invalid = |reason: &str| AppError::Init(format!("{} is not a usable backup: {}", path.display(), reason));
+    if !path.is_file() {
+        return Err(invalid("no such file"));
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| invalid(&e.to_string()))?;
+    if let Some(key) = key {
+        encryption::apply_key(&conn, key)?;
+    }
+    let check: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| invalid(&e.to_string()))?;
+    if check != "ok" {
+        return Err(invalid(&check));
+    }
+    let tracked = conn
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'")?
+        .exists([])?;
+    let latest = migrations::MIGRATIONS.last().map_or(0, |migration| migration.version);
+    if tracked && migrations::current_version(&conn)? > latest {
+        return Err(invalid("it was made by a newer version of the app"));
+    }
+    Ok(())
+}
+
+/// `<prefix>-<local time>.db` in `dir`, e.g. `backup-20240131-020000.db`.
+pub fn backup_path(dir: &Path, prefix: &str) -> PathBuf {
+    dir.join(format!("{}-{}.db", prefix, chrono::Local::now().format("%Y%m%d-%H%M%S")))
+}
+
+/// The `.db` files in `dir`, newest first.
+pub fn list_backups(dir: &Path) -> AppResult<Vec<BackupFile>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(metadata) = entry.metadata() else { continue };
+        if !name.ends_with(".db") || !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since| since.as_millis() as i64);
+        backups.push(BackupFile { name, size: metadata.len(), modified });
+    }
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(backups)
+}
+
+/// Deletes all but the newest `keep` `backup-*.db` files in `dir`. Copies
+/// taken before a restore are left alone.
+fn prune_backups(dir: &Path, keep: usize) -> AppResult<usize> {
+    let mut deleted = 0;
+    for backup in list_backups(dir)?.iter().filter(|backup| backup.name.starts_with("backup-")).skip(keep) {
+        match fs::remove_file(dir.join(&backup.name)) {
+            Ok(()) => deleted += 1,
+            Err(e) => warn!("Failed to delete old backup {}: {}", backup.name, e),
+        }
+    }
+    Ok(deleted)
+}
+
+/// Registers the automatic backup as a scheduler job on
+/// `database.backup.schedule` (daily at 02:00 by default), off unless
+/// `database.backup.enabled`. Each run emits `database.backup_completed` on `bus`.
+pub fn register_backup_job(
+    scheduler: &Scheduler,
+    config: &AppConfig,
+    db: Arc<Database>,
+    bus: EventBus,
+) -> anyhow::Result<()> {
+    let settings = &config.database.backup;
+    let dir = PathBuf::from(config.get_backup_dir());
+    let keep = config.get_backups_kept();
+    scheduler.register_handler(BACKUP_JOB, move || {
+        let mut backup = db.backup(&backup_path(&dir, "backup"))?;
+        backup.trigger = "scheduled";
+        let deleted = prune_backups(&dir, keep)?;
+        let message = format!("Backed up {} bytes to {}, deleted {} old backup(s)", backup.size, backup.path, deleted);
+        let payload = serde_json::to_value(&backup).unwrap_or_default();
+        let bus = bus.clone();
+        tokio::spawn(async move {
+            if let Err(e) = bus.emit_custom("database.backup_completed", payload, "database").await {
+                tracing::error!("Failed to emit backup completed event: {}", e);
+            }
+        });
+        Ok(message)
+    });
+    scheduler.add_job(
+        BACKUP_JOB,
+        settings.schedule.as_deref().unwrap_or("0 2 * * *"),
+        None,
+        settings.enabled.unwrap_or(false),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::memory_db;
+
+    #[test]
+    fn a_backup_restores_the_data_it_was_taken_with() {
+        let dir = std::env::temp_dir().join(format!("rustwebui-backup-test-{}", std::process::id()));
+        let db = memory_db();
+        db.set_setting("answer", "42").unwrap();
+
+        let backup = db.backup(&dir.join("backup-1.db")).unwrap();
+        db.set_setting("answer", "changed").unwrap();
+        db.restore(Path::new(&backup.path)).unwrap();
+        assert_eq!(db.get_setting("answer").unwrap().as_deref(), Some("42"));
+
+        fs::write(dir.join("backup-0.db"), b"not a database").unwrap();
+        assert!(db.restore(&dir.join("backup-0.db")).is_err());
+        assert_eq!(prune_backups(&dir, 1).unwrap(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
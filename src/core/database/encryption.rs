@@ -0,0 +1,95 @@
+//! At-rest encryption with SQLCipher, on when `database.encryption_key` is
+//! set. Every connection is keyed before its first statement, and a
+//! plaintext database left from before the key was set is encrypted in place
+//! on the first start with it.
+
+use crate::core::{AppError, AppResult};
+use rusqlite::{Connection, ErrorCode};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use tracing::info;
+
+/// Every plaintext SQLite file starts with this; an encrypted one doesn't.
+const PLAINTEXT_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Keys `conn`, which must not have run any statement yet.
+pub(super) fn apply_key(conn: &Connection, key: &str) -> rusqlite::Result<()> {
+    conn.pragma_update(None, "key", key)
+}
+
+/// Whether the key opens `conn`'s database. SQLCipher only checks it on
+/// first read, which fails as "not a database" when it's wrong.
+pub(super) fn check_key(conn: &Connection, path: &str) -> AppResult<()> {
+    match conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(())) {
+        Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == ErrorCode::NotADatabase => Err(AppError::Init(format!(
+            "can't decrypt {}: the encryption key is wrong, or it isn't a database",
+            path
+        ))),
+        result => Ok(result?),
+    }
+}
+
+fn is_plaintext(path: &Path) -> bool {
+    let mut header = [0; 16];
+    fs::File::open(path).and_then(|mut file| file.read_exact(&mut header)).is_ok() && &header == PLAINTEXT_HEADER
+}
+
+/// Encrypts `path` with `key` if it is a plaintext database. The encrypted
+/// copy is written next to it and renamed over it, so an interrupted run
+/// leaves the original as it was.
+pub(super) fn encrypt_if_plaintext(path: &Path, key: &str) -> AppResult<()> {
+    if !is_plaintext(path) {
+        return Ok(());
+    }
+    let target = path.with_extension("encrypting");
+    let _ = fs::remove_file(&target);
+
+    let conn = Connection::open(path)?;
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+        rusqlite::params![target.to_string_lossy(), key],
+    )?;
+    let exported = conn
+        .query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))
+        .and_then(|()| conn.execute("DETACH DATABASE encrypted", []));
+    drop(conn);
+    if let Err(e) = exported {
+        let _ = fs::remove_file(&target);
+        return Err(AppError::Init(format!("failed to encrypt {}: {}", path.display(), e)));
+    }
+
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(suffix);
+        let _ = fs::remove_file(sidecar);
+    }
+    fs::rename(&target, path)?;
+    info!("Encrypted the plaintext database {}", path.display());
+    Ok(())
+}
+
+#[cfg(all(test, feature = "sqlcipher"))]
+mod tests {
+    use super::*;
+    use crate::core::Database;
+
+    #[test]
+    fn a_plaintext_database_is_encrypted_on_first_open() {
+        let dir = std::env::temp_dir().join(format!("rustwebui-encryption-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.db");
+        let plain = Database::open(path.to_str().unwrap(), 1, None).unwrap();
+        plain.init().unwrap();
+        plain.set_setting("answer", "42").unwrap();
+        drop(plain);
+
+        let db = Database::open(path.to_str().unwrap(), 1, Some("secret")).unwrap();
+        assert!(!is_plaintext(&path));
+        assert_eq!(db.get_setting("answer").unwrap().as_deref(), Some("42"));
+        drop(db);
+        assert!(Database::open(path.to_str().unwrap(), 1, Some("wrong")).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,135 @@
+//! Versioned schema changes. Each migration is a SQL script in
+//! `migrations/`, applied once and in order inside its own transaction, and
+//! recorded in `schema_migrations`. To change the schema, add the next
+//! script and list it in [`MIGRATIONS`]; never edit one that has shipped.
+
+use crate::core::{AppError, AppResult};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::time::Instant;
+use tracing::info;
+
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// Every migration, by version.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial_schema",
+        sql: include_str!("migrations/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "users_fts",
+        sql: include_str!("migrations/0002_users_fts.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "audit_log",
+        sql: include_str!("migrations/0003_audit_log.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "settings_sessions",
+        sql: include_str!("migrations/0004_settings_sessions.sql"),
+    },
+];
+
+/// Payload of the `migration.applied` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+    pub duration_ms: u64,
+}
+
+/// Highest version recorded in `schema_migrations`, 0 for a new database.
+pub fn current_version(conn: &Connection) -> AppResult<u32> {
+    let version = conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_migrations", [], |row| row.get(0))?;
+    Ok(version)
+}
+
+/// Applies the migrations `conn` hasn't had yet and returns them.
+pub fn run(conn: &Connection) -> AppResult<Vec<AppliedMigration>> {
+    let tracked = conn.prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'")?.exists([])?;
+    if !tracked {
+        add_legacy_columns(conn)?;
+        conn.execute(
+            "CREATE TABLE schema_migrations (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+    }
+
+    let current = current_version(conn)?;
+    let latest = MIGRATIONS.last().map_or(0, |migration| migration.version);
+    if current > latest {
+        return Err(AppError::Init(format!(
+            "the database is at schema version {}, but this build only knows up to {}",
+            current, latest
+        )));
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        let started = Instant::now();
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(migration.sql).map_err(|e| {
+            AppError::Init(format!("migration {} ({}) failed: {}", migration.version, migration.name, e))
+        })?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![migration.version, migration.name, chrono::Utc::now().timestamp_millis()],
+        )?;
+        tx.commit()?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        info!("Applied database migration {} ({}) in {} ms", migration.version, migration.name, duration_ms);
+        applied.push(AppliedMigration { version: migration.version, name: migration.name.to_string(), duration_ms });
+    }
+    Ok(applied)
+}
+
+/// Databases created before migrations may have tables from before these
+/// columns were added, which the initial schema's indexes rely on.
+fn add_legacy_columns(conn: &Connection) -> AppResult<()> {
+    for (table, column, definition) in [
+        ("scheduled_jobs", "catch_up", "TEXT"),
+        ("logs", "run_id", "TEXT NOT NULL DEFAULT ''"),
+    ] {
+        let table_exists = conn
+            .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1")?
+            .exists([table])?;
+        let column_exists = conn
+            .prepare(&format!("SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1", table))?
+            .exists([column])?;
+        if table_exists && !column_exists {
+            conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_apply_once_and_adopt_older_databases() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE logs (id INTEGER PRIMARY KEY, time INTEGER NOT NULL, level TEXT NOT NULL, target TEXT NOT NULL, message TEXT NOT NULL)").unwrap();
+
+        let applied = run(&conn).unwrap();
+        assert_eq!(applied.len(), MIGRATIONS.len());
+        assert_eq!(current_version(&conn).unwrap(), MIGRATIONS.last().unwrap().version);
+        assert!(conn.prepare("SELECT run_id FROM logs").is_ok());
+
+        assert!(run(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,19 @@
+-- Full-text index over users for `search_users`, kept in step with the
+-- table by triggers.
+
+CREATE VIRTUAL TABLE users_fts USING fts5(name, email, role, content = 'users', content_rowid = 'id');
+
+CREATE TRIGGER users_fts_insert AFTER INSERT ON users BEGIN
+    INSERT INTO users_fts (rowid, name, email, role) VALUES (new.id, new.name, new.email, new.role);
+END;
+
+CREATE TRIGGER users_fts_delete AFTER DELETE ON users BEGIN
+    INSERT INTO users_fts (users_fts, rowid, name, email, role) VALUES ('delete', old.id, old.name, old.email, old.role);
+END;
+
+CREATE TRIGGER users_fts_update AFTER UPDATE ON users BEGIN
+    INSERT INTO users_fts (users_fts, rowid, name, email, role) VALUES ('delete', old.id, old.name, old.email, old.role);
+    INSERT INTO users_fts (rowid, name, email, role) VALUES (new.id, new.name, new.email, new.role);
+END;
+
+INSERT INTO users_fts (users_fts) VALUES ('rebuild');
@@ -0,0 +1,14 @@
+-- One row per insert, update or delete made through the repositories and
+-- settings, with the row as JSON before and after.
+
+CREATE TABLE audit_log (
+    id INTEGER PRIMARY KEY,
+    time INTEGER NOT NULL,
+    actor TEXT NOT NULL,
+    operation TEXT NOT NULL,
+    entity TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    before TEXT,
+    after TEXT
+);
+CREATE INDEX idx_audit_log_entity ON audit_log (entity, entity_id);
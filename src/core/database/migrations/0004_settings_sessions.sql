@@ -0,0 +1,14 @@
+-- Typed settings, and sessions for frontend state and future sign-in.
+
+ALTER TABLE app_settings ADD COLUMN type TEXT NOT NULL DEFAULT 'string';
+
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY,
+    user_id INTEGER,
+    data TEXT NOT NULL DEFAULT '{}',
+    created_at INTEGER NOT NULL,
+    last_seen_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+CREATE INDEX idx_sessions_expires_at ON sessions (expires_at);
+CREATE INDEX idx_sessions_user_id ON sessions (user_id);
@@ -0,0 +1,228 @@
+pub mod audit;
+pub mod backup;
+mod encryption;
+pub mod maintenance;
+pub mod migrations;
+mod pragmas;
+pub mod repository;
+pub mod seed;
+pub mod sessions;
+mod settings;
+mod statements;
+
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::types::Value;
+use rusqlite::{Connection, Transaction};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+use crate::core::{perf, AppError, AppResult};
+
+pub use migrations::AppliedMigration;
+pub use pragmas::{Pragmas, PRAGMA_KEYS, SYNCHRONOUS_MODES};
+pub use repository::{Repository, UserRepository};
+pub use statements::{prepare_cached, statement_cache_stats, StatementCacheStats};
+
+/// A connection borrowed from the pool; it goes back when dropped.
+pub type DbConnection = r2d2::PooledConnection<SqliteConnectionManager>;
+
+pub struct Database {
+    pool: r2d2::Pool<SqliteConnectionManager>,
+    /// SQLCipher key, also used for backups.
+    key: Option<String>,
+}
+
+/// Connection pool usage, reported with the database stats.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PoolStats {
+    pub max_size: u32,
+    /// Connections open, in use or idle.
+    pub connections: u32,
+    pub idle_connections: u32,
+}
+
+impl Database {
+    /// Opens `db_path` with up to `pool_size` connections. `:memory:` gets a
+    /// single connection, since each connection would have its own database.
+    /// With a `key` the file is encrypted, a plaintext one at `db_path` first.
+    pub fn open(db_path: &str, pool_size: u32, key: Option<&str>) -> AppResult<Self> {
+        Self::open_with_pragmas(db_path, pool_size, key, &Pragmas::default())
+    }
+
+    /// Like [`Database::open`], applying `pragmas` to every connection.
+    pub fn open_with_pragmas(db_path: &str, pool_size: u32, key: Option<&str>, pragmas: &Pragmas) -> AppResult<Self> {
+        let in_memory = db_path == ":memory:";
+        if let Some(key) = key {
+            if !cfg!(feature = "sqlcipher") {
+                return Err(AppError::Init("database encryption needs a build with the `sqlcipher` feature".to_string()));
+            }
+            if !in_memory {
+                encryption::encrypt_if_plaintext(Path::new(db_path), key)?;
+            }
+        }
+
+        // Opening the file once up front reports a bad path or key at once
+        // rather than after the pool's connection timeout.
+        let conn = Connection::open(db_path)?;
+        if let Some(key) = key {
+            encryption::apply_key(&conn, key)?;
+            encryption::check_key(&conn, db_path)?;
+        }
+        conn.execute_batch("PRAGMA journal_mode=WAL;")?;
+        drop(conn);
+
+        let manager = if in_memory {
+            SqliteConnectionManager::memory()
+        } else {
+            SqliteConnectionManager::file(db_path)
+        };
+        let pool_key = key.map(str::to_string);
+        let pragma_sql = pragmas.to_sql();
+        let manager = manager.with_init(move |conn| {
+            if let Some(key) = &pool_key {
+                encryption::apply_key(conn, key)?;
+            }
+            conn.set_prepared_statement_cache_capacity(statements::STATEMENT_CACHE_CAPACITY);
+            conn.execute_batch(&pragma_sql)
+        });
+        let mut builder = r2d2::Pool::builder()
+            .max_size(if in_memory { 1 } else { pool_size.max(1) })
+            .connection_timeout(Duration::from_secs(10));
+        if in_memory {
+            // The data lives in the one connection; r2d2 must never replace it.
+            builder = builder.max_lifetime(None).idle_timeout(None);
+        }
+        let pool = builder.build(manager)?;
+
+        Ok(Database { pool, key: key.map(str::to_string) })
+    }
+
+    /// Opens a standalone connection to `path`, keyed like the pool's.
+    fn open_file(&self, path: &Path) -> AppResult<Connection> {
+        let conn = Connection::open(path)?;
+        if let Some(key) = &self.key {
+            encryption::apply_key(&conn, key)?;
+        }
+        Ok(conn)
+    }
+
+    /// Borrows a connection, waiting up to 10 seconds for one to be free.
+    pub fn get_connection(&self) -> AppResult<DbConnection> {
+        Ok(self.pool.get()?)
+    }
+
+    /// Like [`Database::get_connection`], but gives up after `timeout`.
+    pub fn get_connection_within(&self, timeout: Duration) -> AppResult<DbConnection> {
+        Ok(self.pool.get_timeout(timeout)?)
+    }
+
+    pub fn pool_stats(&self) -> PoolStats {
+        let state = self.pool.state();
+        PoolStats {
+            max_size: self.pool.max_size(),
+            connections: state.connections,
+            idle_connections: state.idle_connections,
+        }
+    }
+
+    /// Runs `f` with a connection on Tokio's blocking pool, so waiting for a
+    /// free connection or a slow query doesn't stall the async workers.
+    pub async fn run<T, F>(self: &Arc<Self>, f: F) -> AppResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> AppResult<T> + Send + 'static,
+    {
+        let db = Arc::clone(self);
+        tokio::task::spawn_blocking(move || {
+            let conn = db.get_connection()?;
+            f(&conn)
+        })
+        .await
+        .map_err(|e| AppError::Runtime(e.to_string()))?
+    }
+
+    /// Runs `f` in a transaction on a pooled connection; see [`in_transaction`].
+    pub fn transaction<T>(&self, f: impl FnOnce(&Transaction) -> AppResult<T>) -> AppResult<T> {
+        let conn = self.get_connection()?;
+        in_transaction(&conn, f)
+    }
+
+    /// [`Database::transaction`] on Tokio's blocking pool.
+    pub async fn transaction_async<T, F>(self: &Arc<Self>, f: F) -> AppResult<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Transaction) -> AppResult<T> + Send + 'static,
+    {
+        self.run(move |conn| in_transaction(conn, f)).await
+    }
+
+    /// Runs the statement `sql` with `params` off the async workers and
+    /// returns the number of rows changed.
+    pub async fn execute_async(self: &Arc<Self>, name: &'static str, sql: impl Into<String>, params: Vec<Value>) -> AppResult<usize> {
+        let sql = sql.into();
+        self.run(move |conn| {
+            let _timer = perf::query(name);
+            Ok(prepare_cached(conn, &sql)?.execute(rusqlite::params_from_iter(params))?)
+        })
+        .await
+    }
+
+    /// Copies the write-ahead log into the database file and truncates it,
+    /// so the file is complete on its own after shutdown.
+    pub fn checkpoint(&self) -> AppResult<()> {
+        let conn = self.get_connection()?;
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    /// Brings the schema up to date, returning the migrations applied.
+    pub fn init(&self) -> AppResult<Vec<AppliedMigration>> {
+        let conn = self.get_connection()?;
+        let applied = migrations::run(&conn)?;
+        info!("Database schema at version {}", migrations::current_version(&conn)?);
+        Ok(applied)
+    }
+}
+
+/// Runs `f` between BEGIN and COMMIT on `conn`. The transaction is rolled
+/// back if `f` fails or panics, so none of its writes are kept.
+pub fn in_transaction<T>(conn: &Connection, f: impl FnOnce(&Transaction) -> AppResult<T>) -> AppResult<T> {
+    let tx = conn.unchecked_transaction()?;
+    match f(&tx) {
+        Ok(value) => {
+            tx.commit()?;
+            Ok(value)
+        }
+        Err(e) => {
+            if let Err(rollback) = tx.rollback() {
+                warn!("Failed to roll back transaction: {}", rollback);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::UserInput;
+    use crate::testing::memory_db;
+
+    #[test]
+    fn failed_transaction_keeps_no_writes() {
+        let db = memory_db();
+        let input = UserInput { name: "Ada".into(), email: "ada@example.com".into(), role: "admin".into() };
+
+        let result: AppResult<()> = db.transaction(|tx| {
+            UserRepository::new(tx).insert(&input)?;
+            Err(AppError::Runtime("stop".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(UserRepository::new(&db.get_connection().unwrap()).count().unwrap(), 0);
+
+        db.transaction(|tx| UserRepository::new(tx).insert(&input)).unwrap();
+        assert_eq!(UserRepository::new(&db.get_connection().unwrap()).count().unwrap(), 1);
+    }
+}
@@ -0,0 +1,70 @@
+//! SQLite settings applied to every pooled connection, from
+//! `[database.pragmas]`.
+
+/// Accepted values of `synchronous`.
+pub const SYNCHRONOUS_MODES: &[&str] = &["off", "normal", "full", "extra"];
+
+/// Keys of `[database.pragmas]`.
+pub const PRAGMA_KEYS: &[&str] = &["synchronous", "cache_size", "busy_timeout", "foreign_keys", "mmap_size"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pragmas {
+    /// One of [`SYNCHRONOUS_MODES`]; `normal` is safe with WAL and much
+    /// faster than `full`.
+    pub synchronous: String,
+    /// Page cache per connection: pages when positive, KiB when negative.
+    pub cache_size: i64,
+    /// Milliseconds to wait for a locked database before failing.
+    pub busy_timeout: u64,
+    pub foreign_keys: bool,
+    /// Bytes of the file to memory-map; 0 turns it off.
+    pub mmap_size: u64,
+}
+
+impl Default for Pragmas {
+    fn default() -> Self {
+        Self {
+            synchronous: "normal".to_string(),
+            cache_size: -8000,
+            busy_timeout: 5000,
+            foreign_keys: true,
+            mmap_size: 0,
+        }
+    }
+}
+
+impl Pragmas {
+    /// The statements that apply these settings. Values are checked by
+    /// config validation; an unknown `synchronous` falls back to `normal`.
+    pub fn to_sql(&self) -> String {
+        let synchronous = self.synchronous.to_ascii_lowercase();
+        let synchronous = if SYNCHRONOUS_MODES.contains(&synchronous.as_str()) { synchronous } else { "normal".to_string() };
+        format!(
+            "PRAGMA synchronous = {}; PRAGMA cache_size = {}; PRAGMA busy_timeout = {}; PRAGMA foreign_keys = {}; PRAGMA mmap_size = {};",
+            synchronous,
+            self.cache_size,
+            self.busy_timeout,
+            if self.foreign_keys { "ON" } else { "OFF" },
+            self.mmap_size,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Database;
+
+    #[test]
+    fn pragmas_apply_to_pooled_connections() {
+        let pragmas = Pragmas { synchronous: "FULL".to_string(), cache_size: -4000, foreign_keys: false, ..Pragmas::default() };
+        let db = Database::open_with_pragmas(":memory:", 1, None, &pragmas).unwrap();
+        let conn = db.get_connection().unwrap();
+        let pragma = |name: &str| conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0)).unwrap();
+
+        assert_eq!(pragma("synchronous"), 2);
+        assert_eq!(pragma("cache_size"), -4000);
+        assert_eq!(pragma("busy_timeout"), 5000);
+        assert_eq!(pragma("foreign_keys"), 0);
+    }
+}
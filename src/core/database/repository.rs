@@ -0,0 +1,184 @@
+//! Typed access to the app's tables, so modules share one set of queries
+//! instead of each writing its own SQL. Repositories borrow a connection, so
+//! they work inside [`Database::run`](super::Database::run), on a pooled
+//! connection or within a transaction alike. Every write is recorded in the
+//! [audit log](super::audit).
+
+use super::audit::{self, Operation};
+use super::prepare_cached;
+use crate::core::AppResult;
+use crate::models::{User, UserInput};
+use rusqlite::{Connection, OptionalExtension, Row};
+
+/// Reads and writes the `T` rows of one table.
+pub trait Repository<T> {
+    type Id;
+    /// What `insert` and `update` store: a row without its ID.
+    type Input;
+
+    fn find(&self, id: Self::Id) -> AppResult<Option<T>>;
+    /// Rows by ID, at most `limit` of them when given.
+    fn list(&self, limit: Option<usize>) -> AppResult<Vec<T>>;
+    fn insert(&self, input: &Self::Input) -> AppResult<T>;
+    /// `None` when no row has `id`.
+    fn update(&self, id: Self::Id, input: &Self::Input) -> AppResult<Option<T>>;
+    /// Whether a row was deleted.
+    fn delete(&self, id: Self::Id) -> AppResult<bool>;
+}
+
+pub struct UserRepository<'c> {
+    conn: &'c Connection,
+    /// Who the audit log names for writes; `system` by default.
+    actor: &'c str,
+}
+
+impl<'c> UserRepository<'c> {
+    pub fn new(conn: &'c Connection) -> Self {
+        Self { conn, actor: "system" }
+    }
+
+    pub fn acting_as(self, actor: &'c str) -> Self {
+        Self { actor, ..self }
+    }
+
+    pub fn count(&self) -> AppResult<i64> {
+        Ok(prepare_cached(self.conn, "SELECT COUNT(*) FROM users")?.query_row([], |row| row.get(0))?)
+    }
+
+    /// Whether a user already has `email`, ignoring case.
+    pub fn email_taken(&self, email: &str) -> AppResult<bool> {
+        let mut stmt = prepare_cached(self.conn, "SELECT 1 FROM users WHERE lower(email) = lower(?1)")?;
+        Ok(stmt.exists([email])?)
+    }
+}
+
+impl Repository<User> for UserRepository<'_> {
+    type Id = i32;
+    type Input = UserInput;
+
+    fn find(&self, id: i32) -> AppResult<Option<User>> {
+        let mut stmt = prepare_cached(self.conn, "SELECT id, name, email, role FROM users WHERE id = ?1")?;
+        Ok(stmt.query_row([id], user_from_row).optional()?)
+    }
+
+    fn list(&self, limit: Option<usize>) -> AppResult<Vec<User>> {
+        // SQLite reads a negative limit as no limit.
+        let limit = limit.map_or(-1, |limit| limit as i64);
+        let mut stmt = prepare_cached(self.conn, "SELECT id, name, email, role FROM users ORDER BY id LIMIT ?1")?;
+        let users = stmt.query_map([limit], user_from_row)?.collect::<Result<_, _>>()?;
+        Ok(users)
+    }
+
+    fn insert(&self, input: &UserInput) -> AppResult<User> {
+        atomically(self.conn, || {
+            prepare_cached(self.conn, "INSERT INTO users (name, email, role) VALUES (?1, ?2, ?3)")?
+                .execute([&input.name, &input.email, &input.role])?;
+            let user = input.to_user(self.conn.last_insert_rowid() as i32);
+            self.audit(Operation::Insert, user.id, None, Some(&user))?;
+            Ok(user)
+        })
+    }
+
+    fn update(&self, id: i32, input: &UserInput) -> AppResult<Option<User>> {
+        atomically(self.conn, || {
+            let Some(before) = self.find(id)? else { return Ok(None) };
+            prepare_cached(self.conn, "UPDATE users SET name = ?1, email = ?2, role = ?3 WHERE id = ?4")?
+                .execute(rusqlite::params![input.name, input.email, input.role, id])?;
+            let user = input.to_user(id);
+            self.audit(Operation::Update, id, Some(&before), Some(&user))?;
+            Ok(Some(user))
+        })
+    }
+
+    fn delete(&self, id: i32) -> AppResult<bool> {
+        atomically(self.conn, || {
+            let Some(before) = self.find(id)? else { return Ok(false) };
+            prepare_cached(self.conn, "DELETE FROM users WHERE id = ?1")?.execute([id])?;
+            self.audit(Operation::Delete, id, Some(&before), None)?;
+            Ok(true)
+        })
+    }
+}
+
+impl UserRepository<'_> {
+    fn audit(&self, operation: Operation, id: i32, before: Option<&User>, after: Option<&User>) -> AppResult<()> {
+        let json = |user: Option<&User>| user.map(serde_json::to_value).transpose();
+        audit::record(self.conn, self.actor, operation, "users", &id.to_string(), json(before)?, json(after)?)
+    }
+}
+
+/// Runs `f` under a savepoint, so a write and its audit entry are kept or
+/// undone together whether or not a transaction is open.
+fn atomically<T>(conn: &Connection, f: impl FnOnce() -> AppResult<T>) -> AppResult<T> {
+    conn.execute_batch("SAVEPOINT repository_write")?;
+    match f() {
+        Ok(value) => {
+            conn.execute_batch("RELEASE repository_write")?;
+            Ok(value)
+        }
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO repository_write; RELEASE repository_write");
+            Err(e)
+        }
+    }
+}
+
+/// Maps a `SELECT id, name, email, role` row.
+pub fn user_from_row(row: &Row) -> rusqlite::Result<User> {
+    Ok(User {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        email: row.get(2)?,
+        role: row.get(3)?,
+        status: "Active".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::memory_db;
+
+    #[test]
+    fn users_round_trip() {
+        let db = memory_db();
+        let conn = db.get_connection().unwrap();
+        let users = UserRepository::new(&conn);
+
+        let input = UserInput { name: "Ada".into(), email: "ada@example.com".into(), role: "admin".into() };
+        let ada = users.insert(&input).unwrap();
+        assert_eq!(users.find(ada.id).unwrap().unwrap().email, "ada@example.com");
+        assert!(users.email_taken("ADA@example.com").unwrap());
+
+        let renamed = UserInput { name: "Ada L.".into(), ..input };
+        assert_eq!(users.update(ada.id, &renamed).unwrap().unwrap().name, "Ada L.");
+        assert!(users.update(ada.id + 1, &renamed).unwrap().is_none());
+        assert_eq!(users.list(None).unwrap().len(), 1);
+
+        assert!(users.delete(ada.id).unwrap());
+        assert!(!users.delete(ada.id).unwrap());
+        assert_eq!(users.count().unwrap(), 0);
+    }
+
+    #[test]
+    fn writes_are_audited() {
+        let db = memory_db();
+        let conn = db.get_connection().unwrap();
+        let users = UserRepository::new(&conn).acting_as("api");
+
+        let input = UserInput { name: "Ada".into(), email: "ada@example.com".into(), role: "admin".into() };
+        let ada = users.insert(&input).unwrap();
+        users.update(ada.id, &UserInput { role: "user".into(), ..input }).unwrap();
+        users.delete(ada.id).unwrap();
+
+        let log = audit::page(&conn, 0, 2).unwrap();
+        assert_eq!(log.total, 3);
+        let operations: Vec<_> = log.entries.iter().map(|entry| entry.operation.as_str()).collect();
+        assert_eq!(operations, ["delete", "update"]);
+        let update = &log.entries[1];
+        assert_eq!((update.actor.as_str(), update.entity.as_str()), ("api", "users"));
+        assert_eq!(update.before.as_ref().unwrap()["role"], "admin");
+        assert_eq!(update.after.as_ref().unwrap()["role"], "user");
+        assert!(log.entries[0].after.is_none());
+    }
+}
@@ -0,0 +1,152 @@
+//! Seed data from fixture files: `database.seed_file`, or the built-in
+//! sample users. A fixture lists tables in the order they are filled, each
+//! with rows of column values:
+//!
+//! ```toml
+//! [[tables]]
+//! name = "users"
+//! rows = [{ name = "Ada", email = "ada@example.com", role = "admin" }]
+//! ```
+//!
+//! JSON fixtures have the same shape. Tables that already have rows are left
+//! alone, so seeding twice doesn't duplicate anything. Inserted rows are
+//! audited with the `seed` actor.
+
+use super::{audit, prepare_cached, Database};
+use crate::core::{AppError, AppResult};
+use rusqlite::types::Value as SqlValue;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::path::Path;
+use tracing::info;
+
+const SAMPLE_FIXTURE: &str = include_str!("seeds/sample.toml");
+
+#[derive(Debug, Deserialize)]
+pub struct Fixture {
+    pub tables: Vec<FixtureTable>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FixtureTable {
+    pub name: String,
+    #[serde(default)]
+    pub rows: Vec<Map<String, Value>>,
+}
+
+impl Fixture {
+    /// Reads a `.toml` or `.json` fixture.
+    pub fn load(path: &Path) -> AppResult<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| AppError::Config(format!("Failed to read seed file {}: {}", path.display(), e)))?;
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => serde_json::from_str(&content).map_err(|e| e.to_string()),
+            Some("toml") => toml::from_str(&content).map_err(|e| e.to_string()),
+            _ => Err("expected a .toml or .json file".to_string()),
+        };
+        parsed.map_err(|e| AppError::Config(format!("Failed to parse seed file {}: {}", path.display(), e)))
+    }
+
+    fn sample() -> Self {
+        toml::from_str(SAMPLE_FIXTURE).expect("the built-in sample fixture parses")
+    }
+}
+
+impl Database {
+    /// Loads the built-in sample users into an empty `users` table.
+    pub fn insert_sample_data(&self) -> AppResult<()> {
+        if self.seed(&Fixture::sample())? > 0 {
+            info!("Sample data inserted into database");
+        }
+        Ok(())
+    }
+
+    /// Loads the fixture at `path`, returning the rows inserted.
+    pub fn seed_from_file(&self, path: &Path) -> AppResult<usize> {
+        let inserted = self.seed(&Fixture::load(path)?)?;
+        info!("Seeded {} row(s) from {}", inserted, path.display());
+        Ok(inserted)
+    }
+
+    /// Inserts the rows of `fixture` in one transaction, skipping tables
+    /// that already have rows. Nothing is kept if any row fails.
+    pub fn seed(&self, fixture: &Fixture) -> AppResult<usize> {
+        self.transaction(|tx| {
+            let mut inserted = 0;
+            for table in &fixture.tables {
+                let name = identifier(&table.name)?;
+                let filled: bool = tx.query_row(&format!("SELECT EXISTS (SELECT 1 FROM {})", name), [], |row| row.get(0))?;
+                if filled {
+                    continue;
+                }
+                for row in &table.rows {
+                    let columns = row.keys().map(|column| identifier(column)).collect::<AppResult<Vec<_>>>()?;
+                    let placeholders = vec!["?"; columns.len()].join(", ");
+                    let sql = format!("INSERT INTO {} ({}) VALUES ({})", name, columns.join(", "), placeholders);
+                    prepare_cached(tx, &sql)?.execute(rusqlite::params_from_iter(row.values().map(sql_value)))?;
+                    let id = tx.last_insert_rowid().to_string();
+                    let after = Some(Value::Object(row.clone()));
+                    audit::record(tx, "seed", audit::Operation::Insert, &table.name, &id, None, after)?;
+                    inserted += 1;
+                }
+            }
+            Ok(inserted)
+        })
+    }
+}
+
+/// `name` quoted for SQL, if it's a plain table or column name.
+fn identifier(name: &str) -> AppResult<String> {
+    let plain = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !plain {
+        return Err(AppError::Config(format!("Seed data names an invalid table or column \"{}\"", name)));
+    }
+    Ok(format!("\"{}\"", name))
+}
+
+/// Arrays and objects are stored as their JSON text.
+fn sql_value(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(flag) => SqlValue::Integer(*flag as i64),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => SqlValue::Integer(integer),
+            None => SqlValue::Real(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(text) => SqlValue::Text(text.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::database::UserRepository;
+    use crate::testing::memory_db;
+
+    #[test]
+    fn fixtures_fill_empty_tables_once() {
+        let db = memory_db();
+        let path = std::env::temp_dir().join(format!("rustwebui-seed-test-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"tables": [
+                {"name": "users", "rows": [{"name": "Ada", "email": "ada@example.com", "role": "admin"}]},
+                {"name": "app_settings", "rows": [{"key": "locale", "value": "de"}]}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(db.seed_from_file(&path).unwrap(), 2);
+        assert_eq!(db.seed_from_file(&path).unwrap(), 0);
+        db.insert_sample_data().unwrap();
+        assert_eq!(UserRepository::new(&db.get_connection().unwrap()).count().unwrap(), 1);
+        assert_eq!(db.get_setting("locale").unwrap().as_deref(), Some("de"));
+
+        let bad = Fixture { tables: vec![FixtureTable { name: "users; DROP TABLE users".into(), rows: Vec::new() }] };
+        assert!(db.seed(&bad).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,162 @@
+//! The `sessions` table: opaque IDs with an expiry, optionally tied to a user,
+//! carrying a JSON object of session data. Expired sessions read as absent
+//! and are dropped by [`Database::purge_expired_sessions`]. Every write is
+//! audited with the `system` actor.
+
+use super::{audit, prepare_cached, Database};
+use crate::core::AppResult;
+use rusqlite::{Connection, OptionalExtension, Row};
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    pub id: String,
+    pub user_id: Option<i32>,
+    pub data: Value,
+    /// Unix times in milliseconds.
+    pub created_at: i64,
+    pub last_seen_at: i64,
+    pub expires_at: i64,
+}
+
+impl Database {
+    /// Starts a session lasting `ttl` from now.
+    pub fn create_session(&self, user_id: Option<i32>, data: Value, ttl: Duration) -> AppResult<Session> {
+        let now = now_ms();
+        let session = Session {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id,
+            data,
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now + ttl.as_millis() as i64,
+        };
+        self.transaction(|tx| {
+            tx.execute(
+                "INSERT INTO sessions (id, user_id, data, created_at, last_seen_at, expires_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                rusqlite::params![
+                    session.id,
+                    session.user_id,
+                    session.data.to_string(),
+                    session.created_at,
+                    session.last_seen_at,
+                    session.expires_at
+                ],
+            )?;
+            record(tx, audit::Operation::Insert, &session.id, None, Some(&session))
+        })?;
+        Ok(session)
+    }
+
+    /// Marks the session `id` as seen and extends it to `ttl` from now.
+    /// `false` when it doesn't exist or has expired.
+    pub fn touch_session(&self, id: &str, ttl: Duration) -> AppResult<bool> {
+        let now = now_ms();
+        self.transaction(|tx| {
+            let Some(before) = read(tx, id)?.filter(|session| session.expires_at > now) else { return Ok(false) };
+            let after = Session { last_seen_at: now, expires_at: now + ttl.as_millis() as i64, ..before.clone() };
+            tx.execute(
+                "UPDATE sessions SET last_seen_at = ?2, expires_at = ?3 WHERE id = ?1",
+                rusqlite::params![id, after.last_seen_at, after.expires_at],
+            )?;
+            record(tx, audit::Operation::Update, id, Some(&before), Some(&after))?;
+            Ok(true)
+        })
+    }
+
+    pub fn delete_session(&self, id: &str) -> AppResult<bool> {
+        self.transaction(|tx| {
+            let Some(before) = read(tx, id)? else { return Ok(false) };
+            tx.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+            record(tx, audit::Operation::Delete, id, Some(&before), None)?;
+            Ok(true)
+        })
+    }
+
+    /// Deletes expired sessions, returning how many there were.
+    pub fn purge_expired_sessions(&self) -> AppResult<usize> {
+        self.transaction(|tx| {
+            let expired = prepare_cached(
+                tx,
+                "SELECT id, user_id, data, created_at, last_seen_at, expires_at FROM sessions WHERE expires_at <= ?1",
+            )?
+            .query_map([now_ms()], session_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+            for session in &expired {
+                tx.execute("DELETE FROM sessions WHERE id = ?1", [&session.id])?;
+                record(tx, audit::Operation::Delete, &session.id, Some(session), None)?;
+            }
+            Ok(expired.len())
+        })
+    }
+}
+
+/// The session `id`, expired or not.
+fn read(conn: &Connection, id: &str) -> AppResult<Option<Session>> {
+    let session = prepare_cached(
+        conn,
+        "SELECT id, user_id, data, created_at, last_seen_at, expires_at FROM sessions WHERE id = ?1",
+    )?
+    .query_row([id], session_from_row)
+    .optional()?;
+    Ok(session)
+}
+
+fn record(
+    conn: &Connection,
+    operation: audit::Operation,
+    id: &str,
+    before: Option<&Session>,
+    after: Option<&Session>,
+) -> AppResult<()> {
+    let json = |session: Option<&Session>| session.map(serde_json::to_value).transpose();
+    audit::record(conn, "system", operation, "sessions", id, json(before)?, json(after)?)
+}
+
+fn session_from_row(row: &Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        data: serde_json::from_str(&row.get::<_, String>(2)?).unwrap_or(Value::Null),
+        created_at: row.get(3)?,
+        last_seen_at: row.get(4)?,
+        expires_at: row.get(5)?,
+    })
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::memory_db;
+    use serde_json::json;
+
+    #[test]
+    fn sessions_expire_and_can_be_extended() {
+        let db = memory_db();
+
+        let session = db.create_session(Some(1), json!({ "theme": "dark" }), Duration::from_secs(60)).unwrap();
+        let found = read(&db.get_connection().unwrap(), &session.id).unwrap().unwrap();
+        assert_eq!((found.user_id, found.data["theme"].as_str()), (Some(1), Some("dark")));
+        assert!(db.touch_session(&session.id, Duration::from_secs(120)).unwrap());
+
+        let expired = db.create_session(None, json!({}), Duration::ZERO).unwrap();
+        assert!(!db.touch_session(&expired.id, Duration::from_secs(60)).unwrap());
+        assert_eq!(db.purge_expired_sessions().unwrap(), 1);
+        assert!(read(&db.get_connection().unwrap(), &expired.id).unwrap().is_none());
+
+        assert!(db.delete_session(&session.id).unwrap());
+        assert!(!db.delete_session(&session.id).unwrap());
+
+        let log = audit::page(&db.get_connection().unwrap(), 0, 50).unwrap();
+        let writes: Vec<_> =
+            log.entries.iter().filter(|entry| entry.entity == "sessions").map(|entry| entry.operation.as_str()).collect();
+        assert_eq!(writes, ["delete", "delete", "insert", "update", "insert"]);
+    }
+}
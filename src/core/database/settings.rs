@@ -0,0 +1,139 @@
+//! The `app_settings` table: values the app keeps between runs, such as the
+//! locale, the last port and frontend preferences. Each value is stored as
+//! text along with its type, so typed settings read back as what was written.
+
+use super::{audit, prepare_cached, Database};
+use crate::core::AppResult;
+use rusqlite::{Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What a setting holds. Strings are stored as they are, everything else as
+/// JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingType {
+    String,
+    Integer,
+    Float,
+    Bool,
+    Json,
+}
+
+impl SettingType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SettingType::String => "string",
+            SettingType::Integer => "integer",
+            SettingType::Float => "float",
+            SettingType::Bool => "bool",
+            SettingType::Json => "json",
+        }
+    }
+
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::String(_) => SettingType::String,
+            Value::Number(number) if number.is_i64() || number.is_u64() => SettingType::Integer,
+            Value::Number(_) => SettingType::Float,
+            Value::Bool(_) => SettingType::Bool,
+            _ => SettingType::Json,
+        }
+    }
+}
+
+impl Database {
+    /// Value the app stored under `key` in a previous run. Settings that
+    /// aren't strings come back as their JSON text.
+    pub fn get_setting(&self, key: &str) -> AppResult<Option<String>> {
+        let conn = self.get_connection()?;
+        Ok(read(&conn, key)?.map(|(text, _)| text))
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> AppResult<()> {
+        self.write(key, Value::String(value.to_string()))
+    }
+
+    /// The setting `key` as a `T`; an error if it holds something else.
+    pub fn get_typed_setting<T: DeserializeOwned>(&self, key: &str) -> AppResult<Option<T>> {
+        let conn = self.get_connection()?;
+        match read(&conn, key)? {
+            Some((text, kind)) => Ok(Some(serde_json::from_value(decode(text, kind)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_typed_setting<T: Serialize>(&self, key: &str, value: &T) -> AppResult<()> {
+        self.write(key, serde_json::to_value(value)?)
+    }
+
+    pub fn delete_setting(&self, key: &str) -> AppResult<()> {
+        self.transaction(|tx| {
+            let Some((text, kind)) = read(tx, key)? else { return Ok(()) };
+            tx.execute("DELETE FROM app_settings WHERE key = ?1", [key])?;
+            audit::record(tx, "system", audit::Operation::Delete, "app_settings", key, Some(decode(text, kind)?), None)
+        })
+    }
+
+    fn write(&self, key: &str, value: Value) -> AppResult<()> {
+        let kind = SettingType::of(&value);
+        let text = match &value {
+            Value::String(text) => text.clone(),
+            value => value.to_string(),
+        };
+        self.transaction(|tx| {
+            let before = read(tx, key)?;
+            if before.as_ref().is_some_and(|(old, old_kind)| *old == text && old_kind == kind.as_str()) {
+                return Ok(());
+            }
+            tx.execute(
+                "INSERT INTO app_settings (key, value, type) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value, type = excluded.type",
+                [key, text.as_str(), kind.as_str()],
+            )?;
+            let operation = if before.is_some() { audit::Operation::Update } else { audit::Operation::Insert };
+            let before = before.map(|(text, kind)| decode(text, kind)).transpose()?;
+            audit::record(tx, "system", operation, "app_settings", key, before, Some(value))
+        })
+    }
+}
+
+/// The stored text and type of `key`.
+fn read(conn: &Connection, key: &str) -> AppResult<Option<(String, String)>> {
+    let row = prepare_cached(conn, "SELECT value, type FROM app_settings WHERE key = ?1")?
+        .query_row([key], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?;
+    Ok(row)
+}
+
+fn decode(text: String, kind: String) -> AppResult<Value> {
+    if kind == SettingType::String.as_str() {
+        Ok(Value::String(text))
+    } else {
+        Ok(serde_json::from_str(&text)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::memory_db;
+
+    #[test]
+    fn typed_settings_read_back_as_written() {
+        let db = memory_db();
+
+        db.set_typed_setting("ui.sidebar_width", &280).unwrap();
+        db.set_typed_setting("ui.dark_mode", &true).unwrap();
+        db.set_typed_setting("ui.pinned", &vec!["users", "logs"]).unwrap();
+        db.set_setting("locale", "de").unwrap();
+
+        assert_eq!(db.get_typed_setting::<u32>("ui.sidebar_width").unwrap(), Some(280));
+        assert_eq!(db.get_typed_setting::<bool>("ui.dark_mode").unwrap(), Some(true));
+        assert_eq!(db.get_typed_setting::<Vec<String>>("ui.pinned").unwrap().unwrap(), ["users", "logs"]);
+        assert_eq!(db.get_typed_setting::<String>("locale").unwrap().as_deref(), Some("de"));
+        assert_eq!(db.get_setting("ui.sidebar_width").unwrap().as_deref(), Some("280"));
+        assert!(db.get_typed_setting::<bool>("locale").is_err());
+        assert_eq!(db.get_typed_setting::<bool>("missing").unwrap(), None);
+    }
+}
@@ -0,0 +1,60 @@
+//! Prepared statements kept per connection, so hot queries skip parsing and
+//! planning. Hits and misses are counted across connections for the database
+//! stats.
+
+use rusqlite::{CachedStatement, Connection, StatementStatus};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Statements each connection keeps prepared.
+pub const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// Reported with the database stats.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct StatementCacheStats {
+    /// Statements kept per connection.
+    pub capacity: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// `conn.prepare_cached(sql)`, counted in [`statement_cache_stats`].
+pub fn prepare_cached<'c>(conn: &'c Connection, sql: &str) -> rusqlite::Result<CachedStatement<'c>> {
+    let stmt = conn.prepare_cached(sql)?;
+    // A statement fresh from `prepare` has never run; a cached one has.
+    let counter = if stmt.get_status(StatementStatus::Run) > 0 { &HITS } else { &MISSES };
+    counter.fetch_add(1, Ordering::Relaxed);
+    Ok(stmt)
+}
+
+/// Counts since startup.
+pub fn statement_cache_stats() -> StatementCacheStats {
+    StatementCacheStats {
+        capacity: STATEMENT_CACHE_CAPACITY,
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reused_statements_count_as_hits() {
+        let conn = Connection::open_in_memory().unwrap();
+        let sql = "SELECT 1 + 41 -- statement cache test";
+        let before = statement_cache_stats();
+        for _ in 0..3 {
+            let answer: i64 = prepare_cached(&conn, sql).unwrap().query_row([], |row| row.get(0)).unwrap();
+            assert_eq!(answer, 42);
+        }
+        let after = statement_cache_stats();
+        // Other tests share the counters, so only lower bounds hold.
+        assert!(after.misses > before.misses);
+        assert!(after.hits >= before.hits + 2);
+    }
+}
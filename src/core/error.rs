@@ -1,3 +1,4 @@
+use super::response::RpcResponse;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -32,6 +33,9 @@ pub enum AppError {
     #[error("HTTP server error: {0}")]
     HttpServer(String),
 
+    #[error("No free port found after {0} attempts")]
+    PortUnavailable(u32),
+
     #[error("Initialization error: {0}")]
     Init(String),
 
@@ -41,6 +45,58 @@ pub enum AppError {
 
 pub type AppResult<T> = Result<T, AppError>;
 
+impl From<r2d2::Error> for AppError {
+    fn from(e: r2d2::Error) -> Self {
+        AppError::DatabaseConnection(e.to_string())
+    }
+}
+
+impl AppError {
+    /// Catalog key of the message shown in the UI, e.g. `error.database`.
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            AppError::Config(_) => "error.config",
+            AppError::Database(_) => "error.database",
+            AppError::DatabaseConnection(_) => "error.database_connection",
+            AppError::Io(_) => "error.io",
+            AppError::Serialization(_) => "error.serialization",
+            AppError::Plugin(_) => "error.plugin",
+            AppError::Window(_) => "error.window",
+            AppError::EventBus(_) => "error.event_bus",
+            AppError::WebSocket(_) => "error.websocket",
+            AppError::HttpServer(_) => "error.http_server",
+            AppError::PortUnavailable(_) => "error.port_unavailable",
+            AppError::Init(_) => "error.init",
+            AppError::Runtime(_) => "error.runtime",
+        }
+    }
+
+    /// The variant's own text, filled into the `{detail}` of its message.
+    pub fn detail(&self) -> String {
+        match self {
+            AppError::Config(detail)
+            | AppError::DatabaseConnection(detail)
+            | AppError::Plugin(detail)
+            | AppError::Window(detail)
+            | AppError::EventBus(detail)
+            | AppError::WebSocket(detail)
+            | AppError::HttpServer(detail)
+            | AppError::Init(detail)
+            | AppError::Runtime(detail) => detail.clone(),
+            AppError::Database(e) => e.to_string(),
+            AppError::Io(e) => e.to_string(),
+            AppError::Serialization(e) => e.to_string(),
+            AppError::PortUnavailable(attempts) => attempts.to_string(),
+        }
+    }
+
+    /// Payload of `*_failed` and `*.error` events: the failure form of
+    /// [`RpcResponse`].
+    pub fn to_payload(&self) -> serde_json::Value {
+        RpcResponse::from_error(self).to_value()
+    }
+}
+
 impl From<AppError> for String {
     fn from(err: AppError) -> String {
         err.to_string()
@@ -0,0 +1,118 @@
+//! Backend messages shown in the UI, looked up by key in the catalogs under
+//! `locales/`. Logs stay in English; only text sent to the page goes through
+//! [`t`].
+
+use super::{AppResult, Database};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use tracing::{info, warn};
+
+/// `app_settings` key of the language the user picked.
+pub const LOCALE_SETTING: &str = "ui.locale";
+
+/// Used when the selected language has no message for a key.
+pub const DEFAULT_LOCALE: &str = "en";
+
+const CATALOG_SOURCES: [(&str, &str); 2] = [
+    ("en", include_str!("../../locales/en.toml")),
+    ("es", include_str!("../../locales/es.toml")),
+];
+
+static CATALOGS: Lazy<HashMap<&'static str, HashMap<String, String>>> = Lazy::new(|| {
+    CATALOG_SOURCES
+        .iter()
+        .map(|(locale, source)| (*locale, parse_catalog(locale, source)))
+        .collect()
+});
+
+static CURRENT: Lazy<RwLock<&'static str>> = Lazy::new(|| RwLock::new(DEFAULT_LOCALE));
+
+/// Flattens `[error] config = "..."` into `error.config`.
+fn parse_catalog(locale: &str, source: &str) -> HashMap<String, String> {
+    fn flatten(prefix: &str, table: &toml::Table, messages: &mut HashMap<String, String>) {
+        for (key, value) in table {
+            let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+            match value {
+                toml::Value::Table(table) => flatten(&key, table, messages),
+                toml::Value::String(text) => {
+                    messages.insert(key, text.clone());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut messages = HashMap::new();
+    match source.parse::<toml::Table>() {
+        Ok(table) => flatten("", &table, &mut messages),
+        Err(e) => warn!("Ignoring the {} message catalog: {}", locale, e),
+    }
+    messages
+}
+
+/// Languages with a catalog, e.g. `["en", "es"]`.
+pub fn available() -> Vec<&'static str> {
+    CATALOG_SOURCES.iter().map(|(locale, _)| *locale).collect()
+}
+
+pub fn locale() -> &'static str {
+    CURRENT.read().map(|current| *current).unwrap_or(DEFAULT_LOCALE)
+}
+
+/// The catalog for a language tag such as `es-MX` or `es_ES.UTF-8`.
+pub fn resolve(tag: &str) -> Option<&'static str> {
+    let language = tag.split(['-', '_', '.']).next().unwrap_or_default().to_lowercase();
+    CATALOG_SOURCES.iter().map(|(locale, _)| *locale).find(|locale| *locale == language)
+}
+
+/// Switches the UI language to a catalog from [`resolve`] and remembers it
+/// for the next run.
+pub fn set_locale(db: &Database, locale: &'static str) -> AppResult<()> {
+    db.set_setting(LOCALE_SETTING, locale)?;
+    use_locale(locale);
+    info!("UI language set to {}", locale);
+    Ok(())
+}
+
+/// Restores the language stored by [`set_locale`].
+pub fn init(db: &Database) {
+    let stored = db.get_setting(LOCALE_SETTING).ok().flatten();
+    if let Some(locale) = stored.as_deref().and_then(resolve) {
+        use_locale(locale);
+    }
+}
+
+fn use_locale(locale: &'static str) {
+    if let Ok(mut current) = CURRENT.write() {
+        *current = locale;
+    }
+}
+
+/// The message for `key` in the current language, falling back to English
+/// and then to the key itself. `{name}` placeholders are replaced from `args`.
+pub fn t(key: &str, args: &[(&str, &str)]) -> String {
+    let template = [locale(), DEFAULT_LOCALE]
+        .iter()
+        .find_map(|locale| CATALOGS.get(locale).and_then(|messages| messages.get(key)))
+        .map(String::as_str)
+        .unwrap_or(key);
+    args.iter()
+        .fold(template.to_string(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catalogs_have_the_same_keys() {
+        let english = &CATALOGS[DEFAULT_LOCALE];
+        for (locale, messages) in CATALOGS.iter() {
+            let mut missing: Vec<&String> = english.keys().filter(|key| !messages.contains_key(*key)).collect();
+            missing.sort();
+            assert!(missing.is_empty(), "{} catalog lacks {:?}", locale, missing);
+            assert_eq!(messages.len(), english.len(), "{} catalog has keys English lacks", locale);
+        }
+    }
+}
@@ -0,0 +1,306 @@
+use crate::event_bus::EventBus;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::json;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::thread::JoinHandle;
+use tokio::runtime::Handle;
+
+/// Log file writer that rotates by size: `app.log` is renamed to
+/// `app.log.1`, older backups shift up, and anything past `max_files`
+/// backups is deleted. With compression on, backups become `app.log.N.gz`.
+pub struct RollingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+    compress: bool,
+    max_total_size: Option<u64>,
+    /// Where `logging.rotated` goes once a rotation is cleaned up.
+    events: Option<(Handle, EventBus)>,
+    /// Compression and cleanup of the last rotation, run off the logging path.
+    pending: Option<JoinHandle<()>>,
+}
+
+impl RollingFile {
+    pub fn open(path: &Path, append: bool, max_size: u64, max_files: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_size,
+            max_files,
+            compress: false,
+            max_total_size: None,
+            events: None,
+            pending: None,
+        })
+    }
+
+    /// Emits `logging.rotated` on `bus` after each rotation. Does nothing
+    /// outside the runtime.
+    pub fn with_events(mut self, bus: &EventBus) -> Self {
+        self.events = Handle::try_current().ok().map(|runtime| (runtime, bus.clone()));
+        self
+    }
+
+    /// Gzips backups after rotation and deletes the oldest ones while all
+    /// backups together exceed `max_total_size`. Backups left uncompressed or
+    /// over the cap by an earlier run are cleaned up right away.
+    pub fn with_compression(mut self, compress: bool, max_total_size: Option<u64>) -> Self {
+        self.compress = compress;
+        self.max_total_size = max_total_size;
+        if compress || max_total_size.is_some() {
+            let cleanup = self.cleanup(None);
+            self.pending = std::thread::Builder::new()
+                .name("log-cleanup".to_string())
+                .spawn(move || {
+                    cleanup.run();
+                })
+                .ok();
+        }
+        self
+    }
+
+    fn cleanup(&self, rotated: Option<PathBuf>) -> Cleanup {
+        Cleanup {
+            path: self.path.clone(),
+            rotated,
+            max_files: self.max_files,
+            compress: self.compress,
+            max_total_size: self.max_total_size,
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        // The previous cleanup may still be compressing `.1`.
+        if let Some(pending) = self.pending.take() {
+            let _ = pending.join();
+        }
+        if self.max_files == 0 {
+            self.file = File::create(&self.path)?;
+            self.size = 0;
+            return Ok(());
+        }
+
+        for backup in backups(&self.path, self.max_files) {
+            let _ = fs::remove_file(backup);
+        }
+        for index in (1..self.max_files).rev() {
+            for (from, to) in backups(&self.path, index).into_iter().zip(backups(&self.path, index + 1)) {
+                if from.exists() {
+                    fs::rename(&from, to)?;
+                }
+            }
+        }
+        let rotated = backup_path(&self.path, 1, "");
+        fs::rename(&self.path, &rotated)?;
+
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+
+        let cleanup = self.cleanup(Some(rotated));
+        let events = self.events.clone();
+        self.pending = std::thread::Builder::new()
+            .name("log-cleanup".to_string())
+            .spawn(move || {
+                let status = cleanup.run();
+                if let Some((runtime, bus)) = events {
+                    runtime.spawn(async move {
+                        let _ = bus.emit_custom("logging.rotated", status, "log_file").await;
+                    });
+                }
+            })
+            .ok();
+        Ok(())
+    }
+}
+
+fn backup_path(path: &Path, index: usize, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}{}", index, suffix));
+    PathBuf::from(name)
+}
+
+/// Both forms a backup may take, plain first.
+fn backups(path: &Path, index: usize) -> [PathBuf; 2] {
+    [backup_path(path, index, ""), backup_path(path, index, ".gz")]
+}
+
+fn gzip(from: &Path) -> io::Result<PathBuf> {
+    let to = PathBuf::from(format!("{}.gz", from.display()));
+    let partial = PathBuf::from(format!("{}.gz.tmp", from.display()));
+    let mut encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+    io::copy(&mut File::open(from)?, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+    fs::rename(&partial, &to)?;
+    fs::remove_file(from)?;
+    Ok(to)
+}
+
+struct Cleanup {
+    path: PathBuf,
+    /// The backup just created by a rotation, if any.
+    rotated: Option<PathBuf>,
+    max_files: usize,
+    compress: bool,
+    max_total_size: Option<u64>,
+}
+
+impl Cleanup {
+    /// Returns the `logging.rotated` payload.
+    fn run(self) -> serde_json::Value {
+        let mut compressed = Vec::new();
+        let mut errors = Vec::new();
+        if self.compress {
+            for index in 1..=self.max_files {
+                let plain = backup_path(&self.path, index, "");
+                if !plain.exists() {
+                    continue;
+                }
+                match gzip(&plain) {
+                    Ok(archive) => compressed.push(archive.display().to_string()),
+                    Err(e) => errors.push(format!("{}: {}", plain.display(), e)),
+                }
+            }
+        }
+
+        let mut removed = Vec::new();
+        if let Some(cap) = self.max_total_size {
+            let mut existing: Vec<(PathBuf, u64)> = (1..=self.max_files)
+                .flat_map(|index| backups(&self.path, index))
+                .filter_map(|path| fs::metadata(&path).ok().map(|meta| (path, meta.len())))
+                .collect();
+            let mut total: u64 = existing.iter().map(|(_, size)| size).sum();
+            // Oldest backups have the highest index and come last.
+            while total > cap {
+                let Some((path, size)) = existing.pop() else {
+                    break;
+                };
+                match fs::remove_file(&path) {
+                    Ok(()) => {
+                        total -= size;
+                        removed.push(path.display().to_string());
+                    }
+                    Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+                }
+            }
+        }
+
+        for error in &errors {
+            eprintln!("Log cleanup failed for {}", error);
+        }
+        json!({
+            "path": self.path.display().to_string(),
+            "rotated_to": self.rotated.map(|p| p.display().to_string()),
+            "compressed": compressed,
+            "removed": removed,
+            "errors": errors,
+        })
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // A single record larger than the limit still goes into a fresh file.
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rustwebui-{}-test-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn rotates_by_size_and_keeps_max_files_backups() {
+        let dir = test_dir("log-rotation");
+        let path = dir.join("app.log");
+        let mut log = RollingFile::open(&path, true, 10, 2).unwrap();
+
+        for record in ["first-----", "second----", "third-----", "fourth----"] {
+            log.write_all(record.as_bytes()).unwrap();
+        }
+        log.flush().unwrap();
+        if let Some(pending) = log.pending.take() {
+            pending.join().unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth----");
+        assert_eq!(fs::read_to_string(backup_path(&path, 1, "")).unwrap(), "third-----");
+        assert_eq!(fs::read_to_string(backup_path(&path, 2, "")).unwrap(), "second----");
+        assert!(!backup_path(&path, 3, "").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rotated_backups_are_gzipped() {
+        let dir = test_dir("log-gzip");
+        let path = dir.join("app.log");
+        let mut log = RollingFile::open(&path, true, 10, 3).unwrap().with_compression(true, None);
+
+        log.write_all(b"first-----").unwrap();
+        log.write_all(b"second----").unwrap();
+        log.pending.take().unwrap().join().unwrap();
+
+        assert!(!backup_path(&path, 1, "").exists());
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(File::open(backup_path(&path, 1, ".gz")).unwrap())
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "first-----");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn the_oldest_backups_are_removed_past_the_total_size_cap() {
+        let dir = test_dir("log-cap");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.log");
+        for index in 1..=3 {
+            fs::write(backup_path(&path, index, ".gz"), [0; 100]).unwrap();
+        }
+
+        let cleanup = Cleanup {
+            path: path.clone(),
+            rotated: None,
+            max_files: 3,
+            compress: true,
+            max_total_size: Some(250),
+        };
+        let status = cleanup.run();
+        assert_eq!(status["removed"], json!([backup_path(&path, 3, ".gz").display().to_string()]));
+        assert!(backup_path(&path, 2, ".gz").exists());
+        assert!(!backup_path(&path, 3, ".gz").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,170 @@
+use super::config::AppConfig;
+use super::{log_store, paths};
+use crate::event_bus::EventBus;
+use crate::scheduler::Scheduler;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Scheduler job name of the cleanup task.
+pub const CLEANUP_JOB: &str = "log_cleanup";
+
+/// What the cleanup task deletes, resolved from `[logging.retention]`.
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    /// Active log files; only their rotated backups are deleted.
+    log_files: Vec<PathBuf>,
+    crash_dir: PathBuf,
+    log_file_age: Duration,
+    crash_report_age: Duration,
+    max_crash_reports: usize,
+    log_row_age: Duration,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupReport {
+    pub log_files_deleted: usize,
+    pub crash_reports_deleted: usize,
+    pub log_rows_deleted: usize,
+    pub bytes_reclaimed: u64,
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let retention = &config.logging.retention;
+        let log_files = Some(config.logging.file.trim())
+            .filter(|f| !f.is_empty())
+            .map(PathBuf::from)
+            .into_iter()
+            .chain(
+                config
+                    .logging
+                    .sinks
+                    .values()
+                    .filter(|sink| sink.kind == "file")
+                    .filter_map(|sink| sink.path.as_deref().map(PathBuf::from)),
+            )
+            .collect();
+        Self {
+            log_files,
+            crash_dir: paths::log_dir(config.paths.log_dir.as_deref()),
+            log_file_age: days(retention.log_file_days.unwrap_or(14)),
+            crash_report_age: days(retention.crash_report_days.unwrap_or(30)),
+            max_crash_reports: retention.max_crash_reports.unwrap_or(20),
+            log_row_age: days(retention.log_row_days.or(config.logging.store_retention_days).unwrap_or(7)),
+        }
+    }
+
+    /// Deletes expired backups, crash reports and stored log rows. Failures
+    /// on single files are logged and skipped.
+    pub fn run(&self) -> CleanupReport {
+        let mut report = CleanupReport::default();
+        let now = SystemTime::now();
+
+        for log_file in &self.log_files {
+            for (path, size, modified) in list_files(log_file.parent().unwrap_or(Path::new(".")), |name| {
+                is_backup_of(log_file, name)
+            }) {
+                if age(now, modified) > self.log_file_age && remove(&path) {
+                    report.log_files_deleted += 1;
+                    report.bytes_reclaimed += size;
+                }
+            }
+        }
+
+        let mut crash_reports = list_files(&self.crash_dir, |name| {
+            name.starts_with("crash-") && name.ends_with(".log")
+        });
+        crash_reports.sort_by_key(|(_, _, modified)| std::cmp::Reverse(*modified));
+        for (index, (path, size, modified)) in crash_reports.into_iter().enumerate() {
+            let expired = index >= self.max_crash_reports || age(now, modified) > self.crash_report_age;
+            if expired && remove(&path) {
+                report.crash_reports_deleted += 1;
+                report.bytes_reclaimed += size;
+            }
+        }
+
+        let cutoff = chrono::Utc::now().timestamp_millis() - self.log_row_age.as_millis() as i64;
+        match log_store::prune_before(cutoff) {
+            Ok(deleted) => report.log_rows_deleted = deleted,
+            Err(e) => tracing::debug!("Stored log rows not pruned: {}", e),
+        }
+        report
+    }
+}
+
+fn days(days: u64) -> Duration {
+    Duration::from_secs(days * 24 * 60 * 60)
+}
+
+/// `app.log.3` and `app.log.3.gz` are backups of `app.log`.
+fn is_backup_of(log_file: &Path, name: &str) -> bool {
+    let Some(base) = log_file.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    name.strip_prefix(base)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .map(|rest| rest.strip_suffix(".gz").unwrap_or(rest))
+        .is_some_and(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn list_files(dir: &Path, matches: impl Fn(&str) -> bool) -> Vec<(PathBuf, u64, SystemTime)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_str().is_some_and(&matches))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some((entry.path(), metadata.len(), metadata.modified().ok()?))
+        })
+        .collect()
+}
+
+fn age(now: SystemTime, modified: SystemTime) -> Duration {
+    now.duration_since(modified).unwrap_or_default()
+}
+
+fn remove(path: &Path) -> bool {
+    match fs::remove_file(path) {
+        Ok(()) => true,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(e) => {
+            tracing::warn!("Failed to delete {}: {}", path.display(), e);
+            false
+        }
+    }
+}
+
+/// Registers the cleanup as a scheduler job on `logging.retention.schedule`
+/// (daily at 03:00 by default). Each run emits `logging.cleanup_completed`
+/// with a [`CleanupReport`] on `bus`.
+pub fn register_cleanup_job(scheduler: &Scheduler, config: &AppConfig, bus: EventBus) -> anyhow::Result<()> {
+    let retention = &config.logging.retention;
+    let policy = RetentionPolicy::from_config(config);
+    scheduler.register_handler(CLEANUP_JOB, move || {
+        let report = policy.run();
+        let message = format!(
+            "Deleted {} log file(s), {} crash report(s) and {} log row(s), reclaiming {} bytes",
+            report.log_files_deleted, report.crash_reports_deleted, report.log_rows_deleted, report.bytes_reclaimed
+        );
+        tracing::info!("{}", message);
+        let payload = serde_json::to_value(&report).unwrap_or_default();
+        let bus = bus.clone();
+        tokio::spawn(async move {
+            if let Err(e) = bus.emit_custom("logging.cleanup_completed", payload, "log_retention").await {
+                tracing::error!("Failed to emit log cleanup event: {}", e);
+            }
+        });
+        Ok(message)
+    });
+    scheduler.add_job(
+        CLEANUP_JOB,
+        retention.schedule.as_deref().unwrap_or("0 3 * * *"),
+        None,
+        retention.enabled.unwrap_or(true),
+    )
+}
@@ -0,0 +1,352 @@
+use super::config::{LogSinkSettings, LoggingSettings};
+use super::log_file::RollingFile;
+use super::log_stream::{LogRecord, MessageVisitor};
+use super::run_id;
+use crate::event_bus::EventBus;
+use serde_json::json;
+use std::io::{self, Write};
+use std::net::UdpSocket;
+use std::path::Path;
+use once_cell::sync::OnceCell;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer};
+
+/// The event bus sink never forwards the bus's own records, otherwise every
+/// forwarded batch would log another one.
+const EVENT_BUS_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::event_bus");
+const QUEUE_SIZE: usize = 4096;
+const BATCH_SIZE: usize = 256;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_FILES: usize = 5;
+
+/// Destination for log records. Sinks run on a dedicated thread and receive
+/// records in batches, so implementations may block.
+pub trait LogSink: Send {
+    fn write_batch(&mut self, records: &[LogRecord]) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+fn format_text(record: &LogRecord) -> String {
+    format!(
+        "{} {:>5} [{}] {}: {}",
+        record.timestamp, record.level, record.run_id, record.target, record.message
+    )
+}
+
+fn format_json(record: &LogRecord) -> String {
+    serde_json::to_string(record).unwrap_or_default()
+}
+
+/// Appends records to a size-rotated file, one per line.
+pub struct FileSink {
+    file: RollingFile,
+    json: bool,
+}
+
+impl FileSink {
+    pub fn open(path: &Path, json: bool, max_size: u64, max_files: usize, bus: &EventBus) -> io::Result<Self> {
+        Ok(Self {
+            file: RollingFile::open(path, true, max_size, max_files)?.with_events(bus),
+            json,
+        })
+    }
+}
+
+impl LogSink for FileSink {
+    fn write_batch(&mut self, records: &[LogRecord]) -> io::Result<()> {
+        for record in records {
+            let line = if self.json { format_json(record) } else { format_text(record) };
+            self.file.write_all(line.as_bytes())?;
+            self.file.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Emits each batch as one custom event, `{"sink": <name>, "records": [...]}`.
+pub struct EventBusSink {
+    name: String,
+    event: String,
+    runtime: Handle,
+    bus: EventBus,
+}
+
+impl EventBusSink {
+    /// Must be called inside the runtime.
+    pub fn new(name: &str, event: &str, bus: &EventBus) -> io::Result<Self> {
+        let runtime = Handle::try_current().map_err(|e| io::Error::other(e.to_string()))?;
+        Ok(Self {
+            name: name.to_string(),
+            event: event.to_string(),
+            runtime,
+            bus: bus.clone(),
+        })
+    }
+}
+
+impl LogSink for EventBusSink {
+    fn write_batch(&mut self, records: &[LogRecord]) -> io::Result<()> {
+        let records: Vec<&LogRecord> = records
+            .iter()
+            .filter(|record| !record.target.starts_with(EVENT_BUS_TARGET))
+            .collect();
+        if records.is_empty() {
+            return Ok(());
+        }
+        let payload = json!({ "sink": self.name, "records": records });
+        let (event, bus) = (self.event.clone(), self.bus.clone());
+        self.runtime.spawn(async move {
+            let _ = bus.emit_custom(&event, payload, "log_sink").await;
+        });
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Sends one datagram per record, as an RFC 5424 syslog message or as JSON.
+pub struct UdpSink {
+    socket: UdpSocket,
+    json: bool,
+    hostname: String,
+}
+
+impl UdpSink {
+    pub fn connect(address: &str, json: bool) -> io::Result<Self> {
+        let socket = UdpSocket::bind(if address.starts_with('[') { "[::]:0" } else { "0.0.0.0:0" })?;
+        socket.connect(address)?;
+        let hostname = std::env::var("HOSTNAME")
+            .or_else(|_| std::env::var("COMPUTERNAME"))
+            .unwrap_or_else(|_| "-".to_string());
+        Ok(Self { socket, json, hostname })
+    }
+
+    fn syslog_line(&self, record: &LogRecord) -> String {
+        // Facility "user" (1); severities per RFC 5424.
+        let severity = match record.level.as_str() {
+            "ERROR" => 3,
+            "WARN" => 4,
+            "INFO" => 6,
+            _ => 7,
+        };
+        format!(
+            "<{}>1 {} {} {} {} - - [{}] {}: {}",
+            8 + severity,
+            record.timestamp,
+            self.hostname,
+            env!("CARGO_PKG_NAME"),
+            std::process::id(),
+            record.run_id,
+            record.target,
+            record.message
+        )
+    }
+}
+
+impl LogSink for UdpSink {
+    fn write_batch(&mut self, records: &[LogRecord]) -> io::Result<()> {
+        for record in records {
+            let line = if self.json { format_json(record) } else { self.syslog_line(record) };
+            self.socket.send(line.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Message {
+    Record(Level, LogRecord),
+    /// Answered once every record sent before it is written and flushed.
+    Flush(Sender<()>),
+}
+
+static SENDER: OnceCell<SyncSender<Message>> = OnceCell::new();
+
+/// A configured sink and the most verbose level it accepts.
+struct Route {
+    name: String,
+    level: LevelFilter,
+    sink: Box<dyn LogSink>,
+    failing: bool,
+}
+
+impl Route {
+    fn report<T>(&mut self, result: io::Result<T>) {
+        // Reported on stderr once per failure streak: logging it would feed
+        // the error back into the sinks.
+        match result {
+            Ok(_) => self.failing = false,
+            Err(e) if !self.failing => {
+                self.failing = true;
+                eprintln!("Log sink '{}' failed: {}", self.name, e);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+fn build_sink(
+    name: &str,
+    settings: &LogSinkSettings,
+    logging: &LoggingSettings,
+    bus: &EventBus,
+) -> io::Result<Box<dyn LogSink>> {
+    let json = settings.format.as_deref() == Some("json");
+    let sink: Box<dyn LogSink> = match settings.kind.as_str() {
+        "file" => Box::new(FileSink::open(
+            Path::new(settings.path.as_deref().unwrap_or_default()),
+            json,
+            settings
+                .max_file_size
+                .or(logging.max_file_size)
+                .unwrap_or(DEFAULT_MAX_FILE_SIZE),
+            settings.max_files.or(logging.max_files).unwrap_or(DEFAULT_MAX_FILES),
+            bus,
+        )?),
+        "event_bus" => Box::new(EventBusSink::new(name, settings.event.as_deref().unwrap_or("log.batch"), bus)?),
+        "udp" => Box::new(UdpSink::connect(settings.address.as_deref().unwrap_or_default(), json)?),
+        other => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown sink type \"{}\"", other),
+            ));
+        }
+    };
+    Ok(sink)
+}
+
+/// Builds the sinks in `[logging.sinks]` and starts the thread that feeds
+/// them. `None` when no sink is enabled. `event_bus` sinks emit on `bus`
+/// and must be built inside the runtime.
+pub fn layer(settings: &LoggingSettings, bus: &EventBus) -> io::Result<Option<LogSinkLayer>> {
+    let mut routes = Vec::new();
+    for (name, sink) in settings.sinks.iter().filter(|(_, s)| s.enabled.unwrap_or(true)) {
+        let level = sink.level.as_deref().unwrap_or("info").parse().unwrap_or(LevelFilter::INFO);
+        let built = build_sink(name, sink, settings, bus)
+            .map_err(|e| io::Error::new(e.kind(), format!("logging.sinks.{}: {}", name, e)))?;
+        routes.push(Route {
+            name: name.clone(),
+            level,
+            sink: built,
+            failing: false,
+        });
+    }
+    let Some(level) = routes.iter().map(|route| route.level).max() else {
+        return Ok(None);
+    };
+
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_SIZE);
+    std::thread::Builder::new()
+        .name("log-sinks".to_string())
+        .spawn(move || run(receiver, routes))?;
+    let _ = SENDER.set(sender.clone());
+    Ok(Some(LogSinkLayer { sender, level }))
+}
+
+/// Blocks until records logged so far reach every sink and are flushed.
+/// Returns `false` if that takes longer than `timeout`.
+pub fn flush(timeout: Duration) -> bool {
+    let Some(sender) = SENDER.get() else {
+        return true;
+    };
+    let (done, flushed) = mpsc::channel();
+    sender.try_send(Message::Flush(done)).is_ok() && flushed.recv_timeout(timeout).is_ok()
+}
+
+fn flush_routes(routes: &mut [Route]) {
+    for route in routes {
+        let result = route.sink.flush();
+        route.report(result);
+    }
+}
+
+fn run(receiver: Receiver<Message>, mut routes: Vec<Route>) {
+    let mut dirty = false;
+    loop {
+        let first = match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => {
+                if dirty {
+                    flush_routes(&mut routes);
+                    dirty = false;
+                }
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let mut batch = Vec::new();
+        let mut flush = None;
+        let mut next = Some(first);
+        while let Some(message) = next {
+            match message {
+                Message::Record(level, record) => batch.push((level, record)),
+                Message::Flush(done) => {
+                    flush = Some(done);
+                    break;
+                }
+            }
+            next = if batch.len() < BATCH_SIZE { receiver.try_recv().ok() } else { None };
+        }
+
+        for route in &mut routes {
+            let records: Vec<LogRecord> = batch
+                .iter()
+                .filter(|(level, _)| *level <= route.level)
+                .map(|(_, record)| record.clone())
+                .collect();
+            if !records.is_empty() {
+                let result = route.sink.write_batch(&records);
+                route.report(result);
+            }
+        }
+        dirty |= !batch.is_empty();
+        if let Some(done) = flush {
+            flush_routes(&mut routes);
+            dirty = false;
+            let _ = done.send(());
+        }
+    }
+    for route in &mut routes {
+        let _ = route.sink.flush();
+    }
+}
+
+/// Tracing layer feeding the `[logging.sinks]` thread. Records are dropped
+/// rather than blocking when the queue is full.
+pub struct LogSinkLayer {
+    sender: SyncSender<Message>,
+    level: LevelFilter,
+}
+
+impl<S: Subscriber> Layer<S> for LogSinkLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() > self.level {
+            return;
+        }
+        let _ = self.sender.try_send(Message::Record(
+            *metadata.level(),
+            LogRecord {
+                timestamp: chrono::Utc::now().to_rfc3339(),
+                level: metadata.level().to_string(),
+                target: metadata.target().to_string(),
+                message: MessageVisitor::format(event),
+                run_id: run_id(),
+            },
+        ));
+    }
+}
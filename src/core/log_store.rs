@@ -0,0 +1,286 @@
+use super::config::LoggingSettings;
+use super::database::prepare_cached;
+use super::log_stream::MessageVisitor;
+use super::{perf, run_id, AppError, AppResult, Database};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer};
+
+/// The store's own writes are never logged back into it.
+const SKIPPED_TARGET: &str = module_path!();
+const QUEUE_SIZE: usize = 4096;
+const BATCH_SIZE: usize = 256;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+const MAX_QUERY_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredLog {
+    pub id: i64,
+    /// Unix time in milliseconds.
+    pub time: i64,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub run_id: String,
+}
+
+/// Narrows a log query. `level` is a minimum severity, so `warn` matches
+/// warnings and errors; `target` matches by prefix, `search` by substring
+/// and `run_id` exactly.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LogFilter {
+    pub level: Option<String>,
+    pub target: Option<String>,
+    pub search: Option<String>,
+    pub run_id: Option<String>,
+}
+
+/// Inclusive time range in Unix milliseconds.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LogRange {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+enum Command {
+    Store(StoredLog),
+    /// Answered once every record queued before it is written.
+    Flush(oneshot::Sender<()>),
+}
+
+#[derive(Clone, Copy)]
+struct Retention {
+    max_records: i64,
+    max_age_ms: i64,
+}
+
+static LEVEL: OnceCell<LevelFilter> = OnceCell::new();
+static SENDER: OnceCell<mpsc::Sender<Command>> = OnceCell::new();
+static DATABASE: OnceCell<Arc<Database>> = OnceCell::new();
+
+/// Starts persisting records at `logging.store_level` or above into the
+/// `logs` table, pruning by record count and age. Must be called inside the
+/// runtime; records logged before this are not stored.
+pub fn start_log_store(db: Arc<Database>, settings: &LoggingSettings) {
+    let level: LevelFilter = settings.store_level.as_deref().unwrap_or("info").parse().unwrap_or(LevelFilter::INFO);
+    if level == LevelFilter::OFF || DATABASE.set(Arc::clone(&db)).is_err() {
+        return;
+    }
+    let _ = LEVEL.set(level);
+    let retention = Retention {
+        max_records: settings.store_max_records.unwrap_or(50_000) as i64,
+        max_age_ms: settings.store_retention_days.unwrap_or(7) as i64 * 24 * 60 * 60 * 1000,
+    };
+
+    let (tx, mut rx) = mpsc::channel::<Command>(QUEUE_SIZE);
+    let _ = SENDER.set(tx);
+    tokio::spawn(async move {
+        let mut last_prune: Option<Instant> = None;
+        while let Some(first) = rx.recv().await {
+            let mut batch = Vec::new();
+            let mut flush = None;
+            let mut next = Some(first);
+            while let Some(command) = next {
+                match command {
+                    Command::Store(record) => batch.push(record),
+                    Command::Flush(done) => {
+                        flush = Some(done);
+                        break;
+                    }
+                }
+                next = if batch.len() < BATCH_SIZE { rx.try_recv().ok() } else { None };
+            }
+
+            if !batch.is_empty() {
+                let prune = last_prune.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL);
+                if prune {
+                    last_prune = Some(Instant::now());
+                }
+
+                let db = Arc::clone(&db);
+                let result = tokio::task::spawn_blocking(move || {
+                    insert_batch(&db, &batch)?;
+                    if prune {
+                        prune_logs(&db, retention)?;
+                    }
+                    Ok::<_, AppError>(())
+                })
+                .await;
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => eprintln!("Failed to persist log records: {}", e),
+                    Err(e) => eprintln!("Log store task failed: {}", e),
+                }
+            }
+            if let Some(done) = flush {
+                let _ = done.send(());
+            }
+        }
+    });
+}
+
+/// Waits until records logged so far are written. Returns `false` if that
+/// takes longer than `timeout`.
+pub async fn flush(timeout: Duration) -> bool {
+    let Some(sender) = SENDER.get() else {
+        return true;
+    };
+    let (done, written) = oneshot::channel();
+    if sender.send_timeout(Command::Flush(done), timeout).await.is_err() {
+        return false;
+    }
+    tokio::time::timeout(timeout, written).await.is_ok_and(|r| r.is_ok())
+}
+
+/// Records waiting to be written; at most 4096.
+pub fn queued() -> usize {
+    SENDER.get().map(|sender| sender.max_capacity() - sender.capacity()).unwrap_or(0)
+}
+
+fn insert_batch(db: &Database, batch: &[StoredLog]) -> AppResult<()> {
+    let mut conn = db.get_connection()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = prepare_cached(
+            &tx,
+            "INSERT INTO logs (time, level, target, message, run_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for record in batch {
+            stmt.execute(rusqlite::params![record.time, record.level, record.target, record.message, record.run_id])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn prune_logs(db: &Database, retention: Retention) -> AppResult<()> {
+    let conn = db.get_connection()?;
+    let cutoff = chrono::Utc::now().timestamp_millis() - retention.max_age_ms;
+    conn.execute("DELETE FROM logs WHERE time < ?1", [cutoff])?;
+    conn.execute(
+        "DELETE FROM logs WHERE id <= (SELECT MAX(id) FROM logs) - ?1",
+        [retention.max_records],
+    )?;
+    Ok(())
+}
+
+/// Deletes stored records older than `cutoff_ms` (Unix millis) and returns
+/// how many were removed.
+pub fn prune_before(cutoff_ms: i64) -> AppResult<usize> {
+    let db = DATABASE
+        .get()
+        .ok_or_else(|| AppError::Runtime("Log store is not enabled".to_string()))?;
+    let conn = db.get_connection()?;
+    Ok(conn.execute("DELETE FROM logs WHERE time < ?1", [cutoff_ms])?)
+}
+
+/// Levels at or above `min`, e.g. `warn` gives `["ERROR", "WARN"]`.
+fn levels_at_or_above(min: &str) -> AppResult<Vec<&'static str>> {
+    let min: LevelFilter = min
+        .parse()
+        .map_err(|_| AppError::Runtime(format!("unknown level \"{}\"", min)))?;
+    Ok(["ERROR", "WARN", "INFO", "DEBUG", "TRACE"]
+        .into_iter()
+        .filter(|level| level.parse::<LevelFilter>().is_ok_and(|l| l <= min))
+        .collect())
+}
+
+/// Newest-first records matching `filter` within `range`, at most `limit`
+/// (capped at 1000).
+pub fn query_logs(filter: &LogFilter, range: &LogRange, limit: usize) -> AppResult<Vec<StoredLog>> {
+    let db = DATABASE
+        .get()
+        .ok_or_else(|| AppError::Runtime("Log store is not enabled".to_string()))?;
+
+    let mut clauses = Vec::new();
+    let mut params: Vec<rusqlite::types::Value> = Vec::new();
+    if let Some(level) = filter.level.as_deref().filter(|l| !l.is_empty()) {
+        let levels = levels_at_or_above(level)?;
+        let placeholders: Vec<String> = levels
+            .iter()
+            .map(|level| {
+                params.push(level.to_string().into());
+                format!("?{}", params.len())
+            })
+            .collect();
+        clauses.push(format!("level IN ({})", placeholders.join(", ")));
+    }
+    if let Some(target) = filter.target.as_deref().filter(|t| !t.is_empty()) {
+        params.push(format!("{}%", escape_like(target)).into());
+        clauses.push(format!("target LIKE ?{} ESCAPE '\\'", params.len()));
+    }
+    if let Some(search) = filter.search.as_deref().filter(|s| !s.is_empty()) {
+        params.push(format!("%{}%", escape_like(search)).into());
+        clauses.push(format!("message LIKE ?{} ESCAPE '\\'", params.len()));
+    }
+    if let Some(run_id) = filter.run_id.as_deref().filter(|r| !r.is_empty()) {
+        params.push(run_id.to_string().into());
+        clauses.push(format!("run_id = ?{}", params.len()));
+    }
+    if let Some(from) = range.from {
+        params.push(from.into());
+        clauses.push(format!("time >= ?{}", params.len()));
+    }
+    if let Some(to) = range.to {
+        params.push(to.into());
+        clauses.push(format!("time <= ?{}", params.len()));
+    }
+    params.push((limit.clamp(1, MAX_QUERY_LIMIT) as i64).into());
+
+    let where_clause = if clauses.is_empty() { String::new() } else { format!("WHERE {}", clauses.join(" AND ")) };
+    let sql = format!(
+        "SELECT id, time, level, target, message, run_id FROM logs {} ORDER BY time DESC, id DESC LIMIT ?{}",
+        where_clause,
+        params.len()
+    );
+
+    let _timer = perf::query("logs.query");
+    let conn = db.get_connection()?;
+    let mut stmt = prepare_cached(&conn, &sql)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+        Ok(StoredLog {
+            id: row.get(0)?,
+            time: row.get(1)?,
+            level: row.get(2)?,
+            target: row.get(3)?,
+            message: row.get(4)?,
+            run_id: row.get(5)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Tracing layer feeding [`start_log_store`]. Records are dropped rather than
+/// blocking when the queue is full.
+pub struct LogStoreLayer;
+
+impl<S: Subscriber> Layer<S> for LogStoreLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let (Some(sender), Some(level)) = (SENDER.get(), LEVEL.get()) else {
+            return;
+        };
+        let metadata = event.metadata();
+        if *metadata.level() > *level || metadata.target().starts_with(SKIPPED_TARGET) {
+            return;
+        }
+        let _ = sender.try_send(Command::Store(StoredLog {
+            id: 0,
+            time: chrono::Utc::now().timestamp_millis(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: MessageVisitor::format(event),
+            run_id: run_id().to_string(),
+        }));
+    }
+}
@@ -0,0 +1,143 @@
+use super::run_id;
+use super::{AppError, AppResult};
+use crate::event_bus::EventBus;
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Records from the event bus itself are never streamed, otherwise every
+/// forwarded record would log another one.
+const SKIPPED_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::event_bus");
+const QUEUE_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+    pub run_id: &'static str,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct StreamFilter {
+    level: LevelFilter,
+    sample_every: u64,
+}
+
+static FILTER: Lazy<RwLock<StreamFilter>> = Lazy::new(|| {
+    RwLock::new(StreamFilter {
+        level: LevelFilter::INFO,
+        sample_every: 1,
+    })
+});
+static SAMPLE_COUNTER: AtomicU64 = AtomicU64::new(0);
+static SENDER: OnceCell<mpsc::Sender<LogRecord>> = OnceCell::new();
+
+/// Sets the most verbose level streamed to the frontend, and forwards only
+/// every `sample_every`-th record below WARN. Warnings and errors are always
+/// forwarded.
+pub fn set_stream_filter(level: &str, sample_every: u64) -> AppResult<()> {
+    let level: LevelFilter = level
+        .parse()
+        .map_err(|_| AppError::Config(format!("unknown level \"{}\"", level)))?;
+    if sample_every == 0 {
+        return Err(AppError::Config("sample_every must be greater than 0".to_string()));
+    }
+    let mut filter = FILTER.write().map_err(|e| AppError::Runtime(e.to_string()))?;
+    *filter = StreamFilter { level, sample_every };
+    Ok(())
+}
+
+/// Starts forwarding records to `bus` as `log.record`. Records logged
+/// before this are not replayed. Must be called inside the runtime.
+pub fn start_log_stream(bus: EventBus) {
+    let (tx, mut rx) = mpsc::channel::<LogRecord>(QUEUE_SIZE);
+    if SENDER.set(tx).is_err() {
+        return;
+    }
+    tokio::spawn(async move {
+        while let Some(record) = rx.recv().await {
+            let payload = serde_json::to_value(&record).unwrap_or_default();
+            let _ = bus.emit_custom("log.record", payload, "log_stream").await;
+        }
+    });
+}
+
+/// Tracing layer feeding [`start_log_stream`]. Cheap while the stream isn't
+/// started; records are dropped rather than blocking when the queue is full.
+pub struct EventBusLayer;
+
+impl<S: Subscriber> Layer<S> for EventBusLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let Some(sender) = SENDER.get() else {
+            return;
+        };
+        let metadata = event.metadata();
+        if metadata.target().starts_with(SKIPPED_TARGET) {
+            return;
+        }
+
+        let filter = match FILTER.read() {
+            Ok(filter) => *filter,
+            Err(_) => return,
+        };
+        if *metadata.level() > filter.level {
+            return;
+        }
+        if *metadata.level() > Level::WARN
+            && filter.sample_every > 1
+            && !SAMPLE_COUNTER.fetch_add(1, Ordering::Relaxed).is_multiple_of(filter.sample_every)
+        {
+            return;
+        }
+
+        let _ = sender.try_send(LogRecord {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: MessageVisitor::format(event),
+            run_id: run_id(),
+        });
+    }
+}
+
+/// Collects the `message` field followed by any other fields as `key=value`.
+#[derive(Default)]
+pub(crate) struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    pub(crate) fn format(event: &Event<'_>) -> String {
+        let mut visitor = Self::default();
+        event.record(&mut visitor);
+        visitor.message + &visitor.fields
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
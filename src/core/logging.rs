@@ -1,55 +1,295 @@
+use super::config::{LoggingSettings, TelemetrySettings};
+use super::log_file::RollingFile;
+use super::log_sink;
+use super::log_store::LogStoreLayer;
+use super::log_stream::{self, EventBusLayer};
+use super::run_id;
+use super::{AppError, AppResult};
+use super::telemetry;
+use crate::build_logger::{self, LogStatsLayer};
+use crate::event_bus::EventBus;
+use once_cell::sync::{Lazy, OnceCell};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::{FormatEvent, FormatFields, Writer};
+use tracing_subscriber::fmt::FmtContext;
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{
-    fmt, fmt::format::FmtSpan, fmt::time::Uptime, layer::SubscriberExt, util::SubscriberInitExt,
-    EnvFilter,
+    filter::filter_fn, fmt, fmt::format::FmtSpan, fmt::time::SystemTime, fmt::time::Uptime, layer::SubscriberExt, reload,
+    util::SubscriberInitExt, EnvFilter, Layer, Registry,
 };
 
+const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_FILES: usize = 5;
+
+static FILTER_HANDLE: OnceCell<reload::Handle<EnvFilter, Registry>> = OnceCell::new();
+static CURRENT_LEVEL: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+static CONSOLE_QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Limits console output to warnings and errors (`--quiet`). Other
+/// destinations keep the configured level.
+pub fn set_console_quiet(quiet: bool) {
+    CONSOLE_QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Stdout, except while the startup progress is drawn live: records then go
+/// above the display so they don't tear it.
+struct ConsoleWriter;
+
+impl Write for ConsoleWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if build_logger::print_above_progress(buf) {
+            return Ok(buf.len());
+        }
+        io::stdout().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// Prefixes text records with `[<run id>]` and adds a leading `run_id` key
+/// to JSON records.
+struct WithRunId<F> {
+    inner: F,
+    json: bool,
+}
+
+impl<S, N, F> FormatEvent<S, N> for WithRunId<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> std::fmt::Result {
+        if !self.json {
+            write!(writer, "[{}] ", run_id())?;
+            return self.inner.format_event(ctx, writer, event);
+        }
+        let mut line = String::new();
+        self.inner.format_event(ctx, Writer::new(&mut line), event)?;
+        match line.strip_prefix('{') {
+            Some(rest) => write!(writer, "{{\"run_id\":\"{}\",{}", run_id(), rest),
+            None => writer.write_str(&line),
+        }
+    }
+}
+
+/// Installs the console layer, the event bus stream layer, OTLP export when
+/// `[telemetry]` is enabled, any `[logging.sinks]` and, when `logging.file`
+/// is set, a size-rotated file layer. `LOG_FORMAT` overrides `logging.format`.
+/// Rotations and `event_bus` sinks emit on `bus`.
 pub fn init_logging(
-    log_file: Option<&str>,
-    log_level: &str,
-    _append: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+    settings: &LoggingSettings,
+    telemetry_settings: &TelemetrySettings,
+    bus: &EventBus,
+) -> AppResult<()> {
+    let log_level = settings.level.as_str();
     let filter_layer = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new(log_level))
         .unwrap_or_else(|_| EnvFilter::new("info"));
+    if let Ok(mut current) = CURRENT_LEVEL.lock() {
+        *current = filter_layer.to_string();
+    }
+    let (filter_layer, filter_handle) = reload::Layer::new(filter_layer);
+    let _ = FILTER_HANDLE.set(filter_handle);
+
+    let console_format = std::env::var("LOG_FORMAT")
+        .ok()
+        .or_else(|| settings.console_format.clone())
+        .or_else(|| settings.format.clone())
+        .unwrap_or_else(|| "text".to_string())
+        .to_lowercase();
+    let file_format = settings
+        .file_format
+        .clone()
+        .or_else(|| settings.format.clone())
+        .unwrap_or_else(|| "text".to_string())
+        .to_lowercase();
 
-    let is_json_format = std::env::var("LOG_FORMAT")
-        .unwrap_or_else(|_| "text".to_string())
-        .to_lowercase()
-        == "json";
+    let console_layer = match console_format.as_str() {
+        "json" => fmt::layer()
+            .json()
+            .with_file(true)
+            .with_line_number(true)
+            .with_target(true)
+            .with_timer(Uptime::default())
+            .with_span_events(FmtSpan::CLOSE)
+            .map_event_format(|format| WithRunId { inner: format, json: true })
+            .with_writer(|| ConsoleWriter)
+            .boxed(),
+        "pretty" => fmt::layer()
+            .pretty()
+            .with_file(true)
+            .with_line_number(true)
+            .with_target(true)
+            .with_thread_names(true)
+            .with_timer(Uptime::default())
+            .map_event_format(|format| WithRunId { inner: format, json: false })
+            .with_writer(|| ConsoleWriter)
+            .boxed(),
+        _ => fmt::layer()
+            .with_file(true)
+            .with_line_number(true)
+            .with_target(true)
+            .with_thread_ids(true)
+            .with_thread_names(true)
+            .with_timer(Uptime::default())
+            .with_span_events(FmtSpan::CLOSE)
+            .map_event_format(|format| WithRunId { inner: format, json: false })
+            .with_writer(|| ConsoleWriter)
+            .boxed(),
+    };
+    let console_layer = console_layer.with_filter(filter_fn(|metadata| {
+        *metadata.level() <= Level::WARN
+            || !(CONSOLE_QUIET.load(Ordering::Relaxed) || build_logger::live_progress_active())
+    }));
 
-    if is_json_format {
-        tracing_subscriber::registry()
-            .with(filter_layer)
-            .with(
+    let log_file = Some(settings.file.trim()).filter(|f| !f.is_empty());
+    let file_layer = match log_file {
+        Some(path) => {
+            let writer = Mutex::new(RollingFile::open(
+                Path::new(path),
+                settings.append.unwrap_or(true),
+                settings.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE),
+                settings.max_files.unwrap_or(DEFAULT_MAX_FILES),
+            )?
+            .with_compression(settings.compress_rotated.unwrap_or(false), settings.max_total_size)
+            .with_events(bus));
+            let layer = if file_format == "json" {
                 fmt::layer()
                     .json()
+                    .with_ansi(false)
                     .with_file(true)
                     .with_line_number(true)
                     .with_target(true)
-                    .with_timer(Uptime::default())
-                    .with_span_events(FmtSpan::CLOSE),
-            )
-            .init();
-    } else {
-        tracing_subscriber::registry()
-            .with(filter_layer)
-            .with(
+                    .with_timer(SystemTime)
+                    .map_event_format(|format| WithRunId { inner: format, json: true })
+                    .with_writer(writer)
+                    .boxed()
+            } else {
                 fmt::layer()
+                    .with_ansi(false)
                     .with_file(true)
                     .with_line_number(true)
                     .with_target(true)
-                    .with_thread_ids(true)
                     .with_thread_names(true)
-                    .with_timer(Uptime::default())
-                    .with_span_events(FmtSpan::CLOSE),
-            )
-            .init();
+                    .with_timer(SystemTime)
+                    .map_event_format(|format| WithRunId { inner: format, json: false })
+                    .with_writer(writer)
+                    .boxed()
+            };
+            Some(layer)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(console_layer)
+        .with(file_layer)
+        .with(EventBusLayer)
+        .with(LogStoreLayer)
+        .with(LogStatsLayer)
+        .with(log_sink::layer(settings, bus)?)
+        .with(telemetry::layers(telemetry_settings)?)
+        .init();
+
+    if let Err(e) = log_stream::set_stream_filter(
+        settings.stream_level.as_deref().unwrap_or("info"),
+        settings.stream_sample_every.unwrap_or(1),
+    ) {
+        tracing::warn!("Invalid log stream settings: {}", e);
     }
 
-    tracing::info!("Logging initialized with level: {}", log_level);
+    tracing::info!("Logging initialized with level: {} (run {})", log_level, run_id());
     if let Some(file) = log_file {
         tracing::info!("Log file: {}", file);
     }
 
     Ok(())
 }
+
+/// Replaces the active log filter without restarting.
+pub fn set_log_level(log_level: &str) -> AppResult<()> {
+    let filter = EnvFilter::try_new(log_level)
+        .map_err(|e| AppError::Config(format!("invalid log level \"{}\": {}", log_level, e)))?;
+    let handle = FILTER_HANDLE
+        .get()
+        .ok_or_else(|| AppError::Runtime("Logging not initialized".to_string()))?;
+    handle.reload(filter).map_err(|e| AppError::Runtime(e.to_string()))?;
+    if let Ok(mut current) = CURRENT_LEVEL.lock() {
+        *current = log_level.to_string();
+    }
+    tracing::info!("Log level changed to: {}", log_level);
+    Ok(())
+}
+
+/// The active filter directives, e.g. `info,rustwebui_app::plugins=debug`.
+pub fn current_log_level() -> String {
+    CURRENT_LEVEL.lock().map(|level| level.clone()).unwrap_or_default()
+}
+
+/// Changes the global level, or one module's level when `target` is given,
+/// keeping the other directives. Returns the new directive string.
+pub fn set_target_log_level(level: &str, target: Option<&str>) -> AppResult<String> {
+    let directives = with_target_level(&current_log_level(), level, target);
+    set_log_level(&directives)?;
+    Ok(directives)
+}
+
+/// `current` with the global level, or `target`'s level, replaced by `level`.
+fn with_target_level(current: &str, level: &str, target: Option<&str>) -> String {
+    let level = level.trim().to_lowercase();
+    let mut global = None;
+    let mut targets: Vec<(String, String)> = Vec::new();
+    for directive in current.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        match directive.rsplit_once('=') {
+            Some((name, value)) => targets.push((name.to_string(), value.to_string())),
+            None => global = Some(directive.to_string()),
+        }
+    }
+
+    match target.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(target) => {
+            targets.retain(|(name, _)| name != target);
+            targets.push((target.to_string(), level));
+        }
+        None => global = Some(level),
+    }
+
+    let directives: Vec<String> = global
+        .into_iter()
+        .chain(targets.into_iter().map(|(name, value)| format!("{}={}", name, value)))
+        .collect();
+    directives.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_levels_replace_only_their_own_directive() {
+        let current = "info,rustwebui_app::plugins=debug,rustwebui_app::server=warn";
+        assert_eq!(
+            with_target_level(current, " TRACE ", Some("rustwebui_app::plugins")),
+            "info,rustwebui_app::server=warn,rustwebui_app::plugins=trace"
+        );
+        assert_eq!(
+            with_target_level(current, "error", Some("rustwebui_app::scheduler")),
+            "info,rustwebui_app::plugins=debug,rustwebui_app::server=warn,rustwebui_app::scheduler=error"
+        );
+    }
+
+    #[test]
+    fn the_global_level_keeps_module_directives() {
+        assert_eq!(with_target_level("info, tiny_http=warn", "debug", None), "debug,tiny_http=warn");
+        assert_eq!(with_target_level("info", "warn", Some("  ")), "warn");
+        assert_eq!(with_target_level("", "debug", Some("rustwebui_app")), "rustwebui_app=debug");
+    }
+}
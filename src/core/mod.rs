@@ -1,9 +1,31 @@
+pub mod analytics;
+pub mod bootstrap;
+pub mod cache;
 pub mod config;
+pub mod config_migration;
+pub mod config_writer;
+pub mod crash;
 pub mod database;
 pub mod error;
+pub mod i18n;
+pub mod log_file;
+pub mod log_retention;
+pub mod log_sink;
+pub mod log_store;
+pub mod log_stream;
 pub mod logging;
+pub mod paths;
+pub mod perf;
+pub mod resources;
+pub mod response;
+pub mod run;
+pub mod secrets;
+pub mod supervisor;
+pub mod telemetry;
 
 pub use config::AppConfig;
 pub use database::Database;
 pub use error::{AppError, AppResult};
-pub use logging::init_logging;
+pub use response::{ErrorCode, RpcResponse};
+pub use logging::{current_log_level, init_logging, set_console_quiet, set_log_level, set_target_log_level};
+pub use run::{run_id, uptime};
@@ -0,0 +1,112 @@
+//! Platform data locations (XDG on Linux, AppData on Windows, Application
+//! Support on macOS) and migration of files from the old working-directory
+//! layout.
+
+use crate::core::AppConfig;
+use directories::ProjectDirs;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", env!("CARGO_PKG_NAME"))
+}
+
+/// Directory searched for a config file when none is found in the working directory.
+pub fn config_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+/// Machine-wide config directory: `/etc/<app>` on Unix, `%ProgramData%\<app>` on Windows.
+pub fn system_config_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        std::env::var_os("ProgramData").map(|dir| PathBuf::from(dir).join(env!("CARGO_PKG_NAME")))
+    } else {
+        Some(Path::new("/etc").join(env!("CARGO_PKG_NAME")))
+    }
+}
+
+/// `[paths] data_dir` if set, otherwise the platform data directory.
+pub fn data_dir(override_dir: Option<&str>) -> PathBuf {
+    override_dir
+        .map(PathBuf::from)
+        .or_else(|| project_dirs().map(|dirs| dirs.data_dir().to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// `[paths] log_dir` if set, otherwise the platform state (or local data) directory.
+pub fn log_dir(override_dir: Option<&str>) -> PathBuf {
+    override_dir
+        .map(PathBuf::from)
+        .or_else(|| {
+            project_dirs().map(|dirs| match dirs.state_dir() {
+                Some(state) => state.join("logs"),
+                None => dirs.data_local_dir().join("logs"),
+            })
+        })
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Joins a relative path onto `base`; absolute paths are returned unchanged.
+pub fn resolve(base: &Path, path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() { path.to_path_buf() } else { base.join(path) }
+}
+
+/// Moves files left in the working directory by older versions to their
+/// resolved locations (unless something already exists there) and creates
+/// the directories the app writes to.
+pub fn prepare_data_paths(config: &AppConfig) {
+    let in_use = [config.get_db_path(), config.get_log_file(), config.get_plugin_data_dir()];
+    for (legacy, target) in &config.relocated_paths {
+        // Skip paths that a command-line flag has since replaced.
+        if !in_use.iter().any(|path| Path::new(path) == target) {
+            continue;
+        }
+        let mut moves = vec![(legacy.clone(), target.clone())];
+        if target.as_path() == Path::new(config.get_db_path()) {
+            for suffix in ["-wal", "-shm"] {
+                moves.push((with_suffix(legacy, suffix), with_suffix(target, suffix)));
+            }
+        }
+        for (from, to) in moves {
+            if !from.exists() || to.exists() {
+                continue;
+            }
+            match move_path(&from, &to) {
+                Ok(()) => info!("Migrated {:?} to {:?}", from, to),
+                Err(e) => warn!("Failed to migrate {:?} to {:?}: {}", from, to, e),
+            }
+        }
+    }
+
+    for path in [config.get_db_path(), config.get_log_file()] {
+        if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty())
+            && let Err(e) = fs::create_dir_all(parent)
+        {
+            warn!("Failed to create {:?}: {}", parent, e);
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        // Renames fail across filesystems; fall back to copy + remove for files.
+        Err(_) if from.is_file() => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        Err(e) => Err(e),
+    }
+}
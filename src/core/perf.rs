@@ -0,0 +1,159 @@
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::span::EnteredSpan;
+
+/// Latencies kept per operation for the percentiles.
+const MAX_SAMPLES: usize = 512;
+
+static SLOW_HANDLER_US: AtomicU64 = AtomicU64::new(100_000);
+static SLOW_QUERY_US: AtomicU64 = AtomicU64::new(50_000);
+static OPERATIONS: Lazy<Mutex<HashMap<(OpKind, &'static str), Samples>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpKind {
+    Handler,
+    Query,
+}
+
+impl OpKind {
+    fn label(self) -> &'static str {
+        match self {
+            OpKind::Handler => "handler",
+            OpKind::Query => "query",
+        }
+    }
+
+    fn threshold(self) -> Duration {
+        let micros = match self {
+            OpKind::Handler => SLOW_HANDLER_US.load(Ordering::Relaxed),
+            OpKind::Query => SLOW_QUERY_US.load(Ordering::Relaxed),
+        };
+        Duration::from_micros(micros)
+    }
+}
+
+#[derive(Default)]
+struct Samples {
+    recent: VecDeque<u64>,
+    count: u64,
+    slow: u64,
+    max_us: u64,
+}
+
+/// Latency summary of one handler or query since startup. Percentiles cover
+/// the most recent 512 calls.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpStats {
+    pub kind: OpKind,
+    pub name: String,
+    pub count: u64,
+    pub slow: u64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Durations above which handlers and queries are logged as slow.
+pub fn set_thresholds(handler: Duration, query: Duration) {
+    SLOW_HANDLER_US.store(handler.as_micros() as u64, Ordering::Relaxed);
+    SLOW_QUERY_US.store(query.as_micros() as u64, Ordering::Relaxed);
+}
+
+/// Times a WebUI handler until dropped, inside a `handler` span, and counts
+/// the call for usage analytics. Work the handler spawns is not included.
+pub fn handler(name: &'static str) -> OpTimer {
+    super::analytics::record_command(name);
+    OpTimer::start(OpKind::Handler, name, tracing::debug_span!("handler", name).entered())
+}
+
+/// Times a database call until dropped, inside a `db_query` span.
+pub fn query(name: &'static str) -> OpTimer {
+    OpTimer::start(OpKind::Query, name, tracing::debug_span!("db_query", name).entered())
+}
+
+/// Records its lifetime as one call of an operation and warns when it runs
+/// over the operation's threshold. Not `Send`: drop it before an `.await`.
+pub struct OpTimer {
+    kind: OpKind,
+    name: &'static str,
+    started: Instant,
+    _span: EnteredSpan,
+}
+
+impl OpTimer {
+    fn start(kind: OpKind, name: &'static str, span: EnteredSpan) -> Self {
+        Self { kind, name, started: Instant::now(), _span: span }
+    }
+}
+
+impl Drop for OpTimer {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        let threshold = self.kind.threshold();
+        let slow = elapsed > threshold;
+        record(self.kind, self.name, elapsed, slow);
+        if slow {
+            tracing::warn!(
+                "Slow {} {}: {:.1}ms (threshold {}ms)",
+                self.kind.label(),
+                self.name,
+                elapsed.as_secs_f64() * 1000.0,
+                threshold.as_millis()
+            );
+        }
+    }
+}
+
+fn record(kind: OpKind, name: &'static str, elapsed: Duration, slow: bool) {
+    let Ok(mut operations) = OPERATIONS.lock() else {
+        return;
+    };
+    let samples = operations.entry((kind, name)).or_default();
+    let micros = elapsed.as_micros() as u64;
+    if samples.recent.len() == MAX_SAMPLES {
+        samples.recent.pop_front();
+    }
+    samples.recent.push_back(micros);
+    samples.count += 1;
+    samples.slow += u64::from(slow);
+    samples.max_us = samples.max_us.max(micros);
+}
+
+/// Nearest-rank percentile of sorted samples, in milliseconds.
+fn percentile(sorted: &[u64], percent: usize) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1] as f64 / 1000.0
+}
+
+/// Every operation called so far, slowest p95 first.
+pub fn report() -> Vec<OpStats> {
+    let Ok(operations) = OPERATIONS.lock() else {
+        return Vec::new();
+    };
+    let mut stats: Vec<OpStats> = operations
+        .iter()
+        .map(|((kind, name), samples)| {
+            let mut sorted: Vec<u64> = samples.recent.iter().copied().collect();
+            sorted.sort_unstable();
+            OpStats {
+                kind: *kind,
+                name: name.to_string(),
+                count: samples.count,
+                slow: samples.slow,
+                p50_ms: percentile(&sorted, 50),
+                p95_ms: percentile(&sorted, 95),
+                max_ms: samples.max_us as f64 / 1000.0,
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms));
+    stats
+}
@@ -0,0 +1,194 @@
+//! Periodic samples of the process's own resource use: memory, open files,
+//! tokio tasks and the event and log queues. Crossing a limit from the
+//! `[monitoring]` section emits `resources.limit_exceeded` once, until the
+//! value drops back under the limit.
+
+use super::{log_store, supervisor::Task, AppConfig};
+use crate::event_bus::EventBus;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeSet;
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Supervisor name of the resource monitor.
+pub const RESOURCE_MONITOR_TASK: &str = "resource_monitor";
+
+static SETTINGS: Lazy<RwLock<(Duration, ResourceLimits)>> =
+    Lazy::new(|| RwLock::new((Duration::from_secs(30), ResourceLimits::default())));
+
+/// Thresholds checked on every sample. `None` disables a check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ResourceLimits {
+    pub max_rss_mb: Option<u64>,
+    pub max_open_files: Option<u64>,
+    pub max_tasks: Option<u64>,
+    pub max_queued_events: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            max_rss_mb: config.monitoring.max_rss_mb,
+            max_open_files: config.monitoring.max_open_files,
+            max_tasks: config.monitoring.max_tasks,
+            max_queued_events: config.monitoring.max_queued_events,
+        }
+    }
+}
+
+/// One sample. `rss_bytes` and `open_files` are `None` where the platform
+/// does not expose them (currently everywhere but Linux).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResourceUsage {
+    pub rss_bytes: Option<u64>,
+    pub open_files: Option<u64>,
+    pub runtime_workers: usize,
+    /// Tokio tasks that have been spawned and not yet finished.
+    pub tasks: usize,
+    /// Tasks waiting in the runtime's global queue.
+    pub queued_tasks: usize,
+    pub event_history: usize,
+    /// Events broadcast but not yet read by every receiver.
+    pub queued_events: usize,
+    /// Listener tasks started by `emit` that are still running.
+    pub running_listeners: usize,
+    /// Log records waiting to be written to the `logs` table.
+    pub queued_log_records: usize,
+    /// Unix milliseconds.
+    pub sampled_at: i64,
+}
+
+impl ResourceUsage {
+    /// Each limit the sample is over, as `(resource, value, limit)`.
+    pub fn exceeded(&self, limits: &ResourceLimits) -> Vec<(&'static str, u64, u64)> {
+        [
+            ("rss_mb", self.rss_bytes.map(|bytes| bytes / (1024 * 1024)), limits.max_rss_mb),
+            ("open_files", self.open_files, limits.max_open_files),
+            ("tasks", Some(self.tasks as u64), limits.max_tasks),
+            ("queued_events", Some(self.queued_events as u64), limits.max_queued_events),
+        ]
+        .into_iter()
+        .filter_map(|(resource, value, limit)| match (value, limit) {
+            (Some(value), Some(limit)) if value > limit => Some((resource, value, limit)),
+            _ => None,
+        })
+        .collect()
+    }
+}
+
+/// Takes a sample now, with the queue sizes of `bus`. Must be called inside
+/// the runtime for the task counts.
+pub fn sample(bus: &EventBus) -> ResourceUsage {
+    let mut usage = ResourceUsage {
+        rss_bytes: read_rss_bytes(),
+        open_files: count_open_files(),
+        event_history: bus.history_len(),
+        queued_events: bus.queued(),
+        running_listeners: bus.in_flight(),
+        queued_log_records: log_store::queued(),
+        sampled_at: chrono::Utc::now().timestamp_millis(),
+        ..Default::default()
+    };
+    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+        let metrics = runtime.metrics();
+        usage.runtime_workers = metrics.num_workers();
+        usage.tasks = metrics.num_alive_tasks();
+        usage.queued_tasks = metrics.global_queue_depth();
+    }
+    usage
+}
+
+pub fn limits() -> ResourceLimits {
+    SETTINGS.read().map(|settings| settings.1).unwrap_or_default()
+}
+
+/// Applies the poll interval and limits from `config`, from the next sample on.
+pub fn configure(config: &AppConfig) {
+    if let Ok(mut settings) = SETTINGS.write() {
+        *settings = (config.get_resource_poll_interval(), ResourceLimits::from_config(config));
+    }
+}
+
+/// Starts sampling as the supervised [`RESOURCE_MONITOR_TASK`], reporting
+/// limits exceeded on `bus`.
+pub fn start_resource_monitor(config: &AppConfig, bus: EventBus) {
+    configure(config);
+    Task::new(RESOURCE_MONITOR_TASK).with_events(&bus).spawn(move |_| {
+        let bus = bus.clone();
+        async move {
+            let mut over = BTreeSet::new();
+            loop {
+                let interval = SETTINGS.read().map(|settings| settings.0).unwrap_or(Duration::from_secs(30));
+                tokio::time::sleep(interval).await;
+
+                let usage = sample(&bus);
+                let exceeded = usage.exceeded(&limits());
+                for (resource, value, limit) in &exceeded {
+                    if !over.insert(*resource) {
+                        continue;
+                    }
+                    warn!("Resource limit exceeded: {} is {} (limit {})", resource, value, limit);
+                    let payload = json!({ "resource": resource, "value": value, "limit": limit, "usage": usage });
+                    if let Err(e) = bus.emit_custom("resources.limit_exceeded", payload, "resource_monitor").await {
+                        error!("Failed to emit resource limit event: {}", e);
+                    }
+                }
+                over.retain(|resource| {
+                    let still_over = exceeded.iter().any(|(name, _, _)| name == resource);
+                    if !still_over {
+                        info!("Resource back under its limit: {}", resource);
+                    }
+                    still_over
+                });
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn read_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_rss_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn count_open_files() -> Option<u64> {
+    std::fs::read_dir("/proc/self/fd").ok().map(|entries| entries.count() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn count_open_files() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_set_limits_are_checked() {
+        let usage = ResourceUsage {
+            rss_bytes: Some(300 * 1024 * 1024),
+            open_files: None,
+            tasks: 40,
+            ..Default::default()
+        };
+        let limits = ResourceLimits {
+            max_rss_mb: Some(256),
+            max_open_files: Some(10),
+            max_tasks: Some(50),
+            max_queued_events: None,
+        };
+        assert_eq!(usage.exceeded(&limits), vec![("rss_mb", 300, 256)]);
+    }
+}
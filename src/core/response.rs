@@ -0,0 +1,136 @@
+use super::{i18n, AppError};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Stable failure code sent to the frontend, e.g. `database` or `config`, so
+/// it can branch on the kind of failure instead of the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Config,
+    Database,
+    DatabaseConnection,
+    Io,
+    Serialization,
+    Plugin,
+    Window,
+    EventBus,
+    #[serde(rename = "websocket")]
+    WebSocket,
+    HttpServer,
+    PortUnavailable,
+    Init,
+    Runtime,
+    /// A handler was called with missing or malformed arguments.
+    InvalidArgument,
+    /// The record a request names does not exist.
+    NotFound,
+    /// The request clashes with existing data, such as a taken email.
+    Conflict,
+}
+
+impl From<&AppError> for ErrorCode {
+    fn from(error: &AppError) -> Self {
+        match error {
+            AppError::Config(_) => ErrorCode::Config,
+            AppError::Database(_) => ErrorCode::Database,
+            AppError::DatabaseConnection(_) => ErrorCode::DatabaseConnection,
+            AppError::Io(_) => ErrorCode::Io,
+            AppError::Serialization(_) => ErrorCode::Serialization,
+            AppError::Plugin(_) => ErrorCode::Plugin,
+            AppError::Window(_) => ErrorCode::Window,
+            AppError::EventBus(_) => ErrorCode::EventBus,
+            AppError::WebSocket(_) => ErrorCode::WebSocket,
+            AppError::HttpServer(_) => ErrorCode::HttpServer,
+            AppError::PortUnavailable(_) => ErrorCode::PortUnavailable,
+            AppError::Init(_) => ErrorCode::Init,
+            AppError::Runtime(_) => ErrorCode::Runtime,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RpcError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Value,
+}
+
+/// Envelope of every payload sent to the frontend:
+/// `{"ok": true, "data": ..., "error": null}` on success and
+/// `{"ok": false, "data": null, "error": {"code", "message", "details"}}` on
+/// failure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RpcResponse {
+    pub ok: bool,
+    pub data: Value,
+    pub error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn ok(data: impl Serialize) -> Self {
+        Self {
+            ok: true,
+            data: serde_json::to_value(data).unwrap_or_default(),
+            error: None,
+        }
+    }
+
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            data: Value::Null,
+            error: Some(RpcError { code, message: message.into(), details: Value::Null }),
+        }
+    }
+
+    /// A failure whose message is the catalog entry `key` in the UI
+    /// language, e.g. `validation.missing_level`.
+    pub fn localized(code: ErrorCode, key: &str, args: &[(&str, &str)]) -> Self {
+        Self::error(code, i18n::t(key, args))
+    }
+
+    /// The failure form of `error`, with its message in the UI language.
+    pub fn from_error(error: &AppError) -> Self {
+        Self::localized(error.into(), error.message_key(), &[("detail", &error.detail())])
+    }
+
+    /// Extra context for a failure, e.g. the argument that was rejected.
+    /// Ignored on success.
+    pub fn with_details(mut self, details: Value) -> Self {
+        if let Some(error) = self.error.as_mut() {
+            error.details = details;
+        }
+        self
+    }
+
+    /// Wraps a bare event payload as a success. Payloads that already are
+    /// envelopes, such as [`AppError::to_payload`], are kept as they are.
+    pub fn from_payload(payload: Value) -> Self {
+        match serde_json::from_value(payload.clone()) {
+            Ok(response) => response,
+            Err(_) => Self::ok(payload),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelopes_are_not_wrapped_twice() {
+        let failure = AppError::Config("missing key".to_string()).to_payload();
+        assert_eq!(RpcResponse::from_payload(failure.clone()).to_value(), failure);
+
+        let success = RpcResponse::from_payload(serde_json::json!({ "ok": 1, "count": 2 }));
+        assert!(success.ok);
+        assert_eq!(success.data["count"], 2);
+    }
+}
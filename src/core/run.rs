@@ -0,0 +1,20 @@
+use once_cell::sync::Lazy;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+static RUN_ID: Lazy<String> = Lazy::new(|| {
+    Lazy::force(&STARTED_AT);
+    Uuid::new_v4().simple().to_string()[..12].to_string()
+});
+static STARTED_AT: Lazy<Instant> = Lazy::new(Instant::now);
+
+/// Identifies this process run. Attached to log records, event metadata and
+/// crash logs so output from successive restarts can be told apart.
+pub fn run_id() -> &'static str {
+    &RUN_ID
+}
+
+/// Time since the run ID was first used, which is as soon as logging starts.
+pub fn uptime() -> Duration {
+    STARTED_AT.elapsed()
+}
@@ -0,0 +1,69 @@
+//! Secrets stored in the OS keyring (Keychain, Credential Manager, Secret
+//! Service). Config values of the form `keyring:<name>` are replaced with the
+//! stored secret at load time so they never sit in plaintext config files.
+
+use crate::core::{AppError, AppResult};
+use std::collections::BTreeMap;
+
+pub const REFERENCE_PREFIX: &str = "keyring:";
+
+const SERVICE: &str = env!("CARGO_PKG_NAME");
+
+fn entry(name: &str) -> AppResult<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name).map_err(|e| AppError::Config(format!("Keyring error for '{}': {}", name, e)))
+}
+
+pub fn get_secret(name: &str) -> AppResult<String> {
+    entry(name)?.get_password().map_err(|e| match e {
+        keyring::Error::NoEntry => AppError::Config(format!("Secret '{}' not found in the OS keyring", name)),
+        e => AppError::Config(format!("Failed to read secret '{}': {}", name, e)),
+    })
+}
+
+pub fn set_secret(name: &str, value: &str) -> AppResult<()> {
+    entry(name)?
+        .set_password(value)
+        .map_err(|e| AppError::Config(format!("Failed to store secret '{}': {}", name, e)))
+}
+
+pub fn delete_secret(name: &str) -> AppResult<()> {
+    entry(name)?
+        .delete_credential()
+        .map_err(|e| AppError::Config(format!("Failed to delete secret '{}': {}", name, e)))
+}
+
+/// Replaces every `keyring:<name>` string in `table` with its secret and
+/// returns the dotted keys that were resolved, mapped to their reference.
+pub fn resolve_references(table: &mut toml::Table) -> AppResult<BTreeMap<String, String>> {
+    let mut resolved = BTreeMap::new();
+    resolve_table("", table, &mut resolved)?;
+    Ok(resolved)
+}
+
+fn resolve_table(prefix: &str, table: &mut toml::Table, resolved: &mut BTreeMap<String, String>) -> AppResult<()> {
+    for (key, value) in table.iter_mut() {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        resolve_value(&path, value, resolved)?;
+    }
+    Ok(())
+}
+
+fn resolve_value(path: &str, value: &mut toml::Value, resolved: &mut BTreeMap<String, String>) -> AppResult<()> {
+    match value {
+        toml::Value::String(raw) => {
+            if let Some(name) = raw.strip_prefix(REFERENCE_PREFIX) {
+                let secret = get_secret(name).map_err(|e| AppError::Config(format!("{}: {}", path, e)))?;
+                resolved.insert(path.to_string(), raw.clone());
+                *raw = secret;
+            }
+        }
+        toml::Value::Table(table) => resolve_table(path, table, resolved)?,
+        toml::Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                resolve_value(&format!("{}[{}]", path, index), item, resolved)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,354 @@
+//! Named background tasks that are restarted when they fail. A task that
+//! returns an error, panics, or misses its heartbeat is handled according to
+//! its [`RestartPolicy`], with exponential backoff between restarts.
+
+use super::AppResult;
+use crate::event_bus::EventBus;
+use futures::FutureExt;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::task::AbortHandle;
+use tracing::{error, info, warn};
+
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+static TASKS: Lazy<Mutex<BTreeMap<String, Entry>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static STOPPING: AtomicBool = AtomicBool::new(false);
+static HEALTH_CHECK: std::sync::Once = std::sync::Once::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RestartPolicy {
+    /// Restart after an error, panic or missed heartbeat, giving up after
+    /// `max_restarts` failures in a row.
+    OnFailure { max_restarts: u32 },
+    /// Restart whenever the task ends, including when it returns `Ok`.
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    Restarting,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub state: TaskState,
+    pub policy: RestartPolicy,
+    pub restarts: u32,
+    pub last_error: Option<String>,
+    /// Unix milliseconds when the current run started.
+    pub started_at: i64,
+    /// Milliseconds since the last heartbeat, for tasks with a heartbeat timeout.
+    pub heartbeat_age_ms: Option<i64>,
+    pub healthy: bool,
+}
+
+/// Passed to each run of a task.
+#[derive(Clone)]
+pub struct TaskContext {
+    heartbeat: Arc<AtomicI64>,
+}
+
+impl TaskContext {
+    /// Marks the task alive. Tasks with a heartbeat timeout must call this
+    /// more often than the timeout or they are restarted.
+    pub fn heartbeat(&self) {
+        self.heartbeat.store(now_ms(), Ordering::Relaxed);
+    }
+}
+
+struct Entry {
+    generation: u64,
+    policy: RestartPolicy,
+    heartbeat_timeout: Option<Duration>,
+    state: TaskState,
+    restarts: u32,
+    last_error: Option<String>,
+    started_at: i64,
+    heartbeat: Arc<AtomicI64>,
+    /// Wakes the supervisor to restart a run that missed its heartbeat.
+    stalled: Arc<Notify>,
+    abort: Option<AbortHandle>,
+}
+
+impl Entry {
+    fn heartbeat_age(&self, now: i64) -> Option<i64> {
+        self.heartbeat_timeout.map(|_| now - self.heartbeat.load(Ordering::Relaxed))
+    }
+
+    fn is_stalled(&self, now: i64) -> bool {
+        match (self.state, self.heartbeat_timeout, self.heartbeat_age(now)) {
+            (TaskState::Running, Some(timeout), Some(age)) => age > timeout.as_millis() as i64,
+            _ => false,
+        }
+    }
+}
+
+/// A background task to start under supervision.
+pub struct Task {
+    name: String,
+    policy: RestartPolicy,
+    heartbeat_timeout: Option<Duration>,
+    events: Option<EventBus>,
+}
+
+impl Task {
+    /// Defaults to [`RestartPolicy::OnFailure`] with 5 restarts and no
+    /// heartbeat timeout.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            policy: RestartPolicy::OnFailure { max_restarts: 5 },
+            heartbeat_timeout: None,
+            events: None,
+        }
+    }
+
+    /// Reports failures on `bus` as `task.failed`.
+    pub fn with_events(mut self, bus: &EventBus) -> Self {
+        self.events = Some(bus.clone());
+        self
+    }
+
+    pub fn with_policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_heartbeat_timeout(mut self, timeout: Duration) -> Self {
+        self.heartbeat_timeout = Some(timeout);
+        self
+    }
+
+    /// Starts the task on the current runtime, calling `run` again for each
+    /// restart. A running task with the same name is stopped first.
+    pub fn spawn<F, Fut>(self, run: F)
+    where
+        F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = AppResult<()>> + Send + 'static,
+    {
+        HEALTH_CHECK.call_once(|| {
+            tokio::spawn(check_health());
+        });
+        stop(&self.name);
+
+        let generation = GENERATION.fetch_add(1, Ordering::Relaxed);
+        let heartbeat = Arc::new(AtomicI64::new(now_ms()));
+        let stalled = Arc::new(Notify::new());
+        let mut tasks = TASKS.lock().unwrap();
+        tasks.insert(
+            self.name.clone(),
+            Entry {
+                generation,
+                policy: self.policy,
+                heartbeat_timeout: self.heartbeat_timeout,
+                state: TaskState::Running,
+                restarts: 0,
+                last_error: None,
+                started_at: chrono::Utc::now().timestamp_millis(),
+                heartbeat: Arc::clone(&heartbeat),
+                stalled: Arc::clone(&stalled),
+                abort: None,
+            },
+        );
+        let handle =
+            tokio::spawn(supervise(self.name.clone(), generation, self.policy, heartbeat, stalled, self.events, run));
+        if let Some(entry) = tasks.get_mut(&self.name) {
+            entry.abort = Some(handle.abort_handle());
+        }
+    }
+}
+
+async fn supervise<F, Fut>(
+    name: String,
+    generation: u64,
+    policy: RestartPolicy,
+    heartbeat: Arc<AtomicI64>,
+    stalled: Arc<Notify>,
+    events: Option<EventBus>,
+    run: F,
+) where
+    F: Fn(TaskContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = AppResult<()>> + Send + 'static,
+{
+    let mut failures = 0u32;
+    loop {
+        heartbeat.store(now_ms(), Ordering::Relaxed);
+        let context = TaskContext { heartbeat: Arc::clone(&heartbeat) };
+        let started = Instant::now();
+        let outcome = tokio::select! {
+            result = AssertUnwindSafe(run(context)).catch_unwind() => match result {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(panic) => Some(panic_message(&panic)),
+            },
+            _ = stalled.notified() => Some("missed its heartbeat".to_string()),
+        };
+        if STOPPING.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // A run that stayed up for a while starts the failure count over.
+        if started.elapsed() >= MAX_BACKOFF {
+            failures = 0;
+        }
+        let failed = outcome.is_some();
+        failures += u32::from(failed);
+        let restart = match policy {
+            RestartPolicy::OnFailure { max_restarts } => failed && failures <= max_restarts,
+            RestartPolicy::Always => true,
+        };
+        let backoff = MIN_BACKOFF.saturating_mul(1 << failures.saturating_sub(1).min(6)).min(MAX_BACKOFF);
+
+        let updated = update(&name, generation, |entry| {
+            entry.state = match (restart, failed) {
+                (true, _) => TaskState::Restarting,
+                (false, true) => TaskState::Failed,
+                (false, false) => TaskState::Completed,
+            };
+            if let Some(error) = &outcome {
+                entry.last_error = Some(error.clone());
+            }
+        });
+        if !updated {
+            return;
+        }
+
+        match &outcome {
+            Some(error) if restart => warn!("Task {} failed: {}; restarting in {:?}", name, error, backoff),
+            Some(error) => error!("Task {} failed: {}; not restarting", name, error),
+            None if restart => info!("Task {} exited; restarting in {:?}", name, backoff),
+            None => info!("Task {} completed", name),
+        }
+        if let (Some(error), Some(bus)) = (outcome, &events) {
+            let payload = json!({ "task": name, "error": error, "restarting": restart });
+            if let Err(e) = bus.emit_custom("task.failed", payload, "supervisor").await {
+                error!("Failed to emit task failed event: {}", e);
+            }
+        }
+        if !restart {
+            return;
+        }
+
+        tokio::time::sleep(if failed { backoff } else { MIN_BACKOFF }).await;
+        let restarted = update(&name, generation, |entry| {
+            entry.state = TaskState::Running;
+            entry.restarts += 1;
+            entry.started_at = chrono::Utc::now().timestamp_millis();
+        });
+        if !restarted || STOPPING.load(Ordering::Relaxed) {
+            return;
+        }
+    }
+}
+
+/// Applies `change` if the entry still belongs to this run of the task.
+fn update(name: &str, generation: u64, change: impl FnOnce(&mut Entry)) -> bool {
+    let mut tasks = TASKS.lock().unwrap();
+    match tasks.get_mut(name).filter(|entry| entry.generation == generation) {
+        Some(entry) => {
+            change(entry);
+            true
+        }
+        None => false,
+    }
+}
+
+async fn check_health() {
+    let mut interval = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now = now_ms();
+        let stalled: Vec<Arc<Notify>> = TASKS
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.is_stalled(now))
+            .map(|entry| Arc::clone(&entry.stalled))
+            .collect();
+        for notify in stalled {
+            notify.notify_one();
+        }
+    }
+}
+
+/// Stops a task without restarting it and forgets it.
+pub fn stop(name: &str) -> bool {
+    let entry = TASKS.lock().unwrap().remove(name);
+    if let Some(abort) = entry.as_ref().and_then(|entry| entry.abort.as_ref()) {
+        abort.abort();
+    }
+    entry.is_some()
+}
+
+/// Stops every task; none are restarted afterwards.
+pub fn stop_all() {
+    STOPPING.store(true, Ordering::Relaxed);
+    let tasks = std::mem::take(&mut *TASKS.lock().unwrap());
+    for (name, entry) in tasks {
+        if let Some(abort) = entry.abort {
+            abort.abort();
+        }
+        info!("Stopped task {}", name);
+    }
+}
+
+pub fn status(name: &str) -> Option<TaskStatus> {
+    let now = now_ms();
+    TASKS.lock().unwrap().get(name).map(|entry| to_status(name, entry, now))
+}
+
+/// Every supervised task, by name.
+pub fn statuses() -> Vec<TaskStatus> {
+    let now = now_ms();
+    TASKS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, entry)| to_status(name, entry, now))
+        .collect()
+}
+
+fn to_status(name: &str, entry: &Entry, now: i64) -> TaskStatus {
+    TaskStatus {
+        name: name.to_string(),
+        state: entry.state,
+        policy: entry.policy,
+        restarts: entry.restarts,
+        last_error: entry.last_error.clone(),
+        started_at: entry.started_at,
+        heartbeat_age_ms: entry.heartbeat_age(now),
+        healthy: matches!(entry.state, TaskState::Running | TaskState::Completed) && !entry.is_stalled(now),
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+pub(crate) fn panic_message(panic: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        format!("panicked: {}", message)
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        format!("panicked: {}", message)
+    } else {
+        "panicked".to_string()
+    }
+}
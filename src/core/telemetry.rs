@@ -0,0 +1,135 @@
+use super::config::TelemetrySettings;
+use super::AppResult;
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+pub type BoxedLayer<S> = Box<dyn Layer<S> + Send + Sync + 'static>;
+
+#[cfg(feature = "telemetry")]
+mod otlp {
+    use super::{BoxedLayer, TelemetrySettings};
+    use crate::core::{AppError, AppResult};
+    use once_cell::sync::OnceCell;
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_appender_tracing::layer::OpenTelemetryTracingBridge;
+    use opentelemetry_otlp::{LogExporter, Protocol, SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::logs::SdkLoggerProvider;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing::Subscriber;
+    use tracing_subscriber::filter::filter_fn;
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    static TRACER_PROVIDER: OnceCell<SdkTracerProvider> = OnceCell::new();
+    static LOGGER_PROVIDER: OnceCell<SdkLoggerProvider> = OnceCell::new();
+
+    fn span_exporter(settings: &TelemetrySettings) -> Result<SpanExporter, opentelemetry_otlp::ExporterBuildError> {
+        let exporter = if settings.protocol() == "http" {
+            SpanExporter::builder()
+                .with_http()
+                .with_protocol(Protocol::HttpBinary)
+                .with_endpoint(format!("{}/v1/traces", settings.endpoint().trim_end_matches('/')))
+                .build()?
+        } else {
+            SpanExporter::builder().with_tonic().with_endpoint(settings.endpoint()).build()?
+        };
+        Ok(exporter)
+    }
+
+    fn log_exporter(settings: &TelemetrySettings) -> Result<LogExporter, opentelemetry_otlp::ExporterBuildError> {
+        let exporter = if settings.protocol() == "http" {
+            LogExporter::builder()
+                .with_http()
+                .with_protocol(Protocol::HttpBinary)
+                .with_endpoint(format!("{}/v1/logs", settings.endpoint().trim_end_matches('/')))
+                .build()?
+        } else {
+            LogExporter::builder().with_tonic().with_endpoint(settings.endpoint()).build()?
+        };
+        Ok(exporter)
+    }
+
+    pub fn layers<S>(settings: &TelemetrySettings) -> AppResult<Vec<BoxedLayer<S>>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+    {
+        let resource = Resource::builder()
+            .with_service_name(settings.service_name().to_string())
+            .with_attribute(KeyValue::new("service.instance.id", crate::core::run_id()))
+            .build();
+        let mut layers: Vec<BoxedLayer<S>> = Vec::new();
+
+        if settings.traces.unwrap_or(true) {
+            let provider = SdkTracerProvider::builder()
+                .with_batch_exporter(span_exporter(settings).map_err(|e| AppError::Init(format!("OTLP trace exporter: {}", e)))?)
+                .with_resource(resource.clone())
+                .build();
+            let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+            layers.push(tracing_opentelemetry::layer().with_tracer(tracer).boxed());
+            let _ = TRACER_PROVIDER.set(provider);
+        }
+        if settings.logs.unwrap_or(true) {
+            let provider = SdkLoggerProvider::builder()
+                .with_batch_exporter(log_exporter(settings).map_err(|e| AppError::Init(format!("OTLP log exporter: {}", e)))?)
+                .with_resource(resource)
+                .build();
+            // The exporter's own logs would be exported again, so drop them.
+            let bridge = OpenTelemetryTracingBridge::new(&provider)
+                .with_filter(filter_fn(|metadata| !is_exporter_target(metadata.target())));
+            layers.push(bridge.boxed());
+            let _ = LOGGER_PROVIDER.set(provider);
+        }
+        Ok(layers)
+    }
+
+    fn is_exporter_target(target: &str) -> bool {
+        ["opentelemetry", "tonic", "h2", "hyper", "tower", "reqwest"]
+            .iter()
+            .any(|prefix| target.starts_with(prefix))
+    }
+
+    pub fn shutdown() {
+        if let Some(provider) = TRACER_PROVIDER.get() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush trace exporter: {}", e);
+            }
+        }
+        if let Some(provider) = LOGGER_PROVIDER.get() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush log exporter: {}", e);
+            }
+        }
+    }
+}
+
+/// OTLP export layers for `[telemetry]`. `None` when telemetry is disabled or
+/// the binary was built without the `telemetry` feature.
+///
+/// Never returns an empty `Vec`: an empty layer list reports no interest in
+/// any callsite, which would silence every other layer.
+pub fn layers<S>(settings: &TelemetrySettings) -> AppResult<Option<Vec<BoxedLayer<S>>>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    if !settings.enabled.unwrap_or(false) {
+        return Ok(None);
+    }
+    #[cfg(feature = "telemetry")]
+    {
+        Ok(Some(otlp::layers(settings)?).filter(|layers| !layers.is_empty()))
+    }
+    #[cfg(not(feature = "telemetry"))]
+    {
+        eprintln!("telemetry.enabled is set but this build has no OTLP support; rebuild with --features telemetry");
+        Ok(None)
+    }
+}
+
+/// Flushes pending spans and log records. Call once before exiting.
+pub fn shutdown() {
+    #[cfg(feature = "telemetry")]
+    otlp::shutdown();
+}
@@ -0,0 +1,82 @@
+//! Forwards bus events to the page.
+
+use super::bus::{EventBus, EventHandler};
+use super::types::{Event, EventType};
+use crate::viewmodels::window::WindowRegistry;
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+
+pub struct WebUIEventBridge {
+    event_bus: Arc<EventBus>,
+    webui_window: Option<Arc<Mutex<webui_rs::webui::Window>>>,
+    windows: WindowRegistry,
+}
+
+impl WebUIEventBridge {
+    pub fn new(event_bus: Arc<EventBus>) -> Self {
+        Self { event_bus, webui_window: None, windows: WindowRegistry::default() }
+    }
+
+    pub fn set_webui_window(&mut self, window: Arc<Mutex<webui_rs::webui::Window>>) {
+        self.webui_window = Some(window);
+    }
+
+    /// Where forwarded events are delivered.
+    pub fn set_windows(&mut self, windows: WindowRegistry) {
+        self.windows = windows;
+    }
+
+    pub async fn send_to_frontend(&self, event: &Event) -> Result<()> {
+        if let Some(ref _window) = self.webui_window {
+            info!("Sending to frontend: {}", event.name);
+        }
+        Ok(())
+    }
+
+    /// Forwards events matching `event_pattern` to the page through
+    /// `window.handleBackendEvent`.
+    pub async fn subscribe_for_webui(&self, event_pattern: &str) -> Result<()> {
+        let pattern = event_pattern.to_string();
+        let windows = self.windows.clone();
+        let listener = Arc::new(EventHandler::new(move |event| {
+            let windows = windows.clone();
+            Box::pin(async move {
+                debug!("Forwarding to frontend: {}", event.name);
+                let payload = frontend_payload(&event);
+                // run_js waits on the page, so keep it off the async workers.
+                tokio::task::spawn_blocking(move || {
+                    windows.send_to_frontend(&event.name, payload);
+                });
+                Ok(())
+            })
+        }));
+        self.event_bus.subscribe(&pattern, listener);
+        info!("Subscribed frontend to: {}", pattern);
+        Ok(())
+    }
+}
+
+/// The data part of an event as the frontend and dynamic plugins see it: a
+/// custom event's payload, or the fields of a built-in variant.
+pub(crate) fn frontend_payload(event: &Event) -> serde_json::Value {
+    match &event.event_type {
+        EventType::Custom { payload, .. } => payload.clone(),
+        other => match serde_json::to_value(other) {
+            Ok(serde_json::Value::Object(map)) if map.len() == 1 => {
+                map.into_iter().next().map(|(_, fields)| fields).unwrap_or_default()
+            }
+            _ => serde_json::Value::Null,
+        },
+    }
+}
+
+impl Clone for WebUIEventBridge {
+    fn clone(&self) -> Self {
+        Self {
+            event_bus: self.event_bus.clone(),
+            webui_window: self.webui_window.clone(),
+            windows: self.windows.clone(),
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use super::types::{Event, EventType, EventPriority};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use std::future::Future;
 use std::pin::Pin;
 use tokio::sync::broadcast;
-use tracing::{info, error, debug};
+use tracing::{error, debug};
 use anyhow::Result;
 use uuid::Uuid;
 use lazy_static::lazy_static;
@@ -50,6 +52,8 @@ pub struct EventBus {
     broadcast_tx: broadcast::Sender<Event>,
     event_history: Arc<Mutex<Vec<Event>>>,
     max_history_size: usize,
+    /// Listener tasks spawned by `emit` that have not finished yet.
+    in_flight: Arc<AtomicUsize>,
 }
 
 impl EventBus {
@@ -60,6 +64,7 @@ impl EventBus {
             broadcast_tx,
             event_history: Arc::new(Mutex::new(Vec::new())),
             max_history_size: 1000,
+            in_flight: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -71,6 +76,11 @@ impl EventBus {
         id
     }
 
+    pub fn subscribe_scoped(&self, pattern: &str, listener: Arc<dyn EventListener>) -> SubscriptionGuard {
+        let id = self.subscribe(pattern, listener);
+        SubscriptionGuard { bus: self.clone(), id }
+    }
+
     pub fn unsubscribe(&self, subscription_id: &str) -> bool {
         let mut subs = self.subscriptions.write().unwrap();
         for (_, subscriptions) in subs.iter_mut() {
@@ -95,10 +105,13 @@ impl EventBus {
         let matching_subs = self.get_matching_subscriptions(&event.name);
         for (_, listener) in matching_subs {
             let event_clone = event.clone();
+            let in_flight = Arc::clone(&self.in_flight);
+            in_flight.fetch_add(1, Ordering::SeqCst);
             tokio::spawn(async move {
                 if let Err(e) = listener.handle_event(&event_clone).await {
                     error!("Error handling event: {}", e);
                 }
+                in_flight.fetch_sub(1, Ordering::SeqCst);
             });
         }
         Ok(())
@@ -115,7 +128,8 @@ impl EventBus {
         matches
     }
 
-    fn match_pattern(&self, pattern: &str, event_name: &str) -> bool {
+    /// Whether `event_name` matches a subscription pattern such as `user.*`.
+    pub fn match_pattern(&self, pattern: &str, event_name: &str) -> bool {
         if pattern == event_name || pattern == "*" { return true; }
         let pattern_parts: Vec<&str> = pattern.split('.').collect();
         let name_parts: Vec<&str> = event_name.split('.').collect();
@@ -127,6 +141,19 @@ impl EventBus {
         pattern_parts.len() == name_parts.len() || pattern_parts.last() == Some(&"**")
     }
 
+    /// Waits until every listener task started by `emit` has finished, or
+    /// `timeout` passes. Returns how many were still running.
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let pending = self.in_flight.load(Ordering::SeqCst);
+            if pending == 0 || Instant::now() >= deadline {
+                return pending;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
     pub fn get_receiver(&self) -> broadcast::Receiver<Event> {
         self.broadcast_tx.subscribe()
     }
@@ -139,6 +166,26 @@ impl EventBus {
         }
     }
 
+    pub fn history_len(&self) -> usize {
+        self.event_history.lock().map(|history| history.len()).unwrap_or(0)
+    }
+
+    /// Events broadcast but not yet read by every receiver.
+    pub fn queued(&self) -> usize {
+        self.broadcast_tx.len()
+    }
+
+    /// Listener tasks started by `emit` that have not finished yet.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Like `get_event_history` but returns `None` instead of blocking when
+    /// the history is locked, for use from panic hooks.
+    pub fn try_get_event_history(&self) -> Option<Vec<Event>> {
+        self.event_history.try_lock().ok().map(|history| history.clone())
+    }
+
     pub async fn emit_counter_increment(&self, source: &str) -> Result<()> {
         self.emit(Event::new(EventType::CounterIncrement, source)).await
     }
@@ -184,52 +231,26 @@ impl EventBus {
     }
 }
 
-impl Default for EventBus {
-    fn default() -> Self { Self::new() }
-}
-
-pub struct WebUIEventBridge {
-    event_bus: Arc<EventBus>,
-    webui_window: Option<Arc<Mutex<webui_rs::webui::Window>>>,
+/// Unsubscribes its listener from the bus when dropped.
+pub struct SubscriptionGuard {
+    bus: EventBus,
+    id: String,
 }
 
-impl WebUIEventBridge {
-    pub fn new(event_bus: Arc<EventBus>) -> Self {
-        Self { event_bus, webui_window: None }
-    }
-
-    pub fn set_webui_window(&mut self, window: Arc<Mutex<webui_rs::webui::Window>>) {
-        self.webui_window = Some(window);
+impl SubscriptionGuard {
+    pub fn id(&self) -> &str {
+        &self.id
     }
+}
 
-    pub async fn send_to_frontend(&self, event: &Event) -> Result<()> {
-        if let Some(ref _window) = self.webui_window {
-            info!("Sending to frontend: {}", event.name);
+impl Drop for SubscriptionGuard {
+    fn drop(&mut self) {
+        if self.bus.unsubscribe(&self.id) {
+            debug!("Subscription {} removed", self.id);
         }
-        Ok(())
-    }
-
-    pub async fn subscribe_for_webui(&self, event_pattern: &str) -> Result<()> {
-        let event_bus = self.event_bus.clone();
-        let pattern = event_pattern.to_string();
-        let listener = Arc::new(EventHandler::new(move |_event| {
-            let _bus = event_bus.clone();
-            Box::pin(async move {
-                info!("Forwarding to frontend: {}", _event.name);
-                Ok(())
-            })
-        }));
-        self.event_bus.subscribe(&pattern, listener);
-        info!("Subscribed frontend to: {}", pattern);
-        Ok(())
     }
 }
 
-impl Clone for WebUIEventBridge {
-    fn clone(&self) -> Self {
-        Self {
-            event_bus: self.event_bus.clone(),
-            webui_window: self.webui_window.clone(),
-        }
-    }
+impl Default for EventBus {
+    fn default() -> Self { Self::new() }
 }
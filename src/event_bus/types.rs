@@ -90,8 +90,11 @@ pub struct Event {
 }
 
 impl Event {
+    /// Tags the event with the current run ID in `metadata.run_id`.
     pub fn new(event_type: EventType, source: &str) -> Self {
         let name = Self::get_event_name(&event_type);
+        let mut metadata = HashMap::new();
+        metadata.insert("run_id".to_string(), serde_json::Value::from(crate::core::run_id()));
         Event {
             id: Uuid::new_v4().to_string(),
             name,
@@ -100,7 +103,7 @@ impl Event {
             source: source.to_string(),
             target: None,
             priority: EventPriority::Normal,
-            metadata: HashMap::new(),
+            metadata,
             correlation_id: None,
             reply_to: None,
         }
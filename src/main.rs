@@ -1,260 +1,480 @@
 use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Duration;
-use std::net::TcpListener;
+use std::time::{Duration, Instant};
+use std::io::IsTerminal;
 use tracing::{info, error, warn};
 use webui_rs::webui;
 use tokio::runtime::Builder;
+use clap::Parser;
 
+mod app_state;
+mod bridge;
 mod build_logger;
+mod cli;
+mod config_watcher;
 mod event_bus;
 mod models;
+mod plugins;
+mod scheduler;
+mod server;
+mod services;
+mod shutdown;
+#[cfg(test)]
+mod testing;
 mod viewmodels;
 mod websocket_manager;
 mod core;
 
+use app_state::AppState;
+use event_bus::EventBus;
 use core::{AppConfig, Database, init_logging, AppError, AppResult};
-use websocket_manager::WebSocketManager;
+use core::database::AppliedMigration;
+use plugins::{CounterPlugin, PluginRegistry, SystemPlugin, UserPlugin, WindowPlugin};
+use websocket_manager::{WebSocketHandle, WebSocketManager};
 
 include!(concat!(env!("OUT_DIR"), "/build_config.rs"));
 
-fn get_random_port() -> Option<u16> {
-    for port in 8000..9000 {
-        if is_port_available(port) {
-            return Some(port);
-        }
+/// `app_settings` key of the port the HTTP server last listened on.
+const LAST_PORT_SETTING: &str = "http.last_port";
+
+/// How long the run's session outlives the last keepalive, e.g. after a crash.
+const RUN_SESSION_TTL: Duration = Duration::from_secs(600);
+
+/// Supervised task that extends the run's session.
+const SESSION_KEEPALIVE_TASK: &str = "session_keepalive";
+
+fn main() {
+    let cli = cli::Cli::parse();
+    let result = if let Some(name) = &cli.new_plugin {
+        run_new_plugin(name, cli.project_dir.as_deref())
+    } else if let Some(name) = &cli.set_secret {
+        run_set_secret(name)
+    } else if let Some(name) = &cli.delete_secret {
+        core::secrets::delete_secret(name).map(|()| println!("Deleted secret '{}'", name))
+    } else {
+        run(cli)
+    };
+
+    if let Err(e) = result {
+        report_fatal(&e);
+        std::process::exit(1);
     }
-    None
 }
 
-fn is_port_available(port: u16) -> bool {
-    TcpListener::bind(format!("0.0.0.0:{}", port)).is_ok()
+/// Logs a fatal error, or prints it to stderr when it happened before
+/// logging was initialized.
+fn report_fatal(e: &AppError) {
+    if tracing::dispatcher::has_been_set() {
+        error!("{}", e);
+    } else {
+        eprintln!("{}", e);
+    }
 }
 
-fn write_port_to_config(port: u16) -> AppResult<()> {
-    let config_content = format!("{{\"port\":{}}}", port);
-    std::fs::write("frontend/dist/port.json", config_content)
-        .map_err(|e| AppError::Io(e))?;
-    info!("Port {} written to frontend/dist/port.json", port);
-    Ok(())
+fn run(cli: cli::Cli) -> AppResult<()> {
+    let rt = Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| AppError::Init(format!("Failed to create Tokio runtime: {}", e)))?;
+    rt.block_on(run_app(cli))
 }
 
-fn start_http_server(port: u16) -> AppResult<()> {
-    let frontend_path = std::path::PathBuf::from("frontend/dist");
-    info!("Starting HTTP server on port {} for frontend files", port);
-
-    let server = tiny_http::Server::http(format!("0.0.0.0:{}", port))
-        .map_err(|e| AppError::HttpServer(e.to_string()))?;
-
-    thread::spawn(move || {
-        info!("HTTP server listening on http://localhost:{}", port);
-        for request in server.incoming_requests() {
-            let url = request.url().to_string();
-            let sanitized_path = url.trim_start_matches('/').replace("..", "").replace("%2e%2e", "").replace("%252e%252e", "");
-            let path = if url == "/" { frontend_path.join("index.html") } else { frontend_path.join(&sanitized_path) };
-
-            let canonical_path = match path.canonicalize() { 
-                Ok(p) => p, 
-                Err(_) => { 
-                    let _ = request.respond(tiny_http::Response::from_string("Not Found").with_status_code(404)); 
-                    continue; 
-                } 
-            };
-            let frontend_canonical = match frontend_path.canonicalize() { 
-                Ok(p) => p, 
-                Err(e) => { 
-                    warn!("Error canonicalizing path: {}", e); 
-                    let _ = request.respond(tiny_http::Response::from_string("Internal Server Error").with_status_code(500)); 
-                    continue; 
-                } 
-            };
-
-            if !canonical_path.starts_with(&frontend_canonical) {
-                warn!("Security: Path traversal attempt blocked: {}", url);
-                let _ = request.respond(tiny_http::Response::from_string("Forbidden").with_status_code(403));
-                continue;
-            }
+async fn run_app(cli: cli::Cli) -> AppResult<()> {
+    // Config and logging are timed by hand since progress is only tracked
+    // once logging is up.
+    let config_started = Instant::now();
+    let mut config = cli.load_config()?;
+    println!("Configuration loaded! {} v{}", config.get_app_name(), config.get_version());
 
-            info!("HTTP Request: {} -> {:?}", url, path);
-            if path.exists() && path.is_file() {
-                match std::fs::read(&path) {
-                    Ok(content) => {
-                        let content_type = mime_guess::from_path(&path).first_or_octet_stream().to_string();
-                        let security_headers = [
-                            tiny_http::Header::from_bytes(&b"X-Content-Type-Options"[..], b"nosniff"),
-                            tiny_http::Header::from_bytes(&b"X-Frame-Options"[..], b"DENY"),
-                            tiny_http::Header::from_bytes(&b"Referrer-Policy"[..], b"strict-origin-when-cross-origin"),
-                            tiny_http::Header::from_bytes(&b"Content-Security-Policy"[..], b"default-src 'self'; script-src 'self' 'unsafe-inline' 'unsafe-eval'; style-src 'self' 'unsafe-inline'; img-src 'self' data: blob:; connect-src 'self' ws: wss: http: https:; font-src 'self' data:;"),
-                        ];
-                        let mut response = tiny_http::Response::from_data(content);
-                        response = response.with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap());
-                        for header in security_headers.into_iter().flatten() {
-                            response = response.with_header(header);
-                        }
-                        let _ = request.respond(response);
-                    }
-                    Err(e) => { 
-                        warn!("Error reading file {:?}: {}", path, e); 
-                        let _ = request.respond(tiny_http::Response::from_string(format!("Error: {}", e)).with_status_code(500)); 
-                    }
-                }
-            } else { 
-                let _ = request.respond(tiny_http::Response::from_string("Not Found").with_status_code(404)); 
-            }
+    let logging_started = Instant::now();
+    let bus = EventBus::new();
+    core::set_console_quiet(cli.quiet);
+    init_logging(&config.logging, &config.telemetry, &bus)
+        .map_err(|e| AppError::Init(format!("Failed to initialize logger: {}", e)))?;
+    core::crash::install_panic_hook(core::paths::log_dir(config.paths.log_dir.as_deref()));
+    core::perf::set_thresholds(config.get_slow_handler_threshold(), config.get_slow_query_threshold());
+    let logging_finished = Instant::now();
+
+    if !cli.quiet && std::io::stdout().is_terminal() {
+        build_logger::enable_live_progress();
+    }
+    build_logger::init_build_progress("startup", &["config", "logging", "database", "plugins", "http", "window"], &bus);
+    let config_origin = config.source.as_ref().map_or("defaults".to_string(), |p| p.display().to_string());
+    build_logger::record_step("config", config_started, logging_started, &format!("Loaded from {}", config_origin));
+    build_logger::record_step("logging", logging_started, logging_finished, &format!("Level {}", config.logging.level));
+
+    info!("=============================================");
+    info!("Starting: {} v{}", config.get_app_name(), config.get_version());
+    info!("=============================================");
+    
+    info!("");
+    info!("=== Backend-Frontend Communication ===");
+    info!("Transport Options:");
+    info!("  - WebUI Bridge (webui-rs)    [SELECTED]");
+    info!("  - WebSocket                  [available]");
+    info!("  - HTTP/REST                  [available]");
+    info!("  - IPC (tokio)                [available]");
+    info!("");
+    info!("Serialization Options:");
+    info!("  - JSON (serde_json)          [SELECTED]");
+    info!("  - MessagePack (rmp-serde)    [available]");
+    info!("  - CBOR (serde_cbor)          [available]");
+    info!("  - Protobuf (protobuf)        [available]");
+    info!("=============================================");
+    info!("");
+
+    core::paths::prepare_data_paths(&config);
+    if cli.reset_data {
+        reset_data(&config);
+    }
+    let bootstrap_state = core::bootstrap::bootstrap(&mut config);
+    let first_run = bootstrap_state.first_run;
+    core::bootstrap::install_state(bootstrap_state);
+
+    let db_path = config.get_db_path();
+    info!("Database path: {}", db_path);
+    build_logger::start_step("database");
+    let (db, migrations) = open_database(&config, first_run && config.should_create_sample_data())
+        .inspect_err(|e| build_logger::fail_step("database", &e.to_string()))?;
+    build_logger::complete_step("database", db_path);
+    for migration in migrations {
+        let payload = serde_json::to_value(&migration).unwrap_or_default();
+        if let Err(e) = bus.emit_custom("migration.applied", payload, "database").await {
+            error!("Failed to emit migration applied event: {}", e);
         }
-    });
-    Ok(())
-}
+    }
 
-fn main() {
-    let rt = match Builder::new_multi_thread().enable_all().build() {
-        Ok(rt) => rt,
-        Err(e) => {
-            eprintln!("Failed to create Tokio runtime: {}", e);
-            std::process::exit(1);
+    core::log_store::start_log_store(Arc::clone(&db), &config.logging);
+    core::i18n::init(&db);
+
+    let state = AppState::new(Arc::clone(&db), config.clone(), bus.clone());
+    core::crash::record_events_from(bus.clone());
+    viewmodels::invalidate_cached_responses(&state);
+
+    if config.is_scheduler_enabled() {
+        start_scheduler(&config, Arc::clone(&db), &bus);
+    }
+    core::analytics::Analytics::from_config(Arc::clone(&db), &config.analytics).start(&bus);
+    core::resources::start_resource_monitor(&config, bus.clone());
+
+    build_logger::start_step("plugins");
+    let registry = plugins::install_registry(build_plugin_registry(&config, state.clone()));
+    info!("Plugins enabled: {:?}", registry.plugin_names());
+
+    registry.initialize_all().await;
+    build_logger::complete_step("plugins", &format!("{} plugin(s) initialized", registry.plugin_names().len()));
+
+    build_logger::start_step("http");
+    let (http_port, port_source) = match cli.port {
+        Some(port) => (port, server::PortSource::Cli),
+        None => {
+            let last_used = db.get_typed_setting::<u16>(LAST_PORT_SETTING).ok().flatten();
+            server::select_port(config.server.bind_address(), config.server.port, last_used, config.server.port_range())
+                .inspect_err(|e| build_logger::fail_step("http", &e.to_string()))?
         }
     };
+
+    let session_id = start_run_session(&db, &bus).inspect_err(|e| build_logger::fail_step("http", &e.to_string()))?;
+    let mut router = server::Router::new();
+    registry.register_routes(&mut router);
+    let mut runtime_config = server::RuntimeConfig::new(http_port, &config, &session_id);
+    for plugin in registry.plugin_names() {
+        runtime_config = runtime_config.with_plugin(plugin);
+    }
+    runtime_config = runtime_config.with_websocket();
+    runtime_config.register(&mut router);
+    server::health::register(&mut router, state.clone());
+    let http_server = server::start_http_server(http_port, router, &config.server, &state, &session_id)
+        .inspect_err(|e| build_logger::fail_step("http", &e.to_string()))?;
+    build_logger::complete_step("http", &format!("Listening on port {}", http_port));
+    let http_url = http_server.url().to_string();
+    if let Err(e) = db.set_typed_setting(LAST_PORT_SETTING, &http_port) {
+        warn!("Failed to remember port {}: {}", http_port, e);
+    }
+    let payload = serde_json::json!({
+        "port": http_port,
+        "url": http_url,
+        "source": port_source,
+    });
+    if let Err(e) = bus.emit_custom("server.started", payload, "main").await {
+        error!("Failed to emit server started event: {}", e);
+    }
     
-    rt.block_on(async {
-        let config = match AppConfig::load() {
-            Ok(config) => {
-                println!("Configuration loaded! {} v{}", config.get_app_name(), config.get_version());
-                config
-            }
-            Err(e) => {
-                eprintln!("Failed to load configuration: {}", e);
-                AppConfig::default()
-            }
-        };
+    thread::sleep(Duration::from_millis(100));
+
+    build_logger::start_step("window");
+    let mut my_window = webui::Window::new();
+    let _attachment = state.attach(&my_window);
+    viewmodels::window::apply_window_settings(&my_window, &config.window);
+    
+    for (name, e) in registry.setup_all(&mut my_window) {
+        warn!("Plugin {} is disabled after failing to set up: {}", name, e);
+    }
 
-        if let Err(e) = init_logging(Some(config.get_log_file()), config.get_log_level(), config.is_append_log()) {
-            eprintln!("Failed to initialize logger: {}", e);
-            return;
+    viewmodels::setup_utils_viewmodel(&mut my_window);
+    viewmodels::setup_plugins_viewmodel(&mut my_window);
+    viewmodels::setup_scheduler_viewmodel(&mut my_window);
+    viewmodels::setup_settings_viewmodel(&mut my_window);
+    viewmodels::setup_logs_viewmodel(&mut my_window);
+    viewmodels::setup_startup_viewmodel(&mut my_window);
+    viewmodels::setup_performance_viewmodel(&mut my_window);
+    viewmodels::setup_tasks_viewmodel(&mut my_window);
+    viewmodels::setup_analytics_viewmodel(&mut my_window);
+    viewmodels::setup_database_viewmodel(&mut my_window);
+    viewmodels::set_effective_config(&config);
+    if let Some(path) = config.source.clone() {
+        viewmodels::init_config_path(path);
+    }
+
+    let window_arc = Arc::new(Mutex::new(my_window));
+    start_dynamic_plugins(&config, Arc::clone(&registry), Arc::clone(&window_arc));
+    state.set_websocket(init_webui_event_bridge(Arc::clone(&window_arc), &state));
+    core::log_stream::start_log_stream(bus.clone());
+
+    if let Err(e) = bus.emit_webui_connected("main").await {
+        error!("Failed to emit WebUI connected: {}", e);
+    }
+
+    let window_title = config.get_window_title();
+    info!("Window title: {}", window_title);
+    info!("Loading from {}", http_url);
+    
+    if config.is_headless() {
+        info!("Running headless; window not shown");
+        build_logger::complete_step("window", "Headless");
+    } else { 
+        let window_lock = window_arc
+            .lock()
+            .map_err(|e| AppError::Window(format!("Failed to acquire window lock: {}", e)))
+            .inspect_err(|e| build_logger::fail_step("window", &e.to_string()))?;
+        window_lock.show(&http_url); 
+        viewmodels::window::apply_start_maximized(&window_lock, &config.window);
+        build_logger::complete_step("window", &format!("Showing {}", http_url));
+    }
+    registry.start_all();
+    Arc::clone(&registry).watch_health();
+    if let Some(path) = config.source.clone() {
+        let overrides = cli.clone();
+        config_watcher::ConfigWatcher::new(path, state.config().clone(), Arc::clone(&registry), move |c| overrides.apply(c))
+            .with_windows(state.windows().clone())
+            .watch(Duration::from_secs(2));
+    }
+    info!("Application started, waiting for events...");
+    build_logger::finish_build_progress();
+    viewmodels::report_startup(&db, &bus);
+
+    if let Err(e) = bus.emit_webui_ready("main").await {
+        error!("Failed to emit WebUI ready: {}", e);
+    }
+    if first_run {
+        let payload = serde_json::to_value(core::bootstrap::state()).unwrap_or_default();
+        if let Err(e) = bus.emit_custom("app.first_run", payload, "main").await {
+            error!("Failed to emit first run event: {}", e);
         }
+    }
+    shutdown::listen_for_signals();
+    let reason = if config.is_headless() {
+        shutdown::requested().await
+    } else {
+        webui::wait();
+        shutdown::requested_reason().unwrap_or("window_closed")
+    };
+    shutdown::Shutdown::new()
+        .with_http_server(http_server)
+        .with_state(&state)
+        .with_session(session_id)
+        .with_registry(registry)
+        .run(reason)
+        .await;
+    Ok(())
+}
 
-        info!("=============================================");
-        info!("Starting: {} v{}", config.get_app_name(), config.get_version());
-        info!("=============================================");
-        
-        info!("");
-        info!("=== Backend-Frontend Communication ===");
-        info!("Transport Options:");
-        info!("  - WebUI Bridge (webui-rs)    [SELECTED]");
-        info!("  - WebSocket                  [available]");
-        info!("  - HTTP/REST                  [available]");
-        info!("  - IPC (tokio)                [available]");
-        info!("");
-        info!("Serialization Options:");
-        info!("  - JSON (serde_json)          [SELECTED]");
-        info!("  - MessagePack (rmp-serde)    [available]");
-        info!("  - CBOR (serde_cbor)          [available]");
-        info!("  - Protobuf (protobuf)        [available]");
-        info!("=============================================");
-        info!("");
-
-        let db_path = config.get_db_path();
-        info!("Database path: {}", db_path);
-
-        let db = match Database::new(db_path) {
-            Ok(db) => {
-                info!("Database initialized");
-                if let Err(e) = db.init() {
-                    error!("Failed to initialize database: {}", e);
-                    return;
-                }
-                if config.should_create_sample_data() {
-                    if let Err(e) = db.insert_sample_data() {
-                        error!("Failed to insert sample data: {}", e);
-                        return;
-                    }
-                    info!("Sample data created");
-                }
-                Arc::new(db)
+fn open_database(config: &AppConfig, sample_data: bool) -> AppResult<(Arc<Database>, Vec<AppliedMigration>)> {
+    let db = Database::open_with_pragmas(
+        config.get_db_path(),
+        config.get_db_pool_size(),
+        config.get_db_encryption_key(),
+        &config.get_db_pragmas(),
+    )?;
+    let migrations = db.init()?;
+    info!("Database initialized");
+    if sample_data {
+        match config.get_db_seed_file() {
+            Some(seed_file) => {
+                db.seed_from_file(std::path::Path::new(seed_file))?;
             }
-            Err(e) => {
-                error!("Failed to initialize database: {}", e);
-                return;
+            None => {
+                db.insert_sample_data()?;
+                info!("Sample data created");
             }
-        };
+        }
+    }
+    Ok((Arc::new(db), migrations))
+}
 
-        viewmodels::init_db(Arc::clone(&db));
+/// Starts the run's session, whose ID is the token `/api/*` and the event
+/// streams require, and extends it until shutdown deletes it. Sessions left
+/// by crashed runs are purged first.
+fn start_run_session(db: &Arc<Database>, bus: &EventBus) -> AppResult<String> {
+    match db.purge_expired_sessions() {
+        Ok(0) => {}
+        Ok(count) => info!("Purged {} expired session(s)", count),
+        Err(e) => warn!("Failed to purge expired sessions: {}", e),
+    }
+    let data = serde_json::json!({ "run_id": core::run_id() });
+    let id = db.create_session(None, data, RUN_SESSION_TTL)?.id;
 
-        let http_port = match get_random_port() {
-            Some(port) => port,
-            None => {
-                error!("Failed to find available port");
-                return;
+    let (db, session_id) = (Arc::clone(db), id.clone());
+    core::supervisor::Task::new(SESSION_KEEPALIVE_TASK).with_events(bus).spawn(move |_| {
+        let (db, session_id) = (Arc::clone(&db), session_id.clone());
+        async move {
+            loop {
+                tokio::time::sleep(RUN_SESSION_TTL / 3).await;
+                let (db, session_id) = (Arc::clone(&db), session_id.clone());
+                let extended = tokio::task::spawn_blocking(move || db.touch_session(&session_id, RUN_SESSION_TTL))
+                    .await
+                    .map_err(|e| AppError::Runtime(e.to_string()))??;
+                if !extended {
+                    warn!("The run's session is no longer in the database");
+                }
             }
-        };
-        
-        if let Err(e) = start_http_server(http_port) {
-            error!("Failed to start HTTP server: {}", e);
-            return;
         }
-        
-        if let Err(e) = write_port_to_config(http_port) {
-            warn!("Warning: Failed to write port config: {}", e);
-        }
-        
-        thread::sleep(Duration::from_millis(100));
-
-        let mut my_window = webui::Window::new();
-        
-        viewmodels::setup_counter_viewmodel(&mut my_window);
-        viewmodels::setup_user_viewmodel(&mut my_window);
-        viewmodels::setup_system_viewmodel(&mut my_window);
-        viewmodels::setup_utils_viewmodel(&mut my_window);
-        viewmodels::setup_window_viewmodel(&mut my_window);
-
-        let window_arc = Arc::new(Mutex::new(my_window));
-        init_webui_event_bridge(Arc::clone(&window_arc));
-
-        if let Err(e) = event_bus::emit_webui_connected("main").await {
-            error!("Failed to emit WebUI connected: {}", e);
+    });
+    Ok(id)
+}
+
+fn reset_data(config: &AppConfig) {
+    let db_path = config.get_db_path();
+    for path in [db_path.to_string(), format!("{}-wal", db_path), format!("{}-shm", db_path)] {
+        match std::fs::remove_file(&path) {
+            Ok(()) => info!("Removed {}", path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to remove {}: {}", path, e),
         }
+    }
+    let data_dir = config.get_plugin_data_dir();
+    match std::fs::remove_dir_all(data_dir) {
+        Ok(()) => info!("Removed plugin data directory {}", data_dir),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("Failed to remove {}: {}", data_dir, e),
+    }
+}
 
-        let window_title = config.get_window_title();
-        info!("Window title: {}", window_title);
-        let url = format!("http://localhost:{}", http_port);
-        info!("Loading from {}", url);
-        
-        { 
-            let window_lock = match window_arc.lock() {
-                Ok(lock) => lock,
-                Err(e) => {
-                    error!("Failed to acquire window lock: {}", e);
-                    return;
-                }
-            };
-            window_lock.show(&url); 
+fn run_set_secret(name: &str) -> AppResult<()> {
+    let mut value = String::new();
+    std::io::stdin().read_line(&mut value)?;
+    let value = value.trim_end_matches(['\r', '\n']);
+    if value.is_empty() {
+        return Err(AppError::Config("Secret value is empty".to_string()));
+    }
+    core::secrets::set_secret(name, value)?;
+    println!("Stored secret '{}'; reference it in config as \"keyring:{}\"", name, name);
+    Ok(())
+}
+
+fn run_new_plugin(name: &str, project_dir: Option<&std::path::Path>) -> AppResult<()> {
+    let project_root = match project_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => std::env::current_dir()?,
+    };
+    let files = plugins::scaffold::generate_plugin(&project_root, name)?;
+    println!("Created plugin '{}':", name);
+    for file in files {
+        println!("  {}", file);
+    }
+    println!("Rebuild the application to load it.");
+    Ok(())
+}
+
+fn build_plugin_registry(config: &AppConfig, state: AppState) -> PluginRegistry {
+    let candidates: Vec<Box<dyn plugins::PluginTrait>> = vec![
+        Box::new(CounterPlugin::new()),
+        Box::new(UserPlugin::new()),
+        Box::new(SystemPlugin::with_power_poll_interval(config.get_power_poll_interval(), state.bus().clone())),
+        Box::new(WindowPlugin::new()),
+        // --new-plugin inserts registrations above this line
+    ];
+
+    let mut registry = PluginRegistry::new()
+        .with_state(state)
+        .with_grants(config.plugins.grants.clone())
+        .with_settings(config.plugins.settings.clone())
+        .with_health_interval(config.get_plugin_health_interval())
+        .with_data_root(std::path::PathBuf::from(config.get_plugin_data_dir()));
+    for plugin in candidates {
+        if config.is_plugin_enabled(plugin.name()) {
+            registry.register(plugin);
+        } else {
+            info!("Plugin disabled by configuration: {}", plugin.name());
         }
-        info!("Application started, waiting for events...");
+    }
+    registry
+}
+
+fn start_dynamic_plugins(config: &AppConfig, registry: Arc<PluginRegistry>, window: Arc<Mutex<webui::Window>>) {
+    let directory = std::path::PathBuf::from(config.get_plugin_directory());
+    if !directory.is_dir() {
+        return;
+    }
+
+    let host = Arc::new(plugins::DynamicPluginHost::new(directory, registry, window));
+    host.load_all();
+    if config.is_dev_mode() {
+        host.watch(Duration::from_secs(1));
+    }
+}
+
+fn start_scheduler(config: &AppConfig, db: Arc<Database>, bus: &EventBus) {
+    use scheduler::{CatchUp, GLOBAL_SCHEDULER};
+    use std::str::FromStr;
+    GLOBAL_SCHEDULER.set_database(Arc::clone(&db));
+    GLOBAL_SCHEDULER.set_event_bus(bus.clone());
+    if let Some(Ok(policy)) = config.scheduler.catch_up.as_deref().map(CatchUp::from_str) {
+        GLOBAL_SCHEDULER.set_default_catch_up(policy);
+    }
+
+    match GLOBAL_SCHEDULER.load_from_db() {
+        Ok(count) if count > 0 => info!("Loaded {} scheduled job(s) from database", count),
+        Ok(_) => {}
+        Err(e) => warn!("Failed to load scheduled jobs from database: {}", e),
+    }
 
-        if let Err(e) = event_bus::emit_webui_ready("main").await {
-            error!("Failed to emit WebUI ready: {}", e);
+    for job in &config.scheduler.jobs {
+        if let Err(e) = GLOBAL_SCHEDULER.add_job(&job.name, &job.schedule, job.event.as_deref(), job.enabled.unwrap_or(true)) {
+            warn!("Skipping scheduled job '{}': {}", job.name, e);
+            continue;
         }
-        webui::wait();
-        info!("Application shutting down...");
-    });
+        if let Some(Ok(policy)) = job.catch_up.as_deref().map(CatchUp::from_str) {
+            GLOBAL_SCHEDULER.set_catch_up(&job.name, policy);
+        }
+    }
+    if let Err(e) = core::log_retention::register_cleanup_job(&GLOBAL_SCHEDULER, config, bus.clone()) {
+        warn!("Skipping log cleanup job: {}", e);
+    }
+    if let Err(e) = core::database::backup::register_backup_job(&GLOBAL_SCHEDULER, config, Arc::clone(&db), bus.clone()) {
+        warn!("Skipping database backup job: {}", e);
+    }
+    if let Err(e) = core::database::maintenance::register_maintenance_job(&GLOBAL_SCHEDULER, config, db, bus.clone()) {
+        warn!("Skipping database maintenance job: {}", e);
+    }
+
+    GLOBAL_SCHEDULER.start();
 }
 
-fn init_webui_event_bridge(window: Arc<Mutex<webui::Window>>) {
-    use event_bus::{GLOBAL_EVENT_BUS, WebUIEventBridge};
-    let event_bus = Arc::new(GLOBAL_EVENT_BUS.clone());
+fn init_webui_event_bridge(window: Arc<Mutex<webui::Window>>, state: &AppState) -> WebSocketHandle {
+    use event_bus::WebUIEventBridge;
+    let event_bus = Arc::new(state.bus().clone());
     let mut webui_bridge = WebUIEventBridge::new(event_bus);
     webui_bridge.set_webui_window(window.clone());
+    webui_bridge.set_windows(state.windows().clone());
 
-    let ws_manager = WebSocketManager::new(window.clone());
-    ws_manager.start_monitoring();
-    
-    tokio::spawn(async move {
-        if let Err(e) = webui_bridge.subscribe_for_webui("database.users_fetched").await { error!("Failed to subscribe: {}", e); }
-    });
+    let websocket = WebSocketManager::bootstrap(window, state.bus());
 
     tokio::spawn(async move {
-        let _ = ws_manager.get_state();
-        let _ = ws_manager.get_metrics();
+        if let Err(e) = webui_bridge.subscribe_for_webui("database.users_fetched").await { error!("Failed to subscribe: {}", e); }
+        if let Err(e) = webui_bridge.subscribe_for_webui("build.*").await { error!("Failed to subscribe: {}", e); }
     });
 
     tracing::info!("WebUI event bridge initialized");
+    websocket
 }
@@ -1,5 +1,8 @@
 #![allow(dead_code)]
 
+/// Roles a user may have.
+pub const USER_ROLES: &[&str] = &["admin", "editor", "user"];
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct User {
     pub id: i32,
@@ -9,11 +12,91 @@ pub struct User {
     pub status: String,
 }
 
+/// A user's stored fields, for creating or replacing one.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct UserInput {
+    pub name: String,
+    pub email: String,
+    pub role: String,
+}
+
+impl UserInput {
+    pub fn to_user(&self, id: i32) -> User {
+        User {
+            id,
+            name: self.name.clone(),
+            email: self.email.clone(),
+            role: self.role.clone(),
+            status: "Active".to_string(),
+        }
+    }
+}
+
+/// What mutation bindings answer with, so the page can update its state
+/// without fetching again.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MutationResult {
+    /// Row ID of the inserted row; `None` when the call changed several.
+    pub id: Option<i64>,
+    pub affected_rows: usize,
+    /// The new state, for bindings that change a setting or the counter
+    /// rather than rows.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<serde_json::Value>,
+}
+
+impl MutationResult {
+    pub fn rows(id: Option<i64>, affected_rows: usize) -> Self {
+        Self { id, affected_rows, value: None }
+    }
+
+    pub fn with_value(affected_rows: usize, value: serde_json::Value) -> Self {
+        Self { id: None, affected_rows, value: Some(value) }
+    }
+}
+
+/// What bindings that start a long job answer with at once. The outcome
+/// arrives later as an event carrying the same `job_id`.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct JobStarted {
+    pub job_id: String,
+}
+
+impl JobStarted {
+    /// A fresh, random job ID.
+    pub fn with_new_id() -> Self {
+        Self { job_id: uuid::Uuid::new_v4().simple().to_string() }
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct DbStats {
     pub users: i64,
     pub tables: Vec<String>,
-    pub size: String,
+    /// Bytes in use by the database, `page_count * page_size`.
+    pub size: u64,
+    pub page_count: u64,
+    pub page_size: u64,
+    /// Pages no longer used, which `VACUUM` would give back.
+    pub freelist_count: u64,
+    /// Size of the database file on disk; `None` in memory.
+    pub file_size: Option<u64>,
+    /// Size of the write-ahead log; `None` when there is none.
+    pub wal_size: Option<u64>,
+    /// Rows in each table.
+    pub row_counts: std::collections::BTreeMap<String, i64>,
+    pub indexes: Vec<IndexStats>,
+    pub pool: crate::core::database::PoolStats,
+    pub statement_cache: crate::core::database::StatementCacheStats,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct IndexStats {
+    pub name: String,
+    pub table: String,
+    pub unique: bool,
+    /// Indexed columns, in order.
+    pub columns: Vec<String>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
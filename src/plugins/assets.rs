@@ -0,0 +1,25 @@
+use crate::server::{ApiRequest, ApiResponse};
+
+/// Static files a plugin ships for its frontend, served under `/plugins/<name>/`.
+pub enum PluginAssets {
+    /// Files compiled into the app with `include_bytes!`, by relative path.
+    Embedded(Vec<(&'static str, &'static [u8])>),
+}
+
+impl PluginAssets {
+    pub fn serve(&self, request: &ApiRequest) -> ApiResponse {
+        let relative = request.param("*").unwrap_or_default();
+        let relative = if relative.is_empty() { "index.js" } else { relative };
+
+        match self {
+            PluginAssets::Embedded(files) => match files.iter().find(|(path, _)| *path == relative) {
+                Some((path, data)) => ApiResponse::new(200, &content_type(path), data.to_vec()),
+                None => ApiResponse::text(404, "Not Found"),
+            },
+        }
+    }
+}
+
+fn content_type(path: &str) -> String {
+    mime_guess::from_path(path).first_or_octet_stream().to_string()
+}
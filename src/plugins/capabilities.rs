@@ -0,0 +1,206 @@
+use crate::app_state::AppState;
+use crate::core::{AppError, AppResult, Database};
+use crate::plugins::PluginStore;
+use crate::viewmodels::window::WindowRegistry;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Grant entries of the form `network:<host>` let a plugin reach `host`.
+pub const NETWORK_GRANT_PREFIX: &str = "network:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    Database,
+    Filesystem,
+    Network,
+    Clipboard,
+}
+
+impl Capability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Capability::Database => "database",
+            Capability::Filesystem => "filesystem",
+            Capability::Network => "network",
+            Capability::Clipboard => "clipboard",
+        }
+    }
+}
+
+/// Per-plugin handle to shared resources. Each accessor fails unless the
+/// capability was both declared by the plugin and granted by configuration.
+pub struct PluginContext {
+    plugin: String,
+    granted: HashSet<Capability>,
+    db: Option<Arc<Database>>,
+    data_dir: PathBuf,
+    /// Hosts the network capability may reach.
+    hosts: Vec<String>,
+    settings: toml::Table,
+    state: Option<AppState>,
+}
+
+impl PluginContext {
+    pub fn new(plugin: &str, granted: HashSet<Capability>, db: Option<Arc<Database>>, data_root: &Path) -> Self {
+        Self {
+            plugin: plugin.to_string(),
+            granted,
+            db,
+            data_dir: data_root.join(plugin),
+            hosts: Vec::new(),
+            settings: toml::Table::new(),
+            state: None,
+        }
+    }
+
+    pub fn with_settings(mut self, settings: toml::Table) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_hosts(mut self, hosts: Vec<String>) -> Self {
+        self.hosts = hosts;
+        self
+    }
+
+    pub fn with_state(mut self, state: Option<AppState>) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Shared services such as the counter and window registry.
+    pub fn app_state(&self) -> AppResult<&AppState> {
+        self.state
+            .as_ref()
+            .ok_or_else(|| AppError::Plugin(format!("Plugin '{}' has no app state", self.plugin)))
+    }
+
+    /// The plugin's `[plugins.settings.<name>]` config table.
+    pub fn settings(&self) -> &toml::Table {
+        &self.settings
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        self.granted.contains(&capability)
+    }
+
+    fn require(&self, capability: Capability) -> AppResult<()> {
+        if self.has(capability) {
+            Ok(())
+        } else {
+            Err(AppError::Plugin(format!(
+                "Plugin '{}' was not granted the {:?} capability",
+                self.plugin, capability
+            )))
+        }
+    }
+
+    pub fn database(&self) -> AppResult<Arc<Database>> {
+        self.require(Capability::Database)?;
+        self.db
+            .clone()
+            .ok_or_else(|| AppError::DatabaseConnection("Database not available".to_string()))
+    }
+
+    /// Namespaced key-value storage. Available to every plugin since it only
+    /// touches the plugin's own rows.
+    pub fn storage(&self) -> AppResult<PluginStore> {
+        let db = self
+            .db
+            .clone()
+            .ok_or_else(|| AppError::DatabaseConnection("Database not available".to_string()))?;
+        Ok(PluginStore::new(&self.plugin, db))
+    }
+
+    pub fn filesystem(&self) -> AppResult<ScopedFs> {
+        self.require(Capability::Filesystem)?;
+        Ok(ScopedFs { root: self.data_dir.clone() })
+    }
+
+    pub fn network(&self) -> AppResult<NetworkAccess> {
+        self.require(Capability::Network)?;
+        Ok(NetworkAccess { plugin: self.plugin.clone(), hosts: self.hosts.clone() })
+    }
+
+    pub fn clipboard(&self) -> AppResult<ClipboardAccess> {
+        self.require(Capability::Clipboard)?;
+        let windows = self.state.as_ref().map(|state| state.windows().clone()).unwrap_or_default();
+        Ok(ClipboardAccess { plugin: self.plugin.clone(), windows })
+    }
+}
+
+/// File access confined to the plugin's own data directory.
+pub struct ScopedFs {
+    root: PathBuf,
+}
+
+impl ScopedFs {
+    fn resolve(&self, relative: &str) -> AppResult<PathBuf> {
+        let path = Path::new(relative);
+        if path.components().all(|c| matches!(c, Component::Normal(_))) {
+            Ok(self.root.join(path))
+        } else {
+            Err(AppError::Plugin(format!("Path escapes plugin directory: {}", relative)))
+        }
+    }
+
+    pub fn read(&self, relative: &str) -> AppResult<Vec<u8>> {
+        Ok(std::fs::read(self.resolve(relative)?)?)
+    }
+
+    /// Writes `data` to `relative`, creating directories as needed, and
+    /// returns the full path.
+    pub fn write(&self, relative: &str, data: &[u8]) -> AppResult<PathBuf> {
+        let path = self.resolve(relative)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, data)?;
+        Ok(path)
+    }
+}
+
+/// TCP connections to the hosts granted as `network:<host>`.
+pub struct NetworkAccess {
+    plugin: String,
+    hosts: Vec<String>,
+}
+
+impl NetworkAccess {
+    pub fn allows(&self, host: &str) -> bool {
+        self.hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host))
+    }
+
+    /// Connects to `host:port`, trying each address `host` resolves to.
+    pub fn connect(&self, host: &str, port: u16, timeout: Duration) -> AppResult<TcpStream> {
+        if !self.allows(host) {
+            return Err(AppError::Plugin(format!("Plugin '{}' may not connect to {}", self.plugin, host)));
+        }
+        let mut last_error = None;
+        for addr in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&addr, timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.map_or_else(|| AppError::Plugin(format!("{} has no addresses", host)), AppError::from))
+    }
+}
+
+/// Clipboard writes are performed by the frontend, which owns the browser clipboard.
+pub struct ClipboardAccess {
+    plugin: String,
+    windows: WindowRegistry,
+}
+
+impl ClipboardAccess {
+    pub fn write_text(&self, text: &str) {
+        self.windows.send_to_frontend("clipboard.write", json!({ "text": text, "plugin": self.plugin }));
+    }
+}
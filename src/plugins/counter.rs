@@ -1,13 +1,10 @@
-use crate::core::database::Database;
-use crate::event_bus::{emit_counter_increment, emit_counter_reset, emit_event, Event, EventType};
-use crate::plugins::PluginTrait;
-use lazy_static::lazy_static;
-use std::sync::{Arc, Mutex};
-use tracing::info;
-
-lazy_static! {
-    static ref COUNTER: Arc<Mutex<i32>> = Arc::new(Mutex::new(0));
-}
+use crate::app_state::AppState;
+use crate::bridge::event_respond;
+use crate::core::{perf, RpcResponse};
+use crate::models::MutationResult;
+use crate::plugins::{PluginAssets, PluginContext, PluginTrait};
+use tracing::{error, info};
+use webui_rs::webui;
 
 pub struct CounterPlugin;
 
@@ -15,24 +12,6 @@ impl CounterPlugin {
     pub fn new() -> Self {
         Self
     }
-
-    pub fn get_value() -> i32 {
-        *COUNTER.lock().unwrap()
-    }
-
-    pub fn increment() -> i32 {
-        let mut counter = COUNTER.lock().unwrap();
-        *counter += 1;
-        let value = *counter;
-        tracing::info!("Counter incremented to: {}", value);
-        value
-    }
-
-    pub fn reset() {
-        let mut counter = COUNTER.lock().unwrap();
-        *counter = 0;
-        tracing::info!("Counter reset to 0");
-    }
 }
 
 impl Default for CounterPlugin {
@@ -46,22 +25,67 @@ impl PluginTrait for CounterPlugin {
         "counter"
     }
 
-    fn setup(&self, window: &mut webui::Window) -> Result<(), Box<dyn std::error::Error>> {
-        window.bind("increment_counter", |_event| {
-            let value = CounterPlugin::increment();
-            let _ = emit_counter_increment("counter_plugin");
+    fn description(&self) -> &str {
+        "Shared click counter with increment/reset commands"
+    }
+
+    fn assets(&self) -> Option<PluginAssets> {
+        Some(PluginAssets::Embedded(vec![("index.js", include_bytes!("counter/index.js"))]))
+    }
+
+    fn frontend_entry(&self) -> Option<String> {
+        Some("index.js".to_string())
+    }
+
+    fn setup(&self, window: &mut webui::Window, _ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        // `increment_counter` and `reset_counter` answer the call with the new value.
+        window.bind("increment_counter", |event| {
+            let _timer = perf::handler("increment_counter");
+            let Some(state) = AppState::of(&event) else { return };
+            let value = state.counter().increment();
+            let changed = RpcResponse::ok(MutationResult::with_value(1, value.into()));
+            event_respond(&event, &changed.to_value());
+            let bus = state.bus().clone();
             tracing::info!("Frontend: increment_counter -> {}", value);
+            tokio::spawn(async move {
+                if let Err(e) = bus.emit_counter_increment("counter_plugin").await {
+                    error!("Failed to emit counter increment event: {}", e);
+                }
+                if let Err(e) = bus.emit_counter_value_changed(value, "counter_plugin").await {
+                    error!("Failed to emit counter value changed event: {}", e);
+                }
+            });
         });
 
-        window.bind("reset_counter", |_event| {
-            CounterPlugin::reset();
-            let _ = emit_counter_reset("counter_plugin");
+        window.bind("reset_counter", |event| {
+            let _timer = perf::handler("reset_counter");
+            let Some(state) = AppState::of(&event) else { return };
+            let value = state.counter().reset();
+            let changed = RpcResponse::ok(MutationResult::with_value(1, value.into()));
+            event_respond(&event, &changed.to_value());
+            let bus = state.bus().clone();
             tracing::info!("Frontend: reset_counter");
+            tokio::spawn(async move {
+                if let Err(e) = bus.emit_counter_reset("counter_plugin").await {
+                    error!("Failed to emit counter reset event: {}", e);
+                }
+                if let Err(e) = bus.emit_counter_value_changed(value, "counter_plugin").await {
+                    error!("Failed to emit counter value changed event: {}", e);
+                }
+            });
         });
 
-        window.bind("get_counter_value", |_event| {
-            let value = CounterPlugin::get_value();
+        window.bind("get_counter_value", |event| {
+            let _timer = perf::handler("get_counter_value");
+            let Some(state) = AppState::of(&event) else { return };
+            let value = state.counter().get();
+            let bus = state.bus().clone();
             tracing::info!("Frontend: get_counter_value -> {}", value);
+            tokio::spawn(async move {
+                if let Err(e) = bus.emit_counter_value_changed(value, "counter_plugin").await {
+                    error!("Failed to emit counter value changed event: {}", e);
+                }
+            });
         });
 
         info!("CounterPlugin initialized");
@@ -0,0 +1,35 @@
+// Counter widget the shell can load from /plugins/counter/index.js.
+// `mount(element)` renders into `element` and returns a function that removes it.
+
+async function call(name) {
+  const raw = await window.webui.call(name);
+  const response = typeof raw === 'string' ? JSON.parse(raw) : raw;
+  if (!response.ok) {
+    throw new Error(response.error?.message ?? name + ' failed');
+  }
+  return response.data.value;
+}
+
+export function mount(element) {
+  const label = document.createElement('span');
+  const increment = document.createElement('button');
+  const reset = document.createElement('button');
+  label.textContent = 'Clicks: -';
+  increment.textContent = '+1';
+  reset.textContent = 'Reset';
+
+  const show = (value) => {
+    label.textContent = 'Clicks: ' + value;
+  };
+  increment.addEventListener('click', () => call('increment_counter').then(show, console.error));
+  reset.addEventListener('click', () => call('reset_counter').then(show, console.error));
+
+  element.append(label, increment, reset);
+  return () => {
+    label.remove();
+    increment.remove();
+    reset.remove();
+  };
+}
+
+export default mount;
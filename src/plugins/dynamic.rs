@@ -0,0 +1,497 @@
+//! Dynamic plugins loaded from shared libraries, with dev-mode hot reload.
+//!
+//! A dynamic plugin is a `cdylib` built against `rustwebui-plugin-api`
+//! (`crates/plugin-api`) that exports its declaration with
+//! `declare_plugin!`; the crate's `ping_plugin` example is one. Only
+//! `#[repr(C)]` types cross the boundary, so the library may be built with
+//! another compiler than the app, as long as it declares the same
+//! `ABI_VERSION`. Plugins listen to events and emit their own through the
+//! [`Host`] callbacks; they can't bind window handlers or mount routes.
+//!
+//! A library that fails to load is only tried again once its file changes.
+
+use crate::core::supervisor::Task;
+use crate::event_bus::bridge::frontend_payload;
+use crate::event_bus::{EventBus, EventHandler, EventListener, SubscriptionGuard};
+use crate::plugins::{PluginContext, PluginRegistry, PluginTrait};
+use libloading::{Library, Symbol};
+use rustwebui_plugin_api::{DeclarationFn, Host, LogLevel, PluginDeclaration, ABI_VERSION, DECLARATION_SYMBOL};
+use serde_json::json;
+use std::collections::HashMap;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::runtime::Handle;
+use tracing::{debug, error, info, warn};
+use webui_rs::webui;
+
+/// A library's [`PluginDeclaration`] as a [`PluginTrait`].
+struct ForeignPlugin {
+    /// Points into the library, which is never unmapped while the app runs:
+    /// unloaded libraries are retired, not closed.
+    declaration: &'static PluginDeclaration,
+    name: String,
+    version: String,
+    description: String,
+    patterns: Vec<String>,
+    /// Boxed so the pointer handed to `on_start` stays put.
+    host: Box<Host>,
+    /// What `host.context` points at; only read through the callbacks.
+    _context: Box<HostContext>,
+}
+
+/// What the [`Host`] callbacks need, behind `Host::context`.
+struct HostContext {
+    plugin: String,
+    bus: EventBus,
+    runtime: Option<Handle>,
+}
+
+impl ForeignPlugin {
+    fn new(declaration: &'static PluginDeclaration, bus: EventBus) -> Result<Self, String> {
+        if declaration.abi_version != ABI_VERSION {
+            return Err(format!(
+                "built against plugin API version {}, the app has version {}",
+                declaration.abi_version, ABI_VERSION
+            ));
+        }
+        let name = unsafe { text(declaration.name) }.filter(|name| !name.is_empty()).ok_or("the plugin has no name")?;
+        let patterns = unsafe { text(declaration.subscriptions) }
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|pattern| !pattern.is_empty())
+            .map(str::to_string)
+            .collect();
+        let mut context =
+            Box::new(HostContext { plugin: name.to_string(), bus, runtime: Handle::try_current().ok() });
+        let host = Box::new(Host {
+            context: (&mut *context as *mut HostContext).cast::<c_void>(),
+            emit: host_emit,
+            log: host_log,
+        });
+        Ok(Self {
+            declaration,
+            name: name.to_string(),
+            version: unsafe { text(declaration.version) }.unwrap_or_default().to_string(),
+            description: unsafe { text(declaration.description) }.unwrap_or_default().to_string(),
+            patterns,
+            host,
+            _context: context,
+        })
+    }
+}
+
+impl PluginTrait for ForeignPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn version(&self) -> &str {
+        &self.version
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn author(&self) -> &str {
+        "dynamic"
+    }
+
+    fn setup(&self, _window: &mut webui::Window, _ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn subscriptions(&self) -> Vec<(String, Arc<dyn EventListener>)> {
+        let Some(on_event) = self.declaration.on_event else { return Vec::new() };
+        self.patterns
+            .iter()
+            .map(|pattern| {
+                let listener: Arc<dyn EventListener> = Arc::new(EventHandler::new(move |event| {
+                    Box::pin(async move {
+                        let (Ok(name), Ok(payload)) =
+                            (CString::new(event.name.as_str()), CString::new(frontend_payload(&event).to_string()))
+                        else {
+                            return Ok(());
+                        };
+                        // Plugin code may block, so keep it off the async workers.
+                        tokio::task::spawn_blocking(move || unsafe { on_event(name.as_ptr(), payload.as_ptr()) })
+                            .await?;
+                        Ok(())
+                    })
+                }));
+                (pattern.clone(), listener)
+            })
+            .collect()
+    }
+
+    fn on_start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(on_start) = self.declaration.on_start else { return Ok(()) };
+        match unsafe { on_start(&*self.host) } {
+            0 => Ok(()),
+            code => Err(format!("on_start failed with {}", code).into()),
+        }
+    }
+
+    fn on_shutdown(&self) {
+        if let Some(on_shutdown) = self.declaration.on_shutdown {
+            unsafe { on_shutdown() }
+        }
+    }
+}
+
+// `host` and `context` are only read through shared references once built.
+unsafe impl Send for ForeignPlugin {}
+unsafe impl Sync for ForeignPlugin {}
+
+/// A NUL-terminated UTF-8 string from a plugin; `None` for null or invalid
+/// UTF-8.
+unsafe fn text<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(ptr) }.to_str().ok()
+}
+
+unsafe extern "C" fn host_emit(context: *mut c_void, name: *const c_char, payload: *const c_char) -> i32 {
+    let context = unsafe { &*context.cast::<HostContext>() };
+    let (Some(name), Some(payload)) = (unsafe { text(name) }, unsafe { text(payload) }) else { return 1 };
+    let (Ok(payload), Some(runtime)) = (serde_json::from_str(payload), context.runtime.as_ref()) else { return 1 };
+    let (bus, name, source) = (context.bus.clone(), name.to_string(), format!("plugin:{}", context.plugin));
+    runtime.spawn(async move {
+        if let Err(e) = bus.emit_custom(&name, payload, &source).await {
+            error!("Failed to emit {} from {}: {}", name, source, e);
+        }
+    });
+    0
+}
+
+unsafe extern "C" fn host_log(context: *mut c_void, level: LogLevel, message: *const c_char) {
+    let context = unsafe { &*context.cast::<HostContext>() };
+    let Some(message) = (unsafe { text(message) }) else { return };
+    let plugin = context.plugin.as_str();
+    match level {
+        LogLevel::Error => error!(target: "plugin", plugin, "{}", message),
+        LogLevel::Warn => warn!(target: "plugin", plugin, "{}", message),
+        LogLevel::Info => info!(target: "plugin", plugin, "{}", message),
+        LogLevel::Debug => debug!(target: "plugin", plugin, "{}", message),
+    }
+}
+
+/// Libraries that failed to load, with the modification time they had then.
+#[derive(Default)]
+struct FailedLoads(Mutex<HashMap<PathBuf, Option<SystemTime>>>);
+
+impl FailedLoads {
+    /// Whether `path` is new or changed since it last failed.
+    fn should_try(&self, path: &Path) -> bool {
+        self.0.lock().unwrap().get(path).is_none_or(|failed| *failed != modified_time(path))
+    }
+
+    fn record(&self, path: &Path) {
+        self.0.lock().unwrap().insert(path.to_path_buf(), modified_time(path));
+    }
+
+    fn clear(&self, path: &Path) {
+        self.0.lock().unwrap().remove(path);
+    }
+
+    /// Forgets libraries no longer in `present`.
+    fn retain(&self, present: &[PathBuf]) {
+        self.0.lock().unwrap().retain(|path, _| present.contains(path));
+    }
+}
+
+struct LoadedPlugin {
+    // Field order matters: the plugin and its listeners must be dropped
+    // before the library that contains their code.
+    subscriptions: Vec<SubscriptionGuard>,
+    plugin: Box<dyn PluginTrait>,
+    /// Taken by [`DynamicPluginHost::unload`], which keeps it loaded.
+    library: Option<Library>,
+    modified: SystemTime,
+    shadow_path: PathBuf,
+}
+
+impl Drop for LoadedPlugin {
+    fn drop(&mut self) {
+        self.plugin.on_shutdown();
+        self.subscriptions.clear();
+    }
+}
+
+pub struct DynamicPluginHost {
+    directory: PathBuf,
+    registry: Arc<PluginRegistry>,
+    window: Arc<Mutex<webui::Window>>,
+    loaded: Mutex<HashMap<PathBuf, LoadedPlugin>>,
+    /// Libraries of unloaded plugins. They stay mapped until the process
+    /// exits: threads the plugin started, and listener calls in progress,
+    /// can't be taken back and still point into their code.
+    retired: Mutex<Vec<Library>>,
+    failed: FailedLoads,
+}
+
+fn is_library(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("so") | Some("dll") | Some("dylib")
+    )
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl DynamicPluginHost {
+    pub fn new(directory: PathBuf, registry: Arc<PluginRegistry>, window: Arc<Mutex<webui::Window>>) -> Self {
+        Self {
+            directory,
+            registry,
+            window,
+            loaded: Mutex::new(HashMap::new()),
+            retired: Mutex::new(Vec::new()),
+            failed: FailedLoads::default(),
+        }
+    }
+
+    /// Loads every library currently present in the plugins directory.
+    pub fn load_all(&self) {
+        for path in self.scan() {
+            self.try_load(&path);
+        }
+    }
+
+    /// Loads `path` unless it failed to before and hasn't changed since.
+    /// Returns whether it was loaded.
+    fn try_load(&self, path: &Path) -> bool {
+        if !self.failed.should_try(path) {
+            return false;
+        }
+        match self.load(path) {
+            Ok(()) => {
+                self.failed.clear(path);
+                true
+            }
+            Err(e) => {
+                error!("Failed to load dynamic plugin {:?}: {}; it is tried again once it changes", path, e);
+                self.failed.record(path);
+                false
+            }
+        }
+    }
+
+    fn scan(&self) -> Vec<PathBuf> {
+        std::fs::read_dir(&self.directory)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| is_library(p))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn load(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let modified = modified_time(path).unwrap_or(SystemTime::UNIX_EPOCH);
+
+        // Load from a copy so the original can be rebuilt while loaded.
+        let shadow_dir = self.directory.join(".loaded");
+        std::fs::create_dir_all(&shadow_dir)?;
+        let stamp = modified.duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_millis();
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("plugin");
+        let shadow_path = shadow_dir.join(format!("{}-{}", stamp, file_name));
+        std::fs::copy(path, &shadow_path)?;
+
+        let library = match unsafe { Library::new(&shadow_path) } {
+            Ok(library) => library,
+            Err(e) => {
+                let _ = std::fs::remove_file(&shadow_path);
+                return Err(e.into());
+            }
+        };
+        let plugin = unsafe {
+            library
+                .get::<DeclarationFn>(DECLARATION_SYMBOL)
+                .ok()
+                .and_then(|declare: Symbol<DeclarationFn>| declare().as_ref())
+                .ok_or_else(|| "no plugin declaration; build it with declare_plugin!".to_string())
+                .and_then(|declaration| ForeignPlugin::new(declaration, self.registry.bus().clone()))
+        };
+        let plugin: Box<dyn PluginTrait> = match plugin {
+            Ok(plugin) => Box::new(plugin),
+            Err(e) => {
+                // Nothing of the library ran, so it can be closed.
+                drop(library);
+                let _ = std::fs::remove_file(&shadow_path);
+                return Err(e.into());
+            }
+        };
+
+        let ctx = self.registry.create_context(plugin.as_ref());
+        {
+            let mut window = self.window.lock().map_err(|e| e.to_string())?;
+            plugin.setup(&mut window, &ctx)?;
+        }
+        let subscriptions = plugin
+            .subscriptions()
+            .into_iter()
+            .map(|(pattern, listener)| self.registry.bus().subscribe_scoped(&pattern, listener))
+            .collect();
+        plugin.on_start()?;
+
+        info!("Loaded dynamic plugin '{}' from {:?}", plugin.name(), path);
+        self.loaded.lock().unwrap().insert(
+            path.to_path_buf(),
+            LoadedPlugin {
+                subscriptions,
+                plugin,
+                library: Some(library),
+                modified,
+                shadow_path,
+            },
+        );
+        Ok(())
+    }
+
+    fn unload(&self, path: &Path) -> Option<String> {
+        let mut loaded = self.loaded.lock().unwrap().remove(path)?;
+        let name = loaded.plugin.name().to_string();
+        let shadow_path = loaded.shadow_path.clone();
+        let library = loaded.library.take();
+        drop(loaded);
+        self.retired.lock().unwrap().extend(library);
+        // Fails where a loaded library can't be removed; `.loaded` is then
+        // cleaned up by hand.
+        let _ = std::fs::remove_file(shadow_path);
+        info!("Unloaded dynamic plugin '{}'", name);
+        Some(name)
+    }
+
+    /// Reloads libraries whose modification time changed and picks up new or
+    /// removed files. Returns the paths that were reloaded.
+    pub fn poll_changes(&self) -> Vec<PathBuf> {
+        let present = self.scan();
+        let mut changed = Vec::new();
+
+        let stale: Vec<PathBuf> = {
+            let loaded = self.loaded.lock().unwrap();
+            loaded
+                .iter()
+                .filter(|(path, plugin)| !present.contains(path) || modified_time(path) != Some(plugin.modified))
+                .map(|(path, _)| path.clone())
+                .collect()
+        };
+        for path in &stale {
+            self.unload(path);
+        }
+
+        self.failed.retain(&present);
+        for path in present {
+            if self.loaded.lock().unwrap().contains_key(&path) {
+                continue;
+            }
+            if self.try_load(&path) {
+                changed.push(path);
+            }
+        }
+        changed
+    }
+
+    /// Watches the plugins directory and hot-reloads changed libraries, as
+    /// the supervised `plugin_hot_reload` task.
+    pub fn watch(self: Arc<Self>, poll_interval: Duration) {
+        warn!("Plugin hot reload enabled for {:?} (dev mode)", self.directory);
+        Task::new("plugin_hot_reload").with_events(self.registry.bus()).spawn(move |_| {
+            let host = Arc::clone(&self);
+            async move {
+                let mut interval = tokio::time::interval(poll_interval);
+                loop {
+                    interval.tick().await;
+                    let poll = Arc::clone(&host);
+                    let reloaded = tokio::task::spawn_blocking(move || poll.poll_changes())
+                        .await
+                        .unwrap_or_default();
+                    for path in reloaded {
+                        let payload = json!({ "path": path.display().to_string() });
+                        let emitted = host.registry.bus().emit_custom("plugin.reloaded", payload, "plugin_host").await;
+                        if let Err(e) = emitted {
+                            error!("Failed to emit plugin reloaded event: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::EventType;
+
+    unsafe extern "C" fn greet(host: *const Host) -> i32 {
+        let host = unsafe { &*host };
+        if host.emit_event("test.greeting", r#"{"hello":"world"}"#) { 0 } else { 1 }
+    }
+
+    static GREETER: PluginDeclaration = PluginDeclaration {
+        abi_version: ABI_VERSION,
+        name: c"greeter".as_ptr(),
+        version: c"0.1.0".as_ptr(),
+        description: std::ptr::null(),
+        subscriptions: c"user.*, app.shutdown,".as_ptr(),
+        on_start: Some(greet),
+        on_event: None,
+        on_shutdown: None,
+    };
+
+    static FROM_THE_FUTURE: PluginDeclaration =
+        PluginDeclaration { abi_version: ABI_VERSION + 1, ..GREETER };
+
+    #[tokio::test]
+    async fn declarations_become_plugins_that_emit_on_the_bus() {
+        let bus = EventBus::new();
+        let mut events = bus.get_receiver();
+        let plugin = ForeignPlugin::new(&GREETER, bus.clone()).unwrap();
+        assert_eq!(plugin.name(), "greeter");
+        assert_eq!(plugin.description(), "");
+        assert_eq!(plugin.patterns, ["user.*", "app.shutdown"]);
+        // Without on_event there is nothing to deliver.
+        assert!(plugin.subscriptions().is_empty());
+
+        plugin.on_start().unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(2), events.recv()).await.unwrap().unwrap();
+        assert_eq!(event.name, "test.greeting");
+        assert_eq!(event.source, "plugin:greeter");
+        assert!(matches!(event.event_type, EventType::Custom { ref payload, .. } if payload["hello"] == "world"));
+
+        let Err(e) = ForeignPlugin::new(&FROM_THE_FUTURE, bus) else { panic!("loaded a plugin with another ABI") };
+        assert!(e.contains("version 2"));
+    }
+
+    #[test]
+    fn failed_libraries_are_retried_once_they_change() {
+        let dir = std::env::temp_dir().join(format!("dynamic-plugins-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("broken.so");
+        std::fs::write(&path, b"not a library").unwrap();
+
+        let failed = FailedLoads::default();
+        assert!(failed.should_try(&path));
+        failed.record(&path);
+        assert!(!failed.should_try(&path));
+
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60)).unwrap();
+        assert!(failed.should_try(&path));
+
+        failed.record(&path);
+        failed.retain(&[]);
+        assert!(failed.should_try(&path));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
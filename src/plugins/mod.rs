@@ -1,52 +1,615 @@
-use std::sync::Arc;
-
+pub mod assets;
+pub mod capabilities;
 pub mod counter;
+pub mod dynamic;
+pub mod scaffold;
+pub mod storage;
 pub mod system;
 pub mod user;
 pub mod window;
 
+pub use assets::PluginAssets;
+pub use capabilities::{Capability, NetworkAccess, PluginContext};
 pub use counter::CounterPlugin;
+pub use dynamic::DynamicPluginHost;
+pub use storage::PluginStore;
 pub use system::SystemPlugin;
 pub use user::UserPlugin;
 pub use window::WindowPlugin;
 
-use crate::event_bus::Event;
+use crate::app_state::AppState;
+use crate::core::supervisor::{self, panic_message};
+use crate::core::{AppConfig, AppResult, Database};
+use crate::event_bus::{EventBus, EventListener, SubscriptionGuard};
+use crate::server::Router;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use webui_rs::webui;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginFrontend {
+    pub name: String,
+    pub entry_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", content = "error", rename_all = "lowercase")]
+pub enum PluginStatus {
+    Registered,
+    Active,
+    Errored(String),
+    Disabled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginInfo {
+    #[serde(flatten)]
+    pub manifest: PluginManifest,
+    pub status: PluginStatus,
+}
+
+/// Liveness reported by a plugin's `health()` hook.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", content = "reason", rename_all = "lowercase")]
+pub enum PluginHealth {
+    Healthy,
+    Degraded(String),
+    Unhealthy(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginHealthReport {
+    pub name: String,
+    pub health: PluginHealth,
+}
+
+pub type SetupFuture<'a> = Pin<Box<dyn Future<Output = AppResult<()>> + Send + 'a>>;
+
+static REGISTRY: OnceCell<Arc<PluginRegistry>> = OnceCell::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    pub author: String,
+    pub permissions: Vec<Capability>,
+}
 
 pub trait PluginTrait: Send + Sync {
     fn name(&self) -> &str;
-    fn setup(&self, window: &mut webui::Window) -> Result<(), Box<dyn std::error::Error>>;
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn description(&self) -> &str {
+        ""
+    }
+
+    fn author(&self) -> &str {
+        "built-in"
+    }
+
+    /// Capabilities the plugin needs; only these can be granted to its context.
+    fn capabilities(&self) -> Vec<Capability> {
+        Vec::new()
+    }
+
+    fn manifest(&self) -> PluginManifest {
+        PluginManifest {
+            name: self.name().to_string(),
+            version: self.version().to_string(),
+            description: self.description().to_string(),
+            author: self.author().to_string(),
+            permissions: self.capabilities(),
+        }
+    }
+
+    /// Names of plugins whose `async_setup` must finish before this one's starts.
+    fn dependencies(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Asynchronous initialization (DB queries, network) run before `setup`.
+    /// Independent plugins are initialized concurrently.
+    fn async_setup<'a>(&'a self, _ctx: &'a PluginContext) -> SetupFuture<'a> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn setup(&self, window: &mut webui::Window, ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>>;
+
+    /// Event bus listeners wired by the registry for as long as the plugin is enabled.
+    fn subscriptions(&self) -> Vec<(String, Arc<dyn EventListener>)> {
+        Vec::new()
+    }
+
+    /// Static UI bundle served under `/plugins/<name>/`.
+    fn assets(&self) -> Option<PluginAssets> {
+        None
+    }
+
+    /// Entry module the React shell loads, relative to the plugin's asset root.
+    fn frontend_entry(&self) -> Option<String> {
+        None
+    }
+
+    /// Lets the plugin mount HTTP endpoints on the internal server.
+    fn register_routes(&self, _router: &mut Router, _ctx: &PluginContext) {}
+
+    /// Called once the window is shown; spawn background tasks here.
+    fn on_start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Called after the event loop exits; stop anything started in `on_start`.
+    fn on_shutdown(&self) {}
+
+    /// Called when the application configuration is reloaded.
+    fn on_config_changed(&self, _config: &AppConfig) {}
+
+    /// Polled periodically; plugins with background workers should report
+    /// when those workers have stalled or exited.
+    fn health(&self) -> PluginHealth {
+        PluginHealth::Healthy
+    }
 }
 
 pub struct PluginRegistry {
     plugins: Vec<Box<dyn PluginTrait>>,
+    contexts: HashMap<String, PluginContext>,
+    subscriptions: Mutex<HashMap<String, Vec<SubscriptionGuard>>>,
+    statuses: Mutex<HashMap<String, PluginStatus>>,
+    database: Option<Arc<Database>>,
+    state: Option<AppState>,
+    /// Where plugins subscribe and registry events go: the state's bus once
+    /// [`PluginRegistry::with_state`] is called.
+    bus: EventBus,
+    grants: HashMap<String, Vec<String>>,
+    settings: HashMap<String, toml::Table>,
+    data_root: PathBuf,
+    health_interval: Mutex<std::time::Duration>,
 }
 
 impl PluginRegistry {
     pub fn new() -> Self {
         Self {
             plugins: Vec::new(),
+            contexts: HashMap::new(),
+            subscriptions: Mutex::new(HashMap::new()),
+            statuses: Mutex::new(HashMap::new()),
+            database: None,
+            state: None,
+            bus: EventBus::new(),
+            grants: HashMap::new(),
+            settings: HashMap::new(),
+            data_root: PathBuf::from("plugin-data"),
+            health_interval: Mutex::new(std::time::Duration::from_secs(60)),
         }
     }
 
+    #[cfg(test)]
+    pub fn with_database(mut self, db: Arc<Database>) -> Self {
+        self.database = Some(db);
+        self
+    }
+
+    /// Hands `state` to every plugin context; also sets the database and
+    /// event bus.
+    pub fn with_state(mut self, state: AppState) -> Self {
+        self.database = Some(Arc::clone(state.db()));
+        self.bus = state.bus().clone();
+        self.state = Some(state);
+        self
+    }
+
+    /// The capabilities each plugin is granted, by name: `database`,
+    /// `filesystem`, `clipboard`, or `network:<host>` once per host. A plugin
+    /// gets the ones it also declares; plugins without an entry get none.
+    pub fn with_grants(mut self, grants: HashMap<String, Vec<String>>) -> Self {
+        self.grants = grants;
+        self
+    }
+
+    pub fn with_settings(mut self, settings: HashMap<String, toml::Table>) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_health_interval(self, interval: std::time::Duration) -> Self {
+        *self.health_interval.lock().unwrap() = interval;
+        self
+    }
+
+    pub fn with_data_root(mut self, data_root: PathBuf) -> Self {
+        self.data_root = data_root;
+        self
+    }
+
     pub fn register(&mut self, plugin: Box<dyn PluginTrait>) {
+        let ctx = self.create_context(plugin.as_ref());
+        self.contexts.insert(plugin.name().to_string(), ctx);
+        self.set_status(plugin.name(), PluginStatus::Registered);
         self.plugins.push(plugin);
     }
 
-    pub fn setup_all(&self, window: &mut webui::Window) -> Result<(), Box<dyn std::error::Error>> {
+    fn granted_capabilities(&self, plugin: &dyn PluginTrait) -> HashSet<Capability> {
+        let declared: HashSet<Capability> = plugin.capabilities().into_iter().collect();
+        let allowed = self.grants.get(plugin.name()).map(Vec::as_slice).unwrap_or_default();
+        let granted: HashSet<Capability> = declared
+            .iter()
+            .copied()
+            .filter(|cap| match cap {
+                Capability::Network => allowed.iter().any(|a| a.starts_with(capabilities::NETWORK_GRANT_PREFIX)),
+                _ => allowed.iter().any(|a| a == cap.as_str()),
+            })
+            .collect();
+        for denied in declared.difference(&granted) {
+            tracing::warn!("Plugin {} denied capability {:?}", plugin.name(), denied);
+        }
+        if allowed.iter().any(|a| a == Capability::Network.as_str()) {
+            tracing::warn!(
+                "Plugin {}: grant network access per host, as \"{}<host>\"",
+                plugin.name(),
+                capabilities::NETWORK_GRANT_PREFIX
+            );
+        }
+        granted
+    }
+
+    fn granted_hosts(&self, plugin: &dyn PluginTrait) -> Vec<String> {
+        self.grants
+            .get(plugin.name())
+            .into_iter()
+            .flatten()
+            .filter_map(|grant| grant.strip_prefix(capabilities::NETWORK_GRANT_PREFIX))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Builds a capability-scoped context for a plugin using the registry's grants.
+    pub fn create_context(&self, plugin: &dyn PluginTrait) -> PluginContext {
+        let granted = self.granted_capabilities(plugin);
+        PluginContext::new(plugin.name(), granted, self.database.clone(), &self.data_root)
+            .with_hosts(self.granted_hosts(plugin))
+            .with_settings(self.settings.get(plugin.name()).cloned().unwrap_or_default())
+            .with_state(self.state.clone())
+    }
+
+    fn context(&self, plugin: &dyn PluginTrait) -> &PluginContext {
+        &self.contexts[plugin.name()]
+    }
+
+    fn set_status(&self, name: &str, status: PluginStatus) {
+        self.statuses.lock().unwrap().insert(name.to_string(), status);
+    }
+
+    pub fn status(&self, name: &str) -> Option<PluginStatus> {
+        self.statuses.lock().unwrap().get(name).cloned()
+    }
+
+    fn is_active(&self, name: &str) -> bool {
+        self.status(name) == Some(PluginStatus::Active)
+    }
+
+    fn record_failure(&self, name: &str, error: &str) {
+        tracing::error!("Plugin {} failed to set up: {}", name, error);
+        self.set_status(name, PluginStatus::Errored(error.to_string()));
+        let payload = json!({ "plugin": name, "error": error });
+        let bus = self.bus.clone();
+        tokio::spawn(async move {
+            if let Err(e) = bus.emit_custom("plugin.setup_failed", payload, "plugin_registry").await {
+                tracing::error!("Failed to emit plugin setup failed event: {}", e);
+            }
+        });
+    }
+
+    fn is_errored(&self, name: &str) -> bool {
+        matches!(self.status(name), Some(PluginStatus::Errored(_)))
+    }
+
+    /// Runs every plugin's `async_setup` in dependency order, awaiting
+    /// plugins whose dependencies are satisfied concurrently. Plugins whose
+    /// dependencies fail or never resolve are marked as errored.
+    pub async fn initialize_all(&self) {
+        let mut done: HashSet<String> = HashSet::new();
+        let mut pending: Vec<&dyn PluginTrait> = self.plugins.iter().map(|p| p.as_ref()).collect();
+
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|p| p.dependencies().iter().all(|d| done.contains(d)));
+            pending = waiting;
+
+            if ready.is_empty() {
+                for plugin in &pending {
+                    self.record_failure(plugin.name(), "unresolved or failed dependencies");
+                }
+                break;
+            }
+
+            let results = futures::future::join_all(ready.iter().map(|plugin| {
+                let ctx = self.context(*plugin);
+                async move { (plugin.name(), plugin.async_setup(ctx).await) }
+            }))
+            .await;
+
+            for (name, result) in results {
+                match result {
+                    Ok(()) => {
+                        done.insert(name.to_string());
+                    }
+                    Err(e) => self.record_failure(name, &e.to_string()),
+                }
+            }
+        }
+    }
+
+    /// Sets up every plugin, isolating failures (errors and panics) so one
+    /// broken plugin doesn't prevent the rest from loading. Returns the
+    /// names and errors of the plugins that failed.
+    pub fn setup_all(&self, window: &mut webui::Window) -> Vec<(String, String)> {
+        let mut failures = Vec::new();
         for plugin in &self.plugins {
+            if self.is_errored(plugin.name()) {
+                continue;
+            }
             tracing::info!("Setting up plugin: {}", plugin.name());
-            plugin.setup(window)?;
+            let ctx = self.context(plugin.as_ref());
+            let result = catch_unwind(AssertUnwindSafe(|| plugin.setup(window, ctx)));
+            let error = match result {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(panic) => Some(panic_message(&panic)),
+            };
+
+            match error {
+                None => {
+                    self.subscribe_plugin(plugin.as_ref());
+                    self.set_status(plugin.name(), PluginStatus::Active);
+                }
+                Some(error) => {
+                    self.record_failure(plugin.name(), &error);
+                    failures.push((plugin.name().to_string(), error));
+                }
+            }
         }
-        Ok(())
+        failures
+    }
+
+    fn subscribe_plugin(&self, plugin: &dyn PluginTrait) {
+        let guards: Vec<SubscriptionGuard> = plugin
+            .subscriptions()
+            .into_iter()
+            .map(|(pattern, listener)| {
+                tracing::debug!("Plugin {} subscribed to {}", plugin.name(), pattern);
+                self.bus.subscribe_scoped(&pattern, listener)
+            })
+            .collect();
+        if !guards.is_empty() {
+            self.subscriptions
+                .lock()
+                .unwrap()
+                .insert(plugin.name().to_string(), guards);
+        }
+    }
+
+    /// Stops a plugin at runtime, dropping its event subscriptions.
+    pub fn disable_plugin(&self, name: &str) -> bool {
+        let Some(plugin) = self.plugins.iter().find(|p| p.name() == name) else {
+            return false;
+        };
+        self.subscriptions.lock().unwrap().remove(name);
+        if self.is_active(name) {
+            plugin.on_shutdown();
+        }
+        self.set_status(name, PluginStatus::Disabled);
+        tracing::info!("Plugin disabled: {}", name);
+        true
+    }
+
+    pub fn register_routes(&self, router: &mut Router) {
+        for plugin in &self.plugins {
+            plugin.register_routes(router, self.context(plugin.as_ref()));
+            if let Some(assets) = plugin.assets() {
+                let pattern = format!("/plugins/{}/*", plugin.name());
+                router.get(&pattern, move |request| assets.serve(request));
+            }
+        }
+    }
+
+    /// URLs of the UI bundles plugins provide, for lazy loading by the frontend.
+    pub fn list_frontends(&self) -> Vec<PluginFrontend> {
+        self.plugins
+            .iter()
+            .filter_map(|p| {
+                p.frontend_entry().map(|entry| PluginFrontend {
+                    name: p.name().to_string(),
+                    entry_url: format!("/plugins/{}/{}", p.name(), entry.trim_start_matches('/')),
+                })
+            })
+            .collect()
+    }
+
+    pub fn start_all(&self) {
+        for plugin in self.plugins.iter().filter(|p| self.is_active(p.name())) {
+            if let Err(e) = plugin.on_start() {
+                tracing::error!("Plugin {} failed to start: {}", plugin.name(), e);
+            }
+        }
+    }
+
+    /// Shuts plugins down in reverse registration order.
+    pub fn shutdown_all(&self) {
+        for plugin in self.plugins.iter().rev().filter(|p| self.is_active(p.name())) {
+            tracing::info!("Shutting down plugin: {}", plugin.name());
+            self.subscriptions.lock().unwrap().remove(plugin.name());
+            plugin.on_shutdown();
+        }
+    }
+
+    pub fn notify_config_changed(&self, config: &AppConfig) {
+        *self.health_interval.lock().unwrap() = config.get_plugin_health_interval();
+        for plugin in self.plugins.iter().filter(|p| self.is_active(p.name())) {
+            plugin.on_config_changed(config);
+        }
+    }
+
+    pub fn list_plugins(&self) -> Vec<PluginInfo> {
+        self.plugins
+            .iter()
+            .map(|p| PluginInfo {
+                manifest: p.manifest(),
+                status: self.status(p.name()).unwrap_or(PluginStatus::Registered),
+            })
+            .collect()
+    }
+
+    /// Health of every active plugin.
+    pub fn health_report(&self) -> Vec<PluginHealthReport> {
+        self.plugins
+            .iter()
+            .filter(|p| self.is_active(p.name()))
+            .map(|p| {
+                let health = catch_unwind(AssertUnwindSafe(|| p.health()))
+                    .unwrap_or_else(|panic| PluginHealth::Unhealthy(panic_message(&panic)));
+                PluginHealthReport { name: p.name().to_string(), health }
+            })
+            .collect()
+    }
+
+    /// Polls plugin health and emits `plugin.unhealthy` for each unhealthy
+    /// plugin, as the supervised `plugin_health` task.
+    pub fn watch_health(self: Arc<Self>) {
+        supervisor::Task::new("plugin_health").with_events(&self.bus).spawn(move |_| {
+            let registry = Arc::clone(&self);
+            async move {
+                loop {
+                    let interval = *registry.health_interval.lock().unwrap();
+                    tokio::time::sleep(interval).await;
+                    for report in registry.health_report() {
+                        if let PluginHealth::Unhealthy(reason) = report.health {
+                            tracing::warn!("Plugin {} is unhealthy: {}", report.name, reason);
+                            let payload = json!({ "plugin": report.name, "reason": reason });
+                            let emitted = registry.bus.emit_custom("plugin.unhealthy", payload, "plugin_registry").await;
+                            if let Err(e) = emitted {
+                                tracing::error!("Failed to emit plugin unhealthy event: {}", e);
+                            }
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
     }
 
-    pub fn list_plugins(&self) -> Vec<&str> {
+    pub fn plugin_names(&self) -> Vec<&str> {
         self.plugins.iter().map(|p| p.name()).collect()
     }
 }
 
+/// Publishes the registry so WebUI handlers can reach it.
+pub fn install_registry(registry: PluginRegistry) -> Arc<PluginRegistry> {
+    let registry = Arc::new(registry);
+    if REGISTRY.set(Arc::clone(&registry)).is_err() {
+        tracing::warn!("Plugin registry already installed");
+    }
+    registry
+}
+
+pub fn registry() -> Option<Arc<PluginRegistry>> {
+    REGISTRY.get().cloned()
+}
+
 impl Default for PluginRegistry {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::ApiRequest;
+    use crate::testing::memory_db;
+    use std::time::Duration;
+
+    struct Needy;
+
+    impl PluginTrait for Needy {
+        fn name(&self) -> &str {
+            "needy"
+        }
+
+        fn capabilities(&self) -> Vec<Capability> {
+            vec![Capability::Database, Capability::Filesystem, Capability::Network]
+        }
+
+        fn setup(&self, _window: &mut webui::Window, _ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn plugins_only_get_what_they_are_granted() {
+        let ungranted = PluginRegistry::new().with_database(Arc::new(memory_db())).create_context(&Needy);
+        assert!(ungranted.database().is_err());
+        assert!(ungranted.filesystem().is_err());
+        assert!(ungranted.network().is_err());
+
+        let grants = HashMap::from([(
+            "needy".to_string(),
+            vec!["database".to_string(), "clipboard".to_string(), "network:127.0.0.1".to_string()],
+        )]);
+        let ctx = PluginRegistry::new().with_database(Arc::new(memory_db())).with_grants(grants).create_context(&Needy);
+        assert!(ctx.database().is_ok());
+        assert!(ctx.filesystem().is_err());
+        // Granted but not declared.
+        assert!(!ctx.has(Capability::Clipboard));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let network = ctx.network().unwrap();
+        assert!(network.connect("127.0.0.1", port, Duration::from_secs(1)).is_ok());
+        assert!(network.connect("localhost", port, Duration::from_secs(1)).is_err());
+    }
+
+    #[test]
+    fn disabled_plugins_stay_listed_as_disabled() {
+        let mut registry = PluginRegistry::new();
+        registry.register(Box::new(Needy));
+        assert!(registry.disable_plugin("needy"));
+        assert_eq!(registry.status("needy"), Some(PluginStatus::Disabled));
+        assert!(!registry.disable_plugin("missing"));
+    }
+
+    #[test]
+    fn the_counter_widget_is_served_and_listed() {
+        let mut registry = PluginRegistry::new();
+        registry.register(Box::new(counter::CounterPlugin::new()));
+        let mut router = Router::new();
+        registry.register_routes(&mut router);
+
+        let get = |path: &str| {
+            let (handler, params) = router.find("GET", path).unwrap();
+            handler(&ApiRequest { query: HashMap::new(), headers: Vec::new(), body: Vec::new(), params })
+        };
+
+        let entry = &registry.list_frontends()[0];
+        assert_eq!(entry.entry_url, "/plugins/counter/index.js");
+        let widget = get(&entry.entry_url);
+        assert_eq!(widget.status, 200);
+        assert!(String::from_utf8_lossy(&widget.body).contains("export function mount"));
+        assert_eq!(get("/plugins/counter/missing.js").status, 404);
+    }
+}
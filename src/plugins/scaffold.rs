@@ -0,0 +1,164 @@
+//! `--new-plugin <name>` generator producing a ready-to-edit plugin skeleton.
+
+use crate::core::{AppError, AppResult};
+use std::fs;
+use std::path::Path;
+
+const REGISTRY_MARKER: &str = "// --new-plugin inserts registrations above this line";
+
+pub fn is_valid_plugin_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+fn struct_name(name: &str) -> String {
+    let camel: String = name
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    format!("{}Plugin", camel)
+}
+
+fn plugin_source(name: &str) -> String {
+    let struct_name = struct_name(name);
+    format!(
+        r#"use crate::app_state::AppState;
+use crate::core::perf;
+use crate::event_bus::{{EventHandler, EventListener}};
+use crate::plugins::{{PluginContext, PluginTrait}};
+use serde_json::json;
+use std::sync::Arc;
+use tracing::{{error, info}};
+use webui_rs::webui;
+
+pub struct {struct_name};
+
+impl {struct_name} {{
+    pub fn new() -> Self {{
+        Self
+    }}
+}}
+
+impl Default for {struct_name} {{
+    fn default() -> Self {{
+        Self::new()
+    }}
+}}
+
+impl PluginTrait for {struct_name} {{
+    fn name(&self) -> &str {{
+        "{name}"
+    }}
+
+    fn description(&self) -> &str {{
+        "Answers {name}_ping with a {name}.pong event"
+    }}
+
+    fn setup(&self, window: &mut webui::Window, ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {{
+        let greeting = ctx
+            .settings()
+            .get("greeting")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Hello")
+            .to_string();
+        info!("{struct_name} configured with greeting: {{}}", greeting);
+
+        window.bind("{name}_ping", |event| {{
+            let _timer = perf::handler("{name}_ping");
+            info!("Frontend: {name}_ping called");
+            let Some(state) = AppState::of(&event) else {{ return }};
+            let bus = state.bus().clone();
+            tokio::spawn(async move {{
+                if let Err(e) = bus.emit_custom("{name}.pong", json!({{ "ok": true }}), "{name}_plugin").await {{
+                    error!("Failed to emit {name}.pong event: {{}}", e);
+                }}
+            }});
+        }});
+
+        info!("{struct_name} initialized");
+        Ok(())
+    }}
+
+    fn subscriptions(&self) -> Vec<(String, Arc<dyn EventListener>)> {{
+        let on_ready = EventHandler::new(|event| {{
+            Box::pin(async move {{
+                info!("{struct_name} saw {{}} from {{}}", event.name, event.source);
+                Ok(())
+            }})
+        }});
+        vec![("webui.ready".to_string(), Arc::new(on_ready))]
+    }}
+}}
+"#
+    )
+}
+
+fn config_section(name: &str) -> String {
+    format!(
+        "\n[plugins.settings.{name}]\n# Settings available to the {name} plugin via ctx.settings()\ngreeting = \"Hello\"\n"
+    )
+}
+
+/// Generates `src/plugins/<name>.rs`, declares it in `plugins/mod.rs`,
+/// registers it in `main.rs`, and appends a config section.
+pub fn generate_plugin(project_root: &Path, name: &str) -> AppResult<Vec<String>> {
+    if !is_valid_plugin_name(name) {
+        return Err(AppError::Plugin(format!(
+            "Invalid plugin name '{}': use lowercase letters, digits and underscores",
+            name
+        )));
+    }
+
+    let main_path = project_root.join("src/main.rs");
+    if !main_path.is_file() {
+        return Err(AppError::Plugin(format!(
+            "{} is not the project directory; run from it or pass --project-dir",
+            project_root.display()
+        )));
+    }
+
+    let plugins_dir = project_root.join("src/plugins");
+    let module_path = plugins_dir.join(format!("{}.rs", name));
+    if module_path.exists() {
+        return Err(AppError::Plugin(format!("Plugin module already exists: {}", module_path.display())));
+    }
+
+    let main_source = fs::read_to_string(&main_path)?;
+    if !main_source.contains(REGISTRY_MARKER) {
+        return Err(AppError::Plugin("Could not find the plugin registration marker in src/main.rs".to_string()));
+    }
+
+    let struct_name = struct_name(name);
+    let mut changed = Vec::new();
+
+    fs::write(&module_path, plugin_source(name))?;
+    changed.push(module_path.display().to_string());
+
+    let mod_path = plugins_dir.join("mod.rs");
+    let mod_source = fs::read_to_string(&mod_path)?;
+    let mod_source = format!("pub mod {};\n{}", name, mod_source);
+    fs::write(&mod_path, mod_source)?;
+    changed.push(mod_path.display().to_string());
+
+    let registration = format!("Box::new(plugins::{}::{}::new()),\n        {}", name, struct_name, REGISTRY_MARKER);
+    fs::write(&main_path, main_source.replacen(REGISTRY_MARKER, &registration, 1))?;
+    changed.push(main_path.display().to_string());
+
+    let config_path = project_root.join("app.config.toml");
+    if config_path.exists() {
+        let mut config = fs::read_to_string(&config_path)?;
+        config.push_str(&config_section(name));
+        fs::write(&config_path, config)?;
+        changed.push(config_path.display().to_string());
+    }
+
+    Ok(changed)
+}
@@ -0,0 +1,88 @@
+use crate::core::database::{audit, prepare_cached};
+use crate::core::{perf, AppResult, Database};
+use rusqlite::{Connection, OptionalExtension};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Key-value storage namespaced to a single plugin, backed by `plugin_storage`.
+/// Writes are audited with the plugin as actor, as `plugin:<name>`.
+pub struct PluginStore {
+    plugin: String,
+    db: Arc<Database>,
+}
+
+impl PluginStore {
+    pub fn new(plugin: &str, db: Arc<Database>) -> Self {
+        Self { plugin: plugin.to_string(), db }
+    }
+
+    pub fn get(&self, key: &str) -> AppResult<Option<Value>> {
+        let _timer = perf::query("plugin_storage.get");
+        let conn = self.db.get_connection()?;
+        self.read(&conn, key)
+    }
+
+    pub fn set(&self, key: &str, value: &Value) -> AppResult<()> {
+        let _timer = perf::query("plugin_storage.set");
+        self.db.transaction(|tx| {
+            let before = self.read(tx, key)?;
+            tx.execute(
+                "INSERT INTO plugin_storage (plugin, key, value, updated_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(plugin, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                rusqlite::params![self.plugin, key, value.to_string(), chrono::Utc::now().timestamp_millis()],
+            )?;
+            let operation = if before.is_some() { audit::Operation::Update } else { audit::Operation::Insert };
+            self.record(tx, operation, key, before, Some(value.clone()))
+        })
+    }
+
+    pub fn delete(&self, key: &str) -> AppResult<bool> {
+        let _timer = perf::query("plugin_storage.delete");
+        self.db.transaction(|tx| {
+            let Some(before) = self.read(tx, key)? else { return Ok(false) };
+            tx.execute(
+                "DELETE FROM plugin_storage WHERE plugin = ?1 AND key = ?2",
+                rusqlite::params![self.plugin, key],
+            )?;
+            self.record(tx, audit::Operation::Delete, key, Some(before), None)?;
+            Ok(true)
+        })
+    }
+
+    pub fn list(&self) -> AppResult<Vec<(String, Value)>> {
+        let _timer = perf::query("plugin_storage.list");
+        let conn = self.db.get_connection()?;
+        let mut stmt = prepare_cached(&conn, "SELECT key, value FROM plugin_storage WHERE plugin = ?1 ORDER BY key")?;
+        let rows = stmt
+            .query_map([&self.plugin], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.into_iter()
+            .map(|(key, raw)| Ok((key, serde_json::from_str(&raw)?)))
+            .collect()
+    }
+
+    fn read(&self, conn: &Connection, key: &str) -> AppResult<Option<Value>> {
+        let raw: Option<String> = conn
+            .query_row(
+                "SELECT value FROM plugin_storage WHERE plugin = ?1 AND key = ?2",
+                rusqlite::params![self.plugin, key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(raw.map(|raw| serde_json::from_str(&raw)).transpose()?)
+    }
+
+    /// Audits a change to `key`, identified as `<plugin>/<key>` in `plugin_storage`.
+    fn record(
+        &self,
+        conn: &Connection,
+        operation: audit::Operation,
+        key: &str,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> AppResult<()> {
+        let actor = format!("plugin:{}", self.plugin);
+        let id = format!("{}/{}", self.plugin, key);
+        audit::record(conn, &actor, operation, "plugin_storage", &id, before, after)
+    }
+}
@@ -1,19 +1,48 @@
-use crate::event_bus::emit_event;
-use crate::plugins::PluginTrait;
-use serde_json::json;
-use tracing::info;
+use crate::app_state::AppState;
+use crate::plugins::{Capability, NetworkAccess, PluginContext, PluginHealth, PluginStore, PluginTrait};
+use crate::core::{perf, AppConfig, AppError, AppResult, ErrorCode, RpcResponse};
+use crate::event_bus::{EventBus, EventHandler, EventListener, EventType};
+use crate::core::supervisor::{self, TaskState};
+use crate::server::{ApiResponse, Router};
+use crate::viewmodels::{setup_power_viewmodel, setup_system_viewmodel, start_power_monitor, POWER_MONITOR_TASK};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+use webui_rs::webui;
 
-pub struct SystemPlugin;
+/// Where `POST /api/system/diagnostics` writes its report, in the plugin's
+/// data directory.
+const DIAGNOSTICS_FILE: &str = "diagnostics.json";
+
+/// How long each connectivity probe may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Prefix of the plugin storage keys holding when each probe target last
+/// answered.
+const LAST_REACHABLE_KEY: &str = "last_reachable:";
+
+pub struct SystemPlugin {
+    power_poll_interval: Mutex<Duration>,
+    power_monitor_running: AtomicBool,
+    /// Where the power monitor emits `power.changed`.
+    bus: EventBus,
+}
 
 impl SystemPlugin {
-    pub fn new() -> Self {
-        Self
+    pub fn with_power_poll_interval(interval: Duration, bus: EventBus) -> Self {
+        Self {
+            power_poll_interval: Mutex::new(interval),
+            power_monitor_running: AtomicBool::new(false),
+            bus,
+        }
     }
-}
 
-impl Default for SystemPlugin {
-    fn default() -> Self {
-        Self::new()
+    fn restart_power_monitor(&self) {
+        let interval = *self.power_poll_interval.lock().unwrap();
+        start_power_monitor(interval, self.bus.clone());
+        self.power_monitor_running.store(true, Ordering::Relaxed);
     }
 }
 
@@ -22,24 +51,261 @@ impl PluginTrait for SystemPlugin {
         "system"
     }
 
-    fn setup(&self, window: &mut webui::Window) -> Result<(), Box<dyn std::error::Error>> {
-        window.bind("get_system_info", |_event| {
-            info!("Frontend: get_system_info called");
+    fn description(&self) -> &str {
+        "System information, app version and power status"
+    }
 
-            let system_info = serde_json::json!({
-                "os": std::env::consts::OS,
-                "arch": std::env::consts::ARCH,
-                "family": std::env::consts::FAMILY,
-            });
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability::Filesystem, Capability::Clipboard, Capability::Network]
+    }
 
-            info!("System info: {:?}", system_info);
-        });
+    fn setup(&self, window: &mut webui::Window, _ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        setup_system_viewmodel(window);
+        setup_power_viewmodel(window);
 
-        window.bind("get_app_version", |_event| {
+        window.bind("get_app_version", |event| {
+            let _timer = perf::handler("get_app_version");
             info!("Frontend: get_app_version called");
+            let Some(state) = AppState::of(&event) else { return };
+            let bus = state.bus().clone();
+
+            tokio::spawn(async move {
+                let payload = json!({
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "os": std::env::consts::OS,
+                    "arch": std::env::consts::ARCH,
+                    "family": std::env::consts::FAMILY,
+                });
+                if let Err(e) = bus.emit_custom("system.app_version", payload, "system_plugin").await {
+                    error!("Failed to emit app version event: {}", e);
+                }
+            });
         });
 
         info!("SystemPlugin initialized");
         Ok(())
     }
+
+    /// Mounts `POST /api/system/diagnostics`, which writes a diagnostics
+    /// report to the plugin's data directory (and with `?copy=true` to the
+    /// clipboard), `GET /api/system/diagnostics`, which returns the last
+    /// one, and `GET /api/system/connectivity`, which probes the
+    /// `host:port` targets in `[plugins.settings.system] probes` and reports
+    /// when each last answered.
+    fn register_routes(&self, router: &mut Router, ctx: &PluginContext) {
+        match ctx.filesystem() {
+            Ok(fs) => {
+                let fs = Arc::new(fs);
+                let clipboard = ctx.clipboard().ok();
+                let export_fs = Arc::clone(&fs);
+                router.post("/api/system/diagnostics", move |request| {
+                    let _timer = perf::handler("system.export_diagnostics");
+                    let report = serde_json::to_string_pretty(&diagnostics_report()).unwrap_or_default();
+                    let path = match export_fs.write(DIAGNOSTICS_FILE, report.as_bytes()) {
+                        Ok(path) => path,
+                        Err(e) => return failure(e),
+                    };
+                    let copied = match &clipboard {
+                        Some(clipboard) if request.query.get("copy").is_some_and(|copy| copy == "true") => {
+                            clipboard.write_text(&report);
+                            true
+                        }
+                        _ => false,
+                    };
+                    info!("Diagnostics written to {}", path.display());
+                    ApiResponse::json(200, &RpcResponse::ok(json!({ "path": path, "copied": copied })).to_value())
+                });
+                router.get("/api/system/diagnostics", move |_request| match fs.read(DIAGNOSTICS_FILE) {
+                    Ok(report) => match serde_json::from_slice::<Value>(&report) {
+                        Ok(report) => ApiResponse::json(200, &RpcResponse::ok(report).to_value()),
+                        Err(e) => failure(e.into()),
+                    },
+                    Err(AppError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => ApiResponse::json(
+                        404,
+                        &RpcResponse::localized(ErrorCode::NotFound, "system.no_diagnostics", &[]).to_value(),
+                    ),
+                    Err(e) => failure(e),
+                });
+            }
+            Err(e) => warn!("Diagnostics routes not mounted: {}", e),
+        }
+
+        let probes: Vec<String> = ctx
+            .settings()
+            .get("probes")
+            .and_then(|probes| probes.as_array())
+            .map(|probes| probes.iter().filter_map(|probe| probe.as_str()).map(str::to_string).collect())
+            .unwrap_or_default();
+        let network = ctx.network();
+        let store = ctx.storage();
+        router.get("/api/system/connectivity", move |_request| {
+            let _timer = perf::handler("system.connectivity");
+            let results = probes.iter().map(|target| (target, probe(&network, target))).collect::<Vec<_>>();
+            let store = match &store {
+                Ok(store) => store,
+                Err(e) => return ApiResponse::json(500, &e.to_payload()),
+            };
+            match remember_probes(store, &results) {
+                Ok(probes) => ApiResponse::json(200, &RpcResponse::ok(json!({ "probes": probes })).to_value()),
+                Err(e) => failure(e),
+            }
+        });
+    }
+
+    fn subscriptions(&self) -> Vec<(String, Arc<dyn EventListener>)> {
+        let on_power_changed = EventHandler::new(|event| {
+            Box::pin(async move {
+                if let EventType::Custom { payload, .. } = &event.event_type {
+                    let on_ac = payload["current"]["on_ac_power"].as_bool().unwrap_or(true);
+                    info!("Power source changed: {}", if on_ac { "AC" } else { "battery" });
+                }
+                Ok(())
+            })
+        });
+        vec![("power.changed".to_string(), Arc::new(on_power_changed))]
+    }
+
+    fn on_start(&self) -> Result<(), Box<dyn std::error::Error>> {
+        self.restart_power_monitor();
+        Ok(())
+    }
+
+    fn on_shutdown(&self) {
+        if self.power_monitor_running.swap(false, Ordering::Relaxed) {
+            supervisor::stop(POWER_MONITOR_TASK);
+        }
+    }
+
+    fn on_config_changed(&self, config: &AppConfig) {
+        let interval = config.get_power_poll_interval();
+        let changed = {
+            let mut current = self.power_poll_interval.lock().unwrap();
+            let changed = *current != interval;
+            *current = interval;
+            changed
+        };
+        if changed && self.power_monitor_running.load(Ordering::Relaxed) {
+            self.restart_power_monitor();
+        }
+    }
+
+    fn health(&self) -> PluginHealth {
+        if !self.power_monitor_running.load(Ordering::Relaxed) {
+            return PluginHealth::Healthy;
+        }
+        match supervisor::status(POWER_MONITOR_TASK) {
+            Some(status) if status.healthy => PluginHealth::Healthy,
+            // Power events are missed until the supervisor's backoff runs out.
+            Some(status) if status.state == TaskState::Restarting => PluginHealth::Degraded(format!(
+                "Power monitor restarting after: {}",
+                status.last_error.as_deref().unwrap_or("unknown error")
+            )),
+            _ => PluginHealth::Unhealthy("Power monitor stopped".to_string()),
+        }
+    }
+}
+
+/// App version, platform and the state of the background tasks.
+fn diagnostics_report() -> Value {
+    json!({
+        "generated_at": chrono::Utc::now().to_rfc3339(),
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "family": std::env::consts::FAMILY,
+        "tasks": supervisor::statuses(),
+    })
+}
+
+fn failure(e: AppError) -> ApiResponse {
+    error!("System API request failed: {}", e);
+    ApiResponse::json(500, &e.to_payload())
+}
+
+fn probe(network: &AppResult<NetworkAccess>, target: &str) -> Result<(), String> {
+    let network = network.as_ref().map_err(|e| e.to_string())?;
+    let (host, port) = target.rsplit_once(':').ok_or_else(|| format!("expected host:port, got {}", target))?;
+    let port = port.parse().map_err(|_| format!("invalid port in {}", target))?;
+    network.connect(host, port, PROBE_TIMEOUT).map(drop).map_err(|e| e.to_string())
+}
+
+/// Records when each target answered, forgets targets no longer probed, and
+/// returns the results with the time each target last answered.
+fn remember_probes(store: &PluginStore, results: &[(&String, Result<(), String>)]) -> AppResult<Vec<Value>> {
+    let now = Value::from(chrono::Utc::now().to_rfc3339());
+    let mut probes = Vec::with_capacity(results.len());
+    for (target, outcome) in results {
+        let key = format!("{}{}", LAST_REACHABLE_KEY, target);
+        if outcome.is_ok() {
+            store.set(&key, &now)?;
+        }
+        probes.push(json!({
+            "target": target,
+            "reachable": outcome.is_ok(),
+            "error": outcome.as_ref().err(),
+            "last_reachable": store.get(&key)?,
+        }));
+    }
+    for (key, _) in store.list()? {
+        let probed = key.strip_prefix(LAST_REACHABLE_KEY).is_some_and(|target| results.iter().any(|(t, _)| *t == target));
+        if !probed {
+            store.delete(&key)?;
+        }
+    }
+    Ok(probes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::PluginRegistry;
+    use crate::server::ApiRequest;
+    use crate::testing::memory_db;
+    use std::collections::HashMap;
+
+    fn call(router: &Router, method: &str, path: &str) -> (u16, Value) {
+        let (handler, params) = router.find(method, path).unwrap();
+        let request = ApiRequest {
+            query: HashMap::new(),
+            headers: Vec::new(),
+            body: Vec::new(),
+            params,
+        };
+        let response = handler(&request);
+        (response.status, serde_json::from_slice(&response.body).unwrap())
+    }
+
+    #[test]
+    fn probes_remember_when_targets_last_answered() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (granted, refused) = (format!("127.0.0.1:{}", port), format!("localhost:{}", port));
+        let settings: toml::Table = toml::from_str(&format!("probes = [\"{}\", \"{}\"]", granted, refused)).unwrap();
+
+        let db = Arc::new(memory_db());
+        let store = PluginStore::new("system", Arc::clone(&db));
+        store.set("last_reachable:gone:80", &json!("2020-01-01T00:00:00Z")).unwrap();
+
+        let plugin = SystemPlugin::with_power_poll_interval(Duration::from_secs(30), EventBus::new());
+        let registry = PluginRegistry::new()
+            .with_database(db)
+            .with_grants(HashMap::from([("system".to_string(), vec!["network:127.0.0.1".to_string()])]))
+            .with_settings(HashMap::from([("system".to_string(), settings)]));
+        let mut router = Router::new();
+        plugin.register_routes(&mut router, &registry.create_context(&plugin));
+        // Not granted the filesystem.
+        assert!(router.find("GET", "/api/system/diagnostics").is_none());
+
+        let (status, body) = call(&router, "GET", "/api/system/connectivity");
+        assert_eq!(status, 200);
+        let probes = &body["data"]["probes"];
+        assert_eq!(probes[0]["reachable"], true);
+        assert!(probes[0]["last_reachable"].is_string());
+        assert_eq!(probes[1]["reachable"], false);
+        assert!(probes[1]["last_reachable"].is_null());
+        assert!(store.get(&format!("last_reachable:{}", granted)).unwrap().is_some());
+        assert!(store.get("last_reachable:gone:80").unwrap().is_none());
+    }
 }
@@ -1,25 +1,26 @@
-use crate::core::database::Database;
-use crate::event_bus::{emit_event, emit_users_fetched, Event, EventType};
-use crate::plugins::PluginTrait;
-use serde_json::json;
+use crate::core::database::{Repository, UserRepository};
+use crate::core::{perf, AppResult, Database, ErrorCode, RpcResponse};
+use crate::event_bus::{Event, EventBus, EventType};
+use crate::models::{UserInput, USER_ROLES};
+use crate::plugins::{Capability, PluginContext, PluginTrait, SetupFuture};
+use crate::server::{ApiRequest, ApiResponse, Router};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+use crate::viewmodels::setup_user_viewmodel;
+use crate::viewmodels::user::{query_db_stats, USER_LIST_LIMIT};
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+use webui_rs::webui;
 
-pub struct UserPlugin {
-    db: Option<Arc<Database>>,
-}
+/// Audit log actor of changes made through the REST API.
+const API_ACTOR: &str = "api";
+
+pub struct UserPlugin;
 
 impl UserPlugin {
     pub fn new() -> Self {
-        Self { db: None }
-    }
-
-    pub fn with_database(db: Arc<Database>) -> Self {
-        Self { db: Some(db) }
-    }
-
-    pub fn set_database(&mut self, db: Arc<Database>) {
-        self.db = Some(db);
+        Self
     }
 }
 
@@ -34,58 +35,312 @@ impl PluginTrait for UserPlugin {
         "user"
     }
 
-    fn setup(&self, window: &mut webui::Window) -> Result<(), Box<dyn std::error::Error>> {
-        let db = self.db.clone();
-
-        window.bind("get_users", move |_event| {
-            info!("Frontend: get_users called");
-
-            if let Some(ref database) = db {
-                let conn = database.get_connection().lock().unwrap();
-                let mut stmt = conn.prepare("SELECT id, name, email, role FROM users")?;
-                let users: Vec<serde_json::Value> = stmt
-                    .query_map([], |row| {
-                        Ok(serde_json::json!({
-                            "id": row.get::<_, i32>(0)?,
-                            "name": row.get::<_, String>(1)?,
-                            "email": row.get::<_, String>(2)?,
-                            "role": row.get::<_, String>(3)?,
-                            "status": "Active"
-                        }))
-                    })?
-                    .filter_map(|r| r.ok())
-                    .collect();
-
-                let count = users.len();
-                info!("Fetched {} users from database", count);
-
-                let _ = emit_users_fetched(count, users.clone(), "user_plugin");
+    fn description(&self) -> &str {
+        "User listing and management backed by SQLite"
+    }
+
+    fn capabilities(&self) -> Vec<Capability> {
+        vec![Capability::Database]
+    }
+
+    fn async_setup<'a>(&'a self, ctx: &'a PluginContext) -> SetupFuture<'a> {
+        Box::pin(async move {
+            let db = ctx.database()?;
+            let count: i64 = db
+                .run(|conn| {
+                    let _timer = perf::query("users.count");
+                    UserRepository::new(conn).count()
+                })
+                .await?;
+            info!("UserPlugin found {} users", count);
+            Ok(())
+        })
+    }
+
+    fn setup(&self, window: &mut webui::Window, ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        // The handlers read the database from the app state; without the
+        // grant they are not bound at all.
+        ctx.database()?;
+        setup_user_viewmodel(window);
+
+        info!("UserPlugin initialized");
+        Ok(())
+    }
+
+    fn register_routes(&self, router: &mut Router, ctx: &PluginContext) {
+        let db = match ctx.database() {
+            Ok(db) => db,
+            Err(e) => {
+                warn!("User export routes not mounted: {}", e);
+                return;
+            }
+        };
+
+        register_api_routes(router, Arc::clone(&db), UserEvents::new(ctx));
+
+        router.get("/api/export/users", move |_request| {
+            let _timer = perf::query("users.export");
+            let result = with_conn(&db, |conn| {
+                let users = UserRepository::new(conn).list(None)?;
+                Ok(users
+                    .into_iter()
+                    .map(|user| json!({ "id": user.id, "name": user.name, "email": user.email, "role": user.role }))
+                    .collect::<Vec<_>>())
+            });
+
+            match result {
+                Ok(users) => ApiResponse::json(200, &json!({ "users": users, "count": users.len() }))
+                    .with_header("Content-Disposition", "attachment; filename=\"users.json\""),
+                Err(e) => ApiResponse::json(500, &e.to_payload()),
+            }
+        });
+    }
+}
+
+/// Mounts the REST API: `/api/users` (list, create), `/api/users/:id` (get,
+/// update, delete) and `/api/stats`. Bodies are JSON objects with `name`,
+/// `email` and `role`, sent as `application/json`; responses use the
+/// [`RpcResponse`] envelope. Emails are unique regardless of case.
+fn register_api_routes(router: &mut Router, db: Arc<Database>, events: UserEvents) {
+    let list_db = Arc::clone(&db);
+    router.get("/api/users", move |_request| {
+        let _timer = perf::query("users.list");
+        respond(200, with_conn(&list_db, |conn| UserRepository::new(conn).list(Some(USER_LIST_LIMIT))))
+    });
+
+    let get_db = Arc::clone(&db);
+    router.get("/api/users/:id", move |request| {
+        let Some(id) = user_id(request) else { return invalid_id(request) };
+        let _timer = perf::query("users.get");
+        match with_conn(&get_db, |conn| UserRepository::new(conn).find(id)) {
+            Ok(Some(user)) => respond(200, Ok(user)),
+            Ok(None) => not_found(id),
+            Err(e) => respond::<()>(500, Err(e)),
+        }
+    });
+
+    let create_db = Arc::clone(&db);
+    let create_events = events.clone();
+    router.post("/api/users", move |request| {
+        let fields = match UserFields::parse(request) {
+            Ok(fields) => fields,
+            Err(response) => return response,
+        };
+        let Some(name) = fields.name else { return bad_request("validation.missing_field", &[("field", "name")]) };
+        let Some(email) = fields.email else { return bad_request("validation.missing_field", &[("field", "email")]) };
+        let role = fields.role.unwrap_or_else(|| "user".to_string());
+        let _timer = perf::query("users.insert");
+        let input = UserInput { name, email, role };
+        let result = create_db.transaction(|tx| {
+            let users = UserRepository::new(tx).acting_as(API_ACTOR);
+            if users.email_taken(&input.email)? {
+                return Ok(Err(email_taken(&input.email)));
             }
+            users.insert(&input).map(Ok)
         });
+        match result {
+            Ok(Ok(user)) => {
+                create_events.emit(EventType::UserAdded { id: user.id, name: user.name.clone() });
+                respond(201, Ok(user))
+            }
+            Ok(Err(response)) => response,
+            Err(e) => respond::<()>(500, Err(e)),
+        }
+    });
 
-        window.bind("add_user", |event| {
-            info!("Frontend: add_user called");
-            if let Some(data) = event.payload.as_str() {
-                if let Ok(parsed) = serde_json::from_str::<serde_json::Value>(data) {
-                    let name = parsed
-                        .get("name")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Unknown");
-                    let email = parsed
-                        .get("email")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("unknown@example.com");
-                    let role = parsed
-                        .get("role")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("user");
-
-                    info!("Adding user: {} ({}) role: {}", name, email, role);
-                }
+    let update_db = Arc::clone(&db);
+    let update_events = events.clone();
+    router.put("/api/users/:id", move |request| {
+        let Some(id) = user_id(request) else { return invalid_id(request) };
+        let fields = match UserFields::parse(request) {
+            Ok(fields) => fields,
+            Err(response) => return response,
+        };
+        let _timer = perf::query("users.update");
+        let result = update_db.transaction(|tx| {
+            let users = UserRepository::new(tx).acting_as(API_ACTOR);
+            let Some(user) = users.find(id)? else { return Ok(Err(not_found(id))) };
+            let email_changed = fields.email.as_deref().is_some_and(|email| !email.eq_ignore_ascii_case(&user.email));
+            if let Some(email) = fields.email.as_deref().filter(|_| email_changed)
+                && users.email_taken(email)?
+            {
+                return Ok(Err(email_taken(email)));
             }
+            let input = UserInput {
+                name: fields.name.unwrap_or(user.name),
+                email: fields.email.unwrap_or(user.email),
+                role: fields.role.unwrap_or(user.role),
+            };
+            users.update(id, &input).map(|user| user.ok_or_else(|| not_found(id)))
         });
+        match result {
+            Ok(Ok(user)) => {
+                update_events.emit(EventType::UserUpdated { id, name: user.name.clone() });
+                respond(200, Ok(user))
+            }
+            Ok(Err(response)) => response,
+            Err(e) => respond::<()>(500, Err(e)),
+        }
+    });
 
-        info!("UserPlugin initialized");
-        Ok(())
+    let delete_db = Arc::clone(&db);
+    router.delete("/api/users/:id", move |request| {
+        let Some(id) = user_id(request) else { return invalid_id(request) };
+        let _timer = perf::query("users.delete");
+        match with_conn(&delete_db, |conn| UserRepository::new(conn).acting_as(API_ACTOR).delete(id)) {
+            Ok(false) => not_found(id),
+            Ok(true) => {
+                events.emit(EventType::UserDeleted { id });
+                respond(200, Ok(json!({ "id": id })))
+            }
+            Err(e) => respond::<()>(500, Err(e)),
+        }
+    });
+
+    router.get("/api/stats", move |_request| {
+        let _timer = perf::query("users.stats");
+        respond(200, with_conn(&db, |conn| Ok(query_db_stats(conn, db.pool_stats())?)))
+    });
+}
+
+/// Name, email and role from a request body; absent fields are `None`.
+struct UserFields {
+    name: Option<String>,
+    email: Option<String>,
+    role: Option<String>,
+}
+
+impl UserFields {
+    /// Refuses bodies not sent as JSON: browsers let any site send a form or
+    /// `text/plain` body cross-origin without asking first.
+    fn parse(request: &ApiRequest) -> Result<Self, ApiResponse> {
+        let media_type = request.header("Content-Type").and_then(|value| value.split(';').next()).unwrap_or_default();
+        if !media_type.trim().eq_ignore_ascii_case("application/json") {
+            let response = RpcResponse::localized(ErrorCode::InvalidArgument, "validation.expected_json_content_type", &[]);
+            return Err(ApiResponse::json(415, &response.to_value()));
+        }
+        let Ok(Value::Object(body)) = request.json() else {
+            return Err(bad_request("validation.expected_json", &[]));
+        };
+        let field = |key: &str| match body.get(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(text)) if !text.trim().is_empty() => Ok(Some(text.trim().to_string())),
+            Some(_) => Err(bad_request("validation.missing_field", &[("field", key)])),
+        };
+        let fields = Self { name: field("name")?, email: field("email")?, role: field("role")? };
+        if fields.email.as_deref().is_some_and(|email| !email.contains('@')) {
+            return Err(bad_request("validation.invalid_email", &[]));
+        }
+        if let Some(role) = fields.role.as_deref().filter(|role| !USER_ROLES.contains(role)) {
+            return Err(bad_request("validation.unknown_role", &[("role", role), ("roles", &USER_ROLES.join(", "))]));
+        }
+        Ok(fields)
+    }
+}
+
+/// Announces changes made through the API on the app's event bus, so open
+/// windows and the `get_db_stats` cache see them.
+#[derive(Clone)]
+struct UserEvents {
+    target: Option<(EventBus, tokio::runtime::Handle)>,
+}
+
+impl UserEvents {
+    fn new(ctx: &PluginContext) -> Self {
+        let bus = ctx.app_state().ok().map(|state| state.bus().clone());
+        let runtime = tokio::runtime::Handle::try_current().ok();
+        Self { target: bus.zip(runtime) }
+    }
+
+    fn emit(&self, event_type: EventType) {
+        let Some((bus, runtime)) = self.target.clone() else { return };
+        runtime.spawn(async move {
+            if let Err(e) = bus.emit(Event::new(event_type, "user_api")).await {
+                error!("Failed to emit user event: {}", e);
+            }
+        });
+    }
+}
+
+fn with_conn<T>(db: &Database, query: impl FnOnce(&Connection) -> AppResult<T>) -> AppResult<T> {
+    let conn = db.get_connection()?;
+    query(&conn)
+}
+
+fn user_id(request: &ApiRequest) -> Option<i32> {
+    request.param("id")?.parse().ok()
+}
+
+fn respond<T: Serialize>(status: u16, result: AppResult<T>) -> ApiResponse {
+    match result {
+        Ok(data) => ApiResponse::json(status, &RpcResponse::ok(data).to_value()),
+        Err(e) => {
+            error!("User API request failed: {}", e);
+            ApiResponse::json(500, &e.to_payload())
+        }
+    }
+}
+
+fn bad_request(key: &str, args: &[(&str, &str)]) -> ApiResponse {
+    ApiResponse::json(400, &RpcResponse::localized(ErrorCode::InvalidArgument, key, args).to_value())
+}
+
+fn invalid_id(request: &ApiRequest) -> ApiResponse {
+    bad_request("validation.invalid_user_id", &[("id", request.param("id").unwrap_or_default())])
+}
+
+fn not_found(id: i32) -> ApiResponse {
+    let id = id.to_string();
+    ApiResponse::json(404, &RpcResponse::localized(ErrorCode::NotFound, "validation.unknown_user", &[("id", &id)]).to_value())
+}
+
+fn email_taken(email: &str) -> ApiResponse {
+    ApiResponse::json(409, &RpcResponse::localized(ErrorCode::Conflict, "validation.email_taken", &[("email", email)]).to_value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::memory_db;
+    use std::collections::HashMap;
+
+    fn call(router: &Router, method: &str, path: &str, content_type: &str, body: Value) -> (u16, Value) {
+        let (handler, params) = router.find(method, path).unwrap();
+        let request = ApiRequest {
+            query: HashMap::new(),
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.to_string().into_bytes(),
+            params,
+        };
+        let response = handler(&request);
+        (response.status, serde_json::from_slice(&response.body).unwrap())
+    }
+
+    #[test]
+    fn writes_need_json_a_known_role_and_a_free_email() {
+        let mut router = Router::new();
+        register_api_routes(&mut router, Arc::new(memory_db()), UserEvents { target: None });
+        let ada = json!({ "name": "Ada", "email": "ada@example.com", "role": "admin" });
+
+        assert_eq!(call(&router, "POST", "/api/users", "text/plain", ada.clone()).0, 415);
+        let (status, body) = call(&router, "POST", "/api/users", "application/json", json!({ "name": "Ada", "email": "a@b.c", "role": "root" }));
+        assert_eq!((status, body["error"]["code"].as_str()), (400, Some("invalid_argument")));
+
+        let (status, created) = call(&router, "POST", "/api/users", "application/json; charset=utf-8", ada);
+        assert_eq!(status, 201);
+        let (status, body) = call(&router, "POST", "/api/users", "application/json", json!({ "name": "Ada", "email": "ADA@example.com" }));
+        assert_eq!((status, body["error"]["code"].as_str()), (409, Some("conflict")));
+
+        let grace = json!({ "name": "Grace", "email": "grace@example.com" });
+        let (_, grace) = call(&router, "POST", "/api/users", "application/json", grace);
+        let path = format!("/api/users/{}", grace["data"]["id"]);
+        let (status, _) = call(&router, "PUT", &path, "application/json", json!({ "email": "ada@example.com" }));
+        assert_eq!(status, 409);
+        let path = format!("/api/users/{}", created["data"]["id"]);
+        let (status, _) = call(&router, "PUT", &path, "application/json", json!({ "email": "Ada@example.com" }));
+        assert_eq!(status, 200);
+
+        let (status, body) = call(&router, "DELETE", "/api/users/999", "application/json", Value::Null);
+        assert_eq!((status, body["error"]["code"].as_str()), (404, Some("not_found")));
     }
 }
@@ -1,7 +1,9 @@
-use crate::plugins::PluginTrait;
-use once_cell::sync::Lazy;
+use crate::core::perf;
+use crate::plugins::{PluginContext, PluginTrait};
+use crate::viewmodels::setup_window_viewmodel;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tracing::info;
+use webui_rs::webui;
 
 static WINDOW_ID: AtomicUsize = AtomicUsize::new(1);
 
@@ -28,33 +30,29 @@ impl PluginTrait for WindowPlugin {
         "window"
     }
 
-    fn setup(&self, window: &mut webui::Window) -> Result<(), Box<dyn std::error::Error>> {
-        let window_id = window.id();
-
-        window.bind("test_handler", move |_event| {
-            info!(
-                "[TEST] test_handler called from frontend! Window ID: {}",
-                window_id
-            );
-        });
+    fn description(&self) -> &str {
+        "Window lifecycle and frontend event bridging"
+    }
 
-        window.bind("handleFrontendEvent", |event| {
-            info!("[WEBUI] handleFrontendEvent called");
-        });
+    fn setup(&self, window: &mut webui::Window, _ctx: &PluginContext) -> Result<(), Box<dyn std::error::Error>> {
+        setup_window_viewmodel(window);
 
         window.bind("minimize_window", |event| {
-            info!("[WEBUI] minimize_window called");
+            let _timer = perf::handler("minimize_window");
+            info!("[WEBUI] minimize_window called (window {})", event.window);
         });
 
         window.bind("maximize_window", |event| {
-            info!("[WEBUI] maximize_window called");
+            let _timer = perf::handler("maximize_window");
+            info!("[WEBUI] maximize_window called (window {})", event.window);
         });
 
         window.bind("close_window", |event| {
-            info!("[WEBUI] close_window called");
+            let _timer = perf::handler("close_window");
+            info!("[WEBUI] close_window called (window {})", event.window);
         });
 
-        info!("WindowPlugin initialized for window {}", window_id);
+        info!("WindowPlugin initialized for window {}", window.id);
         Ok(())
     }
 }
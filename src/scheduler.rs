@@ -0,0 +1,493 @@
+use crate::core::supervisor::{RestartPolicy, Task};
+use crate::core::database::prepare_cached;
+use crate::core::{perf, Database};
+use crate::event_bus::EventBus;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+
+pub type JobHandler = Arc<dyn Fn() -> Result<String> + Send + Sync>;
+
+/// A run is treated as missed, rather than merely due, once it is this late.
+/// The loop ticks every second, so only a stalled process, a suspended
+/// machine or a clock change gets there.
+const MISSED_AFTER: chrono::TimeDelta = chrono::TimeDelta::seconds(60);
+
+/// Most missed runs [`CatchUp::All`] replays at once.
+const MAX_CATCH_UP_RUNS: usize = 10;
+
+lazy_static! {
+    pub static ref GLOBAL_SCHEDULER: Scheduler = Scheduler::new();
+}
+
+/// What to do with runs missed while the machine slept or the clock jumped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    /// Drop missed runs and wait for the next scheduled time.
+    Skip,
+    /// Run once for all missed runs.
+    #[default]
+    Once,
+    /// Run once per missed run, up to 10.
+    All,
+}
+
+impl FromStr for CatchUp {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "skip" => Ok(CatchUp::Skip),
+            "once" => Ok(CatchUp::Once),
+            "all" => Ok(CatchUp::All),
+            other => Err(anyhow!("expected \"skip\", \"once\" or \"all\", got \"{}\"", other)),
+        }
+    }
+}
+
+/// Why a job ran, reported as `trigger` in `scheduler.job_completed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Trigger {
+    Schedule,
+    CatchUp,
+    Manual,
+}
+
+impl Trigger {
+    fn as_str(self) -> &'static str {
+        match self {
+            Trigger::Schedule => "schedule",
+            Trigger::CatchUp => "catch_up",
+            Trigger::Manual => "manual",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub name: String,
+    pub schedule: String,
+    pub event: Option<String>,
+    pub enabled: bool,
+    pub catch_up: CatchUp,
+    /// Whether a registered handler runs the job, rather than an event.
+    pub has_handler: bool,
+    pub next_run: Option<i64>,
+    pub last_run: Option<i64>,
+}
+
+/// A job with its next few run times, as listed by `list_schedules`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleInfo {
+    #[serde(flatten)]
+    pub job: JobInfo,
+    /// Unix milliseconds.
+    pub upcoming: Vec<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRun {
+    pub id: i64,
+    pub job_name: String,
+    pub started_at: i64,
+    pub duration_ms: i64,
+    pub success: bool,
+    pub message: String,
+}
+
+struct ScheduledJob {
+    expression: String,
+    schedule: Schedule,
+    event: Option<String>,
+    enabled: bool,
+    catch_up: CatchUp,
+    next_run: Option<DateTime<Utc>>,
+    last_run: Option<DateTime<Utc>>,
+}
+
+#[derive(Clone)]
+pub struct Scheduler {
+    jobs: Arc<Mutex<HashMap<String, ScheduledJob>>>,
+    handlers: Arc<Mutex<HashMap<String, JobHandler>>>,
+    db: Arc<Mutex<Option<Arc<Database>>>>,
+    /// Where job events and `scheduler.job_completed` go.
+    bus: Arc<Mutex<Option<EventBus>>>,
+    default_catch_up: Arc<Mutex<CatchUp>>,
+}
+
+/// Parses a cron expression. Standard five-field expressions
+/// (`min hour dom month dow`) are accepted and run at second zero.
+pub fn parse_schedule(expression: &str) -> Result<Schedule> {
+    let expression = expression.trim();
+    let normalized = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    Schedule::from_str(&normalized)
+        .map_err(|e| anyhow!("Invalid cron expression '{}': {}", expression, e))
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+            handlers: Arc::new(Mutex::new(HashMap::new())),
+            db: Arc::new(Mutex::new(None)),
+            bus: Arc::new(Mutex::new(None)),
+            default_catch_up: Arc::new(Mutex::new(CatchUp::default())),
+        }
+    }
+
+    pub fn set_database(&self, db: Arc<Database>) {
+        *self.db.lock().unwrap() = Some(db);
+    }
+
+    pub fn set_event_bus(&self, bus: EventBus) {
+        *self.bus.lock().unwrap() = Some(bus);
+    }
+
+    /// Catch-up policy of jobs added from now on without one of their own.
+    pub fn set_default_catch_up(&self, policy: CatchUp) {
+        *self.default_catch_up.lock().unwrap() = policy;
+    }
+
+    /// Returns false if there is no job named `name`.
+    pub fn set_catch_up(&self, name: &str, policy: CatchUp) -> bool {
+        match self.jobs.lock().unwrap().get_mut(name) {
+            Some(job) => {
+                job.catch_up = policy;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds or replaces a job. When no handler is registered under the same
+    /// name the job emits `event` (or `scheduler.<name>`) on the event bus.
+    pub fn add_job(&self, name: &str, expression: &str, event: Option<&str>, enabled: bool) -> Result<()> {
+        let schedule = parse_schedule(expression)?;
+        let next_run = schedule.upcoming(Utc).next();
+        let job = ScheduledJob {
+            expression: expression.to_string(),
+            schedule,
+            event: event.map(|e| e.to_string()),
+            enabled,
+            catch_up: *self.default_catch_up.lock().unwrap(),
+            next_run,
+            last_run: None,
+        };
+        self.jobs.lock().unwrap().insert(name.to_string(), job);
+        debug!("Scheduled job '{}' ({})", name, expression);
+        Ok(())
+    }
+
+    pub fn register_handler<F>(&self, name: &str, handler: F)
+    where
+        F: Fn() -> Result<String> + Send + Sync + 'static,
+    {
+        self.handlers.lock().unwrap().insert(name.to_string(), Arc::new(handler));
+    }
+
+    /// Loads job definitions stored in the `scheduled_jobs` table.
+    pub fn load_from_db(&self) -> Result<usize> {
+        let Some(db) = self.db.lock().unwrap().clone() else {
+            return Ok(0);
+        };
+        let rows: Vec<StoredJob> = {
+            let _timer = perf::query("scheduler.load_jobs");
+            let conn = db.get_connection()?;
+            let mut stmt = prepare_cached(&conn, "SELECT name, schedule, event, enabled, catch_up FROM scheduled_jobs")?;
+            stmt.query_map([], |row| {
+                Ok(StoredJob {
+                    name: row.get(0)?,
+                    schedule: row.get(1)?,
+                    event: row.get(2)?,
+                    enabled: row.get(3)?,
+                    catch_up: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+        };
+
+        let mut loaded = 0;
+        for job in rows {
+            if let Err(e) = self.add_job(&job.name, &job.schedule, job.event.as_deref(), job.enabled) {
+                warn!("Skipping scheduled job '{}': {}", job.name, e);
+                continue;
+            }
+            match job.catch_up.as_deref().map(CatchUp::from_str) {
+                Some(Ok(policy)) => {
+                    self.set_catch_up(&job.name, policy);
+                }
+                Some(Err(e)) => warn!("Ignoring catch_up of scheduled job '{}': {}", job.name, e),
+                None => {}
+            }
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+
+    pub fn list_jobs(&self) -> Vec<JobInfo> {
+        let handlers = self.handlers.lock().unwrap();
+        let jobs = self.jobs.lock().unwrap();
+        let mut list: Vec<JobInfo> = jobs
+            .iter()
+            .map(|(name, job)| JobInfo {
+                name: name.clone(),
+                schedule: job.expression.clone(),
+                event: job.event.clone(),
+                enabled: job.enabled,
+                catch_up: job.catch_up,
+                has_handler: handlers.contains_key(name),
+                next_run: job.next_run.map(|t| t.timestamp_millis()),
+                last_run: job.last_run.map(|t| t.timestamp_millis()),
+            })
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+
+    /// Every job with its next `count` run times.
+    pub fn list_schedules(&self, count: usize) -> Vec<ScheduleInfo> {
+        let list = self.list_jobs();
+        let jobs = self.jobs.lock().unwrap();
+        list.into_iter()
+            .map(|job| {
+                let upcoming = jobs
+                    .get(&job.name)
+                    .map(|scheduled| scheduled.schedule.upcoming(Utc).take(count).map(|t| t.timestamp_millis()).collect())
+                    .unwrap_or_default();
+                ScheduleInfo { job, upcoming }
+            })
+            .collect()
+    }
+
+    pub fn has_job(&self, name: &str) -> bool {
+        self.jobs.lock().unwrap().contains_key(name)
+    }
+
+    /// Runs `name` now, whether or not it is enabled, without moving its
+    /// next scheduled run.
+    pub async fn run_now(&self, name: &str) -> Result<()> {
+        match self.jobs.lock().unwrap().get_mut(name) {
+            Some(job) => job.last_run = Some(Utc::now()),
+            None => return Err(anyhow!("Unknown job '{}'", name)),
+        }
+        self.run_job(name, Trigger::Manual).await;
+        Ok(())
+    }
+
+    pub fn next_run(&self, name: &str) -> Option<DateTime<Utc>> {
+        self.jobs.lock().unwrap().get(name).and_then(|job| job.next_run)
+    }
+
+    pub fn get_history(&self, job_name: Option<&str>, limit: usize) -> Result<Vec<JobRun>> {
+        let Some(db) = self.db.lock().unwrap().clone() else {
+            return Ok(Vec::new());
+        };
+        let _timer = perf::query("scheduler.history");
+        let conn = db.get_connection()?;
+        let mut stmt = prepare_cached(
+            &conn,
+            "SELECT id, job_name, started_at, duration_ms, success, message FROM job_runs
+             WHERE (?1 IS NULL OR job_name = ?1) ORDER BY id DESC LIMIT ?2",
+        )?;
+        let runs = stmt
+            .query_map(rusqlite::params![job_name, limit as i64], |row| {
+                Ok(JobRun {
+                    id: row.get(0)?,
+                    job_name: row.get(1)?,
+                    started_at: row.get(2)?,
+                    duration_ms: row.get(3)?,
+                    success: row.get(4)?,
+                    message: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(runs)
+    }
+
+    /// Spawns the scheduler loop on the current Tokio runtime as the
+    /// supervised `scheduler` task.
+    pub fn start(&self) {
+        let scheduler = self.clone();
+        let mut task = Task::new("scheduler").with_policy(RestartPolicy::Always);
+        if let Some(bus) = self.bus.lock().unwrap().as_ref() {
+            task = task.with_events(bus);
+        }
+        task.spawn(move |_| {
+            let scheduler = scheduler.clone();
+            async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(1));
+                let mut last_tick = (Instant::now(), Utc::now());
+                loop {
+                    interval.tick().await;
+                    let now = Utc::now();
+                    // The monotonic clock stops while the machine sleeps; the
+                    // wall clock does not.
+                    let slept = (now - last_tick.1).to_std().unwrap_or_default().saturating_sub(last_tick.0.elapsed());
+                    if slept > MISSED_AFTER.to_std().unwrap_or_default() {
+                        info!("Scheduler resumed after {}s (sleep or clock change)", slept.as_secs());
+                    }
+                    last_tick = (Instant::now(), now);
+                    for (name, trigger) in scheduler.take_due_jobs(now) {
+                        scheduler.run_job(&name, trigger).await;
+                    }
+                }
+            }
+        });
+        info!("Scheduler started with {} job(s)", self.jobs.lock().unwrap().len());
+    }
+
+    /// Jobs to run at `now`, applying each job's [`CatchUp`] policy to runs
+    /// that are more than a minute late.
+    fn take_due_jobs(&self, now: DateTime<Utc>) -> Vec<(String, Trigger)> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let mut due = Vec::new();
+        for (name, job) in jobs.iter_mut() {
+            if !job.enabled {
+                continue;
+            }
+            let Some(next) = job.next_run.filter(|next| *next <= now) else {
+                continue;
+            };
+            job.next_run = job.schedule.after(&now).next();
+            if now - next <= MISSED_AFTER {
+                job.last_run = Some(now);
+                due.push((name.clone(), Trigger::Schedule));
+                continue;
+            }
+
+            let missed = 1 + job.schedule.after(&next).take_while(|t| *t <= now).take(MAX_CATCH_UP_RUNS).count();
+            let runs = match job.catch_up {
+                CatchUp::Skip => 0,
+                CatchUp::Once => 1,
+                CatchUp::All => missed.min(MAX_CATCH_UP_RUNS),
+            };
+            info!("Job '{}' missed {} run(s); catching up with {} ({:?})", name, missed, runs, job.catch_up);
+            if runs > 0 {
+                job.last_run = Some(now);
+            }
+            due.extend(std::iter::repeat_n((name.clone(), Trigger::CatchUp), runs));
+        }
+        due
+    }
+
+    async fn run_job(&self, name: &str, trigger: Trigger) {
+        let started_at = Utc::now();
+        let handler = self.handlers.lock().unwrap().get(name).cloned();
+        let event = self.jobs.lock().unwrap().get(name).and_then(|job| job.event.clone());
+        let bus = self.bus.lock().unwrap().clone();
+
+        // Handlers may touch files and the database, so they run on the
+        // blocking pool rather than the scheduler loop.
+        let result = match handler {
+            Some(handler) => tokio::task::spawn_blocking(move || handler())
+                .await
+                .unwrap_or_else(|e| Err(anyhow!("Job handler panicked: {}", e))),
+            None => {
+                let event_name = event.unwrap_or_else(|| format!("scheduler.{}", name));
+                match &bus {
+                    Some(bus) => bus
+                        .emit_custom(&event_name, json!({ "job": name }), "scheduler")
+                        .await
+                        .map(|_| format!("Emitted {}", event_name)),
+                    None => Err(anyhow!("No event bus to emit {} on", event_name)),
+                }
+            }
+        };
+
+        let duration_ms = (Utc::now() - started_at).num_milliseconds();
+        let (success, message) = match result {
+            Ok(message) => (true, message),
+            Err(e) => {
+                error!("Scheduled job '{}' failed: {}", name, e);
+                (false, e.to_string())
+            }
+        };
+
+        if let Err(e) = self.record_run(name, started_at, duration_ms, success, &message).await {
+            warn!("Failed to record run of job '{}': {}", name, e);
+        }
+
+        let payload = json!({
+            "job": name,
+            "trigger": trigger.as_str(),
+            "success": success,
+            "message": message,
+            "duration_ms": duration_ms,
+            "next_run": self.next_run(name).map(|t| t.timestamp_millis()),
+        });
+        if let Some(bus) = bus
+            && let Err(e) = bus.emit_custom("scheduler.job_completed", payload, "scheduler").await
+        {
+            error!("Failed to emit job completed event: {}", e);
+        }
+    }
+
+    async fn record_run(&self, name: &str, started_at: DateTime<Utc>, duration_ms: i64, success: bool, message: &str) -> Result<()> {
+        let Some(db) = self.db.lock().unwrap().clone() else {
+            return Ok(());
+        };
+        db.execute_async(
+            "scheduler.record_run",
+            "INSERT INTO job_runs (job_name, started_at, duration_ms, success, message) VALUES (?1, ?2, ?3, ?4, ?5)",
+            vec![
+                name.to_string().into(),
+                started_at.timestamp_millis().into(),
+                duration_ms.into(),
+                success.into(),
+                message.to_string().into(),
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// A row of the `scheduled_jobs` table.
+struct StoredJob {
+    name: String,
+    schedule: String,
+    event: Option<String>,
+    enabled: bool,
+    catch_up: Option<String>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn missed_runs(policy: CatchUp) -> Vec<(String, Trigger)> {
+        let scheduler = Scheduler::new();
+        scheduler.set_default_catch_up(policy);
+        scheduler.add_job("tick", "* * * * *", None, true).unwrap();
+        let now = Utc::now();
+        // As if the machine slept through the last five minutes.
+        scheduler.jobs.lock().unwrap().get_mut("tick").unwrap().next_run = Some(now - chrono::TimeDelta::minutes(5));
+        scheduler.take_due_jobs(now)
+    }
+
+    #[test]
+    fn missed_runs_follow_the_catch_up_policy() {
+        assert!(missed_runs(CatchUp::Skip).is_empty());
+        assert_eq!(missed_runs(CatchUp::Once), vec![("tick".to_string(), Trigger::CatchUp)]);
+        assert!(matches!(missed_runs(CatchUp::All).len(), 5 | 6));
+    }
+}
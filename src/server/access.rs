@@ -0,0 +1,92 @@
+//! The run's session token. `/runtime-config.json` hands it to the page,
+//! which sends it back with every request to `/api/*`, `/events` and
+//! `/ws/events`: in the [`TOKEN_HEADER`] header, or as the [`TOKEN_PARAM`]
+//! query parameter where the browser can't set headers (`EventSource` and
+//! `WebSocket`). Pages on other sites can't read the config, so they can't
+//! call the API or listen to events.
+//!
+//! Browsers let any site send requests to localhost, so the guarded paths
+//! also refuse pages that are neither served from localhost nor listed in
+//! `server.cors.allowed_origins`.
+
+use super::{sse, websocket};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub const TOKEN_HEADER: &str = "X-Session-Token";
+pub const TOKEN_PARAM: &str = "token";
+
+/// Checks requests to the guarded paths for the session token and the
+/// page's origin.
+#[derive(Clone)]
+pub struct AccessGate {
+    token: Arc<str>,
+    /// Accepted in addition to localhost; `*` accepts any page.
+    allowed_origins: Arc<Vec<String>>,
+}
+
+impl AccessGate {
+    pub fn new(token: &str, allowed_origins: Vec<String>) -> Self {
+        Self { token: Arc::from(token), allowed_origins: Arc::new(allowed_origins) }
+    }
+
+    /// Whether requests to `path` need the token. The frontend, the runtime
+    /// config and the health checks stay open.
+    pub fn guards(path: &str) -> bool {
+        path.starts_with("/api/") || path == sse::EVENTS_PATH || path == websocket::EVENTS_PATH
+    }
+
+    /// Whether the token arrived in `header` or, failing that, in `query`.
+    pub fn admits(&self, header: Option<&str>, query: &HashMap<String, String>) -> bool {
+        header
+            .or_else(|| query.get(TOKEN_PARAM).map(String::as_str))
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), self.token.as_bytes()))
+    }
+
+    /// Pages on localhost, such as the app's own frontend, and the configured origins.
+    pub fn allows_origin(&self, origin: &str) -> bool {
+        let host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+        let host = host.rsplit_once(':').map_or(host, |(host, _)| host);
+        matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+            || self.allowed_origins.iter().any(|allowed| allowed == "*" || allowed == origin)
+    }
+}
+
+/// Compares without stopping at the first difference, so response times
+/// don't tell how much of a guess was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_run_token_is_admitted() {
+        let gate = AccessGate::new("secret", Vec::new());
+        let query = |token: &str| HashMap::from([(TOKEN_PARAM.to_string(), token.to_string())]);
+        assert!(gate.admits(Some("secret"), &HashMap::new()));
+        assert!(gate.admits(None, &query("secret")));
+        assert!(!gate.admits(Some("wrong"), &query("secret")));
+        assert!(!gate.admits(Some("secre"), &HashMap::new()));
+        assert!(!gate.admits(None, &HashMap::new()));
+
+        assert!(AccessGate::guards("/api/users"));
+        assert!(AccessGate::guards("/api/upload"));
+        assert!(AccessGate::guards("/events"));
+        assert!(AccessGate::guards("/ws/events"));
+        assert!(!AccessGate::guards("/runtime-config.json"));
+        assert!(!AccessGate::guards("/healthz"));
+        assert!(!AccessGate::guards("/index.html"));
+    }
+
+    #[test]
+    fn only_local_and_listed_pages_are_allowed() {
+        let gate = AccessGate::new("secret", vec!["https://example.com".to_string()]);
+        assert!(gate.allows_origin("http://localhost:8080"));
+        assert!(gate.allows_origin("http://127.0.0.1:3000"));
+        assert!(gate.allows_origin("https://example.com"));
+        assert!(!gate.allows_origin("https://evil.example"));
+    }
+}
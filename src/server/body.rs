@@ -0,0 +1,119 @@
+//! Request body limits: `server.max_body_size` and `server.request_timeout`.
+//! A body that needs reading from the network is read on its own thread, at
+//! most [`MAX_BODY_READERS`] at a time, while the server waits at most the
+//! timeout for it. The [relay](super::relay) ends the body once the client
+//! is silent for the timeout, so the thread doesn't outlive the client.
+
+use super::error_page::HttpError;
+use super::HttpResponse;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Bodies up to this size were read by tiny_http with the headers.
+const BUFFERED_BODY: usize = 1024;
+
+/// Bodies read on their own thread at once; requests past it get a 503.
+pub const MAX_BODY_READERS: usize = 32;
+
+static BODY_READERS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimits {
+    pub max_bytes: u64,
+    pub timeout: Duration,
+}
+
+pub enum Received {
+    Body(tiny_http::Request, Vec<u8>),
+    /// The body is too large or could not be read.
+    Rejected(tiny_http::Request, HttpError),
+    /// The body didn't arrive in time. The reading thread answers the
+    /// request with a 408 once the relay cuts the body off, at most another
+    /// timeout later.
+    TimedOut,
+}
+
+/// Reads the body of `request` within `limits`. `timed_out` builds the
+/// answer for a body that is too slow.
+pub fn read(mut request: tiny_http::Request, limits: BodyLimits, timed_out: impl FnOnce() -> HttpResponse) -> Received {
+    if request.body_length().is_some_and(|length| length as u64 > limits.max_bytes) {
+        return Received::Rejected(request, too_large(limits.max_bytes));
+    }
+    let has_body = request.headers().iter().any(|h| h.field.equiv("Transfer-Encoding"))
+        || request.body_length().is_some_and(|length| length > 0);
+    if !has_body || request.body_length().is_some_and(|length| length <= BUFFERED_BODY) {
+        return match read_limited(&mut request, limits) {
+            Ok(body) => Received::Body(request, body),
+            Err(error) => Received::Rejected(request, error),
+        };
+    }
+
+    let Some(reader) = ReaderSlot::take() else {
+        warn!("{} body readers are busy; turning {} away", MAX_BODY_READERS, request.url());
+        return Received::Rejected(request, HttpError::new(503, "The server is busy reading other requests."));
+    };
+    let late_answer = timed_out();
+    let (sender, receiver) = mpsc::sync_channel(1);
+    thread::spawn(move || {
+        let _reader = reader;
+        let body = read_limited(&mut request, limits);
+        if let Err(mpsc::SendError((request, _))) = sender.send((request, body)) {
+            debug!("Answering {} late with 408", request.url());
+            let _ = request.respond(late_answer);
+        }
+    });
+    match receiver.recv_timeout(limits.timeout) {
+        Ok((request, Ok(body))) => Received::Body(request, body),
+        Ok((request, Err(error))) => Received::Rejected(request, error),
+        Err(_) => Received::TimedOut,
+    }
+}
+
+/// A counted body reader thread, given back when dropped.
+struct ReaderSlot;
+
+impl ReaderSlot {
+    fn take() -> Option<Self> {
+        BODY_READERS
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |busy| (busy < MAX_BODY_READERS).then_some(busy + 1))
+            .ok()
+            .map(|_| ReaderSlot)
+    }
+}
+
+impl Drop for ReaderSlot {
+    fn drop(&mut self) {
+        BODY_READERS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A body that ends before its `Content-Length` was cut off by the relay,
+/// so it is answered as timed out. A cut-off chunked body fails as
+/// malformed.
+fn read_limited(request: &mut tiny_http::Request, limits: BodyLimits) -> Result<Vec<u8>, HttpError> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(limits.max_bytes + 1)
+        .read_to_end(&mut body)
+        .map_err(|_| HttpError::new(400, "The request body could not be read."))?;
+    if body.len() as u64 > limits.max_bytes {
+        return Err(too_large(limits.max_bytes));
+    }
+    if request.body_length().is_some_and(|length| body.len() < length) {
+        return Err(timeout_error(limits.timeout));
+    }
+    Ok(body)
+}
+
+fn too_large(max_bytes: u64) -> HttpError {
+    HttpError::new(413, format!("Request bodies are limited to {} bytes.", max_bytes))
+}
+
+pub fn timeout_error(timeout: Duration) -> HttpError {
+    HttpError::new(408, format!("The request body did not arrive within {} seconds.", timeout.as_secs()))
+}
@@ -0,0 +1,93 @@
+use super::HttpResponse;
+use crate::core::config::CorsSettings;
+use tiny_http::Header;
+
+/// CORS headers for the origins in `[server.cors]`. Requests from other
+/// origins, and requests without an `Origin` header, are served unchanged.
+pub struct Cors {
+    origins: Vec<String>,
+    methods: String,
+    headers: String,
+    allow_credentials: bool,
+    max_age_secs: u64,
+}
+
+impl Cors {
+    pub fn new(settings: &CorsSettings) -> Self {
+        Self {
+            origins: settings.allowed_origins.clone(),
+            methods: settings.allowed_methods.join(", "),
+            headers: settings.allowed_headers.join(", "),
+            allow_credentials: settings.allow_credentials,
+            max_age_secs: settings.max_age_secs,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.origins.is_empty()
+    }
+
+    /// The `Access-Control-Allow-Origin` value for `origin`, if it is allowed.
+    pub fn allow_origin<'a>(&'a self, origin: &'a str) -> Option<&'a str> {
+        if self.origins.iter().any(|allowed| allowed == origin) {
+            Some(origin)
+        } else if self.origins.iter().any(|allowed| allowed == "*") {
+            Some("*")
+        } else {
+            None
+        }
+    }
+
+    /// Answers a preflight `OPTIONS` request from an allowed origin.
+    pub fn preflight(&self, allow_origin: &str) -> HttpResponse {
+        let mut response = self.apply(tiny_http::Response::from_data(Vec::new()).with_status_code(204), allow_origin);
+        for (name, value) in [
+            ("Access-Control-Allow-Methods", self.methods.as_str()),
+            ("Access-Control-Allow-Headers", self.headers.as_str()),
+            ("Access-Control-Max-Age", &self.max_age_secs.to_string()),
+        ] {
+            if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                response = response.with_header(header);
+            }
+        }
+        response
+    }
+
+    /// Adds the headers that let the page at `allow_origin` read `response`.
+    pub fn apply(&self, mut response: HttpResponse, allow_origin: &str) -> HttpResponse {
+        let mut headers = vec![("Access-Control-Allow-Origin", allow_origin)];
+        if allow_origin != "*" {
+            headers.push(("Vary", "Origin"));
+        }
+        if self.allow_credentials {
+            headers.push(("Access-Control-Allow-Credentials", "true"));
+        }
+        for (name, value) in headers {
+            if let Ok(header) = Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                response = response.with_header(header);
+            }
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_origins_win_over_the_wildcard() {
+        let mut settings = CorsSettings {
+            allowed_origins: vec!["http://localhost:5173".to_string()],
+            ..Default::default()
+        };
+        let cors = Cors::new(&settings);
+        assert_eq!(cors.allow_origin("http://localhost:5173"), Some("http://localhost:5173"));
+        assert_eq!(cors.allow_origin("http://evil.example"), None);
+
+        settings.allowed_origins.push("*".to_string());
+        let cors = Cors::new(&settings);
+        assert_eq!(cors.allow_origin("http://localhost:5173"), Some("http://localhost:5173"));
+        assert_eq!(cors.allow_origin("http://other.example"), Some("*"));
+    }
+}
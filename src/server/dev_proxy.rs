@@ -0,0 +1,164 @@
+//! Forwards frontend requests to a dev server (`server.dev_proxy_url`), so
+//! the window gets hot module reload while bindings and the API stay on this
+//! server. Requests go out as HTTP/1.0 with `Connection: close`, which keeps
+//! responses unchunked and lets the body be read to the end of the stream.
+
+use super::error_page::HttpError;
+use super::HttpResponse;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use tiny_http::Header;
+use tracing::warn;
+
+/// Served by this server rather than the dev server, whose pages don't
+/// include it.
+pub const WEBUI_SCRIPT_PATH: &str = "/webui.js";
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Long enough for the dev server's first compile.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Hop-by-hop headers, and those rewritten for the dev server.
+const SKIPPED_REQUEST_HEADERS: &[&str] =
+    &["host", "connection", "keep-alive", "upgrade", "accept-encoding", "content-length", "transfer-encoding"];
+
+/// Headers tiny_http sets itself or that don't apply to the buffered body.
+const SKIPPED_RESPONSE_HEADERS: &[&str] = &["connection", "keep-alive", "content-length", "transfer-encoding", "date", "server"];
+
+pub struct DevProxy {
+    url: String,
+    /// `host:port` of the dev server.
+    authority: String,
+}
+
+impl DevProxy {
+    /// `url` is validated by the config, e.g. `http://localhost:3000`.
+    pub fn new(url: &str) -> Self {
+        let authority = url.trim_start_matches("http://").trim_end_matches('/');
+        let authority = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+        Self { url: url.trim_end_matches('/').to_string(), authority }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Sends `request` to the dev server and returns its answer, or 502 if it
+    /// can't be reached.
+    pub fn forward(&self, request: &tiny_http::Request, body: &[u8], security_headers: &[Header]) -> Result<HttpResponse, HttpError> {
+        match self.exchange(request, body) {
+            Ok(mut response) => {
+                for header in security_headers {
+                    response = response.with_header(header.clone());
+                }
+                Ok(response)
+            }
+            Err(e) => {
+                warn!("Dev server at {} failed to answer {}: {}", self.url, request.url(), e);
+                Err(HttpError::new(502, format!("Dev server unavailable at {}: {}", self.url, e)))
+            }
+        }
+    }
+
+    fn exchange(&self, request: &tiny_http::Request, body: &[u8]) -> std::io::Result<HttpResponse> {
+        let address = self
+            .authority
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no address"))?;
+        let mut upstream = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+        upstream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+        let mut head = format!("{} {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n", request.method(), request.url(), self.authority);
+        for header in request.headers() {
+            let name = header.field.as_str().as_str();
+            if !SKIPPED_REQUEST_HEADERS.iter().any(|skipped| name.eq_ignore_ascii_case(skipped)) {
+                head.push_str(&format!("{}: {}\r\n", name, header.value));
+            }
+        }
+        if !body.is_empty() {
+            head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+        }
+        head.push_str("\r\n");
+        upstream.write_all(head.as_bytes())?;
+        upstream.write_all(body)?;
+
+        let mut raw = Vec::new();
+        upstream.read_to_end(&mut raw)?;
+        parse_response(&raw).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "malformed response"))
+    }
+}
+
+/// Turns a raw HTTP/1.x response into a tiny_http one, adding the webui.js
+/// script to HTML pages.
+fn parse_response(raw: &[u8]) -> Option<HttpResponse> {
+    let head_end = raw.windows(4).position(|window| window == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&raw[..head_end]).ok()?;
+    let mut lines = head.split("\r\n");
+    let status: u16 = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+
+    let mut headers = Vec::new();
+    let mut is_html = false;
+    for line in lines {
+        let (name, value) = line.split_once(':')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.eq_ignore_ascii_case("content-type") && value.starts_with("text/html") {
+            is_html = true;
+        }
+        if SKIPPED_RESPONSE_HEADERS.iter().any(|skipped| name.eq_ignore_ascii_case(skipped)) {
+            continue;
+        }
+        headers.push(Header::from_bytes(name.as_bytes(), value.as_bytes()).ok()?);
+    }
+
+    let mut body = raw[head_end + 4..].to_vec();
+    if is_html {
+        body = with_webui_script(body);
+    }
+    let mut response = tiny_http::Response::from_data(body).with_status_code(status);
+    for header in headers {
+        response = response.with_header(header);
+    }
+    Some(response)
+}
+
+/// Adds `<script src="/webui.js">` before `</body>` unless the page has it,
+/// as the production build does.
+fn with_webui_script(body: Vec<u8>) -> Vec<u8> {
+    let html = match String::from_utf8(body) {
+        Ok(html) => html,
+        Err(e) => return e.into_bytes(),
+    };
+    if html.contains(WEBUI_SCRIPT_PATH) {
+        return html.into_bytes();
+    }
+    match html.rfind("</body>") {
+        Some(index) => {
+            let script = format!("  <script src=\"{}\"></script>\n", WEBUI_SCRIPT_PATH);
+            format!("{}{}{}", &html[..index], script, &html[index..]).into_bytes()
+        }
+        None => html.into_bytes(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_pages_get_the_webui_script() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: 26\r\n\r\n<html><body></body></html>";
+        let response = parse_response(raw).unwrap();
+        assert_eq!(response.status_code().0, 200);
+        let mut body = String::new();
+        response.into_reader().read_to_string(&mut body).unwrap();
+        assert_eq!(body, "<html><body>  <script src=\"/webui.js\"></script>\n</body></html>");
+
+        let raw = b"HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\n\r\n</body>";
+        let response = parse_response(raw).unwrap();
+        assert_eq!(response.status_code().0, 404);
+        assert_eq!(response.data_length(), Some(7));
+    }
+}
@@ -0,0 +1,121 @@
+//! Pages for errors the server answers itself, such as a missing file, as
+//! opposed to errors returned by route handlers. `frontend/dist/404.html`
+//! and `frontend/dist/500.html` replace the built-in page when they exist.
+
+use super::HttpResponse;
+use crate::core::{ErrorCode, RpcResponse};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use tiny_http::Header;
+
+/// Used when the frontend has no page for an error. `500.html` may use the
+/// same placeholders.
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{{status}} {{title}}</title>
+<style>body { font-family: system-ui, sans-serif; max-width: 40rem; margin: 4rem auto; color: #333; }</style>
+</head>
+<body>
+<h1>{{status}} {{title}}</h1>
+<p>{{message}}</p>
+<p><a href="/">Back to the app</a></p>
+</body>
+</html>
+"#;
+
+/// An error the server answers itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl HttpError {
+    pub fn new(status: u16, message: impl Into<String>) -> Self {
+        Self { status, message: message.into() }
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(404, "The page you asked for does not exist.")
+    }
+
+    pub fn forbidden() -> Self {
+        Self::new(403, "You can't open this path.")
+    }
+
+    pub fn internal() -> Self {
+        Self::new(500, "Something went wrong on the server. The log has the details.")
+    }
+}
+
+pub struct ErrorPages {
+    frontend_path: PathBuf,
+}
+
+impl ErrorPages {
+    pub fn new(frontend_path: &Path) -> Self {
+        Self { frontend_path: frontend_path.to_path_buf() }
+    }
+
+    /// `error` as JSON in the usual `{ok, data, error}` envelope when
+    /// `json`, otherwise as an HTML page. The pages are read on every error,
+    /// so a rebuilt frontend's pages are used without a restart.
+    pub fn render(&self, error: &HttpError, json: bool, security_headers: &[Header]) -> HttpResponse {
+        let (body, content_type) = if json {
+            let body = RpcResponse::error(ErrorCode::HttpServer, &error.message)
+                .with_details(json!({ "status": error.status }))
+                .to_value();
+            (body.to_string(), "application/json")
+        } else {
+            let custom = if error.status == 404 { "404.html" } else { "500.html" };
+            let page = std::fs::read_to_string(self.frontend_path.join(custom)).ok();
+            (fill(page.as_deref().unwrap_or(TEMPLATE), error), "text/html; charset=utf-8")
+        };
+        let mut response = tiny_http::Response::from_data(body.into_bytes()).with_status_code(error.status);
+        if let Ok(header) = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
+            response = response.with_header(header);
+        }
+        if !json {
+            for header in security_headers {
+                response = response.with_header(header.clone());
+            }
+        }
+        response
+    }
+}
+
+fn fill(page: &str, error: &HttpError) -> String {
+    let title = tiny_http::StatusCode(error.status).default_reason_phrase();
+    page.replace("{{status}}", &error.status.to_string())
+        .replace("{{title}}", title)
+        .replace("{{message}}", &escape(&error.message))
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn errors_render_as_the_template_or_json() {
+        let pages = ErrorPages::new(Path::new("/nonexistent"));
+        let mut body = String::new();
+        pages.render(&HttpError::new(502, "<down>"), false, &[]).into_reader().read_to_string(&mut body).unwrap();
+        assert!(body.contains("<h1>502 Bad Gateway</h1>"));
+        assert!(body.contains("<p>&lt;down&gt;</p>"));
+
+        let response = pages.render(&HttpError::not_found(), true, &[]);
+        assert_eq!(response.status_code().0, 404);
+        let mut body = String::new();
+        response.into_reader().read_to_string(&mut body).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(value["ok"], false);
+        assert_eq!(value["error"]["details"]["status"], 404);
+    }
+}
@@ -0,0 +1,206 @@
+//! `[server.files]`: a folder served read-only under a URL path, with
+//! optional listings of its subfolders.
+
+use super::error_page::{escape, HttpError};
+use super::mime::MimeTypes;
+use super::router::percent_decode;
+use super::HttpResponse;
+use crate::core::config::FilesSettings;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tiny_http::Header;
+use tracing::warn;
+
+pub struct FileMount {
+    mount: String,
+    root: PathBuf,
+    listing: bool,
+}
+
+/// One row of a folder listing.
+#[derive(Debug, Serialize)]
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+    /// Unix time in milliseconds.
+    modified: Option<i64>,
+}
+
+impl FileMount {
+    /// `None` unless the mount is enabled.
+    pub fn new(settings: &FilesSettings) -> Option<Self> {
+        settings.enabled.then(|| Self {
+            mount: settings.mount.clone(),
+            root: PathBuf::from(&settings.dir),
+            listing: settings.listing,
+        })
+    }
+
+    pub fn matches(&self, path: &str) -> bool {
+        path.strip_prefix(&self.mount).is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    }
+
+    /// Answers `path` (which [`FileMount::matches`]) with a file or a folder
+    /// listing. `wants_json` picks the JSON listing.
+    pub fn serve(
+        &self,
+        path: &str,
+        wants_json: bool,
+        mime: &MimeTypes,
+        security_headers: &[Header],
+    ) -> Result<HttpResponse, HttpError> {
+        let relative: Vec<String> = path[self.mount.len()..]
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| percent_decode(&segment.replace('+', "%2B")))
+            .collect();
+        if relative.iter().any(|segment| segment == ".." || segment.contains(['/', '\\'])) {
+            return Err(HttpError::forbidden());
+        }
+        // Hidden files and folders, such as `.env` or `.git`, are left out of
+        // listings and aren't served either.
+        if relative.iter().any(|segment| segment.starts_with('.')) {
+            return Err(HttpError::not_found());
+        }
+        let target = relative.iter().fold(self.root.clone(), |path, segment| path.join(segment));
+
+        let (Ok(root), Ok(canonical)) = (self.root.canonicalize(), target.canonicalize()) else {
+            return Err(HttpError::not_found());
+        };
+        if !canonical.starts_with(&root) {
+            warn!("Security: Path traversal attempt blocked: {}", path);
+            return Err(HttpError::forbidden());
+        }
+
+        let mut response = if canonical.is_dir() {
+            if !self.listing {
+                return Err(HttpError::not_found());
+            }
+            let url = format!("{}/{}", self.mount, relative.iter().map(|s| encode_segment(s)).collect::<Vec<_>>().join("/"));
+            let url = url.trim_end_matches('/').to_string();
+            match read_entries(&canonical) {
+                Ok(entries) if wants_json => {
+                    let body = serde_json::json!({ "path": url, "entries": entries });
+                    with_content_type(tiny_http::Response::from_data(body.to_string().into_bytes()), "application/json")
+                }
+                Ok(entries) => with_content_type(
+                    tiny_http::Response::from_data(listing_html(&url, &relative, &entries).into_bytes()),
+                    "text/html; charset=utf-8",
+                ),
+                Err(e) => {
+                    warn!("Error listing {:?}: {}", canonical, e);
+                    return Err(HttpError::internal());
+                }
+            }
+        } else {
+            match std::fs::read(&canonical) {
+                Ok(content) => {
+                    let content_type = mime.content_type(&canonical);
+                    with_content_type(tiny_http::Response::from_data(content), &content_type)
+                }
+                Err(e) => {
+                    warn!("Error reading file {:?}: {}", canonical, e);
+                    return Err(HttpError::internal());
+                }
+            }
+        };
+        for header in security_headers {
+            response = response.with_header(header.clone());
+        }
+        Ok(response)
+    }
+}
+
+fn with_content_type(response: HttpResponse, content_type: &str) -> HttpResponse {
+    match Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()) {
+        Ok(header) => response.with_header(header),
+        Err(()) => response,
+    }
+}
+
+/// Folders first, then files, each by name. Hidden entries are left out.
+fn read_entries(dir: &Path) -> std::io::Result<Vec<Entry>> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else { continue };
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|since| since.as_millis() as i64);
+        entries.push(Entry { name, is_dir: metadata.is_dir(), size: metadata.len(), modified });
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    Ok(entries)
+}
+
+fn listing_html(url: &str, relative: &[String], entries: &[Entry]) -> String {
+    let title = format!("/{}", relative.join("/"));
+    let mut rows = String::new();
+    if !relative.is_empty() {
+        let parent = url.rsplit_once('/').map_or(url, |(parent, _)| parent);
+        rows.push_str(&format!("<tr><td><a href=\"{}\">..</a></td><td></td></tr>\n", escape(parent)));
+    }
+    for entry in entries {
+        let href = format!("{}/{}", url, encode_segment(&entry.name));
+        let (name, size) = if entry.is_dir {
+            (format!("{}/", entry.name), String::new())
+        } else {
+            (entry.name.clone(), entry.size.to_string())
+        };
+        rows.push_str(&format!("<tr><td><a href=\"{}\">{}</a></td><td>{}</td></tr>\n", escape(&href), escape(&name), size));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\n\
+         <body><h1>{0}</h1>\n<table>\n<tr><th>Name</th><th>Size</th></tr>\n{1}</table></body></html>\n",
+        escape(&title),
+        rows
+    )
+}
+
+/// Percent-encodes everything but unreserved characters.
+fn encode_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fs;
+
+    #[test]
+    fn hidden_paths_are_not_served() {
+        let dir = std::env::temp_dir().join(format!("rustwebui-files-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join(".git")).unwrap();
+        fs::write(dir.join("export.csv"), b"a,b").unwrap();
+        fs::write(dir.join(".env"), b"SECRET=1").unwrap();
+        fs::write(dir.join(".git/config"), b"[core]").unwrap();
+        let settings = FilesSettings {
+            enabled: true,
+            mount: "/files".to_string(),
+            dir: dir.display().to_string(),
+            listing: true,
+        };
+        let mount = FileMount::new(&settings).unwrap();
+        let serve = |path: &str| mount.serve(path, false, &MimeTypes::new(&BTreeMap::new()), &[]);
+
+        assert!(serve("/files/export.csv").is_ok());
+        for hidden in ["/files/.env", "/files/%2Eenv", "/files/.git/config", "/files/.git"] {
+            assert_eq!(serve(hidden).err().map(|e| e.status), Some(404), "{}", hidden);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
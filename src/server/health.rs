@@ -0,0 +1,63 @@
+use super::{ApiResponse, Router};
+use crate::app_state::AppState;
+use crate::core::{run_id, uptime, Database};
+use crate::websocket_manager::WebSocketState;
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+/// Answers 200 while the HTTP thread is serving requests.
+pub const HEALTH_PATH: &str = "/healthz";
+
+/// Answers 200 when the database and the window connection work, 503 otherwise.
+pub const READY_PATH: &str = "/readyz";
+
+/// How long the readiness check waits for a free database connection, when
+/// long queries or stuck handlers hold all of them.
+const DB_LOCK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Mounts [`HEALTH_PATH`] and [`READY_PATH`].
+pub fn register(router: &mut Router, state: AppState) {
+    router.get(HEALTH_PATH, |_request| {
+        let body = json!({
+            "status": "ok",
+            "run_id": run_id(),
+            "uptime_secs": uptime().as_secs(),
+        });
+        ApiResponse::json(200, &body).with_header("Cache-Control", "no-store")
+    });
+
+    router.get(READY_PATH, move |_request| {
+        let database = check_database(state.db());
+        // Until the window is created there is no WebSocket to check.
+        let websocket = match state.websocket_manager().map(|manager| (manager.get_state(), manager.get_metrics())) {
+            Some((websocket_state, metrics)) => json!({
+                "ok": websocket_state != WebSocketState::Failed,
+                "state": websocket_state,
+                "reconnects": metrics.reconnect_count,
+                "last_error": metrics.last_error,
+            }),
+            None => json!({ "ok": true, "state": null }),
+        };
+        let ready = database["ok"] == true && websocket["ok"] == true;
+        let body = json!({
+            "ready": ready,
+            "run_id": run_id(),
+            "uptime_secs": uptime().as_secs(),
+            "checks": { "database": database, "websocket": websocket },
+        });
+        ApiResponse::json(if ready { 200 } else { 503 }, &body).with_header("Cache-Control", "no-store")
+    });
+}
+
+/// Runs `SELECT 1`, giving up if no connection is free within
+/// [`DB_LOCK_TIMEOUT`].
+fn check_database(db: &Database) -> Value {
+    let started = Instant::now();
+    let Ok(conn) = db.get_connection_within(DB_LOCK_TIMEOUT) else {
+        return json!({ "ok": false, "error": "every database connection is busy" });
+    };
+    match conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => json!({ "ok": true, "latency_ms": started.elapsed().as_millis() as u64 }),
+        Err(e) => json!({ "ok": false, "error": e.to_string() }),
+    }
+}
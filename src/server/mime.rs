@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Content types for served files: `[server.mime_overrides]` first, then
+/// `mime_guess`.
+pub struct MimeTypes {
+    /// (`.ext`, content type), longest extension first so `.wasm.map` wins
+    /// over `.map`.
+    overrides: Vec<(String, String)>,
+}
+
+impl MimeTypes {
+    pub fn new(overrides: &BTreeMap<String, String>) -> Self {
+        let mut overrides: Vec<(String, String)> = overrides
+            .iter()
+            .map(|(extension, content_type)| {
+                (format!(".{}", extension.trim_start_matches('.').to_lowercase()), content_type.clone())
+            })
+            .collect();
+        overrides.sort_by_key(|(extension, _)| std::cmp::Reverse(extension.len()));
+        Self { overrides }
+    }
+
+    pub fn content_type(&self, path: &Path) -> String {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_lowercase()).unwrap_or_default();
+        self.overrides
+            .iter()
+            .find(|(extension, _)| name.ends_with(extension.as_str()))
+            .map(|(_, content_type)| content_type.clone())
+            .unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_longest_matching_override_wins() {
+        let overrides = BTreeMap::from([
+            ("map".to_string(), "application/json".to_string()),
+            (".wasm.map".to_string(), "application/x-wasm-map".to_string()),
+            ("Blob".to_string(), "application/x-blob".to_string()),
+        ]);
+        let mime = MimeTypes::new(&overrides);
+        assert_eq!(mime.content_type(Path::new("static/app.wasm.map")), "application/x-wasm-map");
+        assert_eq!(mime.content_type(Path::new("static/app.js.map")), "application/json");
+        assert_eq!(mime.content_type(Path::new("data.BLOB")), "application/x-blob");
+        assert_eq!(mime.content_type(Path::new("index.html")), "text/html");
+    }
+}
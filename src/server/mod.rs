@@ -0,0 +1,510 @@
+pub mod access;
+pub mod body;
+pub mod cors;
+pub mod dev_proxy;
+pub mod error_page;
+pub mod files;
+pub mod health;
+pub mod mime;
+pub mod port;
+pub mod relay;
+pub mod router;
+pub mod runtime_config;
+pub mod sse;
+#[cfg(unix)]
+pub mod unix_socket;
+pub mod upload;
+pub mod websocket;
+
+pub use access::AccessGate;
+pub use cors::Cors;
+pub use dev_proxy::DevProxy;
+pub use error_page::{ErrorPages, HttpError};
+pub use files::FileMount;
+pub use mime::MimeTypes;
+pub use port::{local_url, select_port, PortSource};
+pub use router::{ApiRequest, ApiResponse, Router};
+pub use runtime_config::RuntimeConfig;
+
+use crate::app_state::AppState;
+use crate::core::config::ServerSettings;
+use crate::core::{AppError, AppResult};
+use crate::event_bus::EventBus;
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use tracing::{debug, info, info_span, warn};
+use websocket::WebSocketEndpoint;
+
+/// Running HTTP server; [`HttpServerHandle::stop`] ends it.
+pub struct HttpServerHandle {
+    server: Arc<tiny_http::Server>,
+    workers: Vec<thread::JoinHandle<()>>,
+    stopping: Arc<AtomicBool>,
+    relay: relay::Relay,
+    socket: Option<SocketBinding>,
+    url: String,
+}
+
+impl HttpServerHandle {
+    /// The URL the window loads, e.g. `http://localhost:8080`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Stops accepting connections and waits for the requests in progress.
+    pub fn stop(self) {
+        self.relay.stop();
+        self.stopping.store(true, Ordering::SeqCst);
+        // Each call wakes one waiting worker.
+        for _ in &self.workers {
+            self.server.unblock();
+        }
+        for worker in self.workers {
+            if worker.join().is_err() {
+                warn!("HTTP worker thread panicked");
+            }
+        }
+        if let Some(socket) = self.socket {
+            socket.close();
+        }
+        info!("HTTP server stopped");
+    }
+}
+
+/// What the workers share to answer requests.
+struct Context {
+    router: Router,
+    frontend_path: PathBuf,
+    security_headers: Vec<tiny_http::Header>,
+    access_log: bool,
+    cors: Arc<Cors>,
+    /// `None` unless `server.uploads_enabled` is on.
+    uploads: Option<upload::UploadPool>,
+    error_pages: ErrorPages,
+    mime: MimeTypes,
+    limits: body::BodyLimits,
+    files: Option<FileMount>,
+    dev_proxy: Option<DevProxy>,
+    /// Event streams and uploads emit on the runtime from their own threads.
+    runtime: Option<tokio::runtime::Handle>,
+    bus: EventBus,
+    gate: AccessGate,
+}
+
+/// Serves `router` and the frontend on `port`. `/events`, `/ws/events` and
+/// uploads use `state`'s event bus; they and `/api/*` need `session_token`.
+pub fn start_http_server(
+    port: u16,
+    router: Router,
+    settings: &ServerSettings,
+    state: &AppState,
+    session_token: &str,
+) -> AppResult<HttpServerHandle> {
+    let frontend_path = PathBuf::from("frontend/dist");
+    info!("Starting HTTP server on {}:{} for frontend files", settings.bind_address(), port);
+    let security_headers: Vec<tiny_http::Header> = settings
+        .security_headers()
+        .into_iter()
+        .filter_map(|(name, value)| match tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            Ok(header) => Some(header),
+            Err(()) => {
+                warn!("Ignoring invalid security header {}", name);
+                None
+            }
+        })
+        .collect();
+
+    let runtime = tokio::runtime::Handle::try_current()
+        .map_err(|_| AppError::HttpServer("The HTTP server needs a Tokio runtime".to_string()))?;
+    // tiny_http listens privately; clients reach it through the relay.
+    let (server, upstream, socket) = match settings.unix_socket.as_deref().map(PathBuf::from) {
+        Some(path) => bind_unix_socket(path)?,
+        None => {
+            let server =
+                tiny_http::Server::http((Ipv4Addr::LOCALHOST, 0)).map_err(|e| AppError::HttpServer(e.to_string()))?;
+            let address = server
+                .server_addr()
+                .to_ip()
+                .ok_or_else(|| AppError::HttpServer("tiny_http has no TCP address".to_string()))?;
+            (server, relay::Upstream::Tcp(address), None)
+        }
+    };
+    let server = Arc::new(server);
+    let gate = AccessGate::new(session_token, settings.cors.allowed_origins.clone());
+    let public_address = if socket.is_some() { Ipv4Addr::LOCALHOST.into() } else { settings.bind_address() };
+    let listener = std::net::TcpListener::bind((public_address, port))?;
+    let relay = relay::start(
+        listener,
+        upstream,
+        settings.request_timeout(),
+        Some(WebSocketEndpoint::new(gate.clone(), state.clone())),
+        &runtime,
+    )?;
+
+    let files = FileMount::new(&settings.files);
+    if files.is_some() {
+        info!("Serving {} under {}", settings.files.dir, settings.files.mount);
+    }
+    let dev_proxy = settings.dev_proxy_url.as_deref().map(DevProxy::new);
+    if let Some(proxy) = &dev_proxy {
+        info!("Proxying frontend requests to the dev server at {}", proxy.url());
+    }
+    let cors = Arc::new(Cors::new(&settings.cors));
+    let uploads = settings.uploads_enabled.unwrap_or(false).then(|| {
+        let upload_settings = upload::UploadSettings {
+            dir: PathBuf::from(settings.upload_dir.as_deref().unwrap_or("uploads")),
+            max_bytes: settings.max_upload_bytes(),
+        };
+        let events = Some((runtime.clone(), state.bus().clone()));
+        upload::UploadPool::new(upload_settings, Arc::clone(&cors), events, settings.access_log.unwrap_or(true))
+    });
+    let context = Arc::new(Context {
+        router,
+        error_pages: ErrorPages::new(&frontend_path),
+        frontend_path,
+        security_headers,
+        access_log: settings.access_log.unwrap_or(true),
+        cors,
+        uploads,
+        mime: MimeTypes::new(&settings.mime_overrides),
+        limits: body::BodyLimits { max_bytes: settings.max_body_size(), timeout: settings.request_timeout() },
+        files,
+        dev_proxy,
+        runtime: Some(runtime),
+        bus: state.bus().clone(),
+        gate,
+    });
+
+    let url = local_url(public_address, port);
+    let stopping = Arc::new(AtomicBool::new(false));
+    let workers = (0..settings.workers())
+        .map(|_| {
+            let (server, context, stopping) = (Arc::clone(&server), Arc::clone(&context), Arc::clone(&stopping));
+            thread::spawn(move || loop {
+                match server.recv() {
+                    Ok(request) => context.handle(request),
+                    Err(_) if stopping.load(Ordering::SeqCst) => break,
+                    Err(e) => debug!("Failed to receive a request: {}", e),
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    info!("HTTP server listening on {} with {} worker(s)", url, workers.len());
+    Ok(HttpServerHandle { server, workers, stopping, relay, socket, url })
+}
+
+impl Context {
+    fn handle(&self, request: tiny_http::Request) {
+        let started = Instant::now();
+        let method = request.method().as_str().to_uppercase();
+        let url = request.url().to_string();
+        let route_path = url.split('?').next().unwrap_or("/").to_string();
+        let span = info_span!("http_request", method = %method, path = %route_path);
+        let _entered = span.enter();
+        let cors = &self.cors;
+
+        let origin = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Origin"))
+            .map(|h| h.value.as_str().to_string());
+        let allow_origin = origin.as_deref().filter(|_| cors.is_enabled()).and_then(|origin| cors.allow_origin(origin));
+        let is_preflight = method == "OPTIONS"
+            && request.headers().iter().any(|h| h.field.equiv("Access-Control-Request-Method"));
+
+        // Preflights carry no custom headers; the request that follows does.
+        if AccessGate::guards(&route_path) && !is_preflight {
+            let query = router::parse_query(url.split_once('?').map_or("", |(_, query)| query));
+            let token = request.headers().iter().find(|h| h.field.equiv(access::TOKEN_HEADER)).map(|h| h.value.as_str());
+            let foreign_origin = origin.as_deref().filter(|origin| !self.gate.allows_origin(origin));
+            let refusal = match foreign_origin {
+                Some(origin) => {
+                    warn!("Refused {} {} from page at {}", method, route_path, origin);
+                    Some(HttpError::new(403, "Pages on this origin can't use the API or the event stream."))
+                }
+                _ if !self.gate.admits(token, &query) => {
+                    warn!("Refused {} {} without the session token", method, route_path);
+                    Some(HttpError::new(401, "This request needs the session token from /runtime-config.json."))
+                }
+                _ => None,
+            };
+            if let Some(error) = refusal {
+                let status = error.status;
+                let response = self.error_pages.render(&error, true, &self.security_headers);
+                let response = match allow_origin {
+                    Some(allow_origin) => cors.apply(response, allow_origin),
+                    None => response,
+                };
+                if let Err(e) = request.respond(response) {
+                    debug!("Failed to send response for {}: {}", route_path, e);
+                }
+                if self.access_log {
+                    log_access(&method, &route_path, status, 0, started);
+                }
+                return;
+            }
+        }
+
+        if method == "GET" && route_path == sse::EVENTS_PATH {
+            let query = router::parse_query(url.split_once('?').map_or("", |(_, query)| query));
+            let allow_origin = allow_origin.map(str::to_string);
+            sse::stream(request, event_patterns(&query), allow_origin, self.runtime.clone(), self.bus.clone());
+            if self.access_log {
+                log_access(&method, &route_path, 200, 0, started);
+            }
+            return;
+        }
+        if let Some(uploads) = self.uploads.as_ref().filter(|_| method == "POST" && route_path == upload::UPLOAD_PATH) {
+            uploads.handle(request, allow_origin.map(str::to_string));
+            return;
+        }
+
+        let wants_json = accepts_json(&request);
+        let security_headers = &self.security_headers;
+        let limits = self.limits;
+        let timed_out = || self.error_pages.render(&body::timeout_error(limits.timeout), wants_json, security_headers);
+        let (request, body) = match body::read(request, limits, timed_out) {
+            body::Received::Body(request, body) => (request, Ok(body)),
+            body::Received::Rejected(request, error) => (request, Err(error)),
+            body::Received::TimedOut => {
+                warn!("Request body for {} did not arrive within {:?}", route_path, limits.timeout);
+                if self.access_log {
+                    log_access(&method, &route_path, 408, 0, started);
+                }
+                return;
+            }
+        };
+
+        let result = match (allow_origin, body) {
+            (_, Err(error)) => Err(error),
+            (Some(allow_origin), _) if is_preflight => Ok(cors.preflight(allow_origin)),
+            (_, Ok(body)) if self.router.matches_path(&route_path) => handle_api_request(&self.router, &request, body),
+            (_, Ok(body)) => match (self.files.as_ref().filter(|files| files.matches(&route_path)), &self.dev_proxy) {
+                (Some(_), _) if method != "GET" => Err(HttpError::new(405, "Only GET is allowed here.")),
+                (Some(files), _) => {
+                    let query = router::parse_query(url.split_once('?').map_or("", |(_, query)| query));
+                    let wants_json = wants_json || query.get("format").is_some_and(|f| f == "json");
+                    files.serve(&route_path, wants_json, &self.mime, security_headers)
+                }
+                (None, Some(proxy)) if route_path != dev_proxy::WEBUI_SCRIPT_PATH => {
+                    proxy.forward(&request, &body, security_headers)
+                }
+                _ => serve_file(&self.frontend_path, &url, &self.mime, security_headers),
+            },
+        };
+        let response = result.unwrap_or_else(|error| self.error_pages.render(&error, wants_json, security_headers));
+        let response = match allow_origin {
+            Some(allow_origin) if !is_preflight => cors.apply(response, allow_origin),
+            _ => response,
+        };
+        let status = response.status_code().0;
+        let bytes = response.data_length().unwrap_or(0);
+        if let Err(e) = request.respond(response) {
+            debug!("Failed to send response for {}: {}", route_path, e);
+        }
+        if self.access_log {
+            log_access(&method, &route_path, status, bytes, started);
+        }
+    }
+}
+
+/// The Unix socket file, removed on stop.
+#[cfg(unix)]
+struct SocketBinding {
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl SocketBinding {
+    fn close(self) {
+        let _ = std::fs::remove_file(self.path);
+    }
+}
+
+#[cfg(not(unix))]
+enum SocketBinding {}
+
+#[cfg(not(unix))]
+impl SocketBinding {
+    fn close(self) {
+        match self {}
+    }
+}
+
+/// Serves on the Unix socket at `path`. The relay on 127.0.0.1 is the
+/// window's way in.
+#[cfg(unix)]
+fn bind_unix_socket(path: PathBuf) -> AppResult<(tiny_http::Server, relay::Upstream, Option<SocketBinding>)> {
+    let server = unix_socket::bind(&path)?;
+    info!("HTTP server listening on unix:{}", path.display());
+    Ok((server, relay::Upstream::Unix(path.clone()), Some(SocketBinding { path })))
+}
+
+#[cfg(not(unix))]
+fn bind_unix_socket(_path: PathBuf) -> AppResult<(tiny_http::Server, relay::Upstream, Option<SocketBinding>)> {
+    Err(AppError::HttpServer("server.unix_socket is only supported on Unix".to_string()))
+}
+
+/// Records one request under the `http.access` target. Event streams are
+/// logged when they start, with no bytes; uploads when they are answered.
+fn log_access(method: &str, path: &str, status: u16, bytes: usize, started: Instant) {
+    info!(
+        target: "http.access",
+        status,
+        bytes,
+        latency_us = started.elapsed().as_micros() as u64,
+        "{} {} {}",
+        method,
+        path,
+        status
+    );
+}
+
+/// Event name patterns from a `pattern=a.*,b.*` query parameter; all events
+/// when it is missing.
+pub(crate) fn event_patterns(query: &HashMap<String, String>) -> Vec<String> {
+    query
+        .get("pattern")
+        .map(|pattern| pattern.split(',').filter(|p| !p.is_empty()).map(str::to_string).collect())
+        .unwrap_or_else(|| vec!["*".to_string()])
+}
+
+/// Whether the request's `Accept` header asks for JSON.
+fn accepts_json(request: &tiny_http::Request) -> bool {
+    request
+        .headers()
+        .iter()
+        .any(|h| h.field.equiv("Accept") && h.value.as_str().contains("application/json"))
+}
+
+pub(crate) type HttpResponse = tiny_http::Response<std::io::Cursor<Vec<u8>>>;
+
+/// Answers with a file under `frontend_path`, refusing paths that lead out of it.
+fn serve_file(
+    frontend_path: &Path,
+    url: &str,
+    mime: &MimeTypes,
+    security_headers: &[tiny_http::Header],
+) -> Result<HttpResponse, HttpError> {
+    let sanitized_path = url.trim_start_matches('/').replace("..", "").replace("%2e%2e", "").replace("%252e%252e", "");
+    let path = if url == "/" { frontend_path.join("index.html") } else { frontend_path.join(&sanitized_path) };
+
+    let Ok(canonical_path) = path.canonicalize() else {
+        return Err(HttpError::not_found());
+    };
+    let frontend_canonical = match frontend_path.canonicalize() {
+        Ok(p) => p,
+        Err(e) => {
+            warn!("Error canonicalizing path: {}", e);
+            return Err(HttpError::internal());
+        }
+    };
+
+    if !canonical_path.starts_with(&frontend_canonical) {
+        warn!("Security: Path traversal attempt blocked: {}", url);
+        return Err(HttpError::forbidden());
+    }
+
+    if !path.is_file() {
+        return Err(HttpError::not_found());
+    }
+    match std::fs::read(&path) {
+        Ok(content) => {
+            let content_type = mime.content_type(&path);
+            let mut response = tiny_http::Response::from_data(content);
+            response = response.with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes()).unwrap());
+            response = response.with_header(tiny_http::Header::from_bytes(&b"Cache-Control"[..], cache_control(&path).as_bytes()).unwrap());
+            for header in security_headers {
+                response = response.with_header(header.clone());
+            }
+            Ok(response)
+        }
+        Err(e) => {
+            warn!("Error reading file {:?}: {}", path, e);
+            Err(HttpError::internal())
+        }
+    }
+}
+
+/// Files named by rspack's `[name].[contenthash:8].ext`, such as
+/// `static/js/index.1a2b3c4d.js` and its `.map`, never change, so browsers may
+/// keep them for a year. Everything else, `index.html` included, is checked
+/// on every load.
+fn cache_control(path: &Path) -> &'static str {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let name = name.strip_suffix(".map").unwrap_or(&name);
+    let mut parts = name.rsplitn(3, '.');
+    let hashed = match (parts.next(), parts.next(), parts.next()) {
+        (Some(ext), Some(hash), Some(stem)) => {
+            !stem.is_empty()
+                && !ext.is_empty()
+                && hash.len() == 8
+                && hash.chars().all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+        }
+        _ => false,
+    };
+    if hashed { "public, max-age=31536000, immutable" } else { "no-cache" }
+}
+
+fn handle_api_request(router: &Router, request: &tiny_http::Request, body: Vec<u8>) -> Result<HttpResponse, HttpError> {
+    let method = request.method().as_str().to_uppercase();
+    let url = request.url().to_string();
+    let (path, query) = url.split_once('?').unwrap_or((url.as_str(), ""));
+
+    let Some((handler, params)) = router.find(&method, path) else {
+        return Err(HttpError::new(405, format!("{} is not allowed here.", method)));
+    };
+
+    let api_request = ApiRequest {
+        query: router::parse_query(query),
+        headers: request
+            .headers()
+            .iter()
+            .map(|h| (h.field.as_str().to_string(), h.value.as_str().to_string()))
+            .collect(),
+        body,
+        params,
+    };
+
+    Ok(into_tiny_response(handler(&api_request)))
+}
+
+fn into_tiny_response(response: ApiResponse) -> HttpResponse {
+    let mut tiny_response = tiny_http::Response::from_data(response.body).with_status_code(response.status);
+    for (name, value) in &response.headers {
+        if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+            tiny_response = tiny_response.with_header(header);
+        }
+    }
+    tiny_response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_content_hashed_files_are_immutable() {
+        for hashed in ["static/js/index.1a2b3c4d.js", "static/css/index.deadbeef.css", "static/js/vendors.0123abcd.js.map"] {
+            assert_eq!(cache_control(Path::new(hashed)), "public, max-age=31536000, immutable", "{}", hashed);
+        }
+        for plain in [
+            "index.html",
+            "favicon.ico",
+            "js/main.js",
+            "1a2b3c4d.js",
+            "static/js/index.decade.js",
+            "static/js/index.1a2b3c4d5.js",
+            "static/js/index.DEADBEEF.js",
+            "static/js/index.1a2b3c4d.min.js",
+            "static/js/index.1a2b3c4d.",
+        ] {
+            assert_eq!(cache_control(Path::new(plain)), "no-cache", "{}", plain);
+        }
+    }
+}
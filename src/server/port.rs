@@ -0,0 +1,69 @@
+use crate::core::{AppError, AppResult};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::ops::RangeInclusive;
+use tracing::{debug, info};
+
+/// Ports tried when neither `--port`, `server.port` nor the last used port is
+/// free, unless `server.port_range` says otherwise.
+pub const PORT_RANGE: RangeInclusive<u16> = 8000..=8999;
+
+/// Where the HTTP server port came from, reported in `server.started`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortSource {
+    /// `--port` on the command line.
+    Cli,
+    /// `server.port` in the config.
+    Config,
+    /// The port of the previous run.
+    LastUsed,
+    /// The first free port in the range.
+    Range,
+}
+
+/// Picks the HTTP server port on `address`: `preferred` if it is free, then
+/// `last_used`, then the lowest free port in `range`, so the same machine gets
+/// the same port from run to run. Fails with [`AppError::PortUnavailable`] when every
+/// port in `range` is taken.
+pub fn select_port(
+    address: IpAddr,
+    preferred: Option<u16>,
+    last_used: Option<u16>,
+    range: RangeInclusive<u16>,
+) -> AppResult<(u16, PortSource)> {
+    if let Some(port) = preferred {
+        if is_port_available(address, port) {
+            return Ok((port, PortSource::Config));
+        }
+        info!("Configured port {} is taken, trying others", port);
+    }
+    if let Some(port) = last_used {
+        if is_port_available(address, port) {
+            info!("Reusing port {}", port);
+            return Ok((port, PortSource::LastUsed));
+        }
+        debug!("Last used port {} is taken", port);
+    }
+
+    let attempts = range.len() as u32;
+    range
+        .into_iter()
+        .find(|port| is_port_available(address, *port))
+        .map(|port| (port, PortSource::Range))
+        .ok_or(AppError::PortUnavailable(attempts))
+}
+
+pub fn is_port_available(address: IpAddr, port: u16) -> bool {
+    TcpListener::bind((address, port)).is_ok()
+}
+
+/// The URL the window loads for a server bound to `address`; `localhost` when
+/// it listens on every interface.
+pub fn local_url(address: IpAddr, port: u16) -> String {
+    if address.is_unspecified() {
+        format!("http://localhost:{}", port)
+    } else {
+        format!("http://{}", SocketAddr::new(address, port))
+    }
+}
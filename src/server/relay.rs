@@ -0,0 +1,157 @@
+//! The server's public listener. tiny_http neither hands out the sockets it
+//! accepts nor has socket timeouts, so clients connect here and each
+//! connection is copied to tiny_http on a private socket. A client that
+//! sends nothing for `server.request_timeout` has its sending side closed:
+//! a body still being read then ends early and is answered with 408, and an
+//! idle keep-alive connection is closed.
+//!
+//! tiny_http therefore sees every request as coming from the relay: its
+//! `Request::remote_addr` is a loopback address, or none over a Unix
+//! socket. Only the relay, which logs connection errors with it, and the
+//! WebSocket endpoint know the client's address; nothing behind the relay
+//! may rely on it.
+//!
+//! Upgrade requests for [`EVENTS_PATH`](super::websocket::EVENTS_PATH) are
+//! recognised from their first bytes and served by the
+//! [`WebSocketEndpoint`] instead, so the event stream shares the port.
+
+use super::websocket::{self, WebSocketEndpoint};
+use crate::core::AppResult;
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::pin::pin;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Bytes copied from the client per read.
+const CHUNK: usize = 16 * 1024;
+
+/// Bytes looked at to tell a WebSocket upgrade from other requests.
+const PEEK: usize = 64;
+
+/// Where tiny_http listens.
+#[derive(Debug, Clone)]
+pub enum Upstream {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+/// The running listener; [`Relay::stop`] closes it.
+pub struct Relay {
+    accept: JoinHandle<()>,
+}
+
+impl Relay {
+    /// Stops accepting connections. Connections already accepted run until
+    /// either side closes them, so requests in progress are still answered.
+    pub fn stop(self) {
+        self.accept.abort();
+    }
+}
+
+/// Copies connections to `listener` to `upstream` until [`Relay::stop`],
+/// handing WebSocket upgrades to `websocket` when there is one.
+pub fn start(
+    listener: std::net::TcpListener,
+    upstream: Upstream,
+    timeout: Duration,
+    websocket: Option<WebSocketEndpoint>,
+    runtime: &tokio::runtime::Handle,
+) -> AppResult<Relay> {
+    listener.set_nonblocking(true)?;
+    let _entered = runtime.enter();
+    let listener = TcpListener::from_std(listener)?;
+    let accept = runtime.spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((client, peer)) => {
+                    let upstream = upstream.clone();
+                    let websocket = websocket.clone();
+                    tokio::spawn(async move {
+                        if let Some(endpoint) = websocket && opens_websocket(&client, timeout).await {
+                            endpoint.serve(client, peer).await;
+                            return;
+                        }
+                        if let Err(e) = relay(client, &upstream, timeout).await {
+                            debug!("Connection from {} ended: {}", peer, e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept a connection: {}", e),
+            }
+        }
+    });
+    Ok(Relay { accept })
+}
+
+/// Waits, for at most `timeout`, until the client has sent enough to tell
+/// whether it is opening the event stream. Nothing is consumed.
+async fn opens_websocket(client: &TcpStream, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    let mut start = [0; PEEK];
+    loop {
+        let peeked = match tokio::time::timeout_at(deadline, client.peek(&mut start)).await {
+            Ok(Ok(peeked)) if peeked > 0 => peeked,
+            _ => return false,
+        };
+        match websocket::is_upgrade_request(&start[..peeked]) {
+            Some(upgrade) => return upgrade,
+            None if peeked == PEEK => return false,
+            // peek returns at once while unread bytes are waiting.
+            None => tokio::time::sleep(Duration::from_millis(10)).await,
+        }
+    }
+}
+
+async fn relay(client: TcpStream, upstream: &Upstream, timeout: Duration) -> io::Result<()> {
+    match upstream {
+        Upstream::Tcp(address) => pipe(client, TcpStream::connect(address).await?, timeout).await,
+        #[cfg(unix)]
+        Upstream::Unix(path) => pipe(client, tokio::net::UnixStream::connect(path).await?, timeout).await,
+    }
+}
+
+/// Copies bytes both ways until the server closes the connection. The
+/// client's side is closed when it is silent for `timeout`, while the
+/// server may still answer.
+async fn pipe<S: AsyncRead + AsyncWrite>(client: TcpStream, server: S, timeout: Duration) -> io::Result<()> {
+    let (mut client_read, mut client_write) = client.into_split();
+    let (mut server_read, mut server_write) = tokio::io::split(server);
+    let mut requests = pin!(async move {
+        let mut buffer = vec![0; CHUNK];
+        loop {
+            let read = match tokio::time::timeout(timeout, client_read.read(&mut buffer)).await {
+                Ok(read) => read?,
+                Err(_) => {
+                    debug!("Client silent for {:?}; closing its side", timeout);
+                    0
+                }
+            };
+            if read == 0 {
+                break;
+            }
+            server_write.write_all(&buffer[..read]).await?;
+        }
+        server_write.shutdown().await
+    });
+    let mut responses = pin!(async move {
+        tokio::io::copy(&mut server_read, &mut client_write).await?;
+        client_write.shutdown().await
+    });
+    tokio::select! {
+        result = &mut responses => result,
+        result = &mut requests => {
+            if let Err(e) = result {
+                debug!("Failed to pass on a request: {}", e);
+            }
+            responses.await
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+pub type RouteHandler = Arc<dyn Fn(&ApiRequest) -> ApiResponse + Send + Sync>;
+
+#[derive(Debug, Clone)]
+pub struct ApiRequest {
+    pub query: HashMap<String, String>,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub params: HashMap<String, String>,
+}
+
+impl ApiRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(|v| v.as_str())
+    }
+
+    pub fn json(&self) -> Result<Value, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl ApiResponse {
+    pub fn new(status: u16, content_type: &str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body: body.into(),
+        }
+    }
+
+    pub fn json(status: u16, value: &Value) -> Self {
+        Self::new(status, "application/json", value.to_string())
+    }
+
+    pub fn text(status: u16, text: &str) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", text)
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+struct Route {
+    method: String,
+    segments: Vec<String>,
+    handler: RouteHandler,
+}
+
+/// Minimal method + path router. Patterns support `:name` parameters and a
+/// trailing `*` that matches the rest of the path (exposed as the `*` param).
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    pub fn route<F>(&mut self, method: &str, pattern: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&ApiRequest) -> ApiResponse + Send + Sync + 'static,
+    {
+        self.routes.push(Route {
+            method: method.to_uppercase(),
+            segments: split_path(pattern),
+            handler: Arc::new(handler),
+        });
+        self
+    }
+
+    pub fn get<F>(&mut self, pattern: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&ApiRequest) -> ApiResponse + Send + Sync + 'static,
+    {
+        self.route("GET", pattern, handler)
+    }
+
+    pub fn post<F>(&mut self, pattern: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&ApiRequest) -> ApiResponse + Send + Sync + 'static,
+    {
+        self.route("POST", pattern, handler)
+    }
+
+    pub fn put<F>(&mut self, pattern: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&ApiRequest) -> ApiResponse + Send + Sync + 'static,
+    {
+        self.route("PUT", pattern, handler)
+    }
+
+    pub fn delete<F>(&mut self, pattern: &str, handler: F) -> &mut Self
+    where
+        F: Fn(&ApiRequest) -> ApiResponse + Send + Sync + 'static,
+    {
+        self.route("DELETE", pattern, handler)
+    }
+
+    /// Finds the handler for a method and path, returning the captured params.
+    pub fn find(&self, method: &str, path: &str) -> Option<(RouteHandler, HashMap<String, String>)> {
+        let path_segments = split_path(path);
+        self.routes
+            .iter()
+            .filter(|route| route.method == method)
+            .find_map(|route| {
+                match_segments(&route.segments, &path_segments)
+                    .map(|params| (Arc::clone(&route.handler), params))
+            })
+    }
+
+    /// Returns true when any route matches the path regardless of method.
+    pub fn matches_path(&self, path: &str) -> bool {
+        let path_segments = split_path(path);
+        self.routes
+            .iter()
+            .any(|route| match_segments(&route.segments, &path_segments).is_some())
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
+
+fn match_segments(pattern: &[String], path: &[String]) -> Option<HashMap<String, String>> {
+    let mut params = HashMap::new();
+    for (i, segment) in pattern.iter().enumerate() {
+        if segment == "*" {
+            params.insert("*".to_string(), path.get(i..).unwrap_or_default().join("/"));
+            return Some(params);
+        }
+        let part = path.get(i)?;
+        if let Some(name) = segment.strip_prefix(':') {
+            params.insert(name.to_string(), part.clone());
+        } else if segment != part {
+            return None;
+        }
+    }
+    (pattern.len() == path.len()).then_some(params)
+}
+
+pub fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+pub(crate) fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
@@ -0,0 +1,63 @@
+use super::{ApiResponse, Router};
+use crate::core::{run_id, AppConfig};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Path the frontend fetches [`RuntimeConfig`] from.
+pub const RUNTIME_CONFIG_PATH: &str = "/runtime-config.json";
+
+/// Values the frontend can only learn at startup. Served by the HTTP server
+/// instead of being written into `frontend/dist`, which may be read-only.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeConfig {
+    pub port: u16,
+    pub run_id: String,
+    pub version: String,
+    pub dev_mode: bool,
+    /// The run's session ID, which `/api/*` and the event streams require;
+    /// see [`access`](super::access). A new one also tells the page the
+    /// backend was restarted.
+    pub session_token: String,
+    /// Where to open the event stream, e.g. `ws://localhost:8080/ws/events`.
+    pub ws_url: Option<String>,
+    pub features: BTreeMap<String, bool>,
+}
+
+impl RuntimeConfig {
+    pub fn new(port: u16, config: &AppConfig, session_token: &str) -> Self {
+        let features = BTreeMap::from([
+            ("telemetry".to_string(), cfg!(feature = "telemetry")),
+            ("scheduler".to_string(), config.is_scheduler_enabled()),
+        ]);
+        Self {
+            port,
+            run_id: run_id().to_string(),
+            version: config.app.version.clone(),
+            dev_mode: config.is_dev_mode(),
+            session_token: session_token.to_string(),
+            ws_url: None,
+            features,
+        }
+    }
+
+    /// Publishes the `/ws/events` URL on the HTTP server's port.
+    pub fn with_websocket(mut self) -> Self {
+        self.ws_url = Some(format!("ws://localhost:{}{}", self.port, super::websocket::EVENTS_PATH));
+        self
+    }
+
+    /// Marks `plugin` as a feature, so the frontend can hide views whose
+    /// backend is not loaded.
+    pub fn with_plugin(mut self, plugin: &str) -> Self {
+        self.features.insert(format!("plugin.{}", plugin), true);
+        self
+    }
+
+    /// Serves the config at [`RUNTIME_CONFIG_PATH`].
+    pub fn register(self, router: &mut Router) {
+        let body = serde_json::to_value(&self).unwrap_or_default();
+        router.get(RUNTIME_CONFIG_PATH, move |_request| {
+            ApiResponse::json(200, &body).with_header("Cache-Control", "no-store")
+        });
+    }
+}
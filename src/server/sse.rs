@@ -0,0 +1,72 @@
+//! `GET /events`: a Server-Sent Events stream of event bus events, for pages
+//! that only need to listen. Each stream gets its own thread so it doesn't
+//! occupy one of the request workers. Like `/ws/events`, it needs the session
+//! token and a page on an allowed origin, which the server checks before
+//! calling [`stream`]; see [`access`](super::access).
+
+use crate::event_bus::EventBus;
+use std::io::Write;
+use std::thread;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+/// Path of the stream, e.g. `/events?pattern=database.*,user.*`.
+pub const EVENTS_PATH: &str = "/events";
+
+/// A comment is sent this often so dead connections are noticed and proxies
+/// keep the stream open.
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Answers `request` with an event stream of the events matching `patterns`,
+/// on `bus` until the client disconnects. Events are sent without an `event:` field,
+/// so `EventSource.onmessage` sees all of them. `allow_origin` is sent as
+/// `Access-Control-Allow-Origin` for pages on other origins.
+pub fn stream(
+    request: tiny_http::Request,
+    patterns: Vec<String>,
+    allow_origin: Option<String>,
+    runtime: Option<Handle>,
+    bus: EventBus,
+) {
+    let Some(runtime) = runtime else {
+        let response = tiny_http::Response::from_string("Event stream unavailable").with_status_code(503);
+        let _ = request.respond(response);
+        return;
+    };
+    let mut events = bus.get_receiver();
+
+    thread::spawn(move || {
+        let mut writer = request.into_writer();
+        let mut head = String::from(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: close\r\n",
+        );
+        if let Some(origin) = allow_origin {
+            head.push_str(&format!("Access-Control-Allow-Origin: {}\r\nVary: Origin\r\n", origin));
+        }
+        head.push_str("\r\n: connected\n\n");
+        if writer.write_all(head.as_bytes()).and_then(|_| writer.flush()).is_err() {
+            return;
+        }
+
+        loop {
+            let message = match runtime.block_on(async { tokio::time::timeout(KEEP_ALIVE, events.recv()).await }) {
+                Ok(Ok(event)) => {
+                    if !patterns.iter().any(|pattern| bus.match_pattern(pattern, &event.name)) {
+                        continue;
+                    }
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    format!("id: {}\ndata: {}\n\n", event.id, data)
+                }
+                Ok(Err(RecvError::Lagged(skipped))) => format!("event: lagged\ndata: {{\"skipped\":{}}}\n\n", skipped),
+                Ok(Err(RecvError::Closed)) => break,
+                Err(_) => ": keep-alive\n\n".to_string(),
+            };
+            if let Err(e) = writer.write_all(message.as_bytes()).and_then(|_| writer.flush()) {
+                debug!("Event stream client went away: {}", e);
+                break;
+            }
+        }
+    });
+}
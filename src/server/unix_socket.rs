@@ -0,0 +1,34 @@
+//! Serving HTTP on a Unix domain socket (`server.unix_socket`), so only
+//! processes allowed to open the socket file can reach the server. The WebUI
+//! window can only load `http://` URLs, so the [relay](super::relay) listens
+//! on 127.0.0.1 and copies the window's connections to the socket. Anyone on
+//! the machine can connect to that port, so the socket's permissions only
+//! keep other users out of the socket itself, not out of the server.
+
+use crate::core::{AppError, AppResult};
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use tracing::debug;
+
+/// Binds the server to `path`, replacing a socket file left by a previous
+/// run. The file is made readable and writable by its owner only.
+pub fn bind(path: &Path) -> AppResult<tiny_http::Server> {
+    if is_socket(path) {
+        if UnixStream::connect(path).is_ok() {
+            return Err(AppError::HttpServer(format!("{} is in use by another process", path.display())));
+        }
+        debug!("Removing stale socket {}", path.display());
+        std::fs::remove_file(path)?;
+    }
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    let server = tiny_http::Server::http_unix(path).map_err(|e| AppError::HttpServer(e.to_string()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(server)
+}
+
+fn is_socket(path: &Path) -> bool {
+    std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket())
+}
@@ -0,0 +1,351 @@
+//! `POST /api/upload`: saves the files of a `multipart/form-data` request to
+//! `server.upload_dir`, when `server.uploads_enabled` is on. Bodies are read
+//! by the [`UploadPool`]'s threads so large uploads don't hold up other
+//! requests, with `upload.progress` events along the way and
+//! `upload.completed` or `upload.failed` at the end.
+
+use super::{log_access, Cors, HttpResponse};
+use crate::core::{ErrorCode, RpcResponse};
+use crate::event_bus::EventBus;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::runtime::Handle;
+use tracing::{error, info, warn};
+
+pub const UPLOAD_PATH: &str = "/api/upload";
+
+/// The body is read in pieces of this size.
+const READ_CHUNK: usize = 64 * 1024;
+
+/// `upload.progress` is emitted at most this often.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Uploads read at once.
+const UPLOAD_WORKERS: usize = 2;
+
+/// Uploads waiting for a worker; more are refused with 503.
+const QUEUED_UPLOADS: usize = 8;
+
+/// Where uploads go and how large a request may be.
+#[derive(Debug, Clone)]
+pub struct UploadSettings {
+    pub dir: PathBuf,
+    pub max_bytes: u64,
+}
+
+/// A file saved by an upload, as reported to the page.
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedFile {
+    /// The form field the file was sent in.
+    pub field: String,
+    /// The name it was saved under, which differs from the sent name when a
+    /// file of that name already existed.
+    pub name: String,
+    pub size: usize,
+}
+
+/// One part of a multipart body.
+#[derive(Debug, PartialEq)]
+struct Part<'a> {
+    name: String,
+    filename: Option<String>,
+    data: &'a [u8],
+}
+
+type Failure = (u16, RpcResponse);
+
+/// An upload request and the `Access-Control-Allow-Origin` to answer it with.
+type Job = (tiny_http::Request, Option<String>);
+
+/// What every upload worker needs.
+struct Shared {
+    settings: UploadSettings,
+    cors: Arc<Cors>,
+    events: Option<(Handle, EventBus)>,
+    access_log: bool,
+}
+
+/// A fixed set of threads that read and save uploads, so a burst of uploads
+/// can't start a thread each. The threads end when the pool is dropped.
+pub struct UploadPool {
+    jobs: mpsc::SyncSender<Job>,
+    shared: Arc<Shared>,
+}
+
+impl UploadPool {
+    pub fn new(settings: UploadSettings, cors: Arc<Cors>, events: Option<(Handle, EventBus)>, access_log: bool) -> Self {
+        let shared = Arc::new(Shared { settings, cors, events, access_log });
+        let (jobs, receiver) = mpsc::sync_channel::<Job>(QUEUED_UPLOADS);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..UPLOAD_WORKERS {
+            let (shared, receiver) = (Arc::clone(&shared), Arc::clone(&receiver));
+            thread::spawn(move || loop {
+                // The lock is only held while waiting, not while saving.
+                let job = receiver.lock().map_err(|_| ()).and_then(|receiver| receiver.recv().map_err(|_| ()));
+                let Ok((request, allow_origin)) = job else { break };
+                process(request, allow_origin, &shared);
+            });
+        }
+        Self { jobs, shared }
+    }
+
+    /// Queues the upload in `request`; a worker answers it. Answers 503 at
+    /// once when the queue is full.
+    pub fn handle(&self, request: tiny_http::Request, allow_origin: Option<String>) {
+        let Err(mpsc::TrySendError::Full((request, allow_origin)) | mpsc::TrySendError::Disconnected((request, allow_origin))) =
+            self.jobs.try_send((request, allow_origin))
+        else {
+            return;
+        };
+        warn!("{} uploads are waiting; turning another away", QUEUED_UPLOADS);
+        let failure = RpcResponse::localized(ErrorCode::HttpServer, "upload.busy", &[]);
+        respond(request, 503, &failure, allow_origin.as_deref(), &self.shared.cors);
+    }
+}
+
+/// Reads and saves the upload in `request` and answers it.
+fn process(mut request: tiny_http::Request, allow_origin: Option<String>, shared: &Shared) {
+    let started = Instant::now();
+    let upload_id = uuid::Uuid::new_v4().simple().to_string();
+    let emit = |name: &'static str, payload: Value| {
+        let Some((runtime, bus)) = &shared.events else { return };
+        let bus = bus.clone();
+        runtime.spawn(async move {
+            if let Err(e) = bus.emit_custom(name, payload, "upload").await {
+                error!("Failed to emit {}: {}", name, e);
+            }
+        });
+    };
+
+    let settings = &shared.settings;
+    let result = read_body(&mut request, settings, &upload_id, &emit).and_then(|(body, boundary)| {
+        let parts = parse_multipart(&body, &boundary).map_err(|e| bad_request(&e))?;
+        save_files(&parts, &settings.dir)
+    });
+    let (status, response) = match result {
+        Ok(files) => {
+            info!("Upload {} saved {} file(s) to {:?}", upload_id, files.len(), settings.dir);
+            let payload = json!({ "upload_id": upload_id, "files": files });
+            emit("upload.completed", payload.clone());
+            (200, RpcResponse::ok(payload))
+        }
+        Err((status, failure)) => {
+            let failure = failure.with_details(json!({ "upload_id": upload_id }));
+            warn!("Upload {} failed: {:?}", upload_id, failure.error.as_ref().map(|e| &e.message));
+            emit("upload.failed", json!({ "upload_id": upload_id, "error": failure.error }));
+            (status, failure)
+        }
+    };
+    let bytes = respond(request, status, &response, allow_origin.as_deref(), &shared.cors);
+    if shared.access_log {
+        log_access("POST", UPLOAD_PATH, status, bytes, started);
+    }
+}
+
+/// Answers `request` with `response` as JSON, returning the body's size.
+fn respond(request: tiny_http::Request, status: u16, response: &RpcResponse, allow_origin: Option<&str>, cors: &Cors) -> usize {
+    let mut http_response: HttpResponse = tiny_http::Response::from_data(response.to_value().to_string().into_bytes())
+        .with_status_code(status)
+        .with_header(tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap());
+    if let Some(allow_origin) = allow_origin {
+        http_response = cors.apply(http_response, allow_origin);
+    }
+    let bytes = http_response.data_length().unwrap_or(0);
+    if let Err(e) = request.respond(http_response) {
+        warn!("Failed to answer upload: {}", e);
+    }
+    bytes
+}
+
+/// Reads the whole body, emitting `upload.progress` as it arrives. Returns
+/// the body and the multipart boundary.
+fn read_body(
+    request: &mut tiny_http::Request,
+    settings: &UploadSettings,
+    upload_id: &str,
+    emit: &impl Fn(&'static str, Value),
+) -> Result<(Vec<u8>, String), Failure> {
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .map(|h| h.value.as_str().to_string())
+        .unwrap_or_default();
+    let Some(boundary) = multipart_boundary(&content_type) else {
+        return Err(bad_request("expected a multipart/form-data body"));
+    };
+    let total = request.body_length().map(|len| len as u64);
+    if total.is_some_and(|total| total > settings.max_bytes) {
+        return Err(too_large(settings.max_bytes));
+    }
+
+    let mut body = Vec::with_capacity(total.unwrap_or(0) as usize);
+    let mut chunk = vec![0u8; READ_CHUNK];
+    let mut last_progress = Instant::now();
+    let reader = request.as_reader();
+    loop {
+        let read = reader.read(&mut chunk).map_err(|e| bad_request(&format!("reading the body failed: {}", e)))?;
+        if read == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..read]);
+        if body.len() as u64 > settings.max_bytes {
+            return Err(too_large(settings.max_bytes));
+        }
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            emit("upload.progress", progress(upload_id, body.len() as u64, total));
+        }
+    }
+    emit("upload.progress", progress(upload_id, body.len() as u64, total));
+    Ok((body, boundary))
+}
+
+fn progress(upload_id: &str, received: u64, total: Option<u64>) -> Value {
+    let percent = total.filter(|total| *total > 0).map(|total| (received * 100 / total).min(100));
+    json!({ "upload_id": upload_id, "received": received, "total": total, "percent": percent })
+}
+
+/// The boundary from a `multipart/form-data; boundary=...` content type.
+fn multipart_boundary(content_type: &str) -> Option<String> {
+    let mut params = content_type.split(';').map(str::trim);
+    if !params.next()?.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params
+        .find_map(|param| param.strip_prefix("boundary="))
+        .map(|boundary| boundary.trim_matches('"').to_string())
+        .filter(|boundary| !boundary.is_empty())
+}
+
+/// Splits a multipart body into its parts.
+fn parse_multipart<'a>(body: &'a [u8], boundary: &str) -> Result<Vec<Part<'a>>, String> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut parts = Vec::new();
+    let Some(start) = find(body, &delimiter) else {
+        return Err("the body does not contain the multipart boundary".to_string());
+    };
+    let mut rest = &body[start + delimiter.len()..];
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        rest = rest.strip_prefix(b"\r\n").ok_or("malformed multipart delimiter")?;
+        let header_end = find(rest, b"\r\n\r\n").ok_or("multipart part without headers")?;
+        let headers = String::from_utf8_lossy(&rest[..header_end]);
+        let content = &rest[header_end + 4..];
+        let end = find(content, &[b"\r\n".as_slice(), &delimiter].concat()).ok_or("multipart body is truncated")?;
+
+        let disposition = headers
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.trim().eq_ignore_ascii_case("Content-Disposition").then(|| value.to_string())
+            })
+            .unwrap_or_default();
+        parts.push(Part {
+            name: disposition_param(&disposition, "name").unwrap_or_default(),
+            filename: disposition_param(&disposition, "filename"),
+            data: &content[..end],
+        });
+        rest = &content[end + 2 + delimiter.len()..];
+    }
+}
+
+/// A quoted parameter of a `Content-Disposition` header, e.g. `filename`.
+fn disposition_param(disposition: &str, key: &str) -> Option<String> {
+    disposition.split(';').map(str::trim).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        (name.trim() == key).then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Writes each part that has a file name to `dir`, never overwriting.
+fn save_files(parts: &[Part], dir: &Path) -> Result<Vec<SavedFile>, Failure> {
+    let internal = |e: std::io::Error| (500, RpcResponse::from_error(&e.into()));
+    fs::create_dir_all(dir).map_err(internal)?;
+    let mut saved = Vec::new();
+    for part in parts {
+        let Some(filename) = part.filename.as_deref() else { continue };
+        let Some(filename) = safe_file_name(filename) else {
+            return Err(bad_request(&format!("\"{}\" is not a usable file name", filename)));
+        };
+        let path = unused_path(dir, &filename);
+        fs::write(&path, part.data).map_err(internal)?;
+        saved.push(SavedFile {
+            field: part.name.clone(),
+            name: path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or(filename),
+            size: part.data.len(),
+        });
+    }
+    if saved.is_empty() {
+        return Err(bad_request("the request contains no files"));
+    }
+    Ok(saved)
+}
+
+/// The last path component of a sent file name, without characters that are
+/// unsafe on common filesystems.
+fn safe_file_name(filename: &str) -> Option<String> {
+    let base = filename.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && !matches!(c, ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect();
+    let cleaned = cleaned.trim().to_string();
+    (!cleaned.is_empty() && cleaned.chars().any(|c| c != '.')).then_some(cleaned)
+}
+
+/// `dir/name`, or `dir/stem-1.ext`, `dir/stem-2.ext`, ... if that exists.
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{}-{}{}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .unwrap_or(path)
+}
+
+fn bad_request(reason: &str) -> Failure {
+    (400, RpcResponse::localized(ErrorCode::InvalidArgument, "validation.invalid_upload", &[("reason", reason)]))
+}
+
+fn too_large(max_bytes: u64) -> Failure {
+    let limit = format!("{} MB", max_bytes / (1024 * 1024));
+    (413, RpcResponse::localized(ErrorCode::InvalidArgument, "validation.upload_too_large", &[("limit", &limit)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multipart_bodies_are_split_into_parts() {
+        let body = b"--XyZ\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nhello\r\n\
+--XyZ\r\nContent-Disposition: form-data; name=\"file\"; filename=\"../a b.txt\"\r\nContent-Type: text/plain\r\n\r\n\
+line 1\r\nline 2\r\n--XyZ--\r\n";
+        let boundary = multipart_boundary("multipart/form-data; boundary=XyZ").unwrap();
+        let parts = parse_multipart(body, &boundary).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0], Part { name: "note".to_string(), filename: None, data: b"hello" });
+        assert_eq!(parts[1].filename.as_deref(), Some("../a b.txt"));
+        assert_eq!(parts[1].data, b"line 1\r\nline 2");
+        assert_eq!(safe_file_name("../a b.txt").as_deref(), Some("a b.txt"));
+        assert_eq!(safe_file_name(".."), None);
+    }
+}
@@ -0,0 +1,279 @@
+//! `/ws/events`: streams event bus events to the page and takes commands
+//! back, over tokio-tungstenite. The [relay](super::relay) picks upgrade
+//! requests for the path out before they reach tiny_http, so the endpoint
+//! shares the HTTP server's port and is published in `/runtime-config.json`
+//! as `ws_url`.
+//!
+//! Browsers let any site open a WebSocket to localhost, so only pages served
+//! from localhost or listed in `server.cors.allowed_origins` are accepted.
+//! Clients on other machines must send such an origin too. Every client must
+//! pass the session token as the `token` query parameter, see
+//! [`access`](super::access).
+
+use super::access::{self, AccessGate};
+use crate::app_state::AppState;
+use crate::core::{ErrorCode, RpcResponse};
+use futures::{Sink, SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::error::ProtocolError;
+use tokio_tungstenite::tungstenite::handshake::server::{Callback, ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+use tokio_tungstenite::tungstenite::{http, Error as WsError, Message};
+use tracing::{debug, error, warn};
+
+/// Path clients connect to, e.g. `ws://localhost:8080/ws/events?pattern=user.*`.
+pub const EVENTS_PATH: &str = "/ws/events";
+
+/// Larger messages close the connection.
+const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+/// The only events `emit` accepts are named under this prefix, so clients
+/// can't pass off events as the app's own, such as `app.shutdown`.
+pub const CLIENT_EVENT_PREFIX: &str = "client.";
+
+/// Serves `/ws/events` connections handed over by the relay.
+#[derive(Clone)]
+pub struct WebSocketEndpoint {
+    gate: AccessGate,
+    state: AppState,
+}
+
+impl WebSocketEndpoint {
+    pub fn new(gate: AccessGate, state: AppState) -> Self {
+        Self { gate, state }
+    }
+
+    /// Completes the handshake on `stream` and serves it until the client
+    /// leaves.
+    pub async fn serve(&self, stream: TcpStream, peer: SocketAddr) {
+        debug!("WebSocket client connected from {}", peer);
+        if let Err(e) = handle_connection(stream, peer, &self.gate, &self.state).await {
+            debug!("WebSocket client {} dropped: {}", peer, e);
+            if let Some(manager) = self.state.websocket_manager() {
+                manager.record_error(&e.to_string());
+            }
+        }
+        debug!("WebSocket client {} disconnected", peer);
+    }
+}
+
+/// Whether a connection whose first bytes are `start` asks for
+/// [`EVENTS_PATH`]; `None` until enough has arrived to tell.
+pub fn is_upgrade_request(start: &[u8]) -> Option<bool> {
+    let prefix = format!("GET {}", EVENTS_PATH);
+    let prefix = prefix.as_bytes();
+    let known = start.len().min(prefix.len());
+    if start[..known] != prefix[..known] {
+        return Some(false);
+    }
+    match start.get(prefix.len()) {
+        None => None,
+        Some(b' ' | b'?') => Some(true),
+        Some(_) => Some(false),
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    peer: SocketAddr,
+    gate: &AccessGate,
+    state: &AppState,
+) -> Result<(), WsError> {
+    let mut query = String::new();
+    let check = Handshake { peer, gate, query: &mut query };
+    let config = WebSocketConfig::default()
+        .max_message_size(Some(MAX_MESSAGE_LEN))
+        .max_frame_size(Some(MAX_MESSAGE_LEN));
+    let socket = tokio_tungstenite::accept_hdr_async_with_config(stream, check, Some(config)).await?;
+
+    let mut patterns = super::event_patterns(&super::router::parse_query(&query));
+    let mut events = state.bus().get_receiver();
+    let (mut sink, mut messages) = socket.split();
+    let result = loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if patterns.iter().any(|pattern| state.bus().match_pattern(pattern, &event.name)) {
+                        send_json(&mut sink, state, &json!({ "type": "event", "event": event })).await?;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    send_json(&mut sink, state, &json!({ "type": "lagged", "skipped": skipped })).await?;
+                }
+                Err(broadcast::error::RecvError::Closed) => break Ok(()),
+            },
+            // tungstenite answers pings and close frames itself.
+            message = messages.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    if let Some(manager) = state.websocket_manager() {
+                        manager.increment_message_received(text.len());
+                    }
+                    let reply = handle_command(text.as_bytes(), &mut patterns, state).await;
+                    send_json(&mut sink, state, &reply).await?;
+                }
+                Some(Ok(Message::Binary(_))) => {
+                    let reply = json!({ "type": "reply", "id": null, "response": invalid_command() });
+                    send_json(&mut sink, state, &reply).await?;
+                }
+                Some(Ok(Message::Close(_))) | None => break Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break Err(e),
+            },
+        }
+    };
+    match result {
+        // Clients that go away without a close frame are not errors.
+        Err(WsError::ConnectionClosed | WsError::AlreadyClosed | WsError::Protocol(ProtocolError::ResetWithoutClosingHandshake)) => {
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Checks the upgrade request's path, origin and token, keeping its query.
+struct Handshake<'a> {
+    peer: SocketAddr,
+    gate: &'a AccessGate,
+    query: &'a mut String,
+}
+
+impl Handshake<'_> {
+    /// The request's query, or the status to refuse it with.
+    fn admit(&self, request: &Request) -> Result<String, http::StatusCode> {
+        if request.uri().path() != EVENTS_PATH {
+            return Err(http::StatusCode::NOT_FOUND);
+        }
+        let origin = request.headers().get(http::header::ORIGIN).and_then(|origin| origin.to_str().ok());
+        match origin {
+            Some(origin) if !self.gate.allows_origin(origin) => {
+                warn!("Refused WebSocket connection from page at {}", origin);
+                return Err(http::StatusCode::FORBIDDEN);
+            }
+            None if !self.peer.ip().is_loopback() => {
+                warn!("Refused WebSocket connection without an origin from {}", self.peer);
+                return Err(http::StatusCode::FORBIDDEN);
+            }
+            _ => {}
+        }
+        let query = request.uri().query().unwrap_or_default().to_string();
+        let token = request.headers().get(access::TOKEN_HEADER).and_then(|token| token.to_str().ok());
+        if !self.gate.admits(token, &super::router::parse_query(&query)) {
+            warn!("Refused WebSocket connection without the session token from {}", self.peer);
+            return Err(http::StatusCode::UNAUTHORIZED);
+        }
+        Ok(query)
+    }
+}
+
+// The error type is tungstenite's; only the status is set, see `admit`.
+impl Callback for Handshake<'_> {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        match self.admit(request) {
+            Ok(query) => {
+                *self.query = query;
+                Ok(response)
+            }
+            Err(status) => {
+                let mut refusal = ErrorResponse::new(None);
+                *refusal.status_mut() = status;
+                Err(refusal)
+            }
+        }
+    }
+}
+
+/// Runs a command sent as `{"id", "command", ...}` and returns the reply,
+/// which echoes `id` and wraps the result in an [`RpcResponse`]:
+/// - `subscribe` / `unsubscribe` with `pattern`: changes the events sent.
+/// - `emit` with `name` and `payload`: emits a custom event named under
+///   [`CLIENT_EVENT_PREFIX`].
+/// - `ping`: answers with the run's uptime.
+async fn handle_command(payload: &[u8], patterns: &mut Vec<String>, state: &AppState) -> Value {
+    let Ok(Value::Object(command)) = serde_json::from_slice::<Value>(payload) else {
+        return json!({ "type": "reply", "id": null, "response": invalid_command() });
+    };
+    let id = command.get("id").cloned().unwrap_or(Value::Null);
+    let name = command.get("command").and_then(Value::as_str).unwrap_or_default();
+    let text = |key: &str| command.get(key).and_then(Value::as_str).filter(|text| !text.is_empty());
+
+    let response = match (name, text("pattern"), text("name")) {
+        ("subscribe", Some(pattern), _) => {
+            if !patterns.iter().any(|existing| existing == pattern) {
+                patterns.push(pattern.to_string());
+            }
+            RpcResponse::ok(json!({ "patterns": patterns }))
+        }
+        ("unsubscribe", Some(pattern), _) => {
+            patterns.retain(|existing| existing != pattern);
+            RpcResponse::ok(json!({ "patterns": patterns }))
+        }
+        ("emit", _, Some(event_name)) if !event_name.starts_with(CLIENT_EVENT_PREFIX) => RpcResponse::localized(
+            ErrorCode::InvalidArgument,
+            "validation.reserved_event",
+            &[("event", event_name), ("prefix", CLIENT_EVENT_PREFIX)],
+        ),
+        ("emit", _, Some(event_name)) => {
+            let event_payload = command.get("payload").cloned().unwrap_or(Value::Null);
+            match state.bus().emit_custom(event_name, event_payload, "websocket").await {
+                Ok(()) => RpcResponse::ok(json!({ "emitted": event_name })),
+                Err(e) => {
+                    error!("Failed to emit {} from WebSocket: {}", event_name, e);
+                    RpcResponse::error(ErrorCode::EventBus, e.to_string())
+                }
+            }
+        }
+        ("ping", _, _) => RpcResponse::ok(json!({ "uptime_secs": crate::core::uptime().as_secs() })),
+        _ => RpcResponse::localized(ErrorCode::InvalidArgument, "validation.unknown_command", &[("command", name)]),
+    };
+    json!({ "type": "reply", "id": id, "response": response })
+}
+
+fn invalid_command() -> RpcResponse {
+    RpcResponse::localized(ErrorCode::InvalidArgument, "validation.expected_json", &[])
+}
+
+async fn send_json<S>(sink: &mut S, state: &AppState, message: &Value) -> Result<(), WsError>
+where
+    S: Sink<Message, Error = WsError> + Unpin,
+{
+    let text = message.to_string();
+    let len = text.len();
+    sink.send(Message::text(text)).await?;
+    if let Some(manager) = state.websocket_manager() {
+        manager.increment_message_sent(len);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrade_requests_are_told_apart_from_their_first_bytes() {
+        assert_eq!(is_upgrade_request(b"GET /ws/events HTTP/1.1\r\n"), Some(true));
+        assert_eq!(is_upgrade_request(b"GET /ws/events?pattern=user.*"), Some(true));
+        assert_eq!(is_upgrade_request(b"GET /ws/ev"), None);
+        assert_eq!(is_upgrade_request(b"GET /ws/eventsource"), Some(false));
+        assert_eq!(is_upgrade_request(b"POST /api"), Some(false));
+        assert_eq!(is_upgrade_request(b"GET / HTTP/1.1"), Some(false));
+    }
+
+    #[tokio::test]
+    async fn clients_only_emit_client_events() {
+        let app = crate::testing::TestApp::new();
+        let mut patterns = Vec::new();
+        let emit = |name: &str| json!({ "id": 1, "command": "emit", "name": name, "payload": {} }).to_string();
+
+        let reply = handle_command(emit("app.shutdown").as_bytes(), &mut patterns, &app.state).await;
+        assert_eq!(reply["response"]["error"]["code"], "invalid_argument");
+        let reply = handle_command(emit("client.ping").as_bytes(), &mut patterns, &app.state).await;
+        assert_eq!(reply["response"]["ok"], true);
+
+        let names: Vec<String> = app.state.bus().get_event_history(None).into_iter().map(|event| event.name).collect();
+        assert_eq!(names, ["client.ping"]);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::core::database::audit;
+use crate::core::{perf, AppResult, Database};
+use rusqlite::OptionalExtension;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+const COUNTER_NAME: &str = "default";
+
+/// Single source of truth for the click counter, shared by the counter
+/// plugin and viewmodel through [`crate::app_state::AppState`]. Values are persisted to `counters`, and
+/// audited, when a database is attached.
+pub struct CounterService {
+    value: Mutex<i32>,
+    db: Option<Arc<Database>>,
+}
+
+impl CounterService {
+    pub fn in_memory() -> Self {
+        Self { value: Mutex::new(0), db: None }
+    }
+
+    /// Creates a service backed by the database, restoring the last value.
+    pub fn with_database(db: Arc<Database>) -> AppResult<Self> {
+        let value = {
+            let _timer = perf::query("counter.load");
+            let conn = db.get_connection()?;
+            conn.query_row("SELECT value FROM counters WHERE name = ?1", [COUNTER_NAME], |row| row.get(0))
+                .optional()?
+                .unwrap_or(0)
+        };
+        Ok(Self { value: Mutex::new(value), db: Some(db) })
+    }
+
+    pub fn get(&self) -> i32 {
+        *self.value.lock().unwrap()
+    }
+
+    pub fn increment(&self) -> i32 {
+        let mut counter = self.value.lock().unwrap();
+        *counter += 1;
+        let value = *counter;
+        self.persist(value);
+        tracing::info!("Counter incremented to: {}", value);
+        value
+    }
+
+    pub fn reset(&self) -> i32 {
+        let mut counter = self.value.lock().unwrap();
+        *counter = 0;
+        self.persist(0);
+        tracing::info!("Counter reset to 0");
+        0
+    }
+
+    fn persist(&self, value: i32) {
+        let Some(db) = &self.db else { return };
+        let _timer = perf::query("counter.save");
+        let result = db.transaction(|tx| {
+            let before: Option<i32> = tx
+                .query_row("SELECT value FROM counters WHERE name = ?1", [COUNTER_NAME], |row| row.get(0))
+                .optional()?;
+            tx.execute(
+                "INSERT INTO counters (name, value) VALUES (?1, ?2)
+                 ON CONFLICT(name) DO UPDATE SET value = excluded.value",
+                rusqlite::params![COUNTER_NAME, value],
+            )?;
+            let operation = if before.is_some() { audit::Operation::Update } else { audit::Operation::Insert };
+            let before = before.map(|before| json!({ "name": COUNTER_NAME, "value": before }));
+            let after = json!({ "name": COUNTER_NAME, "value": value });
+            audit::record(tx, "system", operation, "counters", COUNTER_NAME, before, Some(after))
+        });
+        if let Err(e) = result {
+            tracing::error!("Failed to persist counter value: {}", e);
+        }
+    }
+}
+
+impl Default for CounterService {
+    fn default() -> Self {
+        Self::in_memory()
+    }
+}
+
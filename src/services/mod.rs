@@ -0,0 +1,4 @@
+pub mod counter;
+pub mod user_import;
+
+pub use counter::CounterService;
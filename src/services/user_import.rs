@@ -0,0 +1,132 @@
+//! Bulk user import from CSV. The file needs a header row with `name`,
+//! `email` and `role` columns, in any order; other columns are ignored.
+
+use crate::core::database::{Repository, UserRepository};
+use crate::core::{AppError, AppResult};
+use crate::models::{UserInput, USER_ROLES};
+use rusqlite::Transaction;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::Read;
+
+/// Row errors reported in the summary; later ones are only counted.
+const MAX_REPORTED_ERRORS: usize = 100;
+
+/// Payload of `database.users_imported`.
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    /// Rows whose email already belongs to a user, or to an earlier row.
+    pub skipped: usize,
+    /// Rows that failed validation.
+    pub failed: usize,
+    pub errors: Vec<RowError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RowError {
+    /// Line in the file, counting the header as line 1.
+    pub line: u64,
+    pub reason: String,
+}
+
+/// Validates every row of `input` and inserts the good ones within `tx`, so
+/// nothing is kept if the database fails part way. Run it inside
+/// [`Database::transaction`](crate::core::Database::transaction).
+pub fn import_csv(tx: &Transaction, input: impl Read) -> AppResult<ImportSummary> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).flexible(true).from_reader(input);
+    let headers = reader.headers().map_err(|e| AppError::Runtime(format!("Unreadable CSV header: {}", e)))?;
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name))
+            .ok_or_else(|| AppError::Runtime(format!("The CSV header has no \"{}\" column", name)))
+    };
+    let (name_at, email_at, role_at) = (column("name")?, column("email")?, column("role")?);
+
+    let mut summary = ImportSummary::default();
+    let mut seen = HashSet::new();
+    let users = UserRepository::new(tx).acting_as("import");
+    for record in reader.records() {
+        let position = match &record {
+            Ok(record) => record.position(),
+            Err(e) => e.position(),
+        };
+        let line = position.map_or(0, |position| position.line());
+        let row = record.map_err(|e| e.to_string()).and_then(|record| {
+            let field = |at: usize| record.get(at).unwrap_or("").to_string();
+            validate(field(name_at), field(email_at), field(role_at))
+        });
+        let user = match row {
+            Ok(row) => row,
+            Err(reason) => {
+                summary.failed += 1;
+                if summary.errors.len() < MAX_REPORTED_ERRORS {
+                    summary.errors.push(RowError { line, reason });
+                }
+                continue;
+            }
+        };
+        if !seen.insert(user.email.to_lowercase()) || users.email_taken(&user.email)? {
+            summary.skipped += 1;
+            continue;
+        }
+        users.insert(&user)?;
+        summary.inserted += 1;
+    }
+    Ok(summary)
+}
+
+/// Checks a user's fields, returning why they are rejected.
+pub(crate) fn validate(name: String, email: String, role: String) -> Result<UserInput, String> {
+    if name.is_empty() {
+        return Err("name is empty".to_string());
+    }
+    if !is_email(&email) {
+        return Err(format!("\"{}\" is not an email address", email));
+    }
+    let role = role.to_lowercase();
+    if !USER_ROLES.contains(&role.as_str()) {
+        return Err(format!("unknown role \"{}\" (expected one of {})", role, USER_ROLES.join(", ")));
+    }
+    Ok(UserInput { name, email, role })
+}
+
+/// `local@domain.tld` without spaces.
+fn is_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else { return false };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !email.chars().any(char::is_whitespace)
+        && domain.split('.').count() >= 2
+        && domain.split('.').all(|part| !part.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::memory_db;
+
+    #[test]
+    fn rows_are_inserted_skipped_or_reported() {
+        let db = memory_db();
+        db.insert_sample_data().unwrap();
+        let csv = "Email,Name,Role,Notes\n\
+                   new@example.com,New Person,Editor,first\n\
+                   john@example.com,John Again,user,\n\
+                   NEW@example.com,Same Email,user,\n\
+                   bad-email,Nobody,user,\n\
+                   ok@example.com,Wrong Role,owner,\n";
+        let summary = db.transaction(|tx| import_csv(tx, csv.as_bytes())).unwrap();
+        assert_eq!((summary.inserted, summary.skipped, summary.failed), (1, 2, 2));
+        assert_eq!(summary.errors[0].line, 5);
+        assert!(summary.errors[1].reason.contains("owner"));
+
+        let role: String = db
+            .get_connection()
+            .unwrap()
+            .query_row("SELECT role FROM users WHERE email = 'new@example.com'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(role, "editor");
+    }
+}
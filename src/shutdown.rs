@@ -0,0 +1,187 @@
+//! Coordinated shutdown. Ctrl-C, SIGTERM, closing the window and
+//! [`request_shutdown`] all end in [`Shutdown::run`], which stops the app's
+//! subsystems in order before the process exits.
+
+use crate::app_state::AppState;
+use crate::bridge;
+use crate::core::{analytics, log_sink, log_store, supervisor, telemetry, Database};
+use crate::event_bus::EventBus;
+use crate::plugins::PluginRegistry;
+use crate::server::HttpServerHandle;
+use crate::websocket_manager::WebSocketHandle;
+use once_cell::sync::Lazy;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// Longest wait for event listeners and each log destination to drain.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+static REQUESTED: Lazy<watch::Sender<Option<&'static str>>> = Lazy::new(|| watch::channel(None).0);
+
+/// Asks the app to exit and closes the window so `webui::wait()` returns.
+/// Only the first request's reason is kept.
+pub fn request_shutdown(reason: &'static str) {
+    let first = REQUESTED.send_if_modified(|current| {
+        if current.is_some() {
+            return false;
+        }
+        *current = Some(reason);
+        true
+    });
+    if first {
+        info!("Shutdown requested ({})", reason);
+        bridge::exit_webui();
+    }
+}
+
+/// The reason given to [`request_shutdown`], if it was called.
+pub fn requested_reason() -> Option<&'static str> {
+    *REQUESTED.borrow()
+}
+
+/// Resolves with the reason once a shutdown is requested.
+pub async fn requested() -> &'static str {
+    let mut receiver = REQUESTED.subscribe();
+    match receiver.wait_for(Option::is_some).await {
+        Ok(reason) => reason.unwrap_or_default(),
+        Err(_) => std::future::pending().await,
+    }
+}
+
+/// Requests a shutdown on Ctrl-C or SIGTERM. A second Ctrl-C exits at once.
+pub fn listen_for_signals() {
+    tokio::spawn(async {
+        loop {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                error!("Failed to listen for Ctrl-C: {}", e);
+                return;
+            }
+            if requested_reason().is_some() {
+                warn!("Second Ctrl-C received, exiting without cleanup");
+                std::process::exit(130);
+            }
+            request_shutdown("ctrl_c");
+        }
+    });
+
+    #[cfg(unix)]
+    tokio::spawn(async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                if terminate.recv().await.is_some() {
+                    request_shutdown("sigterm");
+                }
+            }
+            Err(e) => error!("Failed to listen for SIGTERM: {}", e),
+        }
+    });
+}
+
+/// The subsystems to stop, registered as they are started.
+#[derive(Default)]
+pub struct Shutdown {
+    http_server: Option<HttpServerHandle>,
+    websocket: Option<WebSocketHandle>,
+    registry: Option<Arc<PluginRegistry>>,
+    database: Option<Arc<Database>>,
+    session: Option<String>,
+    bus: Option<EventBus>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_http_server(mut self, server: HttpServerHandle) -> Self {
+        self.http_server = Some(server);
+        self
+    }
+
+    /// Takes the WebSocket handle, database and event bus from `state`.
+    pub fn with_state(mut self, state: &AppState) -> Self {
+        self.websocket = state.take_websocket();
+        self.database = Some(Arc::clone(state.db()));
+        self.bus = Some(state.bus().clone());
+        self
+    }
+
+    /// The run's session, deleted once the HTTP server is stopped.
+    pub fn with_session(mut self, id: String) -> Self {
+        self.session = Some(id);
+        self
+    }
+
+    pub fn with_registry(mut self, registry: Arc<PluginRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Emits `app.shutdown`, then stops the HTTP server, background tasks,
+    /// WebSocket monitoring and plugins, waits for event listeners, writes queued log
+    /// records and usage counts, deletes the run's session, checkpoints the database
+    /// and flushes every log destination.
+    pub async fn run(self, reason: &str) {
+        let started = Instant::now();
+        info!("Shutting down ({})", reason);
+        if let Some(bus) = &self.bus
+            && let Err(e) = bus.emit_custom("app.shutdown", json!({ "reason": reason }), "shutdown").await
+        {
+            error!("Failed to emit shutdown event: {}", e);
+        }
+
+        if let Some(server) = self.http_server {
+            let stopped = tokio::task::spawn_blocking(move || server.stop()).await;
+            if stopped.is_err() {
+                warn!("Failed to stop HTTP server");
+            }
+        }
+        supervisor::stop_all();
+        if let Some(websocket) = self.websocket {
+            websocket.stop();
+        }
+        if let Some(registry) = &self.registry {
+            registry.shutdown_all();
+        }
+
+        if let Some(bus) = &self.bus {
+            let pending = bus.drain(DRAIN_TIMEOUT).await;
+            if pending > 0 {
+                warn!("{} event listener(s) still running after {:?}", pending, DRAIN_TIMEOUT);
+            }
+        }
+        if !log_store::flush(DRAIN_TIMEOUT).await {
+            warn!("Stored log records not written within {:?}", DRAIN_TIMEOUT);
+        }
+        if let Some(database) = self.database {
+            let session = self.session;
+            let written = tokio::task::spawn_blocking(move || {
+                if let Err(e) = analytics::flush(&database) {
+                    warn!("Failed to queue usage counts: {}", e);
+                }
+                if let Some(Err(e)) = session.map(|id| database.delete_session(&id)) {
+                    warn!("Failed to delete the run's session: {}", e);
+                }
+                match database.checkpoint() {
+                    Ok(()) => info!("Database checkpointed"),
+                    Err(e) => error!("Failed to checkpoint database: {}", e),
+                }
+            })
+            .await;
+            if written.is_err() {
+                warn!("Failed to write usage counts and checkpoint the database");
+            }
+        }
+
+        info!("Shutdown complete in {}ms", started.elapsed().as_millis());
+        let flushed = tokio::task::spawn_blocking(|| log_sink::flush(DRAIN_TIMEOUT)).await;
+        if !flushed.unwrap_or(false) {
+            eprintln!("Log sinks not flushed within {:?}", DRAIN_TIMEOUT);
+        }
+        telemetry::shutdown();
+    }
+}
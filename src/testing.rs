@@ -0,0 +1,250 @@
+//! Runs viewmodel handlers without a browser. [`TestApp`] attaches an
+//! [`AppState`] with an in-memory database and a private event bus to a
+//! [`MockWindow`]; tests call handlers by name and wait for the events they
+//! emit. Handlers that run JavaScript can't be observed this way.
+
+use crate::app_state::{AppState, Attachment};
+use crate::bridge::BindSurface;
+use crate::core::{AppConfig, Database};
+use crate::event_bus::{Event, EventBus};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use webui_rs::webui;
+
+/// Mock window IDs start here, well clear of the IDs webui hands out.
+const FIRST_MOCK_ID: usize = 1 << 20;
+
+/// How long [`TestApp::wait_for`] waits before failing the test.
+const EVENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(FIRST_MOCK_ID);
+static NEXT_EVENT: AtomicUsize = AtomicUsize::new(1);
+/// Arguments of calls in progress, by window ID and event number.
+type PendingArgs = HashMap<(usize, usize), Vec<String>>;
+static ARGS: Lazy<Mutex<PendingArgs>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Responses set by handlers of calls in progress.
+static RESPONSES: Lazy<Mutex<HashMap<(usize, usize), String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Keeps the response to a call made through [`MockWindow::call`]; `false`
+/// if `event` came from a real window.
+pub fn mock_respond(event: &webui::Event, response: &str) -> bool {
+    if !ARGS.lock().unwrap().contains_key(&(event.window, event.event_number)) {
+        return false;
+    }
+    RESPONSES.lock().unwrap().insert((event.window, event.event_number), response.to_string());
+    true
+}
+
+/// Argument `index` of a call made through [`MockWindow::call`], or `None`
+/// if `event` came from a real window.
+pub fn mock_arg(event: &webui::Event, index: usize) -> Option<Option<String>> {
+    let args = ARGS.lock().unwrap();
+    let args = args.get(&(event.window, event.event_number))?;
+    Some(args.get(index).filter(|arg| !arg.is_empty()).cloned())
+}
+
+struct Binding {
+    /// Passed as the event's element name.
+    element: CString,
+    handler: fn(webui::Event),
+}
+
+/// Records the handlers a viewmodel binds, so tests can call them.
+pub struct MockWindow {
+    id: usize,
+    handlers: HashMap<String, Binding>,
+}
+
+impl MockWindow {
+    pub fn new() -> Self {
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Runs the handler bound to `name` as if the page called
+    /// `webui.call(name, ...args)`. Strings are passed as they are and other
+    /// values as JSON. Returns what the handler responded with, if anything.
+    /// Panics if nothing is bound to `name`.
+    pub fn call(&self, name: &str, args: &[Value]) -> Option<Value> {
+        let Some(binding) = self.handlers.get(name) else {
+            panic!("No handler bound to {}", name);
+        };
+        let event_number = NEXT_EVENT.fetch_add(1, Ordering::Relaxed);
+        let args = args
+            .iter()
+            .map(|arg| match arg {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            })
+            .collect();
+        ARGS.lock().unwrap().insert((self.id, event_number), args);
+        (binding.handler)(webui::Event {
+            window: self.id,
+            event_type: 0,
+            element: binding.element.as_ptr() as *mut _,
+            event_number,
+            bind_id: 0,
+        });
+        ARGS.lock().unwrap().remove(&(self.id, event_number));
+        let response = RESPONSES.lock().unwrap().remove(&(self.id, event_number))?;
+        Some(serde_json::from_str(&response).unwrap_or(Value::String(response)))
+    }
+}
+
+impl BindSurface for MockWindow {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn bind(&mut self, name: &str, handler: fn(webui::Event)) {
+        let element = CString::new(name).expect("handler names have no NUL bytes");
+        self.handlers.insert(name.to_string(), Binding { element, handler });
+    }
+}
+
+/// An in-memory database with the schema created, for tests that need one.
+pub fn memory_db() -> Database {
+    let db = Database::open(":memory:", 1, None).expect("in-memory database opens");
+    db.init().expect("schema is created");
+    db
+}
+
+/// A mock window with its own state: the default config, an initialized
+/// in-memory database and an event bus no other test sees.
+pub struct TestApp {
+    pub window: MockWindow,
+    pub state: AppState,
+    _attachment: Attachment,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        let state = AppState::new(Arc::new(memory_db()), AppConfig::default(), EventBus::new());
+        let window = MockWindow::new();
+        let attachment = state.attach(&window);
+        Self { window, state, _attachment: attachment }
+    }
+
+    /// Binds a viewmodel's handlers, e.g. `setup_counter_viewmodel`.
+    pub fn with_viewmodel(mut self, setup: fn(&mut MockWindow)) -> Self {
+        setup(&mut self.window);
+        self
+    }
+
+    pub fn call(&self, name: &str, args: &[Value]) -> Option<Value> {
+        self.window.call(name, args)
+    }
+
+    /// The first event named `name` on this app's bus, waiting for handlers
+    /// that emit from spawned tasks. Panics after two seconds, listing the
+    /// events that were seen.
+    pub async fn wait_for(&self, name: &str) -> Event {
+        let deadline = Instant::now() + EVENT_TIMEOUT;
+        loop {
+            let history = self.state.bus().get_event_history(None);
+            if let Some(event) = history.iter().find(|event| event.name == name) {
+                return event.clone();
+            }
+            if Instant::now() >= deadline {
+                let seen: Vec<&str> = history.iter().map(|event| event.name.as_str()).collect();
+                panic!("No {} event within {:?}; saw {:?}", name, EVENT_TIMEOUT, seen);
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
+
+/// The payload of a custom event, or `Value::Null` for built-in event types.
+pub fn payload(event: &Event) -> Value {
+    match &event.event_type {
+        crate::event_bus::EventType::Custom { payload, .. } => payload.clone(),
+        _ => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_bus::EventType;
+    use crate::viewmodels::{setup_analytics_viewmodel, setup_counter_viewmodel, setup_user_viewmodel};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn counter_handlers_emit_the_new_value() {
+        let app = TestApp::new().with_viewmodel(setup_counter_viewmodel);
+        app.call("increment_counter", &[]);
+
+        let changed = app.wait_for("counter.value_changed").await;
+        assert!(matches!(changed.event_type, EventType::CounterValueChanged { value: 1 }));
+        assert_eq!(app.state.counter().get(), 1);
+    }
+
+    #[tokio::test]
+    async fn analytics_consent_rejects_non_boolean_arguments() {
+        let app = TestApp::new().with_viewmodel(setup_analytics_viewmodel);
+        app.call("set_analytics_consent", &[json!("yes")]);
+
+        let failure = payload(&app.wait_for("analytics.consent_failed").await);
+        assert_eq!(failure["ok"], false);
+        assert_eq!(failure["error"]["code"], "invalid_argument");
+    }
+
+    #[tokio::test]
+    async fn analytics_consent_is_stored() {
+        let app = TestApp::new().with_viewmodel(setup_analytics_viewmodel);
+        app.call("set_analytics_consent", &[json!(true)]);
+
+        let status = payload(&app.wait_for("analytics.consent_changed").await);
+        assert_eq!(status["consent"], "granted");
+        assert_eq!(status["queued"], 0);
+    }
+
+    #[tokio::test]
+    async fn users_are_read_from_the_test_database() {
+        let app = TestApp::new().with_viewmodel(setup_user_viewmodel);
+        app.state.db().insert_sample_data().unwrap();
+        let response = app.call("get_users", &[]).unwrap();
+        assert_eq!(response["ok"], true);
+
+        let fetched = app.wait_for("database.users_fetched").await;
+        let EventType::UsersFetched { count, .. } = fetched.event_type else {
+            panic!("unexpected event type {:?}", fetched.event_type);
+        };
+        assert!(count > 0);
+        assert_eq!(response["data"]["count"], count);
+    }
+
+    #[tokio::test]
+    async fn db_stats_are_returned_to_the_caller() {
+        let app = TestApp::new().with_viewmodel(setup_user_viewmodel);
+        let response = app.call("get_db_stats", &[]).unwrap();
+        assert_eq!(response["ok"], true);
+        assert!(response["data"]["tables"].as_array().is_some_and(|tables| !tables.is_empty()));
+        assert_eq!(payload(&app.wait_for("database.stats_received").await)["tables"], response["data"]["tables"]);
+    }
+
+    #[tokio::test]
+    async fn added_users_are_returned_to_the_caller() {
+        let app = TestApp::new().with_viewmodel(setup_user_viewmodel);
+        let response = app.call("add_user", &[json!({ "name": "Ada", "email": "ada@example.com" })]).unwrap();
+        assert_eq!(response["ok"], true);
+        assert_eq!(response["data"]["affected_rows"], 1);
+
+        let added = app.wait_for("database.user_added").await;
+        let EventType::UserAdded { id, .. } = added.event_type else {
+            panic!("unexpected event type {:?}", added.event_type);
+        };
+        assert_eq!(response["data"]["id"], id);
+
+        let rejected = app.call("add_user", &[json!({ "name": "Ada", "email": "not-an-email" })]).unwrap();
+        assert_eq!(rejected["error"]["code"], "invalid_argument");
+    }
+}
@@ -0,0 +1,96 @@
+use tracing::{info, error, debug};
+use serde_json::{json, Value};
+use crate::app_state::AppState;
+use crate::bridge::{event_arg, event_json, event_respond, BindSurface};
+use crate::core::{analytics, perf, AppResult, ErrorCode, RpcResponse};
+use crate::event_bus::{Event, EventBus, EventType};
+use crate::models::MutationResult;
+
+/// Longest feature name `track_feature` accepts.
+const MAX_FEATURE_NAME: usize = 64;
+
+pub fn setup_analytics_viewmodel(window: &mut impl BindSurface) {
+    // Answers with `analytics.status`: the stored consent, rows waiting to be
+    // uploaded and whether an upload endpoint is configured.
+    window.bind("get_analytics_status", |event| {
+        let _timer = perf::handler("get_analytics_status");
+        info!("Get analytics status event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        match analytics_status(&state) {
+            Ok(status) => emit_analytics_event(state.bus(), "analytics.status", status),
+            Err(e) => {
+                error!("Failed to read analytics status: {}", e);
+                emit_analytics_event(state.bus(), "analytics.status_failed", e.to_payload());
+            }
+        }
+    });
+
+    // Expects `true` to opt in or `false` to opt out. Opting out deletes the
+    // queued counts and the install ID. Answers the call with the new status.
+    window.bind("set_analytics_consent", |event| {
+        let _timer = perf::handler("set_analytics_consent");
+        info!("Set analytics consent event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let Some(granted) = event_json(&event, 0).as_bool() else {
+            let failure = RpcResponse::localized(ErrorCode::InvalidArgument, "validation.expected_bool", &[]);
+            event_respond(&event, &failure.to_value());
+            emit_analytics_event(state.bus(), "analytics.consent_failed", failure.to_value());
+            return;
+        };
+        let response = match analytics::set_consent(state.db(), granted).and_then(|_| analytics_status(&state)) {
+            Ok(status) => {
+                emit_analytics_event(state.bus(), "analytics.consent_changed", status.clone());
+                RpcResponse::ok(MutationResult::with_value(1, status))
+            }
+            Err(e) => {
+                error!("Failed to store analytics consent: {}", e);
+                emit_analytics_event(state.bus(), "analytics.consent_failed", e.to_payload());
+                RpcResponse::from_error(&e)
+            }
+        };
+        event_respond(&event, &response.to_value());
+    });
+
+    // Called by the page when a feature is used, e.g. `track_feature("log_search")`.
+    // Names are lowercase letters, digits, `_`, `.` and `-`; nothing is
+    // recorded without consent.
+    window.bind("track_feature", |event| {
+        let name = event_arg(&event, 0).unwrap_or_default();
+        let valid = !name.is_empty()
+            && name.len() <= MAX_FEATURE_NAME
+            && name.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b"_.-".contains(&b));
+        if valid {
+            analytics::record_feature(&name);
+        } else {
+            debug!("Ignoring feature name {:?}", name);
+        }
+    });
+
+    info!("Analytics viewmodel handlers registered");
+}
+
+fn analytics_status(state: &AppState) -> AppResult<Value> {
+    Ok(json!({
+        "consent": analytics::consent(state.db())?,
+        "queued": analytics::queued(state.db())?,
+        "endpoint_configured": state.config().analytics.endpoint.is_some(),
+    }))
+}
+
+fn emit_analytics_event(bus: &EventBus, name: &str, payload: Value) {
+    let bus = bus.clone();
+    let event = Event::new(
+        EventType::Custom {
+            name: name.to_string(),
+            payload,
+        },
+        "analytics_viewmodel"
+    );
+    tokio::spawn(async move {
+        if let Err(e) = bus.emit(event).await {
+            error!("Failed to emit analytics event: {}", e);
+        }
+    });
+}
@@ -1,48 +1,57 @@
 #![allow(dead_code)]
 
-use lazy_static::lazy_static;
-use std::sync::{Arc, Mutex};
 use tracing::{info, error};
-use webui_rs::webui;
-use crate::core::Database;
-use crate::event_bus::{emit_counter_increment, emit_counter_reset, emit_event, Event, EventType};
+use crate::bridge::{event_respond, BindSurface};
+use crate::app_state::AppState;
+use crate::core::{perf, RpcResponse};
+use crate::models::MutationResult;
 
-lazy_static! {
-    static ref DATABASE: Arc<Mutex<Option<Arc<Database>>>> = Arc::new(Mutex::new(None));
-}
-
-pub fn init_database(db: Arc<Database>) {
-    let mut db_guard = DATABASE.lock().unwrap();
-    *db_guard = Some(db);
-}
-
-pub fn setup_counter_viewmodel(window: &mut webui::Window) {
-    window.bind("increment_counter", |_event| {
+pub fn setup_counter_viewmodel(window: &mut impl BindSurface) {
+    // `increment_counter` and `reset_counter` answer the call with the new value.
+    window.bind("increment_counter", |event| {
+        let _timer = perf::handler("increment_counter");
         info!("Increment counter event received");
-        tokio::spawn(async {
-            if let Err(e) = emit_counter_increment("counter_viewmodel").await {
+        let Some(state) = AppState::of(&event) else { return };
+        let value = state.counter().increment();
+        let changed = RpcResponse::ok(MutationResult::with_value(1, value.into()));
+        event_respond(&event, &changed.to_value());
+        let bus = state.bus().clone();
+        tokio::spawn(async move {
+            if let Err(e) = bus.emit_counter_increment("counter_viewmodel").await {
                 error!("Failed to emit counter increment event: {}", e);
             }
+            if let Err(e) = bus.emit_counter_value_changed(value, "counter_viewmodel").await {
+                error!("Failed to emit counter value changed event: {}", e);
+            }
         });
     });
 
-    window.bind("reset_counter", |_event| {
+    window.bind("reset_counter", |event| {
+        let _timer = perf::handler("reset_counter");
         info!("Reset counter event received");
-        tokio::spawn(async {
-            if let Err(e) = emit_counter_reset("counter_viewmodel").await {
+        let Some(state) = AppState::of(&event) else { return };
+        let value = state.counter().reset();
+        let changed = RpcResponse::ok(MutationResult::with_value(1, value.into()));
+        event_respond(&event, &changed.to_value());
+        let bus = state.bus().clone();
+        tokio::spawn(async move {
+            if let Err(e) = bus.emit_counter_reset("counter_viewmodel").await {
                 error!("Failed to emit counter reset event: {}", e);
             }
+            if let Err(e) = bus.emit_counter_value_changed(value, "counter_viewmodel").await {
+                error!("Failed to emit counter value changed event: {}", e);
+            }
         });
     });
 
-    window.bind("get_counter_value", |_event| {
+    window.bind("get_counter_value", |event| {
+        let _timer = perf::handler("get_counter_value");
         info!("Get counter value event received");
-        tokio::spawn(async {
-            let event = Event::new(
-                EventType::CounterValueChanged { value: 0 },
-                "counter_viewmodel"
-            );
-            if let Err(e) = emit_event(event).await {
+        let Some(state) = AppState::of(&event) else { return };
+        let value = state.counter().get();
+        let bus = state.bus().clone();
+        tokio::spawn(async move {
+            if let Err(e) = bus.emit_counter_value_changed(value, "counter_viewmodel").await {
                 error!("Failed to emit counter value changed event: {}", e);
             }
         });
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+use tracing::{info, error};
+use serde_json::{json, Value};
+use crate::app_state::AppState;
+use crate::bridge::{event_arg, event_json, event_respond, BindSurface};
+use crate::core::database::audit;
+use crate::core::database::backup::{backup_path, list_backups};
+use crate::core::{perf, AppResult, ErrorCode, RpcResponse};
+use crate::event_bus::{Event, EventBus, EventType};
+use crate::models::JobStarted;
+
+const DEFAULT_AUDIT_PAGE: u64 = 50;
+const MAX_AUDIT_PAGE: u64 = 200;
+
+pub fn setup_database_viewmodel(window: &mut impl BindSurface) {
+    // Answers the call with `{job_id}`, then with `database.backup_completed`
+    // under the same `job_id`: the backup's path, size and duration. Backups
+    // go to `database.backup.dir`.
+    window.bind("backup_database", |event| {
+        let _timer = perf::handler("backup_database");
+        info!("Backup database event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let job = JobStarted::with_new_id();
+        event_respond(&event, &RpcResponse::ok(&job).to_value());
+
+        tokio::task::spawn_blocking(move || {
+            let dir = PathBuf::from(state.config().get_backup_dir());
+            match state.db().backup(&backup_path(&dir, "backup")) {
+                Ok(backup) => emit_database_event(state.bus(), "database.backup_completed", for_job(json!(backup), &job)),
+                Err(e) => {
+                    error!("Failed to back up the database: {}", e);
+                    emit_database_event(state.bus(), "database.backup_failed", for_job(e.to_payload(), &job));
+                }
+            }
+        });
+    });
+
+    // Answers with `database.backups_listed`: the files in the backup folder,
+    // newest first.
+    window.bind("list_backups", |event| {
+        let _timer = perf::handler("list_backups");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let dir = state.config().get_backup_dir();
+        match list_backups(Path::new(dir)) {
+            Ok(backups) => emit_database_event(state.bus(), "database.backups_listed", json!({ "dir": dir, "backups": backups })),
+            Err(e) => {
+                error!("Failed to list backups: {}", e);
+                emit_database_event(state.bus(), "database.backups_list_failed", e.to_payload());
+            }
+        }
+    });
+
+    // Expects the name of a file in the backup folder, as listed by
+    // `list_backups`. The current database is backed up to
+    // `pre-restore-<time>.db` first. Answers the call with `{job_id}`, then
+    // with `database.restore_completed` or `database.restore_failed` under the
+    // same `job_id`.
+    window.bind("restore_database", |event| {
+        let _timer = perf::handler("restore_database");
+        info!("Restore database event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let name = event_arg(&event, 0).unwrap_or_default();
+        let dir = PathBuf::from(state.config().get_backup_dir());
+        let path = dir.join(&name);
+        if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') || !path.is_file() {
+            let failure = RpcResponse::localized(ErrorCode::InvalidArgument, "validation.unknown_backup", &[("name", &name)]);
+            event_respond(&event, &failure.to_value());
+            emit_database_event(state.bus(), "database.restore_failed", failure.to_value());
+            return;
+        }
+        let job = JobStarted::with_new_id();
+        event_respond(&event, &RpcResponse::ok(&job).to_value());
+
+        tokio::task::spawn_blocking(move || match restore(&state, &dir, &path) {
+            Ok(payload) => emit_database_event(state.bus(), "database.restore_completed", for_job(payload, &job)),
+            Err(e) => {
+                error!("Failed to restore the database from {}: {}", path.display(), e);
+                emit_database_event(state.bus(), "database.restore_failed", for_job(e.to_payload(), &job));
+            }
+        });
+    });
+
+    // Arguments: an optional offset and page size (default 50, at most 200).
+    // Answers with `database.audit_log_received`: the changes, newest first,
+    // and the total number logged.
+    window.bind("get_audit_log", |event| {
+        let _timer = perf::handler("get_audit_log");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let offset = event_json(&event, 0).as_u64().unwrap_or(0);
+        let limit = event_json(&event, 1).as_u64().unwrap_or(DEFAULT_AUDIT_PAGE).clamp(1, MAX_AUDIT_PAGE);
+        tokio::spawn(async move {
+            let result = state
+                .db()
+                .run(move |conn| {
+                    let _timer = perf::query("audit_log.page");
+                    audit::page(conn, offset, limit)
+                })
+                .await;
+            match result {
+                Ok(page) => emit_database_event(state.bus(), "database.audit_log_received", json!(page)),
+                Err(e) => {
+                    error!("Failed to read the audit log: {}", e);
+                    emit_database_event(state.bus(), "database.audit_log_failed", e.to_payload());
+                }
+            }
+        });
+    });
+}
+
+fn restore(state: &AppState, dir: &Path, path: &Path) -> AppResult<Value> {
+    let mut safety_copy = state.db().backup(&backup_path(dir, "pre-restore"))?;
+    safety_copy.trigger = "pre_restore";
+    let migrations = state.db().restore(path)?;
+    state.cache().invalidate(super::user::DB_STATS_CACHE_KEY);
+    // Recorded in the restored database, whose log replaced the old one.
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let conn = state.db().get_connection()?;
+    audit::record(
+        &conn,
+        "ui",
+        audit::Operation::Update,
+        "database",
+        &name,
+        Some(json!({ "safety_copy": &safety_copy.path })),
+        Some(json!({ "restored_from": path })),
+    )?;
+    Ok(json!({ "path": path, "safety_copy": safety_copy, "migrations": migrations }))
+}
+
+/// Tags an event payload with the job it reports on.
+fn for_job(mut payload: Value, job: &JobStarted) -> Value {
+    payload["job_id"] = json!(job.job_id);
+    payload
+}
+
+fn emit_database_event(bus: &EventBus, name: &str, payload: Value) {
+    let bus = bus.clone();
+    let event = Event::new(
+        EventType::Custom {
+            name: name.to_string(),
+            payload,
+        },
+        "database_viewmodel"
+    );
+    tokio::spawn(async move {
+        if let Err(e) = bus.emit(event).await {
+            error!("Failed to emit database event: {}", e);
+        }
+    });
+}
@@ -0,0 +1,149 @@
+use tracing::{info, error, warn};
+use serde_json::{json, Value};
+use crate::app_state::AppState;
+use crate::bridge::{event_arg, event_json, event_respond, BindSurface};
+use crate::build_logger::log_summary;
+use crate::core::config_writer::update_config_file;
+use crate::core::log_store::{self, LogFilter, LogRange};
+use crate::core::{current_log_level, log_stream, perf, set_target_log_level, ErrorCode, RpcResponse};
+use super::settings::config_path;
+use crate::event_bus::{Event, EventBus, EventType};
+use crate::models::MutationResult;
+
+fn emit_logs_event(bus: &EventBus, name: &'static str, payload: Value) {
+    let bus = bus.clone();
+    tokio::spawn(async move {
+        let event = Event::new(
+            EventType::Custom { name: name.to_string(), payload },
+            "logs_viewmodel"
+        );
+        if let Err(e) = bus.emit(event).await {
+            error!("Failed to emit {} event: {}", name, e);
+        }
+    });
+}
+
+pub fn setup_logs_viewmodel(window: &mut impl BindSurface) {
+    // Expects `{"level": "debug", "sample_every": 10}`; missing keys fall back to info and 1.
+    // Answers the call with the filter in use.
+    window.bind("set_log_stream", |event| {
+        let _timer = perf::handler("set_log_stream");
+        info!("Set log stream event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let args = event_json(&event, 0);
+        let level = args.get("level").and_then(Value::as_str).unwrap_or("info");
+        let sample_every = args.get("sample_every").and_then(Value::as_u64).unwrap_or(1);
+
+        let response = match log_stream::set_stream_filter(level, sample_every) {
+            Ok(()) => {
+                let filter = json!({ "level": level, "sample_every": sample_every });
+                emit_logs_event(state.bus(), "log.stream_updated", filter.clone());
+                RpcResponse::ok(MutationResult::with_value(1, filter))
+            }
+            Err(e) => {
+                emit_logs_event(state.bus(), "log.stream_update_failed", e.to_payload());
+                RpcResponse::from_error(&e)
+            }
+        };
+        event_respond(&event, &response.to_value());
+    });
+
+    window.bind("get_log_level", |event| {
+        let _timer = perf::handler("get_log_level");
+        info!("Get log level event received");
+        let Some(state) = AppState::of(&event) else { return };
+        emit_logs_event(state.bus(), "log.level", json!({ "directives": current_log_level() }));
+    });
+
+    // Expects `{"level": "debug"}` or `{"level": "trace", "target": "rustwebui_app::plugins"}`.
+    // The new directives are written back to `logging.level` so they survive a restart.
+    // Answers the call with the directives and whether they were saved.
+    window.bind("set_log_level", |event| {
+        let _timer = perf::handler("set_log_level");
+        info!("Set log level event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let args = event_json(&event, 0);
+        let Some(level) = args.get("level").and_then(Value::as_str) else {
+            let failure = RpcResponse::localized(ErrorCode::InvalidArgument, "validation.missing_level", &[]);
+            event_respond(&event, &failure.to_value());
+            emit_logs_event(state.bus(), "log.level_change_failed", failure.to_value());
+            return;
+        };
+        let target = args.get("target").and_then(Value::as_str);
+
+        let directives = match set_target_log_level(level, target) {
+            Ok(directives) => directives,
+            Err(e) => {
+                error!("Failed to change log level: {}", e);
+                event_respond(&event, &RpcResponse::from_error(&e).to_value());
+                emit_logs_event(state.bus(), "log.level_change_failed", e.to_payload());
+                return;
+            }
+        };
+
+        let mut changes = serde_json::Map::new();
+        changes.insert("logging.level".to_string(), json!(directives));
+        let persisted = match config_path().and_then(|path| update_config_file(path, &changes)) {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Log level applied but not saved: {}", e);
+                false
+            }
+        };
+        let changed = json!({ "level": level, "target": target, "directives": directives, "persisted": persisted });
+        event_respond(&event, &RpcResponse::ok(MutationResult::with_value(1, changed.clone())).to_value());
+        emit_logs_event(state.bus(), "log.level_changed", changed);
+    });
+
+    // Arguments: filter `{"level": "warn", "target": "rustwebui_app::plugins", "search": "timeout", "run_id": "..."}`,
+    // range `{"from": <unix ms>, "to": <unix ms>}` and limit. All are optional.
+    window.bind("query_logs", |event| {
+        let _timer = perf::handler("query_logs");
+        info!("Query logs event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let filter: LogFilter = serde_json::from_value(event_json(&event, 0)).unwrap_or_default();
+        let range: LogRange = serde_json::from_value(event_json(&event, 1)).unwrap_or_default();
+        let limit = event_json(&event, 2).as_u64().unwrap_or(200) as usize;
+
+        let bus = state.bus().clone();
+        tokio::task::spawn_blocking(move || match log_store::query_logs(&filter, &range, limit) {
+            Ok(records) => emit_logs_event(&bus, "log.query_result", json!({ "count": records.len(), "records": records })),
+            Err(e) => {
+                error!("Failed to query logs: {}", e);
+                emit_logs_event(&bus, "log.query_failed", e.to_payload());
+            }
+        });
+    });
+
+    // Arguments: level (`error`, `warn`, `info`/`log`, `debug` or `trace`), message
+    // and an optional JSON context. Records go through the normal pipeline under
+    // the `frontend` target, so `frontend=debug` style directives apply to them.
+    window.bind("log_from_frontend", |event| {
+        let _timer = perf::handler("log_from_frontend");
+
+        let level = event_arg(&event, 0).unwrap_or_default().to_lowercase();
+        let message = event_arg(&event, 1).unwrap_or_default();
+        let context = event_json(&event, 2);
+        let context = if context.is_null() { String::new() } else { context.to_string() };
+
+        match level.as_str() {
+            "error" => tracing::error!(target: "frontend", context = %context, "{}", message),
+            "warn" | "warning" => tracing::warn!(target: "frontend", context = %context, "{}", message),
+            "debug" => tracing::debug!(target: "frontend", context = %context, "{}", message),
+            "trace" => tracing::trace!(target: "frontend", context = %context, "{}", message),
+            _ => tracing::info!(target: "frontend", context = %context, "{}", message),
+        }
+    });
+
+    window.bind("get_log_summary", |event| {
+        let _timer = perf::handler("get_log_summary");
+        info!("Get log summary event received");
+        let Some(state) = AppState::of(&event) else { return };
+        emit_logs_event(state.bus(), "log.summary", json!(log_summary()));
+    });
+
+    info!("Logs viewmodel handlers registered");
+}
@@ -1,25 +1,46 @@
+pub mod analytics;
 pub mod counter;
+pub mod database;
+pub mod logs;
+pub mod performance;
+pub mod plugins;
+pub mod power;
+pub mod scheduler;
+pub mod settings;
+pub mod startup;
 pub mod system;
+pub mod tasks;
 pub mod user;
 pub mod utils;
 pub mod window;
 
+pub use analytics::setup_analytics_viewmodel;
 pub use counter::setup_counter_viewmodel;
+pub use database::setup_database_viewmodel;
+pub use logs::setup_logs_viewmodel;
+pub use performance::setup_performance_viewmodel;
+pub use plugins::setup_plugins_viewmodel;
+pub use power::{setup_power_viewmodel, start_power_monitor, POWER_MONITOR_TASK};
+pub use scheduler::setup_scheduler_viewmodel;
+pub use settings::{init_config_path, set_effective_config, setup_settings_viewmodel};
+pub use startup::{report_startup, setup_startup_viewmodel};
 pub use system::setup_system_viewmodel;
+pub use tasks::setup_tasks_viewmodel;
 pub use user::setup_user_viewmodel;
 pub use utils::setup_utils_viewmodel;
 pub use window::setup_window_viewmodel;
 
-use std::sync::{Arc, Mutex};
-use crate::core::Database;
+use crate::app_state::AppState;
 
-lazy_static::lazy_static! {
-    pub static ref DATABASE: Arc<Mutex<Option<Arc<Database>>>> = Arc::new(Mutex::new(None));
-}
-
-pub fn init_db(db: Arc<Database>) {
-    match DATABASE.lock() {
-        Ok(mut db_guard) => *db_guard = Some(db),
-        Err(e) => tracing::error!("Failed to lock database: {}", e),
+/// Drops cached handler responses when an event says the data behind them
+/// changed: user changes for `get_db_stats`, power and config changes for
+/// `get_system_info`.
+pub fn invalidate_cached_responses(state: &AppState) {
+    let cache = state.cache();
+    cache.invalidate_on(state.bus(), "user.*", user::DB_STATS_CACHE_KEY);
+    for name in ["database.user_added", "database.user_updated", "database.user_deleted", "database.users_imported"] {
+        cache.invalidate_on(state.bus(), name, user::DB_STATS_CACHE_KEY);
     }
+    cache.invalidate_on(state.bus(), "power.changed", system::SYSTEM_INFO_CACHE_KEY);
+    cache.invalidate_on(state.bus(), "config.changed", system::SYSTEM_INFO_CACHE_KEY);
 }
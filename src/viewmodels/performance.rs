@@ -0,0 +1,55 @@
+use tracing::{info, error};
+use crate::app_state::AppState;
+use crate::bridge::BindSurface;
+use serde_json::json;
+use crate::core::{perf, resources};
+use crate::event_bus::{Event, EventType};
+
+pub fn setup_performance_viewmodel(window: &mut impl BindSurface) {
+    // Answers with `performance.report`: per handler and query call counts,
+    // slow calls and p50/p95/max latencies in milliseconds.
+    window.bind("get_performance_report", |event| {
+        let _timer = perf::handler("get_performance_report");
+        info!("Get performance report event received");
+
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+        let operations = perf::report();
+        tokio::spawn(async move {
+            let event = Event::new(
+                EventType::Custom {
+                    name: "performance.report".to_string(),
+                    payload: json!({ "operations": operations }),
+                },
+                "performance_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit performance report event: {}", e);
+            }
+        });
+    });
+
+    // Answers with `resources.usage`: a fresh sample of memory, open files,
+    // tasks and queue sizes, together with the configured limits.
+    window.bind("get_resource_usage", |event| {
+        let _timer = perf::handler("get_resource_usage");
+        info!("Get resource usage event received");
+
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+        tokio::spawn(async move {
+            let event = Event::new(
+                EventType::Custom {
+                    name: "resources.usage".to_string(),
+                    payload: json!({ "usage": resources::sample(&bus), "limits": resources::limits() }),
+                },
+                "performance_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit resource usage event: {}", e);
+            }
+        });
+    });
+
+    info!("Performance viewmodel handlers registered");
+}
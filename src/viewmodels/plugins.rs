@@ -0,0 +1,98 @@
+use tracing::{info, error};
+use crate::app_state::AppState;
+use crate::bridge::{event_arg, event_respond, BindSurface};
+use serde_json::json;
+use crate::core::{perf, ErrorCode, RpcResponse};
+use crate::event_bus::{Event, EventType};
+use crate::models::MutationResult;
+use crate::plugins::registry;
+
+pub fn setup_plugins_viewmodel(window: &mut impl BindSurface) {
+    window.bind("list_plugins", |event| {
+        let _timer = perf::handler("list_plugins");
+        info!("List plugins event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
+            let plugins = registry().map(|r| r.list_plugins()).unwrap_or_default();
+            let event = Event::new(
+                EventType::Custom {
+                    name: "plugins.listed".to_string(),
+                    payload: json!({ "plugins": plugins, "count": plugins.len() }),
+                },
+                "plugins_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit plugins listed event: {}", e);
+            }
+        });
+    });
+
+    window.bind("get_plugin_frontends", |event| {
+        let _timer = perf::handler("get_plugin_frontends");
+        info!("Get plugin frontends event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
+            let frontends = registry().map(|r| r.list_frontends()).unwrap_or_default();
+            let event = Event::new(
+                EventType::Custom {
+                    name: "plugins.frontends_listed".to_string(),
+                    payload: json!({ "frontends": frontends, "count": frontends.len() }),
+                },
+                "plugins_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit plugin frontends event: {}", e);
+            }
+        });
+    });
+
+    window.bind("get_plugin_health", |event| {
+        let _timer = perf::handler("get_plugin_health");
+        info!("Get plugin health event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
+            let health = registry().map(|r| r.health_report()).unwrap_or_default();
+            let event = Event::new(
+                EventType::Custom {
+                    name: "plugins.health_received".to_string(),
+                    payload: json!({ "plugins": health, "count": health.len() }),
+                },
+                "plugins_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit plugin health event: {}", e);
+            }
+        });
+    });
+
+    // Expects a plugin name. The plugin is shut down and listed as
+    // `disabled` until the app restarts. Answers the call with the result.
+    window.bind("disable_plugin", |event| {
+        let _timer = perf::handler("disable_plugin");
+        let name = event_arg(&event, 0).unwrap_or_default();
+        info!("Disable plugin event received for '{}'", name);
+        let Some(state) = AppState::of(&event) else { return };
+
+        if !registry().is_some_and(|r| r.disable_plugin(&name)) {
+            let failure = RpcResponse::localized(ErrorCode::NotFound, "validation.unknown_plugin", &[("plugin", &name)]);
+            event_respond(&event, &failure.to_value());
+            return;
+        }
+        let disabled = json!({ "plugin": name });
+        event_respond(&event, &RpcResponse::ok(MutationResult::with_value(1, disabled.clone())).to_value());
+        let bus = state.bus().clone();
+        tokio::spawn(async move {
+            if let Err(e) = bus.emit_custom("plugins.disabled", disabled, "plugins_viewmodel").await {
+                error!("Failed to emit plugin disabled event: {}", e);
+            }
+        });
+    });
+
+    info!("Plugins viewmodel handlers registered");
+}
@@ -0,0 +1,250 @@
+use tracing::{info, error, debug};
+use crate::app_state::AppState;
+use crate::bridge::BindSurface;
+use serde::Serialize;
+use serde_json::json;
+use std::time::Duration;
+use crate::core::perf;
+use crate::core::supervisor::Task;
+use crate::event_bus::{Event, EventBus, EventType};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PowerInfo {
+    /// False where the power status can't be read; the other fields are then defaults.
+    pub supported: bool,
+    pub has_battery: bool,
+    pub battery_percent: Option<f32>,
+    pub charging_state: String,
+    pub on_ac_power: bool,
+    pub power_profile: Option<String>,
+}
+
+impl Default for PowerInfo {
+    fn default() -> Self {
+        Self {
+            supported: false,
+            has_battery: false,
+            battery_percent: None,
+            charging_state: "unknown".to_string(),
+            on_ac_power: true,
+            power_profile: None,
+        }
+    }
+}
+
+pub fn setup_power_viewmodel(window: &mut impl BindSurface) {
+    window.bind("get_power_info", |event| {
+        let _timer = perf::handler("get_power_info");
+        info!("Get power info event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
+            let power = read_power_info();
+            let event = Event::new(
+                EventType::Custom {
+                    name: "power.info_received".to_string(),
+                    payload: json!(power),
+                },
+                "power_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit power info event: {}", e);
+            }
+        });
+    });
+
+    info!("Power viewmodel handlers registered");
+}
+
+/// Supervisor name of the power monitor.
+pub const POWER_MONITOR_TASK: &str = "power_monitor";
+
+/// Polls the power status and emits `power.changed` on `bus` whenever it
+/// differs from the previous sample.
+pub fn start_power_monitor(poll_interval: Duration, bus: EventBus) {
+    Task::new(POWER_MONITOR_TASK).with_events(&bus).spawn(move |_| {
+        let bus = bus.clone();
+        async move {
+            let mut interval = tokio::time::interval(poll_interval);
+            let mut last = read_power_info();
+            loop {
+                interval.tick().await;
+                let current = read_power_info();
+                if current == last {
+                    continue;
+                }
+                debug!("Power status changed: {:?}", current);
+                let event = Event::new(
+                    EventType::Custom {
+                        name: "power.changed".to_string(),
+                        payload: json!({ "previous": last, "current": current }),
+                    },
+                    "power_viewmodel"
+                );
+                if let Err(e) = bus.emit(event).await {
+                    error!("Failed to emit power changed event: {}", e);
+                }
+                last = current;
+            }
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+pub fn read_power_info() -> PowerInfo {
+    use std::fs;
+    use std::path::Path;
+
+    let mut info = PowerInfo { supported: true, ..PowerInfo::default() };
+    let supplies = Path::new("/sys/class/power_supply");
+    let read = |path: &Path| fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+
+    let mut ac_seen = false;
+    let mut ac_online = false;
+    if let Ok(entries) = fs::read_dir(supplies) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match read(&path.join("type")).as_deref() {
+                Some("Battery") => {
+                    info.has_battery = true;
+                    if let Some(capacity) = read(&path.join("capacity")).and_then(|c| c.parse().ok()) {
+                        info.battery_percent = Some(capacity);
+                    }
+                    if let Some(status) = read(&path.join("status")) {
+                        info.charging_state = status.to_lowercase().replace(' ', "_");
+                    }
+                }
+                Some("Mains") | Some("USB") => {
+                    ac_seen = true;
+                    ac_online |= read(&path.join("online")).as_deref() == Some("1");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    info.on_ac_power = if ac_seen { ac_online } else { info.charging_state != "discharging" };
+    info.power_profile = read(Path::new("/sys/firmware/acpi/platform_profile"));
+    info
+}
+
+#[cfg(target_os = "macos")]
+pub fn read_power_info() -> PowerInfo {
+    match std::process::Command::new("pmset").args(["-g", "batt"]).output() {
+        Ok(output) if output.status.success() => parse_pmset(&String::from_utf8_lossy(&output.stdout)),
+        Ok(output) => {
+            debug!("pmset exited with {}", output.status);
+            PowerInfo::default()
+        }
+        Err(e) => {
+            debug!("Failed to run pmset: {}", e);
+            PowerInfo::default()
+        }
+    }
+}
+
+/// Parses `pmset -g batt`, e.g.
+/// `Now drawing from 'AC Power'` followed by
+/// ` -InternalBattery-0 (id=123)\t95%; charging; 0:30 remaining present: true`.
+#[cfg(any(target_os = "macos", test))]
+fn parse_pmset(output: &str) -> PowerInfo {
+    let mut info = PowerInfo { supported: true, ..PowerInfo::default() };
+    let mut lines = output.lines();
+    if let Some(source) = lines.next() {
+        info.on_ac_power = source.contains("'AC Power'");
+    }
+    for line in lines.filter(|line| line.contains("InternalBattery")) {
+        info.has_battery = true;
+        let Some((_, status)) = line.split_once('\t') else { continue };
+        let mut fields = status.split(';').map(str::trim);
+        info.battery_percent = fields.next().and_then(|p| p.strip_suffix('%')).and_then(|p| p.parse().ok());
+        if let Some(state) = fields.next() {
+            info.charging_state = state.to_lowercase().replace(' ', "_");
+        }
+    }
+    info
+}
+
+#[cfg(windows)]
+pub fn read_power_info() -> PowerInfo {
+    #[repr(C)]
+    #[derive(Default)]
+    struct SystemPowerStatus {
+        ac_line_status: u8,
+        battery_flag: u8,
+        battery_life_percent: u8,
+        system_status_flag: u8,
+        battery_life_time: u32,
+        battery_full_life_time: u32,
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetSystemPowerStatus(status: *mut SystemPowerStatus) -> i32;
+    }
+
+    const NO_BATTERY: u8 = 128;
+    const UNKNOWN: u8 = 255;
+    const CHARGING: u8 = 8;
+
+    let mut status = SystemPowerStatus::default();
+    // SAFETY: `status` is a valid SYSTEM_POWER_STATUS the call fills in.
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        debug!("GetSystemPowerStatus failed: {}", std::io::Error::last_os_error());
+        return PowerInfo::default();
+    }
+
+    let on_ac_power = status.ac_line_status != 0;
+    let has_battery = status.battery_flag != UNKNOWN && status.battery_flag & NO_BATTERY == 0;
+    let charging_state = if !has_battery {
+        "unknown"
+    } else if status.battery_flag & CHARGING != 0 {
+        "charging"
+    } else if on_ac_power {
+        "not_charging"
+    } else {
+        "discharging"
+    };
+    PowerInfo {
+        supported: true,
+        has_battery,
+        battery_percent: (has_battery && status.battery_life_percent != UNKNOWN)
+            .then_some(f32::from(status.battery_life_percent)),
+        charging_state: charging_state.to_string(),
+        on_ac_power,
+        power_profile: (status.system_status_flag & 1 != 0).then(|| "battery_saver".to_string()),
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn read_power_info() -> PowerInfo {
+    PowerInfo::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pmset_on_battery() {
+        let info = parse_pmset(concat!(
+            "Now drawing from 'Battery Power'\n",
+            " -InternalBattery-0 (id=4653155)\t87%; discharging; 4:12 remaining present: true\n",
+        ));
+        assert!(info.supported);
+        assert!(info.has_battery);
+        assert!(!info.on_ac_power);
+        assert_eq!(info.battery_percent, Some(87.0));
+        assert_eq!(info.charging_state, "discharging");
+    }
+
+    #[test]
+    fn parses_pmset_without_battery() {
+        let info = parse_pmset("Now drawing from 'AC Power'\n");
+        assert!(info.supported);
+        assert!(!info.has_battery);
+        assert!(info.on_ac_power);
+        assert_eq!(info.battery_percent, None);
+    }
+}
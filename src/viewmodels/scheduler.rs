@@ -0,0 +1,106 @@
+use tracing::{info, error};
+use crate::app_state::AppState;
+use crate::bridge::{event_arg, BindSurface};
+use serde_json::{json, Value};
+use crate::core::{perf, ErrorCode, RpcResponse};
+use crate::event_bus::{Event, EventBus, EventType};
+use crate::scheduler::GLOBAL_SCHEDULER;
+
+pub fn setup_scheduler_viewmodel(window: &mut impl BindSurface) {
+    window.bind("get_scheduled_jobs", |event| {
+        let _timer = perf::handler("get_scheduled_jobs");
+        info!("Get scheduled jobs event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
+            let jobs = GLOBAL_SCHEDULER.list_jobs();
+            let event = Event::new(
+                EventType::Custom {
+                    name: "scheduler.jobs_listed".to_string(),
+                    payload: json!({ "jobs": jobs, "count": jobs.len() }),
+                },
+                "scheduler_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit scheduled jobs event: {}", e);
+            }
+        });
+    });
+
+    window.bind("get_job_history", |event| {
+        let _timer = perf::handler("get_job_history");
+        info!("Get job history event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
+            let history = tokio::task::spawn_blocking(|| GLOBAL_SCHEDULER.get_history(None, 100))
+                .await
+                .unwrap_or_else(|e| Err(anyhow::anyhow!(e)));
+            let payload = match history {
+                Ok(runs) => json!({ "runs": runs, "count": runs.len() }),
+                Err(e) => {
+                    error!("Failed to load job history: {}", e);
+                    RpcResponse::localized(ErrorCode::Runtime, "error.runtime", &[("detail", &e.to_string())]).to_value()
+                }
+            };
+            let event = Event::new(
+                EventType::Custom {
+                    name: "scheduler.history_received".to_string(),
+                    payload,
+                },
+                "scheduler_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit job history event: {}", e);
+            }
+        });
+    });
+
+    // Answers with `scheduler.schedules`: every job with its catch-up policy
+    // and next five run times.
+    window.bind("list_schedules", |event| {
+        let _timer = perf::handler("list_schedules");
+        info!("List schedules event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let schedules = GLOBAL_SCHEDULER.list_schedules(5);
+        emit_scheduler_event(state.bus(), "scheduler.schedules", json!({ "schedules": schedules, "count": schedules.len() }));
+    });
+
+    // Expects a job name. The result arrives as `scheduler.job_completed`
+    // with `trigger: "manual"`.
+    window.bind("run_now", |event| {
+        let _timer = perf::handler("run_now");
+        let name = event_arg(&event, 0).unwrap_or_default();
+        info!("Run now event received for job '{}'", name);
+        let Some(state) = AppState::of(&event) else { return };
+
+        if !GLOBAL_SCHEDULER.has_job(&name) {
+            let failure = RpcResponse::localized(ErrorCode::InvalidArgument, "validation.unknown_job", &[("job", &name)]);
+            emit_scheduler_event(state.bus(), "scheduler.run_failed", failure.to_value());
+            return;
+        }
+        tokio::spawn(async move {
+            if let Err(e) = GLOBAL_SCHEDULER.run_now(&name).await {
+                error!("Failed to run job '{}': {}", name, e);
+            }
+        });
+    });
+
+    info!("Scheduler viewmodel handlers registered");
+}
+
+fn emit_scheduler_event(bus: &EventBus, name: &'static str, payload: Value) {
+    let bus = bus.clone();
+    tokio::spawn(async move {
+        let event = Event::new(
+            EventType::Custom { name: name.to_string(), payload },
+            "scheduler_viewmodel"
+        );
+        if let Err(e) = bus.emit(event).await {
+            error!("Failed to emit {} event: {}", name, e);
+        }
+    });
+}
@@ -0,0 +1,169 @@
+use tracing::{info, error};
+use serde_json::{json, Value};
+use once_cell::sync::{Lazy, OnceCell};
+use std::path::PathBuf;
+use std::sync::RwLock;
+use crate::app_state::AppState;
+use crate::bridge::{event_arg, event_json, event_respond, BindSurface};
+use crate::core::bootstrap;
+use crate::core::config_writer::update_config_file;
+use crate::core::{i18n, perf, AppConfig, AppError, AppResult, ErrorCode, RpcResponse};
+use crate::event_bus::{Event, EventBus, EventType};
+use crate::models::MutationResult;
+
+static CONFIG_PATH: OnceCell<PathBuf> = OnceCell::new();
+
+/// Records which file `update_config` writes to.
+pub fn init_config_path(path: PathBuf) {
+    let _ = CONFIG_PATH.set(path);
+}
+
+static EFFECTIVE_CONFIG: Lazy<RwLock<Option<AppConfig>>> = Lazy::new(|| RwLock::new(None));
+
+/// Records the running configuration reported by `get_effective_config`.
+pub fn set_effective_config(config: &AppConfig) {
+    if let Ok(mut effective) = EFFECTIVE_CONFIG.write() {
+        *effective = Some(config.clone());
+    }
+}
+
+pub(crate) fn config_path() -> AppResult<&'static PathBuf> {
+    CONFIG_PATH
+        .get()
+        .ok_or_else(|| AppError::Config("No configuration file was loaded".to_string()))
+}
+
+fn emit_settings_event(bus: &EventBus, name: &'static str, payload: Value) {
+    let bus = bus.clone();
+    tokio::spawn(async move {
+        let event = Event::new(
+            EventType::Custom { name: name.to_string(), payload },
+            "settings_viewmodel"
+        );
+        if let Err(e) = bus.emit(event).await {
+            error!("Failed to emit {} event: {}", name, e);
+        }
+    });
+}
+
+pub fn setup_settings_viewmodel(window: &mut impl BindSurface) {
+    window.bind("get_config", |event| {
+        let _timer = perf::handler("get_config");
+        info!("Get config event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let result = config_path().and_then(|path| Ok((path, AppConfig::load_from(path)?)));
+        match result {
+            Ok((path, config)) => emit_settings_event(
+                state.bus(),
+                "config.current",
+                json!({ "path": path.display().to_string(), "config": config.redacted() }),
+            ),
+            Err(e) => emit_settings_event(state.bus(), "config.update_failed", e.to_payload()),
+        }
+    });
+
+    // Reports the running config together with the layer each key came from.
+    window.bind("get_effective_config", |event| {
+        let _timer = perf::handler("get_effective_config");
+        info!("Get effective config event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let effective = EFFECTIVE_CONFIG.read().ok().and_then(|c| c.clone());
+        match effective {
+            Some(config) => emit_settings_event(
+                state.bus(),
+                "config.effective",
+                json!({
+                    "config": config.redacted(),
+                    "origins": config.origins,
+                    "layers": config.layers,
+                }),
+            ),
+            None => emit_settings_event(state.bus(), "config.update_failed", RpcResponse::localized(ErrorCode::Config, "config.not_initialized", &[]).to_value()),
+        }
+    });
+
+    window.bind("get_bootstrap_state", |event| {
+        let _timer = perf::handler("get_bootstrap_state");
+        info!("Get bootstrap state event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bootstrap = serde_json::to_value(bootstrap::state()).unwrap_or_default();
+        emit_settings_event(state.bus(), "app.bootstrap_state", bootstrap);
+    });
+
+    // Expects an object of dotted keys, e.g. `{"window.title": "App", "logging.level": "debug"}`.
+    // Answers the call with the number of keys changed and the new config.
+    window.bind("update_config", |event| {
+        let _timer = perf::handler("update_config");
+        info!("Update config event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let changes = match event_json(&event, 0) {
+            Value::Object(changes) => changes,
+            other => {
+                let failure = RpcResponse::localized(ErrorCode::InvalidArgument, "validation.expected_object", &[])
+                    .with_details(json!({ "received": other }));
+                event_respond(&event, &failure.to_value());
+                emit_settings_event(state.bus(), "config.update_failed", failure.to_value());
+                return;
+            }
+        };
+
+        let response = match config_path().and_then(|path| update_config_file(path, &changes)) {
+            Ok(config) => {
+                let keys: Vec<&String> = changes.keys().collect();
+                info!("Configuration updated: {:?}", keys);
+                let config = json!(config.redacted());
+                emit_settings_event(state.bus(), "config.updated", json!({ "keys": keys, "config": config }));
+                RpcResponse::ok(MutationResult::with_value(keys.len(), config))
+            }
+            Err(e) => {
+                error!("Failed to update configuration: {}", e);
+                emit_settings_event(state.bus(), "config.update_failed", e.to_payload());
+                RpcResponse::from_error(&e)
+            }
+        };
+        event_respond(&event, &response.to_value());
+    });
+
+    window.bind("get_locale", |event| {
+        let _timer = perf::handler("get_locale");
+        let Some(state) = AppState::of(&event) else { return };
+        emit_settings_event(state.bus(), "app.locale", locale_payload());
+    });
+
+    // Expects a language tag such as `es` or `es-MX`; the choice is stored and
+    // used for every later message. Answers the call with the new locale.
+    window.bind("set_locale", |event| {
+        let _timer = perf::handler("set_locale");
+        info!("Set locale event received");
+        let Some(state) = AppState::of(&event) else { return };
+
+        let tag = event_arg(&event, 0).unwrap_or_default();
+        let Some(locale) = i18n::resolve(&tag) else {
+            let failure = RpcResponse::localized(ErrorCode::InvalidArgument, "validation.unknown_locale", &[("locale", &tag)])
+                .with_details(json!({ "available": i18n::available() }));
+            event_respond(&event, &failure.to_value());
+            emit_settings_event(state.bus(), "app.locale_failed", failure.to_value());
+            return;
+        };
+        let response = match i18n::set_locale(state.db(), locale) {
+            Ok(()) => {
+                emit_settings_event(state.bus(), "app.locale_changed", locale_payload());
+                RpcResponse::ok(MutationResult::with_value(1, locale_payload()))
+            }
+            Err(e) => {
+                error!("Failed to store UI language: {}", e);
+                emit_settings_event(state.bus(), "app.locale_failed", e.to_payload());
+                RpcResponse::from_error(&e)
+            }
+        };
+        event_respond(&event, &response.to_value());
+    });
+
+    info!("Settings viewmodel handlers registered");
+}
+
+fn locale_payload() -> Value {
+    json!({ "locale": i18n::locale(), "available": i18n::available() })
+}
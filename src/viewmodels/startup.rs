@@ -0,0 +1,73 @@
+use tracing::{info, error, warn};
+use crate::app_state::AppState;
+use crate::bridge::BindSurface;
+use serde_json::json;
+use crate::build_logger::{build_progress_snapshot, build_summary};
+use crate::core::{perf, Database};
+use crate::event_bus::{Event, EventBus, EventType};
+
+/// `app_settings` key of the previous run's startup time in milliseconds.
+const LAST_STARTUP_SETTING: &str = "startup.last_elapsed_ms";
+
+pub fn setup_startup_viewmodel(window: &mut impl BindSurface) {
+    // Splash screens call this on load to catch up on steps finished before
+    // the page connected; later steps arrive as `build.progress` events.
+    window.bind("get_startup_progress", |event| {
+        let _timer = perf::handler("get_startup_progress");
+        info!("Get startup progress event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
+            let event = Event::new(
+                EventType::Custom {
+                    name: "build.snapshot".to_string(),
+                    payload: build_progress_snapshot(),
+                },
+                "startup_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit startup progress event: {}", e);
+            }
+        });
+    });
+
+    info!("Startup viewmodel handlers registered");
+}
+
+/// Logs how long each startup step took and emits `app.startup_report` on
+/// `bus`, with the previous run's total for spotting boot time regressions.
+pub fn report_startup(db: &Database, bus: &EventBus) {
+    let Some(summary) = build_summary() else { return };
+    let steps: Vec<String> = summary
+        .steps
+        .iter()
+        .map(|step| match step.duration_ms {
+            Some(ms) => format!("{} {} ms", step.name, ms),
+            None => format!("{} {}", step.name, step.status),
+        })
+        .collect();
+    info!("Startup finished in {} ms: {}", summary.elapsed_ms, steps.join(", "));
+
+    let previous: Option<u64> = db.get_typed_setting(LAST_STARTUP_SETTING).ok().flatten();
+    if let Err(e) = db.set_typed_setting(LAST_STARTUP_SETTING, &summary.elapsed_ms) {
+        warn!("Failed to remember startup time: {}", e);
+    }
+
+    let payload = json!({
+        "success": summary.success,
+        "elapsed_ms": summary.elapsed_ms,
+        "previous_elapsed_ms": previous,
+        "steps": summary.steps,
+    });
+    let bus = bus.clone();
+    tokio::spawn(async move {
+        let event = Event::new(
+            EventType::Custom { name: "app.startup_report".to_string(), payload },
+            "startup_viewmodel"
+        );
+        if let Err(e) = bus.emit(event).await {
+            error!("Failed to emit startup report event: {}", e);
+        }
+    });
+}
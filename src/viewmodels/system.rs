@@ -1,25 +1,39 @@
 use tracing::{info, error};
-use webui_rs::webui;
-use crate::event_bus::{emit_event, emit_system_info_request, Event, EventType};
+use crate::bridge::BindSurface;
+use serde_json::{json, Value};
+use std::time::Duration;
+use crate::app_state::AppState;
+use crate::core::perf;
+use crate::event_bus::{Event, EventType};
 
-pub fn setup_system_viewmodel(window: &mut webui::Window) {
-    window.bind("get_system_info", |_event| {
+/// Cache key of the `get_system_info` response.
+pub const SYSTEM_INFO_CACHE_KEY: &str = "system_info";
+const SYSTEM_INFO_TTL: Duration = Duration::from_secs(30);
+
+pub fn setup_system_viewmodel(window: &mut impl BindSurface) {
+    window.bind("get_system_info", |event| {
+        let _timer = perf::handler("get_system_info");
         info!("Get system info event received");
-        
-        tokio::spawn(async {
-            if let Err(e) = emit_system_info_request("system_viewmodel").await {
+
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+        let cache = state.cache().clone();
+        tokio::spawn(async move {
+            if let Err(e) = bus.emit_system_info_request("system_viewmodel").await {
                 error!("Failed to emit system info request event: {}", e);
             }
-            
+
+            let info = cache.get_or_insert_with(SYSTEM_INFO_CACHE_KEY, SYSTEM_INFO_TTL, read_system_info);
+            let field = |name: &str| info[name].as_str().unwrap_or_default().to_string();
             let event = Event::new(
                 EventType::SystemInfoReceived {
-                    cpu: "Intel Core i7".to_string(),
-                    memory: "16GB".to_string(),
-                    os: std::env::consts::OS.to_string(),
+                    cpu: field("cpu"),
+                    memory: field("memory"),
+                    os: field("os"),
                 },
                 "system_viewmodel"
             );
-            if let Err(e) = emit_event(event).await {
+            if let Err(e) = bus.emit(event).await {
                 error!("Failed to emit system info received event: {}", e);
             }
         });
@@ -27,3 +41,11 @@ pub fn setup_system_viewmodel(window: &mut webui::Window) {
 
     info!("System viewmodel handlers registered");
 }
+
+fn read_system_info() -> Value {
+    json!({
+        "cpu": "Intel Core i7",
+        "memory": "16GB",
+        "os": std::env::consts::OS,
+    })
+}
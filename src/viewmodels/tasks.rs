@@ -0,0 +1,33 @@
+use tracing::{info, error};
+use crate::app_state::AppState;
+use crate::bridge::BindSurface;
+use serde_json::json;
+use crate::core::{perf, supervisor};
+use crate::event_bus::{Event, EventType};
+
+pub fn setup_tasks_viewmodel(window: &mut impl BindSurface) {
+    // Answers with `tasks.status`: state, restart count, last error and
+    // heartbeat age of every supervised background task.
+    window.bind("get_tasks_status", |event| {
+        let _timer = perf::handler("get_tasks_status");
+        info!("Get tasks status event received");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        let tasks = supervisor::statuses();
+        tokio::spawn(async move {
+            let event = Event::new(
+                EventType::Custom {
+                    name: "tasks.status".to_string(),
+                    payload: json!({ "tasks": tasks }),
+                },
+                "tasks_viewmodel"
+            );
+            if let Err(e) = bus.emit(event).await {
+                error!("Failed to emit tasks status event: {}", e);
+            }
+        });
+    });
+
+    info!("Tasks viewmodel handlers registered");
+}
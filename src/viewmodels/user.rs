@@ -1,134 +1,409 @@
 use std::sync::Arc;
-use tracing::{info, error, debug};
-use webui_rs::webui;
+use std::time::Duration;
+use tracing::{info, error};
+use crate::bridge::{event_arg, event_json, event_respond, BindSurface};
 use serde_json::json;
-use crate::core::Database;
-use crate::models::User;
-use crate::event_bus::{emit_users_fetched, emit_event, Event, EventType};
+use crate::core::database::repository::user_from_row;
+use crate::core::database::{prepare_cached, statement_cache_stats, PoolStats, Repository, UserRepository};
+use crate::core::{perf, AppResult, Database, ErrorCode, RpcResponse};
+use crate::models::{DbStats, IndexStats, JobStarted, MutationResult, User};
+use crate::app_state::AppState;
+use crate::event_bus::{Event, EventBus, EventType};
+use crate::services::user_import::{import_csv, validate};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::BTreeMap;
 
-pub fn setup_user_viewmodel(window: &mut webui::Window) {
-    window.bind("get_users", |_event| {
+/// Cache key of the `get_db_stats` response.
+pub const DB_STATS_CACHE_KEY: &str = "db_stats";
+const DB_STATS_TTL: Duration = Duration::from_secs(5);
+
+/// Audit log actor of changes made from the page.
+const UI_ACTOR: &str = "ui";
+
+/// Users sent by `get_users` and `/api/users`.
+pub(crate) const USER_LIST_LIMIT: usize = 100;
+
+const MAX_SEARCH_RESULTS: u64 = 100;
+/// Columns of `users_fts`, in order.
+const SEARCH_FIELDS: [&str; 3] = ["name", "email", "role"];
+/// Wrap matches in `highlight()` output; control characters can't be typed
+/// into the user fields, so they never clash with the text.
+const MATCH_START: char = '\u{1}';
+const MATCH_END: char = '\u{2}';
+
+pub fn setup_user_viewmodel(window: &mut impl BindSurface) {
+    // Answers the call with `{users, count}`, at most `USER_LIST_LIMIT` users,
+    // and announces them with `database.users_fetched`.
+    window.bind("get_users", |event| {
+        let _timer = perf::handler("get_users");
         info!("Get users event received");
-        
-        let db_opt = {
-            let db_guard = crate::viewmodels::DATABASE.lock().unwrap();
-            db_guard.clone()
-        };
-        
-        if let Some(db) = db_opt {
-            tokio::spawn(async move {
-                match fetch_users_from_db(&db).await {
-                    Ok(users) => {
-                        let users_value: Vec<serde_json::Value> = users.iter().map(|u| serde_json::to_value(u).unwrap_or(serde_json::Value::Null)).collect();
-                        info!("Fetched {} users from database", users.len());
-                        if let Err(e) = emit_users_fetched(users.len(), users_value, "user_viewmodel").await {
-                            error!("Failed to emit users fetched event: {}", e);
-                        }
-                        let response = json!({
-                            "success": true,
-                            "data": users,
-                            "count": users.len()
-                        }).to_string();
-                        debug!("Sending users response to frontend: {}", response);
-                    }
-                    Err(e) => {
-                        error!("Failed to fetch users from database: {}", e);
-                        let event = Event::new(
-                            EventType::Custom {
-                                name: "database.error".to_string(),
-                                payload: json!({"error": e.to_string()})
-                            },
-                            "user_viewmodel"
-                        );
-                        if let Err(emission_err) = emit_event(event).await {
-                            error!("Failed to emit database error event: {}", emission_err);
-                        }
+
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+        let response = match read_users(state.db()) {
+            Ok(users) => {
+                info!("Fetched {} users from database", users.len());
+                let users_value: Vec<serde_json::Value> = users.iter().map(|u| serde_json::to_value(u).unwrap_or(serde_json::Value::Null)).collect();
+                let count = users.len();
+                tokio::spawn(async move {
+                    if let Err(e) = bus.emit_users_fetched(count, users_value, "user_viewmodel").await {
+                        error!("Failed to emit users fetched event: {}", e);
                     }
-                }
-            });
-        } else {
-            error!("Database not initialized");
-        }
+                });
+                RpcResponse::ok(json!({ "users": users, "count": count }))
+            }
+            Err(e) => {
+                error!("Failed to fetch users from database: {}", e);
+                emit_user_event(bus, "database.error", e.to_payload());
+                RpcResponse::from_error(&e)
+            }
+        };
+        event_respond(&event, &response.to_value());
     });
 
-    window.bind("get_db_stats", |_event| {
+    // Answers the call with the stats, cached for `DB_STATS_TTL`, and
+    // announces them with `database.stats_received`.
+    window.bind("get_db_stats", |event| {
+        let _timer = perf::handler("get_db_stats");
         info!("Get DB stats event received");
-        
-        let db_opt = {
-            let db_guard = crate::viewmodels::DATABASE.lock().unwrap();
-            db_guard.clone()
+
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+        let stats = state.cache().get_or_try_insert_with(DB_STATS_CACHE_KEY, DB_STATS_TTL, || read_db_stats(state.db()));
+        let response = match stats {
+            Ok(stats) => {
+                info!("Fetched database stats");
+                emit_user_event(bus, "database.stats_received", stats.clone());
+                RpcResponse::ok(stats)
+            }
+            Err(e) => {
+                error!("Failed to fetch database stats: {}", e);
+                RpcResponse::from_error(&e)
+            }
         };
-        
-        if let Some(db) = db_opt {
-            tokio::spawn(async move {
-                match fetch_db_stats(&db).await {
-                    Ok(stats) => {
-                        info!("Fetched database stats");
-                        let event = Event::new(
-                            EventType::Custom {
-                                name: "database.stats_received".to_string(),
-                                payload: json!(stats)
-                            },
-                            "user_viewmodel"
-                        );
-                        if let Err(e) = emit_event(event).await {
-                            error!("Failed to emit database stats event: {}", e);
-                        }
-                        let response = json!({
-                            "success": true,
-                            "stats": stats
-                        }).to_string();
-                        debug!("Sending DB stats response to frontend: {}", response);
-                    }
-                    Err(e) => {
-                        error!("Failed to fetch database stats: {}", e);
+        event_respond(&event, &response.to_value());
+    });
+
+    // Arguments: the search text and an optional limit (default 20, at most
+    // 100). `"jane smith"` matches a phrase, `jo*` a prefix.
+    window.bind("search_users", |event| {
+        let _timer = perf::handler("search_users");
+        info!("Search users event received");
+
+        let Some(state) = AppState::of(&event) else { return };
+        let db = Arc::clone(state.db());
+        let bus = state.bus().clone();
+        let text = event_arg(&event, 0).unwrap_or_default();
+        let limit = event_json(&event, 1).as_u64().unwrap_or(20).clamp(1, MAX_SEARCH_RESULTS);
+        tokio::spawn(async move {
+            let query = text.clone();
+            let result = db
+                .run(move |conn| {
+                    let _timer = perf::query("users.search");
+                    Ok(search_users(conn, &query, limit)?)
+                })
+                .await;
+            match result {
+                Ok(results) => emit_user_event(
+                    bus,
+                    "database.users_searched",
+                    json!({ "query": text, "count": results.len(), "results": results }),
+                ),
+                Err(e) => {
+                    error!("Failed to search users: {}", e);
+                    emit_user_event(bus, "database.search_failed", e.to_payload());
+                }
+            }
+        });
+    });
+
+    // Expects `{name, email, role}`; the role defaults to `user`. Answers the
+    // call with the new user's `{id, affected_rows}` and announces it with
+    // `database.user_added`.
+    window.bind("add_user", |event| {
+        let _timer = perf::handler("add_user");
+        info!("Add user event received");
+
+        let Some(state) = AppState::of(&event) else { return };
+        let fields = event_json(&event, 0);
+        let field = |name: &str| fields.get(name).and_then(|v| v.as_str()).unwrap_or_default().trim().to_string();
+        let role = Some(field("role")).filter(|role| !role.is_empty()).unwrap_or_else(|| "user".to_string());
+        let input = match validate(field("name"), field("email"), role) {
+            Ok(input) => input,
+            Err(reason) => {
+                event_respond(&event, &RpcResponse::error(ErrorCode::InvalidArgument, reason).to_value());
+                return;
+            }
+        };
+        let result = state.db().get_connection().and_then(|conn| {
+            let _timer = perf::query("users.insert");
+            UserRepository::new(&conn).acting_as(UI_ACTOR).insert(&input)
+        });
+        let response = match result {
+            Ok(user) => {
+                info!("Added user {} ({})", user.id, user.email);
+                let bus = state.bus().clone();
+                let added = Event::new(EventType::UserAdded { id: user.id, name: user.name }, "user_viewmodel");
+                tokio::spawn(async move {
+                    if let Err(e) = bus.emit(added).await {
+                        error!("Failed to emit user added event: {}", e);
                     }
+                });
+                RpcResponse::ok(MutationResult::rows(Some(user.id.into()), 1))
+            }
+            Err(e) => {
+                error!("Failed to add user: {}", e);
+                RpcResponse::from_error(&e)
+            }
+        };
+        event_respond(&event, &response.to_value());
+    });
+
+    // Expects the path of a CSV file with `name`, `email` and `role` columns.
+    // Answers the call with `{job_id}` at once and runs the import on the
+    // blocking pool; `database.users_imported` then reports, under the same
+    // `job_id`, how many rows were inserted, skipped as duplicates or failed
+    // validation, and why.
+    window.bind("import_users", |event| {
+        let _timer = perf::handler("import_users");
+        info!("Import users event received");
+
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+        let Some(path) = event_arg(&event, 0).filter(|path| !path.trim().is_empty()) else {
+            let failure = RpcResponse::localized(ErrorCode::InvalidArgument, "validation.missing_field", &[("field", "path")]);
+            event_respond(&event, &failure.to_value());
+            emit_user_event(bus, "database.users_import_failed", failure.to_value());
+            return;
+        };
+        let job = JobStarted::with_new_id();
+        event_respond(&event, &RpcResponse::ok(&job).to_value());
+
+        let db = Arc::clone(state.db());
+        tokio::spawn(async move {
+            let file = path.clone();
+            let result = db
+                .transaction_async(move |tx| {
+                    let _timer = perf::query("users.import");
+                    import_csv(tx, std::fs::File::open(&file)?)
+                })
+                .await;
+            match result {
+                Ok(summary) => {
+                    info!("Imported {} user(s) from {}", summary.inserted, path);
+                    let mut payload = json!(summary);
+                    payload["path"] = json!(path);
+                    payload["job_id"] = json!(job.job_id);
+                    emit_user_event(bus, "database.users_imported", payload);
                 }
-            });
-        } else {
-            error!("Database not initialized");
-        }
+                Err(e) => {
+                    error!("Failed to import users from {}: {}", path, e);
+                    let mut payload = e.to_payload();
+                    payload["job_id"] = json!(job.job_id);
+                    emit_user_event(bus, "database.users_import_failed", payload);
+                }
+            }
+        });
     });
 
     info!("User viewmodel handlers registered");
 }
 
-async fn fetch_users_from_db(db: &Arc<Database>) -> Result<Vec<User>, Box<dyn std::error::Error + Send + Sync>> {
-    let db_conn = db.get_connection();
-    let conn = db_conn.lock().unwrap();
-    
-    let mut stmt = conn.prepare("SELECT id, name, email, role FROM users ORDER BY id LIMIT 100")?;
-    
-    let users = stmt
+fn emit_user_event(bus: EventBus, name: &'static str, payload: serde_json::Value) {
+    tokio::spawn(async move {
+        let event = Event::new(EventType::Custom { name: name.to_string(), payload }, "user_viewmodel");
+        if let Err(e) = bus.emit(event).await {
+            error!("Failed to emit {} event: {}", name, e);
+        }
+    });
+}
+
+fn read_users(db: &Database) -> AppResult<Vec<User>> {
+    let conn = db.get_connection()?;
+    let _timer = perf::query("users.list");
+    UserRepository::new(&conn).list(Some(USER_LIST_LIMIT))
+}
+
+fn read_db_stats(db: &Database) -> AppResult<serde_json::Value> {
+    let conn = db.get_connection()?;
+    let _timer = perf::query("users.stats");
+    let stats = query_db_stats(&conn, db.pool_stats())?;
+    Ok(serde_json::to_value(stats)?)
+}
+
+/// The `get_db_stats` payload. Shared with the `/api/stats` route.
+pub(crate) fn query_db_stats(conn: &Connection, pool: PoolStats) -> rusqlite::Result<DbStats> {
+    let pragma = |name: &str| conn.query_row(&format!("PRAGMA {}", name), [], |row| row.get::<_, i64>(0));
+    let (page_count, page_size) = (pragma("page_count")? as u64, pragma("page_size")? as u64);
+
+    let tables: Vec<String> = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    let mut row_counts = BTreeMap::new();
+    for table in &tables {
+        let sql = format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\""));
+        row_counts.insert(table.clone(), conn.query_row(&sql, [], |row| row.get(0))?);
+    }
+
+    let mut indexes: Vec<IndexStats> = conn
+        .prepare(
+            "SELECT m.name, m.tbl_name, l.\"unique\" FROM sqlite_master m
+             JOIN pragma_index_list(m.tbl_name) l ON l.name = m.name
+             WHERE m.type = 'index' ORDER BY m.tbl_name, m.name",
+        )?
         .query_map([], |row| {
-            Ok(User {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                email: row.get(2)?,
-                role: row.get(3)?,
-                status: "Active".to_string(),
-            })
+            Ok(IndexStats { name: row.get(0)?, table: row.get(1)?, unique: row.get(2)?, columns: Vec::new() })
         })?
-        .collect::<Result<Vec<_>, _>>()?;
-    
-    Ok(users)
+        .collect::<Result<_, _>>()?;
+    let mut columns = conn.prepare("SELECT name FROM pragma_index_info(?1) ORDER BY seqno")?;
+    for index in &mut indexes {
+        index.columns = columns.query_map([&index.name], |row| row.get(0))?.collect::<Result<_, _>>()?;
+    }
+
+    // `path()` is empty for an in-memory database.
+    let file = conn.path().filter(|path| !path.is_empty());
+    let file_len = |suffix: &str| file.and_then(|path| std::fs::metadata(format!("{}{}", path, suffix)).ok()).map(|meta| meta.len());
+
+    Ok(DbStats {
+        users: row_counts.get("users").copied().unwrap_or(0),
+        tables,
+        size: page_count * page_size,
+        page_count,
+        page_size,
+        freelist_count: pragma("freelist_count")? as u64,
+        file_size: file_len(""),
+        wal_size: file_len("-wal"),
+        row_counts,
+        indexes,
+        pool,
+        statement_cache: statement_cache_stats(),
+    })
 }
 
-async fn fetch_db_stats(db: &Arc<Database>) -> Result<serde_json::Value, Box<dyn std::error::Error + Send + Sync>> {
-    let db_conn = db.get_connection();
-    let conn = db_conn.lock().unwrap();
-    
-    let user_count: i64 = conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
-    
-    let mut stmt = conn.prepare("SELECT name FROM sqlite_master WHERE type='table'")?;
-    let tables: Vec<String> = stmt
-        .query_map([], |row| row.get(0))?
-        .map(|table_result| table_result.unwrap_or_default())
-        .collect();
-    
-    Ok(json!({
-        "users": user_count,
-        "tables": tables,
-        "size": "N/A"
-    }))
+/// A `search_users` result, best match first.
+#[derive(Debug, Serialize)]
+pub(crate) struct UserMatch {
+    #[serde(flatten)]
+    pub user: User,
+    /// FTS5's bm25 rank; lower is better.
+    pub rank: f64,
+    /// Matched `[start, end)` ranges per field, in UTF-16 code units so the
+    /// page can slice the strings directly. Fields without a match are left
+    /// out.
+    pub highlights: BTreeMap<&'static str, Vec<[usize; 2]>>,
+}
+
+/// Users matching the search box `text`, ranked, at most `limit`.
+pub(crate) fn search_users(conn: &Connection, text: &str, limit: u64) -> rusqlite::Result<Vec<UserMatch>> {
+    let Some(expression) = match_expression(text) else { return Ok(Vec::new()) };
+    let mut stmt = prepare_cached(
+        conn,
+        "SELECT users.id, users.name, users.email, users.role, users_fts.rank,
+                highlight(users_fts, 0, char(1), char(2)),
+                highlight(users_fts, 1, char(1), char(2)),
+                highlight(users_fts, 2, char(1), char(2))
+         FROM users_fts JOIN users ON users.id = users_fts.rowid
+         WHERE users_fts MATCH ?1
+         ORDER BY users_fts.rank
+         LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(rusqlite::params![expression, limit as i64], |row| {
+        let mut highlights = BTreeMap::new();
+        for (index, field) in SEARCH_FIELDS.into_iter().enumerate() {
+            let ranges = match_ranges(&row.get::<_, String>(5 + index)?);
+            if !ranges.is_empty() {
+                highlights.insert(field, ranges);
+            }
+        }
+        Ok(UserMatch { user: user_from_row(row)?, rank: row.get(4)?, highlights })
+    })?;
+    rows.collect()
+}
+
+/// Turns search box text into an FTS5 query: `"..."` is a phrase, a word
+/// ending in `*` a prefix, and every term must match. Terms are quoted, so
+/// FTS5 operators typed into the box are searched for as text. `None` when
+/// there is nothing to search for.
+fn match_expression(text: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let term;
+        if let Some(phrase) = rest.strip_prefix('"') {
+            let end = phrase.find('"').unwrap_or(phrase.len());
+            term = format!("\"{}\"", &phrase[..end]);
+            rest = phrase.get(end + 1..).unwrap_or("");
+        } else {
+            let end = rest.find(|c: char| c.is_whitespace() || c == '"').unwrap_or(rest.len());
+            let word = &rest[..end];
+            term = match word.strip_suffix('*') {
+                Some(prefix) if !prefix.is_empty() => format!("\"{}\"*", prefix),
+                _ => format!("\"{}\"", word.trim_end_matches('*')),
+            };
+            rest = &rest[end..];
+        }
+        if term.trim_end_matches('*') != "\"\"" {
+            terms.push(term);
+        }
+        rest = rest.trim_start();
+    }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+/// Ranges between the [`MATCH_START`] and [`MATCH_END`] markers of a
+/// `highlight()` value, as offsets into the text without the markers.
+fn match_ranges(highlighted: &str) -> Vec<[usize; 2]> {
+    let mut ranges = Vec::new();
+    let (mut offset, mut start) = (0, 0);
+    for c in highlighted.chars() {
+        match c {
+            MATCH_START => start = offset,
+            MATCH_END => ranges.push([start, offset]),
+            c => offset += c.len_utf16(),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::memory_db;
+
+    #[test]
+    fn search_ranks_users_and_reports_match_offsets() {
+        let db = memory_db();
+        db.insert_sample_data().unwrap();
+        let conn = db.get_connection().unwrap();
+        conn.execute("UPDATE users SET name = 'Jané Smithers' WHERE name = 'Bob Johnson'", []).unwrap();
+
+        let names = |text: &str| -> Vec<String> {
+            search_users(&conn, text, 10).unwrap().into_iter().map(|found| found.user.name).collect()
+        };
+        assert_eq!(names("\"jane smith\""), ["Jane Smith"]);
+        assert_eq!(names("smith*"), ["Jane Smith", "Jané Smithers"]);
+        assert_eq!(names("john"), ["John Doe"]);
+        assert!(names("OR \"").is_empty());
+
+        let found = search_users(&conn, "smithers", 10).unwrap();
+        assert_eq!(found[0].highlights["name"], [[5, 13]]);
+        assert!(!found[0].highlights.contains_key("email"));
+    }
+
+    #[test]
+    fn db_stats_count_rows_pages_and_indexes() {
+        let db = memory_db();
+        db.insert_sample_data().unwrap();
+        let stats = query_db_stats(&db.get_connection().unwrap(), db.pool_stats()).unwrap();
+
+        assert_eq!((stats.users, stats.row_counts["users"]), (4, 4));
+        assert_eq!(stats.size, stats.page_count * stats.page_size);
+        assert!(stats.size > 0);
+        assert_eq!(stats.file_size, None);
+        let index = stats.indexes.iter().find(|index| index.name == "idx_logs_level_time").unwrap();
+        assert_eq!(index.table, "logs");
+        assert_eq!(index.columns, ["level", "time"]);
+    }
 }
@@ -1,12 +1,17 @@
 use tracing::{info, error};
-use webui_rs::webui;
-use crate::event_bus::{emit_event, Event, EventType};
+use crate::app_state::AppState;
+use crate::bridge::BindSurface;
+use crate::core::perf;
+use crate::event_bus::{Event, EventType};
 
-pub fn setup_utils_viewmodel(window: &mut webui::Window) {
-    window.bind("open_folder", |_event| {
+pub fn setup_utils_viewmodel(window: &mut impl BindSurface) {
+    window.bind("open_folder", |event| {
+        let _timer = perf::handler("open_folder");
         info!("Open folder event received");
-        
-        tokio::spawn(async {
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
             let event = Event::new(
                 EventType::Custom {
                     name: "utils.folder_open_requested".to_string(),
@@ -14,16 +19,19 @@ pub fn setup_utils_viewmodel(window: &mut webui::Window) {
                 },
                 "utils_viewmodel"
             );
-            if let Err(e) = emit_event(event).await {
+            if let Err(e) = bus.emit(event).await {
                 error!("Failed to emit folder open event: {}", e);
             }
         });
     });
 
-    window.bind("organize_images", |_event| {
+    window.bind("organize_images", |event| {
+        let _timer = perf::handler("organize_images");
         info!("Organize images event received");
-        
-        tokio::spawn(async {
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
             let event = Event::new(
                 EventType::Custom {
                     name: "utils.images_organized".to_string(),
@@ -31,16 +39,19 @@ pub fn setup_utils_viewmodel(window: &mut webui::Window) {
                 },
                 "utils_viewmodel"
             );
-            if let Err(e) = emit_event(event).await {
+            if let Err(e) = bus.emit(event).await {
                 error!("Failed to emit images organized event: {}", e);
             }
         });
     });
 
-    window.bind("advanced_operation", |_event| {
+    window.bind("advanced_operation", |event| {
+        let _timer = perf::handler("advanced_operation");
         info!("Advanced operation event received");
-        
-        tokio::spawn(async {
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
             let event = Event::new(
                 EventType::Custom {
                     name: "advanced.operation_started".to_string(),
@@ -48,16 +59,19 @@ pub fn setup_utils_viewmodel(window: &mut webui::Window) {
                 },
                 "utils_viewmodel"
             );
-            if let Err(e) = emit_event(event).await {
+            if let Err(e) = bus.emit(event).await {
                 error!("Failed to emit advanced operation event: {}", e);
             }
         });
     });
 
-    window.bind("enhanced_feature", |_event| {
+    window.bind("enhanced_feature", |event| {
+        let _timer = perf::handler("enhanced_feature");
         info!("Enhanced feature event received");
-        
-        tokio::spawn(async {
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
+        tokio::spawn(async move {
             let event = Event::new(
                 EventType::Custom {
                     name: "enhanced.feature_used".to_string(),
@@ -65,7 +79,7 @@ pub fn setup_utils_viewmodel(window: &mut webui::Window) {
                 },
                 "utils_viewmodel"
             );
-            if let Err(e) = emit_event(event).await {
+            if let Err(e) = bus.emit(event).await {
                 error!("Failed to emit enhanced feature event: {}", e);
             }
         });
@@ -1,46 +1,92 @@
 use tracing::{info, error};
 use webui_rs::webui;
 use serde_json::{json, Value};
-use crate::event_bus::{emit_event, Event, EventType};
+use crate::app_state::AppState;
+use crate::bridge::{self, BindSurface};
+use crate::core::config::WindowSettings;
+use crate::core::{perf, RpcResponse};
+use crate::event_bus::{Event, EventType};
 use std::sync::{Arc, Mutex};
-use once_cell::sync::Lazy;
 
-static WEBUI_WINDOW_ID: Lazy<Arc<Mutex<Option<usize>>>> = Lazy::new(|| Arc::new(Mutex::new(None)));
+/// The WebUI window scripts are run in. Clones share the window; until
+/// [`WindowRegistry::set_main`] is called, sends are dropped.
+#[derive(Clone, Default)]
+pub struct WindowRegistry {
+    main: Arc<Mutex<Option<usize>>>,
+}
+
+impl WindowRegistry {
+    pub fn set_main(&self, window_id: usize) {
+        *self.main.lock().unwrap() = Some(window_id);
+    }
+
+    pub fn main(&self) -> Option<usize> {
+        *self.main.lock().unwrap()
+    }
+
+    /// Calls `window.handleBackendEvent` on the page with the event.
+    pub fn send_to_frontend(&self, event_name: &str, data: Value) {
+        let js = format!(
+            "if (window.handleBackendEvent) {{ window.handleBackendEvent({}); }}",
+            serde_json::to_string(&json!({
+                "event": event_name,
+                "data": RpcResponse::from_payload(data),
+                "timestamp": chrono::Utc::now().timestamp_millis()
+            })).unwrap_or_default()
+        );
+        self.run_js(js);
+    }
+
+    /// Updates the page title, which WebUI uses as the native window title.
+    pub fn set_title(&self, title: &str) {
+        self.run_js(format!("document.title = {};", serde_json::to_string(title).unwrap_or_default()));
+    }
 
-pub fn set_webui_window_id(window_id: usize) {
-    let mut guard = WEBUI_WINDOW_ID.lock().unwrap();
-    *guard = Some(window_id);
+    fn run_js(&self, script: String) {
+        let Some(window_id) = self.main() else {
+            return;
+        };
+        let mut js_obj = webui::JavaScript {
+            timeout: 0,
+            script,
+            error: false,
+            data: String::new(),
+        };
+        webui::run_js(window_id, &mut js_obj);
+    }
 }
 
-pub fn send_to_frontend(event_name: &str, data: Value) {
-    if let Ok(guard) = WEBUI_WINDOW_ID.lock() {
-        if let Some(window_id) = *guard {
-            let js = format!(
-                "if (window.handleBackendEvent) {{ window.handleBackendEvent({}); }}",
-                serde_json::to_string(&json!({
-                    "event": event_name,
-                    "data": data,
-                    "timestamp": chrono::Utc::now().timestamp_millis()
-                })).unwrap_or_default()
-            );
-            let mut js_obj = webui::JavaScript {
-                timeout: 0,
-                script: js,
-                error: false,
-                data: String::new(),
-            };
-            webui::run_js(window_id, &mut js_obj);
-        }
+/// Applies `[window]` size and chrome settings; call before `show`.
+pub fn apply_window_settings(window: &webui::Window, settings: &WindowSettings) {
+    if let (Some(width), Some(height)) = (settings.width, settings.height) {
+        bridge::set_window_size(window, width, height);
+    }
+    if let (Some(width), Some(height)) = (settings.min_width, settings.min_height) {
+        bridge::set_window_minimum_size(window, width, height);
+    }
+    bridge::set_window_resizable(window, settings.resizable.unwrap_or(true));
+    if !settings.decorations.unwrap_or(true) {
+        bridge::set_window_frameless(window, true);
+    }
+    info!("Window settings applied: {:?}", settings);
+}
+
+/// Maximizes the window if configured; the window must already be shown.
+pub fn apply_start_maximized(window: &webui::Window, settings: &WindowSettings) {
+    if settings.start_maximized.unwrap_or(false) {
+        bridge::maximize_window(window);
     }
 }
 
-pub fn setup_window_viewmodel(window: &mut webui::Window) {
-    set_webui_window_id(window.id);
+pub fn setup_window_viewmodel(window: &mut impl BindSurface) {
 
-    window.bind("test_handler", |_event| {
+    window.bind("test_handler", |event| {
+        let _timer = perf::handler("test_handler");
         info!("[TEST] test_handler called from frontend!");
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
         
-        tokio::spawn(async {
+        tokio::spawn(async move {
             let event = Event::new(
                 EventType::Custom {
                     name: "test.handler".to_string(),
@@ -48,15 +94,18 @@ pub fn setup_window_viewmodel(window: &mut webui::Window) {
                 },
                 "window_viewmodel"
             );
-            if let Err(e) = emit_event(event).await {
+            if let Err(e) = bus.emit(event).await {
                 error!("Failed to emit test event: {}", e);
             }
         });
     });
 
     window.bind("handleFrontendEvent", |event| {
+        let _timer = perf::handler("handleFrontendEvent");
         info!("[WEBUI] handleFrontendEvent called from frontend");
-        
+        let Some(state) = AppState::of(&event) else { return };
+        let bus = state.bus().clone();
+
         let event_data = parse_event_data(&event);
         
         tokio::spawn(async move {
@@ -68,7 +117,7 @@ pub fn setup_window_viewmodel(window: &mut webui::Window) {
                 "frontend"
             );
             
-            if let Err(e) = emit_event(frontend_event).await {
+            if let Err(e) = bus.emit(frontend_event).await {
                 error!("Failed to emit frontend event: {}", e);
             } else {
                 info!("[WEBUI] Frontend event emitted successfully");
@@ -76,28 +125,41 @@ pub fn setup_window_viewmodel(window: &mut webui::Window) {
         });
     });
 
-    window.bind("window_focused", |_event| {
+    window.bind("window_focused", |event| {
+        let _timer = perf::handler("window_focused");
         info!("[WEBUI] ===> window_focused <===");
-        send_to_frontend("window.focused", json!({ "source": "backend" }));
+        if let Some(state) = AppState::of(&event) {
+            state.windows().send_to_frontend("window.focused", json!({ "source": "backend" }));
+        }
     });
 
-    window.bind("window_minimized", |_event| {
+    window.bind("window_minimized", |event| {
+        let _timer = perf::handler("window_minimized");
         info!("[WEBUI] ===> window_minimized <===");
-        send_to_frontend("window.minimized", json!({ "source": "backend" }));
+        if let Some(state) = AppState::of(&event) {
+            state.windows().send_to_frontend("window.minimized", json!({ "source": "backend" }));
+        }
     });
 
     window.bind("window_closed", |_event| {
+        let _timer = perf::handler("window_closed");
         info!("[WEBUI] ===> window_closed <===");
     });
 
-    window.bind("window_restored", |_event| {
+    window.bind("window_restored", |event| {
+        let _timer = perf::handler("window_restored");
         info!("[WEBUI] ===> window_restored <===");
-        send_to_frontend("window.restored", json!({ "source": "backend" }));
+        if let Some(state) = AppState::of(&event) {
+            state.windows().send_to_frontend("window.restored", json!({ "source": "backend" }));
+        }
     });
 
-    window.bind("window_maximized", |_event| {
+    window.bind("window_maximized", |event| {
+        let _timer = perf::handler("window_maximized");
         info!("[WEBUI] ===> window_maximized <===");
-        send_to_frontend("window.maximized", json!({ "source": "backend" }));
+        if let Some(state) = AppState::of(&event) {
+            state.windows().send_to_frontend("window.maximized", json!({ "source": "backend" }));
+        }
     });
 
     info!("[VIEWMODEL] Window viewmodel handlers registered");
@@ -4,10 +4,12 @@ use tokio::time::interval;
 use tracing::{info, error, warn, trace};
 use webui_rs::webui;
 use serde_json::json;
-use crate::event_bus::{emit_event, Event, EventType};
+use crate::core::supervisor::Task;
+use crate::event_bus::{Event, EventBus, EventHandler, EventListener, EventType, SubscriptionGuard};
 use std::collections::VecDeque;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
 #[allow(dead_code)] // These variants are part of the design and may be used in future implementations
 pub enum WebSocketState {
     Disconnected,
@@ -46,10 +48,12 @@ pub struct WebSocketManager {
     connection_start_time: Arc<Mutex<Option<Instant>>>,
     error_log: Arc<Mutex<VecDeque<(u64, String)>>>,
     max_error_log_size: usize,
+    /// Where state changes and errors are emitted.
+    bus: EventBus,
 }
 
 impl WebSocketManager {
-    pub fn new(window: Arc<Mutex<webui::Window>>) -> Self {
+    pub fn new(window: Arc<Mutex<webui::Window>>, bus: EventBus) -> Self {
         Self {
             state: Arc::new(Mutex::new(WebSocketState::Disconnected)),
             metrics: Arc::new(Mutex::new(WebSocketMetrics {
@@ -75,73 +79,112 @@ impl WebSocketManager {
             connection_start_time: Arc::new(Mutex::new(None)),
             error_log: Arc::new(Mutex::new(VecDeque::new())),
             max_error_log_size: 50,
+            bus,
         }
     }
 
+    /// Creates the manager for the main window, starts monitoring and ties
+    /// its state to the window connection events on `bus`. Keep the handle
+    /// until shutdown.
+    pub fn bootstrap(window: Arc<Mutex<webui::Window>>, bus: &EventBus) -> WebSocketHandle {
+        let manager = Self::new(window, bus.clone());
+        manager.set_state(WebSocketState::Connecting);
+        manager.start_monitoring();
+        manager.into_handle()
+    }
+
+    /// Subscribes the manager to `webui.connected` and `webui.disconnected`
+    /// on its bus; the subscriptions last as long as the handle.
+    pub fn into_handle(self) -> WebSocketHandle {
+        let connected = self.clone();
+        let on_connected = EventHandler::new(move |_event| {
+            connected.handle_connection_success();
+            Box::pin(async { Ok(()) })
+        });
+        let disconnected = self.clone();
+        let on_disconnected = EventHandler::new(move |_event| {
+            disconnected.set_state(WebSocketState::Disconnected);
+            Box::pin(async { Ok(()) })
+        });
+        let subscriptions = vec![
+            self.bus.subscribe_scoped("webui.connected", Arc::new(on_connected) as Arc<dyn EventListener>),
+            self.bus.subscribe_scoped("webui.disconnected", Arc::new(on_disconnected) as Arc<dyn EventListener>),
+        ];
+        WebSocketHandle { manager: self, _subscriptions: subscriptions }
+    }
+
+    /// Runs as the supervised `websocket_monitor` task, restarted if it
+    /// stops ticking.
     pub fn start_monitoring(&self) {
-        let state = Arc::clone(&self.state);
-        let metrics = Arc::clone(&self.metrics);
-        let is_running = Arc::clone(&self.is_running);
-        let connection_start_time = Arc::clone(&self.connection_start_time);
-        let _error_log = Arc::clone(&self.error_log);
-        
         *self.is_running.lock().unwrap() = true;
-        
-        tokio::spawn(async move {
-            let mut interval = interval(Duration::from_secs(1));
+
+        let manager = self.clone();
+        Task::new("websocket_monitor")
+            .with_events(&self.bus)
+            .with_heartbeat_timeout(Duration::from_secs(10))
+            .spawn(move |ctx| {
+                let state = Arc::clone(&manager.state);
+                let metrics = Arc::clone(&manager.metrics);
+                let is_running = Arc::clone(&manager.is_running);
+                let connection_start_time = Arc::clone(&manager.connection_start_time);
+                async move {
+                    let mut interval = interval(Duration::from_secs(1));
             
-            loop {
-                {
-                    let running = is_running.lock().unwrap();
-                    if !*running {
-                        break;
-                    }
-                }
+                    loop {
+                        {
+                            let running = is_running.lock().unwrap();
+                            if !*running {
+                                break;
+                            }
+                        }
                 
-                interval.tick().await;
+                        interval.tick().await;
+                        ctx.heartbeat();
                 
-                // Update uptime
-                {
-                    let mut metrics_guard = metrics.lock().unwrap();
-                    metrics_guard.uptime_seconds += 1;
+                        // Update uptime
+                        {
+                            let mut metrics_guard = metrics.lock().unwrap();
+                            metrics_guard.uptime_seconds += 1;
                     
-                    // Update connection duration if connected
-                    if let Ok(state_guard) = state.lock() {
-                        if *state_guard == WebSocketState::Connected {
-                            if let Ok(conn_start) = connection_start_time.lock() {
-                                if let Some(start_time) = *conn_start {
-                                    metrics_guard.connection_duration = Some(start_time.elapsed());
+                            // Update connection duration if connected
+                            if let Ok(state_guard) = state.lock() {
+                                if *state_guard == WebSocketState::Connected {
+                                    if let Ok(conn_start) = connection_start_time.lock() {
+                                        if let Some(start_time) = *conn_start {
+                                            metrics_guard.connection_duration = Some(start_time.elapsed());
+                                        }
+                                    }
                                 }
                             }
                         }
-                    }
-                }
                 
-                // Log state periodically
-                {
-                    let state_guard = state.lock().unwrap();
-                    trace!("WebSocket state: {:?}, Metrics: {:?}", 
-                           *state_guard, 
-                           metrics.lock().unwrap());
+                        // Log state periodically
+                        {
+                            let state_guard = state.lock().unwrap();
+                            trace!("WebSocket state: {:?}, Metrics: {:?}", 
+                                   *state_guard, 
+                                   metrics.lock().unwrap());
                            
-                    // Log detailed metrics every 30 seconds
-                    if metrics.lock().unwrap().uptime_seconds % 30 == 0 {
-                        info!("WebSocket Monitoring Report:");
-                        info!("  State: {:?}", *state_guard);
-                        let m = metrics.lock().unwrap();
-                        info!("  Connection Attempts: {}, Successful: {}, Failed: {}", 
-                              m.connection_attempts, m.successful_connections, m.failed_connections);
-                        info!("  Messages: Sent={} Received={}", m.messages_sent, m.messages_received);
-                        info!("  Bytes: Sent={} Received={}", m.bytes_sent, m.bytes_received);
-                        info!("  Uptime: {}s", m.uptime_seconds);
-                        info!("  Reconnect Count: {}", m.reconnect_count);
-                        if let Some(ref err) = m.last_error {
-                            info!("  Last Error: {}", err);
+                            // Log detailed metrics every 30 seconds
+                            if metrics.lock().unwrap().uptime_seconds % 30 == 0 {
+                                info!("WebSocket Monitoring Report:");
+                                info!("  State: {:?}", *state_guard);
+                                let m = metrics.lock().unwrap();
+                                info!("  Connection Attempts: {}, Successful: {}, Failed: {}", 
+                                      m.connection_attempts, m.successful_connections, m.failed_connections);
+                                info!("  Messages: Sent={} Received={}", m.messages_sent, m.messages_received);
+                                info!("  Bytes: Sent={} Received={}", m.bytes_sent, m.bytes_received);
+                                info!("  Uptime: {}s", m.uptime_seconds);
+                                info!("  Reconnect Count: {}", m.reconnect_count);
+                                if let Some(ref err) = m.last_error {
+                                    info!("  Last Error: {}", err);
+                                }
+                            }
                         }
                     }
+                    Ok(())
                 }
-            }
-        });
+            });
     }
 
     pub fn stop_monitoring(&self) {
@@ -184,8 +227,9 @@ impl WebSocketManager {
             "timestamp": chrono::Utc::now().timestamp_millis(),
         });
 
+        let bus = self.bus.clone();
         tokio::spawn(async move {
-            if let Err(e) = emit_event(Event::new(
+            if let Err(e) = bus.emit(Event::new(
                 EventType::Custom {
                     name: event_name.to_string(),
                     payload,
@@ -240,8 +284,9 @@ impl WebSocketManager {
             }
         });
 
+        let bus = self.bus.clone();
         tokio::spawn(async move {
-            if let Err(e) = emit_event(Event::new(
+            if let Err(e) = bus.emit(Event::new(
                 EventType::Custom {
                     name: "websocket.error".to_string(),
                     payload,
@@ -350,6 +395,24 @@ impl WebSocketManager {
     }
 }
 
+/// The running manager, returned by [`WebSocketManager::bootstrap`].
+pub struct WebSocketHandle {
+    manager: WebSocketManager,
+    _subscriptions: Vec<SubscriptionGuard>,
+}
+
+impl WebSocketHandle {
+    pub fn manager(&self) -> &WebSocketManager {
+        &self.manager
+    }
+
+    /// Stops monitoring and marks the connection closed.
+    pub fn stop(self) {
+        self.manager.stop_monitoring();
+        self.manager.disconnect();
+    }
+}
+
 impl Clone for WebSocketManager {
     fn clone(&self) -> Self {
         Self {
@@ -363,6 +426,7 @@ impl Clone for WebSocketManager {
             connection_start_time: Arc::clone(&self.connection_start_time),
             error_log: Arc::clone(&self.error_log),
             max_error_log_size: self.max_error_log_size,
+            bus: self.bus.clone(),
         }
     }
 }
\ No newline at end of file